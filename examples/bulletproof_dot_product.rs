@@ -0,0 +1,50 @@
+//! Proves and verifies the inner product of two random vectors over BLS12-381.
+//!
+//! ```text
+//! cargo run --release --example bulletproof_dot_product -- [size]
+//! ```
+//!
+//! The size is a power of two, 1024 by default.
+use std::time::Instant;
+
+use ark_bls12_381::{Fr, G1Projective as G1};
+use ark_ff::UniformRand;
+use ark_std::rand::thread_rng;
+
+use nemesis::bulletproofs::bls12_381::Bls12381BulletproofSystem;
+use nemesis::bulletproofs::BulletproofGenerators;
+use nemesis::report::Metrics;
+use nemesis::BulletproofSystem;
+
+fn main() {
+    let size = match std::env::args().nth(1).map(|arg| arg.parse::<usize>()) {
+        None => 1024,
+        Some(Ok(size)) if size.is_power_of_two() => size,
+        Some(_) => {
+            eprintln!("usage: bulletproof_dot_product [size], the size being a power of two");
+            std::process::exit(2);
+        }
+    };
+
+    let mut rng = thread_rng();
+    let system = Bls12381BulletproofSystem::default();
+    let generators = BulletproofGenerators::<G1>::from_seed(b"nemesis/examples/bulletproof_dot_product", size);
+    let v1: Vec<Fr> = (0..size).map(|_| Fr::rand(&mut rng)).collect();
+    let v2: Vec<Fr> = (0..size).map(|_| Fr::rand(&mut rng)).collect();
+
+    let start = Instant::now();
+    let proof = system.prove(generators.clone(), v1, v2).expect("The vectors have the generators' power of two length");
+    let proving = start.elapsed();
+    let metrics = proof.metrics();
+
+    let start = Instant::now();
+    let accepted = system.verify(proof, generators);
+    let verifying = start.elapsed();
+
+    println!("inner product of {size} elements");
+    println!("proof: {} bytes, proved in {proving:?}", metrics.proof_bytes);
+    println!("verified in {verifying:?}: {}", if accepted { "accepted" } else { "rejected" });
+    if !accepted {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,50 @@
+//! Proves that a random polynomial over the BLS12-381 scalar field has low degree with FRI over
+//! SHA-256 Merkle trees, at 100 bits of security.
+//!
+//! ```text
+//! cargo run --release --example fri_low_degree -- [degree]
+//! ```
+//!
+//! The degree is one less than a power of two, 1023 by default.
+use std::time::Instant;
+
+use ark_bls12_381::Fr;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::DenseUVPolynomial;
+use ark_std::rand::thread_rng;
+
+use nemesis::fri::config::{FriConfig, SecurityPreset};
+use nemesis::fri::protocol::FRIProtocol;
+use nemesis::fri::sha256::sha256_system;
+use nemesis::report::Metrics;
+
+fn main() {
+    let degree = match std::env::args().nth(1).map(|arg| arg.parse::<usize>()) {
+        None => 1023,
+        Some(Ok(degree)) if (degree + 1).is_power_of_two() => degree,
+        Some(_) => {
+            eprintln!("usage: fri_low_degree [degree], the degree being one less than a power of two");
+            std::process::exit(2);
+        }
+    };
+
+    let config = FriConfig::preset(SecurityPreset::Bits100);
+    let system = sha256_system::<Fr>(config).expect("The preset folds by 2");
+    let polynomial = DensePolynomial::<Fr>::rand(degree, &mut thread_rng());
+
+    let start = Instant::now();
+    let proof = system.prove(&polynomial, degree).expect("The field has a domain for the polynomial at the preset's blowup");
+    let proving = start.elapsed();
+    let metrics = proof.metrics();
+
+    let start = Instant::now();
+    let accepted = system.verify(&proof);
+    let verifying = start.elapsed();
+
+    println!("polynomial of degree {degree}, {} queries at blowup {}", config.num_queries, config.blowup);
+    println!("proof: {} bytes, proved in {proving:?}", metrics.proof_bytes);
+    println!("verified in {verifying:?} with {} hashes: {}", metrics.verifier_hashes, if accepted { "accepted" } else { "rejected" });
+    if !accepted {
+        std::process::exit(1);
+    }
+}
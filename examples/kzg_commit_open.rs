@@ -0,0 +1,72 @@
+//! Commits to the bytes of a file with KZG over BLS12-381 and opens the commitment at a point.
+//!
+//! ```text
+//! cargo run --release --example kzg_commit_open -- <file> [point]
+//! ```
+//!
+//! The bytes are the coefficients of the polynomial, 31 to each so that none is reduced modulo
+//! the field. The point is an integer, 1 by default.
+use std::time::Instant;
+
+use ark_bls12_381::{Bls12_381, Fr};
+use ark_ff::PrimeField;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::DenseUVPolynomial;
+use ark_std::rand::thread_rng;
+
+use nemesis::kzg::prover::prover;
+use nemesis::kzg::system::{KZGSystemImpl, KZGVerifierChallenger};
+use nemesis::kzg::verifier::verifier;
+use nemesis::report::Metrics;
+
+/// The bytes of a coefficient, fewer than the 255 bits of the modulus.
+const BYTES_PER_COEFFICIENT: usize = 31;
+
+fn usage() -> ! {
+    eprintln!("usage: kzg_commit_open <file> [point]");
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| usage());
+    let point = match args.next().map(|arg| arg.parse::<u64>()) {
+        None => Fr::from(1u64),
+        Some(Ok(point)) => Fr::from(point),
+        Some(Err(_)) => usage(),
+    };
+    let bytes = std::fs::read(&path).unwrap_or_else(|error| {
+        eprintln!("cannot read {path}: {error}");
+        std::process::exit(1);
+    });
+
+    let coefficients: Vec<Fr> = bytes.chunks(BYTES_PER_COEFFICIENT).map(Fr::from_le_bytes_mod_order).collect();
+    let polynomial = DensePolynomial::from_coefficients_vec(coefficients);
+    let degree = bytes.len().div_ceil(BYTES_PER_COEFFICIENT).saturating_sub(1);
+
+    let start = Instant::now();
+    let system = KZGSystemImpl::setup(degree, KZGVerifierChallenger::default(), &mut thread_rng());
+    let setup = start.elapsed();
+
+    let start = Instant::now();
+    let (commitment, commitment_bytes) = prover::prover_commit_with_bytes(&system.crs, &polynomial);
+    let committing = start.elapsed();
+
+    let start = Instant::now();
+    let proof = prover::try_prover_open(&system.crs, &polynomial, &point, &commitment).expect("The CRS is set up for the degree of the polynomial");
+    let opening = start.elapsed();
+    let metrics = proof.metrics();
+
+    let start = Instant::now();
+    let accepted = verifier::verify::<Bls12_381>(proof, point, system.g2, system.g2_s);
+    let verifying = start.elapsed();
+
+    println!("{path}: {} bytes, a polynomial of degree {degree}", bytes.len());
+    println!("setup in {setup:?}");
+    println!("commitment: {} bytes, committed in {committing:?}", commitment_bytes.len());
+    println!("opening: {} bytes, opened in {opening:?}", metrics.proof_bytes);
+    println!("verified in {verifying:?}: {}", if accepted { "accepted" } else { "rejected" });
+    if !accepted {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,20 @@
+//! The inner product argument over G1 of BLS12-381, the group KZG commits in.
+//!
+//! The vectors and challenges live in the scalar field of BLS12-381, the field KZG polynomials
+//! are over, so a Pedersen commitment to the coefficients of a polynomial can be opened with the
+//! argument and linked to its KZG commitment, see `KZGSystemImpl::prove_kzg_pedersen_equality`.
+use ark_bls12_381::{Fr, G1Projective};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+
+use super::system::BulletproofSystemImpl;
+use super::verifier_challenger::DefaultVerifierChallenger;
+
+/// A bulletproof system over G1 of BLS12-381, whose challenges come from a Poseidon sponge over its scalar field.
+pub type Bls12381BulletproofSystem = BulletproofSystemImpl<Fr, G1Projective, DefaultVerifierChallenger<Fr>>;
+
+pub fn bls12_381_system(poseidon_config: &PoseidonConfig<Fr>) -> Bls12381BulletproofSystem {
+    BulletproofSystemImpl {
+        challenger: DefaultVerifierChallenger::new(poseidon_config),
+        _phantom: std::marker::PhantomData,
+    }
+}
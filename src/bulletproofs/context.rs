@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use ark_ec::{Group, VariableBaseMSM};
+use ark_ff::Field;
+
+use crate::util::folding::FoldingChallenges;
+
+use super::helpers::fold_generators;
+use super::BulletproofGenerators;
+
+/// Generators shared by many verifications, together with their foldings.
+///
+/// Folding the generators down to size 1 only depends on the generators and the challenges of the
+/// rounds, so proofs verified under the same challenge sequence reuse a single folding.
+pub struct VerifierContext<S: Field, G: Group<ScalarField = S> + Clone + Debug> {
+    generators: BulletproofGenerators<G>,
+    /// The generators folded with each challenge sequence seen so far
+    folded: HashMap<Vec<S>, BulletproofGenerators<G>>,
+    folds: usize,
+}
+
+impl<S: Field, G: Group<ScalarField = S> + VariableBaseMSM + Clone + Debug> VerifierContext<S, G> {
+    pub fn new(generators: BulletproofGenerators<G>) -> Self {
+        Self {
+            generators,
+            folded: HashMap::new(),
+            folds: 0,
+        }
+    }
+
+    pub fn generators(&self) -> &BulletproofGenerators<G> {
+        &self.generators
+    }
+
+    /// Replaces the generators, dropping the foldings of the previous ones.
+    pub fn set_generators(&mut self, generators: BulletproofGenerators<G>) {
+        self.generators = generators;
+        self.folded.clear();
+    }
+
+    /// The generators folded with the given challenges, computed on first use.
+    pub fn folded_generators(&mut self, challenges: &FoldingChallenges<S>) -> &BulletproofGenerators<G> {
+        if !self.folded.contains_key(challenges.challenges()) {
+            let folded = fold_generators(&self.generators, challenges);
+            self.folded.insert(challenges.challenges().to_vec(), folded);
+            self.folds += 1;
+        }
+        &self.folded[challenges.challenges()]
+    }
+
+    /// The number of times the generators have been folded.
+    pub fn fold_count(&self) -> usize {
+        self.folds
+    }
+}
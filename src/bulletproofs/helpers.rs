@@ -1,11 +1,19 @@
-use ark_ec::Group;
+use ark_ec::{Group, VariableBaseMSM};
 use ark_ff::Field;
+use ark_std::cfg_iter;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::util::folding::FoldingChallenges;
+use crate::util::msm::msm_projective;
+use crate::util::secret::wipe;
 
 use super::{BulletproofGenerators, verifier_challenger::BulletproofVerifierChallenge};
 
 // Compute: u * <a, b> + <a, g> + <b, h>
-// where <x, y> denotes the dot product or multi-scalar multiplication
-pub fn compute_intermediate_commitment<S: Field, G: Group<ScalarField = S>>(
+// where <x, y> denotes the dot product or multi-scalar multiplication,
+// as a single MSM over g || h || u
+pub fn compute_intermediate_commitment<S: Field, G: Group<ScalarField = S> + VariableBaseMSM>(
     a: &[S],
     b: &[S],
     u: &G,
@@ -13,19 +21,17 @@ pub fn compute_intermediate_commitment<S: Field, G: Group<ScalarField = S>>(
     h: &[G],
 ) -> G {
     let ab_dot = compute_dot_product(a, b);
-    let ag = multi_scalar_mul(a, g);
-    let bh = multi_scalar_mul(b, h);
-    u.mul(&ab_dot) + ag + bh
+    commit_with_generators(a, b, ab_dot, g, h, u)
 }
 
 pub fn compute_dot_product<S: Field>(a: &[S], b: &[S]) -> S {
-    a.iter()
-        .zip(b.iter())
+    cfg_iter!(a)
+        .zip(b)
         .map(|(ai, bi)| *ai * *bi)
         .sum()
 }
 
-pub fn compute_pedersen_commitment<S: Field, G: Group<ScalarField = S>>(
+pub fn compute_pedersen_commitment<S: Field, G: Group<ScalarField = S> + VariableBaseMSM>(
     v1: &[S],
     v2: &[S],
     dot_product: S,
@@ -33,15 +39,23 @@ pub fn compute_pedersen_commitment<S: Field, G: Group<ScalarField = S>>(
     h: &[G],
     u: &G,
 ) -> G {
-    multi_scalar_mul(v1, g) + multi_scalar_mul(v2, h) + u.mul(&dot_product)
+    commit_with_generators(v1, v2, dot_product, g, h, u)
 }
 
-fn multi_scalar_mul<S: Field, G: Group<ScalarField = S>>(scalars: &[S], points: &[G]) -> G {
-    assert_eq!(scalars.len(), points.len(), "Scalars and points must have the same length");
-    scalars.iter()
-        .zip(points.iter())
-        .map(|(s, p)| p.mul(*s))
-        .sum()
+/// <v1, g> + <v2, h> + dot_product * u with one MSM over the concatenated generators.
+fn commit_with_generators<S: Field, G: Group<ScalarField = S> + VariableBaseMSM>(
+    v1: &[S],
+    v2: &[S],
+    dot_product: S,
+    g: &[G],
+    h: &[G],
+    u: &G,
+) -> G {
+    assert_eq!(v1.len(), g.len(), "Scalars and points must have the same length");
+    assert_eq!(v2.len(), h.len(), "Scalars and points must have the same length");
+    let points: Vec<G> = g.iter().chain(h).chain([u]).copied().collect();
+    let scalars: Vec<S> = v1.iter().chain(v2).chain([&dot_product]).copied().collect();
+    msm_projective(&points, &scalars)
 }
 
 pub fn update_generators<S: Field, G: Group<ScalarField = S>>(
@@ -50,12 +64,12 @@ pub fn update_generators<S: Field, G: Group<ScalarField = S>>(
 ) -> BulletproofGenerators<G> {
     let n = generators.g.len();
     let m = n / 2;
-    let x_inv = x.inverse().expect("Challenge should be non-zero");
+    let x_inv = x.inverse().expect("Challenges are generated non-zero");
 
-    let g_new: Vec<G> = generators.g[..m].iter().zip(generators.g[m..].iter())
+    let g_new: Vec<G> = cfg_iter!(generators.g[..m]).zip(&generators.g[m..])
         .map(|(g_l, g_r)| g_l.mul(x_inv) + g_r.mul(x))
         .collect();
-    let h_new: Vec<G> = generators.h[..m].iter().zip(generators.h[m..].iter())
+    let h_new: Vec<G> = cfg_iter!(generators.h[..m]).zip(&generators.h[m..])
         .map(|(h_l, h_r)| h_l.mul(x) + h_r.mul(x_inv))
         .collect();
     let u_new = generators.u;  // U doesn't change
@@ -67,27 +81,48 @@ pub fn update_generators<S: Field, G: Group<ScalarField = S>>(
     }
 }
 
+/// Folds the generators with every challenge at once: g and h collapse to `<s, g>` and `<s^-1, h>`,
+/// where s is the s-vector of the challenges. Its inverse is s reversed, since flipping every bit
+/// of the index flips the sign of every exponent.
+pub fn fold_generators<S: Field, G: Group<ScalarField = S> + VariableBaseMSM>(
+    generators: &BulletproofGenerators<G>,
+    challenges: &FoldingChallenges<S>,
+) -> BulletproofGenerators<G> {
+    let s = challenges.s_vector();
+    let s_inv: Vec<S> = s.iter().rev().copied().collect();
+
+    BulletproofGenerators {
+        g: vec![msm_projective(&generators.g, &s)],
+        h: vec![msm_projective(&generators.h, &s_inv)],
+        u: generators.u,
+    }
+}
+
 pub fn prove_update<S: Field, G: Group<ScalarField = S>>(
     challenge: BulletproofVerifierChallenge<S>,
     generators: BulletproofGenerators<G>,
-    v1: Vec<S>,
-    v2: Vec<S>,
+    mut v1: Vec<S>,
+    mut v2: Vec<S>,
 ) -> (BulletproofGenerators<G>, Vec<S>, Vec<S>) {
     let x = challenge.random_challenge;
-    let x_inv = x.inverse().expect("Challenge should be non-zero");
+    let x_inv = x.inverse().expect("Challenges are generated non-zero");
 
     let n = v1.len();
     assert_eq!(n, v2.len(), "Input vectors must have the same length");
     let m = n / 2;
 
     // Update v1 and v2
-    let v1_new: Vec<S> = v1[..m].iter().zip(v1[m..].iter())
+    let v1_new: Vec<S> = cfg_iter!(v1[..m]).zip(&v1[m..])
         .map(|(a_l, a_r)| *a_l * x + *a_r * x_inv)
         .collect();
-    let v2_new: Vec<S> = v2[..m].iter().zip(v2[m..].iter())
+    let v2_new: Vec<S> = cfg_iter!(v2[..m]).zip(&v2[m..])
         .map(|(b_l, b_r)| *b_l * x_inv + *b_r * x)
         .collect();
 
+    // The folded vectors replace the witness
+    wipe(&mut v1);
+    wipe(&mut v2);
+
     // Update generators
     let new_generators = update_generators(&generators, x);
 
@@ -0,0 +1,27 @@
+//! The inner product argument over Jubjub, the twisted Edwards curve embedded in BLS12-381.
+//!
+//! Jubjub is defined over the scalar field of BLS12-381, so its points have coordinates in the
+//! field a BLS12-381 SNARK computes in, and a circuit can add and multiply them natively. The
+//! vectors and challenges of the argument live in the scalar field of Jubjub, the prime subgroup
+//! order of about 2^252, which is a different and smaller field.
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_ed_on_bls12_381::{EdwardsProjective, Fr};
+
+use super::system::BulletproofSystemImpl;
+use super::verifier_challenger::DefaultVerifierChallenger;
+
+/// The scalar field of Jubjub, the field of the vectors and challenges.
+pub type JubjubScalar = Fr;
+
+/// The base field of Jubjub, the scalar field of BLS12-381.
+pub type JubjubBase = ark_ed_on_bls12_381::Fq;
+
+/// A bulletproof system over Jubjub, whose challenges come from a Poseidon sponge over its scalar field.
+pub type JubjubBulletproofSystem = BulletproofSystemImpl<JubjubScalar, EdwardsProjective, DefaultVerifierChallenger<JubjubScalar>>;
+
+pub fn jubjub_system(poseidon_config: &PoseidonConfig<JubjubScalar>) -> JubjubBulletproofSystem {
+    BulletproofSystemImpl {
+        challenger: DefaultVerifierChallenger::new(poseidon_config),
+        _phantom: std::marker::PhantomData,
+    }
+}
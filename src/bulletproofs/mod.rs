@@ -1,24 +1,158 @@
 mod prover;
 mod verifier;
 mod helpers;
-mod verifier_challenger;
+pub(crate) mod verifier_challenger;
 mod system;
+mod context;
+pub mod jubjub;
+pub mod bls12_381;
 mod test;
 
 use ark_ec::Group;
-use ark_ff::Field;
+use ark_ff::{Field, UniformRand};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
 use verifier_challenger::BulletproofVerifierChallenge;
-use std::fmt::Debug;
+use std::collections::HashSet;
+use std::fmt::{self, Debug};
 
+use crate::util::TranscriptDigest;
 
-#[derive(Clone, Debug)]
+
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct BulletproofGenerators<G: Group + Clone + Debug> {
     pub g: Vec<G>,
     pub h: Vec<G>,
     pub u: G, 
 }
 
-#[derive(Clone, Debug)]
+impl<G: Group + Clone + Debug> BulletproofGenerators<G> {
+    /// Samples independent generators for vectors of the given size. Nobody must know discrete
+    /// logarithms between them, so the randomness must not be reused or revealed.
+    pub fn random<R: Rng>(size: usize, rng: &mut R) -> Self {
+        Self {
+            g: (0..size).map(|_| G::rand(rng)).collect(),
+            h: (0..size).map(|_| G::rand(rng)).collect(),
+            u: G::rand(rng),
+        }
+    }
+
+    /// Derives generators for vectors of the given size from a public seed, so that a verifier
+    /// can rebuild them instead of receiving them. The points are sampled as in `random`, from a
+    /// ChaCha20 stream keyed by the SHA-256 hash of the seed, so nobody knows discrete logarithms
+    /// between them either.
+    pub fn from_seed(seed: &[u8], size: usize) -> Self {
+        let key = Sha256::new().chain_update(GENERATORS_DOMAIN).chain_update(seed).finalize();
+        Self::random(size, &mut ChaCha20Rng::from_seed(key.into()))
+    }
+
+    /// Checks that none of the generators is the identity, which would drop its term from every
+    /// commitment and let the prover open it to anything.
+    pub fn validate(&self) -> Result<(), GeneratorError> {
+        if let Some(index) = self.g.iter().position(|point| point.is_zero()) {
+            return Err(GeneratorError::IdentityG { index });
+        }
+        if let Some(index) = self.h.iter().position(|point| point.is_zero()) {
+            return Err(GeneratorError::IdentityH { index });
+        }
+        if self.u.is_zero() {
+            return Err(GeneratorError::IdentityU);
+        }
+        Ok(())
+    }
+
+    /// A heuristic check that the generators are linearly independent, catching setup mistakes
+    /// such as a point reused in `g` and `h`. It rejects a generator equal to another one or to
+    /// its negation, then checks that a combination of all of them with random scalars is not the
+    /// identity, which a dependence holds with probability about 1 / |F| only for a random one:
+    /// passing is no proof that nobody knows discrete logarithms between them.
+    pub fn check_independence<R: Rng>(&self, rng: &mut R) -> bool {
+        let points = || self.g.iter().chain(&self.h).chain(std::iter::once(&self.u));
+
+        let mut seen = HashSet::new();
+        for point in points() {
+            if point.is_zero() || seen.contains(&-*point) || !seen.insert(*point) {
+                return false;
+            }
+        }
+
+        let combination = points().fold(G::zero(), |acc, point| acc + *point * G::ScalarField::rand(rng));
+        !combination.is_zero()
+    }
+}
+
+/// Why generators cannot be used for binding commitments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneratorError {
+    /// The generator of `g` at the index is the identity
+    IdentityG { index: usize },
+    /// The generator of `h` at the index is the identity
+    IdentityH { index: usize },
+    /// `u` is the identity
+    IdentityU,
+}
+
+/// Why a bulletproof cannot be made for the given vectors and generators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulletproofError {
+    /// The generators cannot be used for binding commitments
+    InvalidGenerators(GeneratorError),
+    /// The vectors are empty, so there is no inner product to prove
+    EmptyVectors,
+    /// The two vectors do not have the same length
+    LengthMismatch { v1: usize, v2: usize },
+    /// The vectors cannot be halved down to a single element
+    NotPowerOfTwo { length: usize },
+    /// There is not one generator in `g` and in `h` per element of the vectors
+    GeneratorCountMismatch { length: usize, g: usize, h: usize },
+}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorError::IdentityG { index } => write!(f, "the generator g_{} is the identity", index),
+            GeneratorError::IdentityH { index } => write!(f, "the generator h_{} is the identity", index),
+            GeneratorError::IdentityU => write!(f, "the generator u is the identity"),
+        }
+    }
+}
+
+impl fmt::Display for BulletproofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BulletproofError::InvalidGenerators(error) => write!(f, "invalid generators: {}", error),
+            BulletproofError::EmptyVectors => write!(f, "the vectors are empty"),
+            BulletproofError::LengthMismatch { v1, v2 } => {
+                write!(f, "the vectors have lengths {} and {}", v1, v2)
+            }
+            BulletproofError::NotPowerOfTwo { length } => {
+                write!(f, "the vectors have length {}, which is not a power of two", length)
+            }
+            BulletproofError::GeneratorCountMismatch { length, g, h } => write!(
+                f,
+                "the vectors have length {}, but there are {} generators in g and {} in h",
+                length, g, h
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GeneratorError {}
+
+impl std::error::Error for BulletproofError {}
+
+impl From<GeneratorError> for BulletproofError {
+    fn from(error: GeneratorError) -> Self {
+        BulletproofError::InvalidGenerators(error)
+    }
+}
+
+/// Separates the generator derivation from other uses of SHA-256 on the same seed.
+const GENERATORS_DOMAIN: &[u8] = b"nemesis/bulletproofs/generators";
+
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct BulletproofRecProof<S: Field + Clone + Debug, G: Group<ScalarField = S> + Clone + Debug> {
     /// The dot product of the two input vectors
     pub dot_product: S,
@@ -36,7 +170,9 @@ pub struct BulletproofRecProof<S: Field + Clone + Debug, G: Group<ScalarField =
 }
 
 pub trait BulletproofSystem<S: Field + Clone + Debug, G: Group<ScalarField = S> + Clone + Debug> {
-    fn prove(&self, generators: BulletproofGenerators<G>, v1: Vec<S>, v2: Vec<S>) -> BulletproofProof<S, G>;
+    /// Proves the inner product of the vectors, which must have the same power of two length, with
+    /// one generator in `g` and in `h` per element.
+    fn prove(&self, generators: BulletproofGenerators<G>, v1: Vec<S>, v2: Vec<S>) -> Result<BulletproofProof<S, G>, BulletproofError>;
     fn verify(&self, proof: BulletproofProof<S, G>, generators: BulletproofGenerators<G>) -> bool;
 }
 
@@ -53,7 +189,7 @@ pub trait BulletproofSystem<S: Field + Clone + Debug, G: Group<ScalarField = S>
 /// The small proof allows for direct verification by computing and comparing
 /// the Pedersen commitment, providing a simple and efficient way to conclude
 /// the recursive proof chain.
-#[derive(Debug)]
+#[derive(Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct BulletproofProofSmall<S: Field + Debug, G: Group<ScalarField = S> + Debug> {
     /// The single remaining value from the first input vector
     pub value1: S,
@@ -65,10 +201,42 @@ pub struct BulletproofProofSmall<S: Field + Debug, G: Group<ScalarField = S> + D
     pub pedersen_commitment: G,
 }
 
-#[derive(Debug)]
+#[derive(Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct BulletproofProof<S: Field + Debug, G: Group<ScalarField = S> + Debug> {
     pub rec_proofs: Vec<(BulletproofRecProof<S, G>, BulletproofVerifierChallenge<S>)>,
     pub small_proof: BulletproofProofSmall<S, G>,
 }
 
+impl<S: Field + Debug, G: Group<ScalarField = S> + Debug> TranscriptDigest for BulletproofProof<S, G> {}
+
+/// The base case proven without revealing the two remaining values a and b.
+///
+/// With the folded generators g and h, the prover samples r and s and sends
+/// A = r·g + s·h + (r·b + s·a)·u and B = r·s·u, then answers the challenge e with
+/// z1 = r + e·a and z2 = s + e·b. The verifier checks
+///
+/// e·(z1·g + z2·h) + z1·z2·u = B + e·A + e²·P
+///
+/// z1 and z2 are uniform whatever a and b are, so they reveal nothing about them.
+#[derive(Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BulletproofProofSmallZK<S: Field + Debug, G: Group<ScalarField = S> + Debug> {
+    /// The Pedersen commitment: g*a + h*b + u*(a*b)
+    pub pedersen_commitment: G,
+    /// A = r*g + s*h + (r*b + s*a)*u
+    pub a_value: G,
+    /// B = r*s*u
+    pub b_value: G,
+    /// z1 = r + e*a
+    pub z1: S,
+    /// z2 = s + e*b
+    pub z2: S,
+}
+
+/// A bulletproof whose base case is blinded, see `BulletproofProofSmallZK`.
+#[derive(Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BulletproofProofZK<S: Field + Debug, G: Group<ScalarField = S> + Debug> {
+    pub rec_proofs: Vec<(BulletproofRecProof<S, G>, BulletproofVerifierChallenge<S>)>,
+    pub small_proof: BulletproofProofSmallZK<S, G>,
+}
 
+impl<S: Field + Debug, G: Group<ScalarField = S> + Debug> TranscriptDigest for BulletproofProofZK<S, G> {}
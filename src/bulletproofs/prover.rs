@@ -1,10 +1,14 @@
-use ark_ec::Group;
+use ark_ec::{Group, VariableBaseMSM};
 use ark_ff::Field;
+use ark_std::rand::Rng;
 use super::helpers::*;
 use super::BulletproofGenerators;
 use super::BulletproofProofSmall;
+use super::BulletproofProofSmallZK;
 use super::BulletproofRecProof;
+use crate::util::secret::{wipe, Secret};
 
+#[allow(clippy::module_inception)]
 pub mod prover {
     use super::*;
 
@@ -27,10 +31,10 @@ pub mod prover {
     /// demonstrate the correctness of the underlying proof we are computing.
     /// By recursively proving these smaller instances, we can build up to
     /// the full proof while maintaining efficiency and soundness.
-    pub fn prove_rec<S: Field, G: Group<ScalarField = S>>(
+    pub fn prove_rec<S: Field, G: Group<ScalarField = S> + VariableBaseMSM>(
         generators: BulletproofGenerators<G>,
-        v1: Vec<S>,
-        v2: Vec<S>,
+        mut v1: Vec<S>,
+        mut v2: Vec<S>,
     ) -> BulletproofRecProof<S, G> {
         let n = v1.len();
         assert_eq!(n, v2.len(), "Input vectors must have the same length");
@@ -56,6 +60,8 @@ pub mod prover {
         let dot_product = compute_dot_product(&v1, &v2);
 
         let pedersen_commitment = compute_pedersen_commitment(&v1, &v2, dot_product, &generators.g, &generators.h, &generators.u);
+        wipe(&mut v1);
+        wipe(&mut v2);
 
         BulletproofRecProof {
             dot_product,
@@ -95,4 +101,31 @@ pub mod prover {
             pedersen_commitment: g1.mul(x1) + g2.mul(x2) + u.mul(x1 * x2),
         }
     }
+
+    /// Proves knowledge of the single elements x1 and x2 opening the Pedersen commitment under the
+    /// folded generators without revealing them, see `BulletproofProofSmallZK`. The challenge is
+    /// drawn from A and B once they are computed.
+    pub fn prove_small_zk<S: Field, G: Group<ScalarField = S>, R: Rng>(
+        x1: S,
+        x2: S,
+        generators: &BulletproofGenerators<G>,
+        pedersen_commitment: G,
+        challenge: impl FnOnce(&G, &G) -> S,
+        rng: &mut R,
+    ) -> BulletproofProofSmallZK<S, G> {
+        let (r, s) = (Secret::new(S::rand(rng)), Secret::new(S::rand(rng)));
+        let (g, h, u) = (generators.g[0], generators.h[0], generators.u);
+
+        let a_value = g.mul(*r) + h.mul(*s) + u.mul(*r * x2 + *s * x1);
+        let b_value = u.mul(*r * *s);
+        let e = challenge(&a_value, &b_value);
+
+        BulletproofProofSmallZK {
+            pedersen_commitment,
+            a_value,
+            b_value,
+            z1: *r + e * x1,
+            z2: *s + e * x2,
+        }
+    }
 }
\ No newline at end of file
@@ -1,13 +1,16 @@
 use std::marker::PhantomData;
-use ark_ec::Group;
+use ark_ec::{Group, VariableBaseMSM};
 use ark_ff::Field;
+use ark_std::rand::Rng;
 
 use crate::bulletproofs::prover::prover;
-use crate::bulletproofs::verifier_challenger::BulletproofVerifierChallenge;
+use crate::bulletproofs::verifier_challenger::{BulletproofVerifierChallenge, ChallengePowers};
 use crate::bulletproofs::BulletproofRecProof;
+use crate::util::folding::FoldingChallenges;
 use crate::BulletproofSystem;
 
-use super::{helpers::*, BulletproofGenerators, BulletproofProof};
+use super::{helpers::*, BulletproofError, BulletproofGenerators, BulletproofProof, BulletproofProofSmall, BulletproofProofZK};
+use super::context::VerifierContext;
 use super::verifier::verifier;
 use super::verifier_challenger::VerifierChallenger;
 
@@ -17,6 +20,7 @@ where
     G: Group<ScalarField = S> + Clone,
     C: VerifierChallenger<S, G>,
 {
+    /// The challenger in its initial state, cloned for every proof
     pub challenger: C,
     pub _phantom: PhantomData<(S, G)>,
 }
@@ -24,17 +28,33 @@ where
 impl<S, G, C> BulletproofSystem<S, G> for BulletproofSystemImpl<S, G, C>
 where
     S: Field + Clone,
-    G: Group<ScalarField = S> + Clone,
-    C: VerifierChallenger<S, G>,
+    G: Group<ScalarField = S> + VariableBaseMSM + Clone,
+    C: VerifierChallenger<S, G> + Clone,
 {
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "bulletproofs::prove", skip_all, fields(length = v1.len())))]
     fn prove(
         &self,
         generators: BulletproofGenerators<G>,
         v1: Vec<S>,
         v2: Vec<S>,
-    ) -> BulletproofProof<S, G> {
+    ) -> Result<BulletproofProof<S, G>, BulletproofError> {
+        generators.validate()?;
+        let length = v1.len();
+        if length == 0 {
+            return Err(BulletproofError::EmptyVectors);
+        }
+        if v2.len() != length {
+            return Err(BulletproofError::LengthMismatch { v1: length, v2: v2.len() });
+        }
+        if !length.is_power_of_two() {
+            return Err(BulletproofError::NotPowerOfTwo { length });
+        }
+        if generators.g.len() != length || generators.h.len() != length {
+            return Err(BulletproofError::GeneratorCountMismatch { length, g: generators.g.len(), h: generators.h.len() });
+        }
+
         fn prove_recursive<S, G, C>(
-            system: &BulletproofSystemImpl<S, G, C>,
+            challenger: &mut C,
             generators: BulletproofGenerators<G>,
             v1: Vec<S>,
             v2: Vec<S>,
@@ -42,53 +62,284 @@ where
         ) -> BulletproofProof<S, G>
         where
             S: Field + Clone,
-            G: Group<ScalarField = S> + Clone,
+            G: Group<ScalarField = S> + VariableBaseMSM + Clone,
             C: VerifierChallenger<S, G>,
         {
-            if v1.len() == 0 {
-                panic!("Invalid input: v1 and v2 must not be empty");
-            } else if v1.len() == 1 {
+            if v1.len() == 1 {
                 let small_proof = prover::prove_small::<S, G>(v1[0], v2[0], generators.g[0], generators.h[0], generators.u);
                 BulletproofProof {
                     rec_proofs,
                     small_proof,
                 }
             } else {
-                let rec_proof = prover::prove_rec(generators.clone(), v1.clone(), v2.clone());
-                let challenge = system.challenger.generate_challenge(&rec_proof);
-                rec_proofs.push((rec_proof, BulletproofVerifierChallenge { random_challenge: challenge }));
+                let (new_generators, new_v1, new_v2) = {
+                    #[cfg(feature = "tracing")]
+                    let _round = tracing::debug_span!("bulletproofs::round", round = rec_proofs.len(), size = v1.len()).entered();
+
+                    let rec_proof = prover::prove_rec(generators.clone(), v1.clone(), v2.clone());
+                    let challenge = challenger.generate_nonzero_challenge(&rec_proof);
+                    rec_proofs.push((rec_proof, BulletproofVerifierChallenge { random_challenge: challenge }));
 
-                let (new_generators, new_v1, new_v2) = prove_update(BulletproofVerifierChallenge { random_challenge: challenge }, generators, v1, v2);
+                    prove_update(BulletproofVerifierChallenge { random_challenge: challenge }, generators, v1, v2)
+                };
 
-                prove_recursive(system, new_generators, new_v1, new_v2, rec_proofs)
+                prove_recursive(challenger, new_generators, new_v1, new_v2, rec_proofs)
             }
         }
 
-        prove_recursive(self, generators, v1, v2, Vec::new())
+        Ok(prove_recursive(&mut self.challenger.clone(), generators, v1, v2, Vec::new()))
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "bulletproofs::verify", skip_all, fields(rounds = proof.rec_proofs.len())))]
     fn verify(&self, proof: BulletproofProof<S, G>, generators: BulletproofGenerators<G>) -> bool {
-        let current_proof = proof;
-        let mut current_generators = generators;
+        if generators.validate().is_err() {
+            return false;
+        }
+        if proof.rec_proofs.is_empty() {
+            return Self::verify_single(&proof.small_proof, &generators);
+        }
+
+        let Some(challenges) = self.verify_rounds(&mut self.challenger.clone(), &proof.rec_proofs, &proof.small_proof.pedersen_commitment, &generators) else {
+            return false;
+        };
+
+        verifier::verify_small(&proof.small_proof, &fold_generators(&generators, &challenges))
+    }
+}
+
+/// A system with the challenger's default, e.g. a Poseidon sponge with `secure_config` for
+/// `DefaultVerifierChallenger`.
+impl<S, G, C> Default for BulletproofSystemImpl<S, G, C>
+where
+    S: Field + Clone,
+    G: Group<ScalarField = S> + Clone,
+    C: VerifierChallenger<S, G> + Default,
+{
+    fn default() -> Self {
+        Self { challenger: C::default(), _phantom: PhantomData }
+    }
+}
+
+impl<S, G, C> BulletproofSystemImpl<S, G, C>
+where
+    S: Field + Clone,
+    G: Group<ScalarField = S> + VariableBaseMSM + Clone,
+    C: VerifierChallenger<S, G> + Clone,
+{
+    /// Verifies a proof under the generators `BulletproofGenerators::from_seed` derives from the
+    /// seed for vectors of size n.
+    pub fn verify_from_seed(&self, proof: BulletproofProof<S, G>, seed: &[u8], n: usize) -> bool {
+        self.verify(proof, BulletproofGenerators::from_seed(seed, n))
+    }
+
+    /// Verifies a proof under the generators of the context, reusing their folding when an earlier
+    /// proof had the same challenges.
+    pub fn verify_with_context(&self, proof: &BulletproofProof<S, G>, context: &mut VerifierContext<S, G>) -> bool {
+        if context.generators().validate().is_err() {
+            return false;
+        }
+        if proof.rec_proofs.is_empty() {
+            return Self::verify_single(&proof.small_proof, context.generators());
+        }
+
+        let Some(challenges) = self.verify_rounds(&mut self.challenger.clone(), &proof.rec_proofs, &proof.small_proof.pedersen_commitment, context.generators()) else {
+            return false;
+        };
+
+        verifier::verify_small(&proof.small_proof, context.folded_generators(&challenges))
+    }
+
+    /// Verifies a proof like `verify`, with the powers of the challenge of every round given rather
+    /// than computed, so that callers whose proofs share their challenges compute them once. The
+    /// powers must be those of the challenges the challenger draws, one per round, in order.
+    pub fn verify_with_powers(&self, proof: &BulletproofProof<S, G>, generators: &BulletproofGenerators<G>, powers: &[ChallengePowers<S>]) -> bool {
+        if generators.validate().is_err() {
+            return false;
+        }
+        if proof.rec_proofs.is_empty() {
+            return powers.is_empty() && Self::verify_single(&proof.small_proof, generators);
+        }
+
+        let Some(challenges) = self.verify_rounds_with_powers(&mut self.challenger.clone(), &proof.rec_proofs, &proof.small_proof.pedersen_commitment, generators, powers) else {
+            return false;
+        };
+
+        verifier::verify_small(&proof.small_proof, &fold_generators(generators, &challenges))
+    }
+
+    /// Verifies a proof of vectors of a single element, which has no recursive rounds: the small
+    /// proof opens the commitment directly under the original generators.
+    fn verify_single(small_proof: &BulletproofProofSmall<S, G>, generators: &BulletproofGenerators<G>) -> bool {
+        if generators.g.len() != 1 || generators.h.len() != 1 {
+            return false;
+        }
 
-        for i in 0..current_proof.rec_proofs.len() {
-            let (rec_proof, challenge) = &current_proof.rec_proofs[i];
-            let next_commitment = if i + 1 == current_proof.rec_proofs.len() {
-                &current_proof.small_proof.pedersen_commitment
+        verifier::verify_small(small_proof, generators)
+    }
+
+    /// Verifies a proof like `verify`, checking the base case under the original generators with
+    /// the folding and the commitment in a single MSM, see `verifier::verify_small_folded`.
+    pub fn verify_msm(&self, proof: &BulletproofProof<S, G>, generators: &BulletproofGenerators<G>) -> bool {
+        if generators.validate().is_err() {
+            return false;
+        }
+        if proof.rec_proofs.is_empty() {
+            return Self::verify_single(&proof.small_proof, generators);
+        }
+
+        let Some(challenges) = self.verify_rounds(&mut self.challenger.clone(), &proof.rec_proofs, &proof.small_proof.pedersen_commitment, generators) else {
+            return false;
+        };
+
+        verifier::verify_small_folded(&proof.small_proof, generators, &challenges)
+    }
+
+    /// Proves the inner product like `prove`, but blinds the base case so the two remaining values
+    /// are not revealed, see `BulletproofProofSmallZK`.
+    pub fn prove_zk<R: Rng>(
+        &self,
+        generators: BulletproofGenerators<G>,
+        v1: Vec<S>,
+        v2: Vec<S>,
+        rng: &mut R,
+    ) -> Result<BulletproofProofZK<S, G>, BulletproofError> {
+        let BulletproofProof { rec_proofs, small_proof } = self.prove(generators.clone(), v1, v2)?;
+
+        // Replay the rounds to bring the challenger to where the prover's ended
+        let mut challenger = self.challenger.clone();
+        for (rec_proof, _) in &rec_proofs {
+            challenger.generate_nonzero_challenge(rec_proof);
+        }
+        let challenges = FoldingChallenges::new(rec_proofs.iter().map(|(_, challenge)| challenge.random_challenge).collect());
+
+        let small_proof = prover::prove_small_zk(
+            small_proof.value1,
+            small_proof.value2,
+            &fold_generators(&generators, &challenges),
+            small_proof.pedersen_commitment,
+            |a_value, b_value| Self::small_zk_challenge(&mut challenger, small_proof.pedersen_commitment, *a_value, *b_value),
+            rng,
+        );
+        Ok(BulletproofProofZK { rec_proofs, small_proof })
+    }
+
+    /// Verifies a proof with a blinded base case, which only proves knowledge of the remaining
+    /// values instead of revealing them.
+    pub fn verify_zk(&self, proof: &BulletproofProofZK<S, G>, generators: &BulletproofGenerators<G>) -> bool {
+        if generators.validate().is_err() {
+            return false;
+        }
+        let mut challenger = self.challenger.clone();
+        let Some(challenges) = self.verify_rounds(&mut challenger, &proof.rec_proofs, &proof.small_proof.pedersen_commitment, generators) else {
+            return false;
+        };
+
+        let small_proof = &proof.small_proof;
+        let challenge = Self::small_zk_challenge(&mut challenger, small_proof.pedersen_commitment, small_proof.a_value, small_proof.b_value);
+        verifier::verify_small_zk(small_proof, &fold_generators(generators, &challenges), challenge)
+    }
+
+    /// The challenge of a blinded base case, drawn from the challenger as one more round whose L
+    /// and R are A and B, so that it is bound to every round before it.
+    fn small_zk_challenge(challenger: &mut C, pedersen_commitment: G, a_value: G, b_value: G) -> S {
+        challenger.generate_nonzero_challenge(&BulletproofRecProof {
+            dot_product: S::zero(),
+            pedersen_commitment,
+            l_value: a_value,
+            r_value: b_value,
+        })
+    }
+
+    /// Checks the challenges and commitments of the recursive rounds ending at the commitment of the
+    /// base case, returning the challenges. The rounds must fold the generators down to a single pair.
+    fn verify_rounds(
+        &self,
+        challenger: &mut C,
+        rec_proofs: &[(BulletproofRecProof<S, G>, BulletproofVerifierChallenge<S>)],
+        final_commitment: &G,
+        generators: &BulletproofGenerators<G>,
+    ) -> Option<FoldingChallenges<S>> {
+        let size = 1usize.checked_shl(rec_proofs.len() as u32)?;
+        if generators.g.len() != size || generators.h.len() != size {
+            return None;
+        }
+
+        let mut challenges = Vec::with_capacity(rec_proofs.len());
+        let mut rounds_hold = true;
+
+        for i in 0..rec_proofs.len() {
+            let (rec_proof, challenge) = &rec_proofs[i];
+            if challenger.generate_nonzero_challenge(rec_proof) != challenge.random_challenge {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(round = i, "bulletproof challenge mismatch");
+                return None;
+            }
+            let next_commitment = if i + 1 == rec_proofs.len() {
+                final_commitment
             } else {
-                &current_proof.rec_proofs[i + 1].0.pedersen_commitment
+                &rec_proofs[i + 1].0.pedersen_commitment
             };
-            let verification_passed = verifier::verify_rec(rec_proof, challenge, next_commitment);
-            println!("Verification passed: {}", verification_passed);
-            if !verification_passed {
-                return false;
+            let round_holds = verifier::verify_rec(rec_proof, challenge, next_commitment);
+            if cfg!(feature = "ct-verify") {
+                // Every round is checked, so the time taken does not tell which one failed
+                rounds_hold &= round_holds;
+            } else if !round_holds {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(round = i, size = size >> i, "bulletproof round rejected");
+                return None;
             }
 
-            current_generators = update_generators(&current_generators, challenge.random_challenge);
+            challenges.push(challenge.random_challenge);
+        }
+
+        rounds_hold.then(|| FoldingChallenges::new(challenges))
+    }
+
+    /// Checks the rounds like `verify_rounds`, with the powers of their challenges given. Each must
+    /// be the powers of the challenge of its round.
+    fn verify_rounds_with_powers(
+        &self,
+        challenger: &mut C,
+        rec_proofs: &[(BulletproofRecProof<S, G>, BulletproofVerifierChallenge<S>)],
+        final_commitment: &G,
+        generators: &BulletproofGenerators<G>,
+        powers: &[ChallengePowers<S>],
+    ) -> Option<FoldingChallenges<S>> {
+        let size = 1usize.checked_shl(rec_proofs.len() as u32)?;
+        if generators.g.len() != size || generators.h.len() != size || powers.len() != rec_proofs.len() {
+            return None;
         }
 
-        let small_proof = &current_proof.small_proof;
+        let mut challenges = Vec::with_capacity(rec_proofs.len());
+        let mut rounds_hold = true;
+
+        for i in 0..rec_proofs.len() {
+            let (rec_proof, challenge) = &rec_proofs[i];
+            if challenger.generate_nonzero_challenge(rec_proof) != challenge.random_challenge
+                || powers[i].x != challenge.random_challenge
+                || !powers[i].is_consistent()
+            {
+                return None;
+            }
+            let next_commitment = if i + 1 == rec_proofs.len() {
+                final_commitment
+            } else {
+                &rec_proofs[i + 1].0.pedersen_commitment
+            };
+            let round_holds = verifier::verify_rec_with_powers(rec_proof, &powers[i], next_commitment);
+            if cfg!(feature = "ct-verify") {
+                // Every round is checked, so the time taken does not tell which one failed
+                rounds_hold &= round_holds;
+            } else if !round_holds {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(round = i, size = size >> i, "bulletproof round rejected");
+                return None;
+            }
+
+            challenges.push(challenge.random_challenge);
+        }
 
-        verifier::verify_small(&small_proof, &current_generators)
+        let inverses = powers.iter().map(|powers| powers.x_inv).collect();
+        rounds_hold.then(|| FoldingChallenges::with_inverses(challenges, inverses))
     }
 }
@@ -2,20 +2,28 @@
 #![allow(dead_code)]
 
 use ark_ec::Group;
-use ark_ff::{Field, UniformRand};
+use ark_ff::{Field, UniformRand, Zero};
 use ark_bls12_381::{Fr as Scalar, G1Projective as G1};
 use ark_std::rand::thread_rng;
-use crate::bulletproofs::helpers::{compute_dot_product, compute_pedersen_commitment, prove_update};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use crate::util::poseidon::secure_config;
+use crate::bulletproofs::helpers::{compute_dot_product, compute_pedersen_commitment, fold_generators, prove_update};
+use crate::bulletproofs::bls12_381::bls12_381_system;
+use crate::util::folding::FoldingChallenges;
 
 use crate::bulletproofs::{
+    context::VerifierContext,
+    jubjub::{jubjub_system, JubjubScalar},
     prover::prover,
     system::BulletproofSystemImpl,
     verifier::verifier,
-    verifier_challenger::{BulletproofVerifierChallenge, VerifierChallenger},
+    verifier_challenger::{BulletproofVerifierChallenge, ChallengePowers, DefaultVerifierChallenger, VerifierChallenger},
 };
 
-use super::{BulletproofGenerators, BulletproofRecProof, BulletproofSystem};
+use super::{BulletproofError, BulletproofGenerators, BulletproofRecProof, BulletproofSystem, GeneratorError};
+use crate::util::{Sha256Transcript, TranscriptDigest};
 
+#[derive(Clone)]
 pub struct ConstantChallenger<S: Field + Clone> {
     constant: S,
 }
@@ -25,8 +33,32 @@ where
     S: Field + Clone,
     G: Group<ScalarField = S> + Clone,
 {
-    fn generate_challenge(&self, _proof: &BulletproofRecProof<S, G>) -> S {
-        self.constant.clone()
+    fn generate_challenge(&mut self, _proof: &BulletproofRecProof<S, G>) -> S {
+        self.constant
+    }
+}
+
+/// Returns zero for the first candidate challenge of every round, then the constant
+#[derive(Clone)]
+pub struct ZeroFirstChallenger<S: Field + Clone> {
+    constant: S,
+}
+
+impl<S, G> VerifierChallenger<S, G> for ZeroFirstChallenger<S>
+where
+    S: Field + Clone,
+    G: Group<ScalarField = S> + Clone,
+{
+    fn generate_challenge(&mut self, _proof: &BulletproofRecProof<S, G>) -> S {
+        S::zero()
+    }
+
+    fn generate_challenge_with_counter(&mut self, proof: &BulletproofRecProof<S, G>, counter: u64) -> S {
+        if counter == 0 {
+            VerifierChallenger::<S, G>::generate_challenge(self, proof)
+        } else {
+            self.constant
+        }
     }
 }
 
@@ -45,12 +77,7 @@ fn generate_random_vectors(size: usize) -> (Vec<Scalar>, Vec<Scalar>) {
 }
 
 fn setup_generators(size: usize) -> BulletproofGenerators<G1> {
-    let mut rng = thread_rng();
-    BulletproofGenerators {
-        g: (0..size).map(|_| G1::rand(&mut rng)).collect(),
-        h: (0..size).map(|_| G1::rand(&mut rng)).collect(),
-        u: G1::rand(&mut rng),
-    }
+    BulletproofGenerators::random(size, &mut thread_rng())
 }
 
 
@@ -83,7 +110,7 @@ fn test_prove_verify_rec_one_round() {
     let proof = prover::prove_rec(generators.clone(), v1.clone(), v2.clone());
 
     // Generate challenge
-    let challenger = ConstantChallenger { constant: Scalar::from(2) };
+    let mut challenger = ConstantChallenger { constant: Scalar::from(2) };
     let challenge = challenger.generate_challenge(&proof);
 
     // We should now be able to compute the pedersen commitment
@@ -147,7 +174,7 @@ fn test_prove_verify_rec_matches_mathematical_statement() {
     let proof = prover::prove_rec(generators.clone(), v1.clone(), v2.clone());
 
     // Generate challenge
-    let challenger = ConstantChallenger { constant: Scalar::from(2) };
+    let mut challenger = ConstantChallenger { constant: Scalar::from(2) };
     let challenge = challenger.generate_challenge(&proof);
 
 
@@ -198,7 +225,7 @@ fn test_prove_verify_rec_two_rounds() {
     let proof_size_8 = prover::prove_rec(generators_size_8.clone(), v1_size_8.clone(), v2_size_8.clone());
 
     // Generate challenge
-    let challenger = ConstantChallenger { constant: Scalar::from(2) };
+    let mut challenger = ConstantChallenger { constant: Scalar::from(2) };
     let challenge = challenger.generate_challenge(&proof_size_8);
 
     // Update the next round of generators, v1, v2
@@ -226,11 +253,48 @@ fn test_prove_verify_dot_product_size_1() {
     let (v1, v2) = generate_random_vectors(1);
     let generators = setup_generators(1);
 
-    let proof = system.prove(generators.clone(), v1.clone(), v2.clone());
+    let proof = system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
     println!("Proof: {:?}", proof);
     assert!(system.verify(proof, generators));
 }
 
+#[test]
+fn test_tampered_size_1_proof_rejected() {
+    let system = setup_system(Scalar::from(1));
+    let (v1, v2) = generate_random_vectors(1);
+    let generators = setup_generators(1);
+    let prove = || system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
+
+    let proof = prove();
+    assert!(proof.rec_proofs.is_empty());
+    assert!(system.verify(proof, generators.clone()));
+
+    let mut proof = prove();
+    proof.small_proof.value1 += Scalar::from(1u64);
+    assert!(!system.verify(proof, generators.clone()));
+
+    let mut proof = prove();
+    proof.small_proof.dot_product += Scalar::from(1u64);
+    assert!(!system.verify(proof, generators.clone()));
+
+    // Opening a different commitment under the same generators
+    let mut proof = prove();
+    proof.small_proof.pedersen_commitment += generators.u;
+    assert!(!system.verify(proof, generators.clone()));
+
+    // A commitment to a wrong dot product, <2, 3> = 7, opens consistently but is rejected
+    let mut proof = prove();
+    let (v1, v2, forged) = (Scalar::from(2u64), Scalar::from(3u64), Scalar::from(7u64));
+    proof.small_proof.value1 = v1;
+    proof.small_proof.value2 = v2;
+    proof.small_proof.dot_product = forged;
+    proof.small_proof.pedersen_commitment = generators.g[0] * v1 + generators.h[0] * v2 + generators.u * forged;
+    assert!(!system.verify(proof, generators.clone()));
+
+    // A proof without rounds only opens single-element generators
+    assert!(!system.verify(prove(), setup_generators(2)));
+}
+
 #[test]
 fn test_prove_verify_dot_product_size_2() {
     let constant_challenge = Scalar::from(2);  // You can change this to any constant you want
@@ -238,7 +302,7 @@ fn test_prove_verify_dot_product_size_2() {
     let (v1, v2) = generate_random_vectors(2);
     let generators = setup_generators(2);
 
-    let proof = system.prove(generators.clone(), v1.clone(), v2.clone());
+    let proof = system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
     println!("Proof: {:?}", proof);
     assert!(system.verify(proof, generators));
 }
@@ -250,7 +314,7 @@ fn test_prove_verify_dot_product_size_4() {
     let (v1, v2) = generate_random_vectors(4);
     let generators = setup_generators(4);
 
-    let proof = system.prove(generators.clone(), v1.clone(), v2.clone());
+    let proof = system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
     assert!(system.verify(proof, generators));
 }
 
@@ -261,7 +325,415 @@ fn test_prove_verify_dot_product_size_8() {
     let (v1, v2) = generate_random_vectors(8);
     let generators = setup_generators(8);
 
-    let proof = system.prove(generators.clone(), v1.clone(), v2.clone());
+    let proof = system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
+    assert!(system.verify(proof, generators));
+}
+
+
+#[test]
+fn test_prove_verify_skips_zero_challenge() {
+    let system = BulletproofSystemImpl::<Scalar, G1, ZeroFirstChallenger<Scalar>> {
+        challenger: ZeroFirstChallenger { constant: Scalar::from(3) },
+        _phantom: std::marker::PhantomData,
+    };
+    let (v1, v2) = generate_random_vectors(8);
+    let generators = setup_generators(8);
+
+    let proof = system.prove(generators.clone(), v1, v2).unwrap();
+    assert_eq!(proof.rec_proofs.len(), 3);
+    for (_, challenge) in &proof.rec_proofs {
+        assert_eq!(challenge.random_challenge, Scalar::from(3));
+    }
+    assert!(system.verify(proof, generators));
+}
+
+fn poseidon_config() -> PoseidonConfig<Scalar> {
+    secure_config::<Scalar>(2, 1)
+}
+
+#[test]
+fn test_sponge_challenger_binds_previous_rounds() {
+    let generators = setup_generators(4);
+    let (v1, v2) = generate_random_vectors(4);
+    let (other_v1, other_v2) = generate_random_vectors(4);
+    let round_1 = prover::prove_rec(generators.clone(), v1, v2);
+    let other_round_1 = prover::prove_rec(generators.clone(), other_v1, other_v2);
+    let round_2 = prover::prove_rec(setup_generators(2), vec![Scalar::from(1u64), Scalar::from(2u64)], vec![Scalar::from(3u64), Scalar::from(4u64)]);
+
+    let mut challenger = DefaultVerifierChallenger::new(&poseidon_config());
+    let mut other_challenger = challenger.clone();
+    VerifierChallenger::<Scalar, G1>::generate_challenge(&mut challenger, &round_1);
+    VerifierChallenger::<Scalar, G1>::generate_challenge(&mut other_challenger, &other_round_1);
+
+    // The same second round gets different challenges after different first rounds
+    let challenge_2 = challenger.generate_challenge(&round_2);
+    let other_challenge_2 = other_challenger.generate_challenge(&round_2);
+    assert_ne!(challenge_2, other_challenge_2);
+
+    // and replaying the same transcript gives the same challenge
+    let mut replay = DefaultVerifierChallenger::new(&poseidon_config());
+    VerifierChallenger::<Scalar, G1>::generate_challenge(&mut replay, &round_1);
+    assert_eq!(replay.generate_challenge(&round_2), challenge_2);
+}
+
+#[test]
+fn test_prove_verify_with_sponge_challenger() {
+    let system = BulletproofSystemImpl::<Scalar, G1, DefaultVerifierChallenger<Scalar>> {
+        challenger: DefaultVerifierChallenger::new(&poseidon_config()),
+        _phantom: std::marker::PhantomData,
+    };
+    let (v1, v2) = generate_random_vectors(8);
+    let generators = setup_generators(8);
+
+    let proof = system.prove(generators.clone(), v1, v2).unwrap();
     assert!(system.verify(proof, generators));
 }
 
+#[test]
+fn test_prover_and_verifier_draw_the_same_challenges_from_a_transcript() {
+    let system = BulletproofSystemImpl::<Scalar, G1, DefaultVerifierChallenger<Scalar, Sha256Transcript>> {
+        challenger: DefaultVerifierChallenger::with_transcript(Sha256Transcript::new(b"bulletproof test")),
+        _phantom: std::marker::PhantomData,
+    };
+    let (v1, v2) = generate_random_vectors(8);
+    let generators = setup_generators(8);
+    let proof = system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
+
+    // Replaying the rounds on a fresh challenger gives the prover's challenges
+    let mut replay = system.challenger.clone();
+    let challenges: Vec<Scalar> = proof.rec_proofs.iter().map(|(round, _)| replay.generate_nonzero_challenge(round)).collect();
+    let prover_challenges: Vec<Scalar> = proof.rec_proofs.iter().map(|(_, challenge)| challenge.random_challenge).collect();
+    assert_eq!(challenges, prover_challenges);
+    assert!(system.verify(proof, generators.clone()));
+
+    // A Poseidon transcript draws other challenges for the same rounds
+    let poseidon_system = BulletproofSystemImpl::<Scalar, G1, DefaultVerifierChallenger<Scalar>> {
+        challenger: DefaultVerifierChallenger::new(&poseidon_config()),
+        _phantom: std::marker::PhantomData,
+    };
+    let poseidon_proof = poseidon_system.prove(generators.clone(), v1, v2).unwrap();
+    assert_ne!(poseidon_proof.rec_proofs[0].1.random_challenge, prover_challenges[0]);
+    assert!(!system.verify(poseidon_proof, generators));
+}
+
+#[test]
+fn test_prove_verify_zk() {
+    let system = BulletproofSystemImpl::<Scalar, G1, DefaultVerifierChallenger<Scalar>> {
+        challenger: DefaultVerifierChallenger::new(&poseidon_config()),
+        _phantom: std::marker::PhantomData,
+    };
+    let mut rng = thread_rng();
+
+    for size in [1, 8] {
+        let (v1, v2) = generate_random_vectors(size);
+        let generators = setup_generators(size);
+
+        let proof = system.prove_zk(generators.clone(), v1.clone(), v2.clone(), &mut rng).unwrap();
+        assert!(system.verify_zk(&proof, &generators));
+
+        // The responses are fresh for every proof of the same vectors
+        let mut other = system.prove_zk(generators.clone(), v1, v2, &mut rng).unwrap();
+        assert!(system.verify_zk(&other, &generators));
+        assert_ne!(proof.small_proof.z1, other.small_proof.z1);
+
+        other.small_proof.z1 += Scalar::from(1u64);
+        assert!(!system.verify_zk(&other, &generators));
+
+        let mut tampered = proof;
+        tampered.small_proof.z2 = Scalar::rand(&mut rng);
+        assert!(!system.verify_zk(&tampered, &generators));
+    }
+}
+
+#[test]
+fn test_transcript_digest_changes_with_the_proof() {
+    let system = setup_system(Scalar::from(3u64));
+    let (v1, v2) = generate_random_vectors(4);
+    let generators = setup_generators(4);
+    let prove = || system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
+
+    let digest = prove().transcript_digest();
+    assert_eq!(digest, prove().transcript_digest());
+
+    let mut proof = prove();
+    proof.small_proof.value1 += Scalar::from(1u64);
+    assert_ne!(proof.transcript_digest(), digest);
+    let mut proof = prove();
+    proof.rec_proofs[1].0.l_value += generators.u;
+    assert_ne!(proof.transcript_digest(), digest);
+    let mut proof = prove();
+    proof.rec_proofs[0].1.random_challenge = Scalar::from(4u64);
+    assert_ne!(proof.transcript_digest(), digest);
+}
+
+#[test]
+fn test_verify_from_seed() {
+    let system = setup_system(Scalar::from(3u64));
+    let (v1, v2) = generate_random_vectors(8);
+
+    let generators = BulletproofGenerators::<G1>::from_seed(b"seed", 8);
+    assert_eq!(generators.g, BulletproofGenerators::<G1>::from_seed(b"seed", 8).g);
+    assert_ne!(generators.g, BulletproofGenerators::<G1>::from_seed(b"other seed", 8).g);
+
+    let proof = system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
+    let same_proof = system.prove(generators.clone(), v1, v2).unwrap();
+    assert!(system.verify(proof, generators));
+    assert!(system.verify_from_seed(same_proof, b"seed", 8));
+
+    let (v1, v2) = generate_random_vectors(8);
+    let proof = system.prove(setup_generators(8), v1, v2).unwrap();
+    assert!(!system.verify_from_seed(proof, b"seed", 8));
+}
+
+#[test]
+fn test_verify_with_shared_context() {
+    let system = setup_system(Scalar::from(5));
+    let generators = setup_generators(8);
+    let mut context = VerifierContext::new(generators.clone());
+
+    for i in 0..100 {
+        let (v1, v2) = generate_random_vectors(8);
+        let mut proof = system.prove(generators.clone(), v1, v2).unwrap();
+        if i % 10 == 0 {
+            proof.small_proof.dot_product += Scalar::from(1u64);
+        }
+
+        let with_context = system.verify_with_context(&proof, &mut context);
+        assert_eq!(with_context, i % 10 != 0);
+        assert_eq!(with_context, system.verify(proof, generators.clone()));
+    }
+    // The constant challenger gives every proof the same challenges
+    assert_eq!(context.fold_count(), 1);
+
+    // New generators are folded again
+    let other_generators = setup_generators(8);
+    context.set_generators(other_generators.clone());
+    let (v1, v2) = generate_random_vectors(8);
+    let proof = system.prove(other_generators, v1, v2).unwrap();
+    assert!(system.verify_with_context(&proof, &mut context));
+    assert_eq!(context.fold_count(), 2);
+}
+
+#[test]
+fn test_verify_with_precomputed_powers() {
+    let system = setup_system(Scalar::from(2));
+    let generators = setup_generators(8);
+    let powers = vec![ChallengePowers::new(Scalar::from(2)).unwrap(); 3];
+    assert_eq!(powers[0].x_inv_square, Scalar::from(4u64).inverse().unwrap());
+
+    for i in 0..10 {
+        let (v1, v2) = generate_random_vectors(8);
+        let mut proof = system.prove(generators.clone(), v1, v2).unwrap();
+        if i % 3 == 0 {
+            proof.rec_proofs[1].0.l_value += G1::generator();
+        }
+
+        let with_powers = system.verify_with_powers(&proof, &generators, &powers);
+        assert_eq!(with_powers, i % 3 != 0);
+        assert_eq!(with_powers, system.verify(proof, generators.clone()));
+    }
+
+    // The powers must be consistent, of the challenges drawn, and one per round
+    let (v1, v2) = generate_random_vectors(8);
+    let proof = system.prove(generators.clone(), v1, v2).unwrap();
+    let mut inconsistent = powers.clone();
+    inconsistent[2].x_inv_square = Scalar::from(4u64);
+    assert!(!system.verify_with_powers(&proof, &generators, &inconsistent));
+    let mut other_challenge = powers.clone();
+    other_challenge[0] = ChallengePowers::new(Scalar::from(3)).unwrap();
+    assert!(!system.verify_with_powers(&proof, &generators, &other_challenge));
+    assert!(!system.verify_with_powers(&proof, &generators, &powers[..2]));
+}
+
+#[test]
+fn test_prove_verify_over_jubjub() {
+    let mut rng = thread_rng();
+    let poseidon_config = secure_config::<JubjubScalar>(2, 1);
+    let system = jubjub_system(&poseidon_config);
+    let generators = BulletproofGenerators::<ark_ed_on_bls12_381::EdwardsProjective>::random(4, &mut rng);
+
+    let v1: Vec<JubjubScalar> = (1..=4u64).map(JubjubScalar::from).collect();
+    let v2: Vec<JubjubScalar> = (5..=8u64).map(JubjubScalar::from).collect();
+    let proof = system.prove(generators.clone(), v1, v2).unwrap();
+    assert_eq!(proof.rec_proofs[0].0.dot_product, JubjubScalar::from(70u64));
+    assert!(system.verify(proof, generators.clone()));
+
+    let (v1, v2): (Vec<JubjubScalar>, Vec<JubjubScalar>) = (0..4).map(|_| (JubjubScalar::rand(&mut rng), JubjubScalar::rand(&mut rng))).unzip();
+    let mut proof = system.prove(generators.clone(), v1, v2).unwrap();
+    proof.small_proof.value1 += JubjubScalar::from(1u64);
+    assert!(!system.verify(proof, generators));
+}
+
+#[test]
+fn test_identity_generators_are_rejected() {
+    let system = setup_system(Scalar::from(3));
+    let (v1, v2) = generate_random_vectors(4);
+    let generators = setup_generators(4);
+    assert_eq!(generators.validate(), Ok(()));
+    let proof = || system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
+    assert!(system.verify(proof(), generators.clone()));
+
+    let mut identity_g = generators.clone();
+    identity_g.g[2] = G1::zero();
+    assert_eq!(identity_g.validate(), Err(GeneratorError::IdentityG { index: 2 }));
+    let mut identity_h = generators.clone();
+    identity_h.h[0] = G1::zero();
+    assert_eq!(identity_h.validate(), Err(GeneratorError::IdentityH { index: 0 }));
+    let mut identity_u = generators.clone();
+    identity_u.u = G1::zero();
+    assert_eq!(identity_u.validate(), Err(GeneratorError::IdentityU));
+
+    for degenerate in [identity_g, identity_h, identity_u] {
+        assert!(!system.verify(proof(), degenerate.clone()));
+        let mut context = VerifierContext::new(degenerate.clone());
+        assert!(!system.verify_with_context(&proof(), &mut context));
+
+        // Proving under them is a misuse
+        let error = degenerate.validate().unwrap_err();
+        assert_eq!(
+            system.prove(degenerate.clone(), v1.clone(), v2.clone()).unwrap_err(),
+            BulletproofError::InvalidGenerators(error)
+        );
+    }
+}
+
+#[test]
+fn test_check_independence() {
+    let mut rng = thread_rng();
+    let generators = setup_generators(8);
+    assert!(generators.check_independence(&mut rng));
+    assert!(BulletproofGenerators::<G1>::from_seed(b"nemesis", 16).check_independence(&mut rng));
+
+    // A point shared by g and h, a point repeated within g and a negated point are dependent
+    let mut shared = generators.clone();
+    shared.h[3] = shared.g[5];
+    let mut repeated = generators.clone();
+    repeated.g[1] = repeated.g[0];
+    let mut negated = generators.clone();
+    negated.u = -negated.h[7];
+    let mut identity = generators.clone();
+    identity.g[4] = G1::zero();
+    for dependent in [shared, repeated, negated, identity] {
+        assert!((0..16).all(|_| !dependent.check_independence(&mut rng)));
+    }
+}
+
+#[test]
+fn test_malformed_inputs_are_rejected() {
+    let system = setup_system(Scalar::from(3));
+    let (v1, v2) = generate_random_vectors(4);
+
+    assert_eq!(system.prove(setup_generators(4), Vec::new(), Vec::new()).unwrap_err(), BulletproofError::EmptyVectors);
+    assert_eq!(
+        system.prove(setup_generators(4), v1.clone(), v2[..2].to_vec()).unwrap_err(),
+        BulletproofError::LengthMismatch { v1: 4, v2: 2 }
+    );
+    assert_eq!(
+        system.prove(setup_generators(3), v1[..3].to_vec(), v2[..3].to_vec()).unwrap_err(),
+        BulletproofError::NotPowerOfTwo { length: 3 }
+    );
+    assert_eq!(
+        system.prove(setup_generators(8), v1.clone(), v2.clone()).unwrap_err(),
+        BulletproofError::GeneratorCountMismatch { length: 4, g: 8, h: 8 }
+    );
+    assert!(system.prove(setup_generators(4), v1, v2).is_ok());
+}
+
+#[test]
+fn test_msm_verify_matches_naive() {
+    let system = bls12_381_system(&poseidon_config());
+
+    for size in [8, 16] {
+        let generators = setup_generators(size);
+        let (v1, v2) = generate_random_vectors(size);
+        let prove = || system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
+        let proof = prove();
+        assert!(system.verify_msm(&proof, &generators));
+        assert!(!system.verify_msm(&proof, &setup_generators(size)));
+
+        // The single MSM recomputes the commitment the folded generators give
+        let challenges = FoldingChallenges::new(proof.rec_proofs.iter().map(|(_, challenge)| challenge.random_challenge).collect());
+        let folded = fold_generators(&generators, &challenges);
+        let small = &proof.small_proof;
+        assert_eq!(
+            folded.g[0] * small.value1 + folded.h[0] * small.value2 + folded.u * small.dot_product,
+            small.pedersen_commitment
+        );
+        assert!(system.verify(proof, generators.clone()));
+
+        // Both reject a tampered base case or round
+        let mut tampered = prove();
+        tampered.small_proof.value2 += Scalar::from(1u64);
+        assert!(!system.verify_msm(&tampered, &generators));
+        assert!(!system.verify(tampered, generators.clone()));
+        let mut tampered = prove();
+        tampered.rec_proofs[1].0.l_value += G1::generator();
+        assert!(!system.verify_msm(&tampered, &generators));
+        assert!(!system.verify(tampered, generators.clone()));
+    }
+}
+
+#[test]
+fn test_msm_verify_rejects_wrong_dot_product() {
+    let system = bls12_381_system(&poseidon_config());
+    let generators = setup_generators(8);
+    let (v1, v2) = generate_random_vectors(8);
+    let proof = system.prove(generators.clone(), v1, v2).unwrap();
+    let challenges = FoldingChallenges::new(proof.rec_proofs.iter().map(|(_, challenge)| challenge.random_challenge).collect());
+    let folded = fold_generators(&generators, &challenges);
+
+    // A base case committing to <2, 3> = 7 under the folded generators opens consistently, but
+    // its dot product is not the product of its values
+    let mut forged = proof.small_proof;
+    let (value1, value2) = (Scalar::from(2u64), Scalar::from(3u64));
+    forged.value1 = value1;
+    forged.value2 = value2;
+    forged.dot_product = Scalar::from(7u64);
+    forged.pedersen_commitment = folded.g[0] * value1 + folded.h[0] * value2 + folded.u * forged.dot_product;
+    assert!(!verifier::verify_small_folded(&forged, &generators, &challenges));
+    assert!(!verifier::verify_small(&forged, &folded));
+
+    // The same base case with the true dot product is accepted by both
+    forged.dot_product = value1 * value2;
+    forged.pedersen_commitment = folded.g[0] * value1 + folded.h[0] * value2 + folded.u * forged.dot_product;
+    assert!(verifier::verify_small_folded(&forged, &generators, &challenges));
+    assert!(verifier::verify_small(&forged, &folded));
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_parallel_folds_match_serial() {
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    let mut rng = ChaCha20Rng::seed_from_u64(1464);
+    let generators = BulletproofGenerators::<G1>::random(64, &mut rng);
+    let v1: Vec<Scalar> = (0..64).map(|_| Scalar::rand(&mut rng)).collect();
+    let v2: Vec<Scalar> = (0..64).map(|_| Scalar::rand(&mut rng)).collect();
+
+    let mut dot_product = Scalar::zero();
+    let mut commitment = G1::zero();
+    for i in 0..64 {
+        dot_product += v1[i] * v2[i];
+        commitment += generators.g[i] * v1[i] + generators.h[i] * v2[i];
+    }
+    commitment += generators.u * dot_product;
+    assert_eq!(compute_dot_product(&v1, &v2), dot_product);
+    assert_eq!(compute_pedersen_commitment(&v1, &v2, dot_product, &generators.g, &generators.h, &generators.u), commitment);
+
+    let x = Scalar::rand(&mut rng);
+    let x_inv = x.inverse().unwrap();
+    let (folded_generators, folded_v1, folded_v2) =
+        prove_update(BulletproofVerifierChallenge { random_challenge: x }, generators.clone(), v1.clone(), v2.clone());
+    for i in 0..32 {
+        assert_eq!(folded_v1[i], v1[i] * x + v1[i + 32] * x_inv);
+        assert_eq!(folded_v2[i], v2[i] * x_inv + v2[i + 32] * x);
+        assert_eq!(folded_generators.g[i], generators.g[i] * x_inv + generators.g[i + 32] * x);
+        assert_eq!(folded_generators.h[i], generators.h[i] * x + generators.h[i + 32] * x_inv);
+    }
+
+    // Proofs from the same seed are the same whichever thread computed each term
+    let system = setup_system(Scalar::from(3));
+    let prove = || system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap().transcript_digest();
+    assert_eq!(prove(), prove());
+}
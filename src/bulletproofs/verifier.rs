@@ -1,8 +1,12 @@
-use ark_ec::Group;
+use ark_ec::{Group, VariableBaseMSM};
 use ark_ff::Field;
 
+#[allow(clippy::module_inception)]
 pub mod verifier {
-    use crate::bulletproofs::{BulletproofGenerators, BulletproofProofSmall, BulletproofRecProof, BulletproofVerifierChallenge};
+    use crate::bulletproofs::verifier_challenger::ChallengePowers;
+    use crate::bulletproofs::{BulletproofGenerators, BulletproofProofSmall, BulletproofProofSmallZK, BulletproofRecProof, BulletproofVerifierChallenge};
+    use crate::util::ct::ct_eq;
+    use crate::util::folding::FoldingChallenges;
 
     use super::*;
 
@@ -23,24 +27,35 @@ pub mod verifier {
         challenge: &BulletproofVerifierChallenge<S>,
         next_commitment: &G
     ) -> bool {
-        let x = challenge.random_challenge;
-        let x_inv = x.inverse().expect("Challenge should be non-zero");
-    
+        match ChallengePowers::new(challenge.random_challenge) {
+            Some(powers) => verify_rec_with_powers(proof, &powers, next_commitment),
+            None => false,
+        }
+    }
+
+    /// Verifies a recursive Bulletproof like `verify_rec`, with the powers x^2 and x^(-2) of the
+    /// challenge already computed.
+    pub fn verify_rec_with_powers<S: Field, G: Group<ScalarField = S>>(
+        proof: &BulletproofRecProof<S, G>,
+        powers: &ChallengePowers<S>,
+        next_commitment: &G
+    ) -> bool {
         // Compute P' = x^2 * L_0 + x^(-2) * R_0 + P
-        let computed_commitment = proof.l_value.mul(x.square())
-            + proof.r_value.mul(x_inv.square())
+        let computed_commitment = proof.l_value.mul(powers.x_square)
+            + proof.r_value.mul(powers.x_inv_square)
             + proof.pedersen_commitment;
     
         // Check if the computed commitment matches the next commitment in the chain
-        computed_commitment == *next_commitment
+        ct_eq(&computed_commitment, next_commitment)
     }
 
     /// Verifies a small Bulletproof for the base case of a single scalar multiplication.
     ///
     /// This function checks the validity of a BulletproofProofSmall by:
     /// 1. Ensuring the generators are of size 1.
-    /// 2. Computing a Pedersen commitment using the provided values and generators.
-    /// 3. Comparing the computed commitment with the one in the proof.
+    /// 2. Checking that the claimed dot product is the product of the two values.
+    /// 3. Computing a Pedersen commitment using the provided values and generators.
+    /// 4. Comparing the computed commitment with the one in the proof.
     ///
     /// # Arguments
     /// * `proof` - The small Bulletproof to verify.
@@ -55,10 +70,64 @@ pub mod verifier {
         let g_value = generators.g[0];
         let h_value = generators.h[0];
 
+        // The commitment binds the dot product, but only this ties it to the values
+        let dot_product_holds = ct_eq(&proof.dot_product, &(proof.value1 * proof.value2));
+
         // Compute the Pedersen commitment
         let computed_commitment = g_value.mul(proof.value1) + h_value.mul(proof.value2) + generators.u.mul(proof.dot_product);
 
         // Check if the computed commitment matches the one in the proof
-        computed_commitment == proof.pedersen_commitment
+        dot_product_holds & ct_eq(&computed_commitment, &proof.pedersen_commitment)
+    }
+
+    /// Verifies the base case under the original generators folded with every challenge, like
+    /// `verify_small` after `fold_generators`, but with the folding and the commitment in a single
+    /// multi-scalar multiplication:
+    ///
+    /// P = Σ (s_i v1) g_i + Σ (s_i^-1 v2) h_i + <v1, v2> u
+    ///
+    /// where s is the s-vector of the challenges, whose inverse is s reversed. Its 2n + 1 terms
+    /// share the doublings of Pippenger's bucket method rather than being two MSMs of size n and
+    /// three scalar multiplications by full-size scalars, which is most of the verifier's time
+    /// for large n.
+    ///
+    /// As in `verify_small`, the claimed dot product must also be the product of the two values.
+    pub fn verify_small_folded<S: Field, G: Group<ScalarField = S> + VariableBaseMSM>(
+        proof: &BulletproofProofSmall<S, G>,
+        generators: &BulletproofGenerators<G>,
+        challenges: &FoldingChallenges<S>,
+    ) -> bool {
+        let s = challenges.s_vector();
+        if generators.g.len() != s.len() || generators.h.len() != s.len() {
+            return false;
+        }
+        let dot_product_holds = ct_eq(&proof.dot_product, &(proof.value1 * proof.value2));
+
+        let scalars: Vec<S> = s
+            .iter()
+            .map(|s| *s * proof.value1)
+            .chain(s.iter().rev().map(|s_inv| *s_inv * proof.value2))
+            .chain(std::iter::once(proof.dot_product))
+            .collect();
+        let bases: Vec<G> = generators.g.iter().chain(&generators.h).chain(std::iter::once(&generators.u)).copied().collect();
+
+        let commitment_holds = G::msm(&G::batch_convert_to_mul_base(&bases), &scalars).is_ok_and(|commitment| ct_eq(&commitment, &proof.pedersen_commitment));
+        dot_product_holds & commitment_holds
+    }
+
+    /// Verifies a blinded base case under the folded generators and the challenge e drawn from A
+    /// and B, checking e*(z1*g + z2*h) + z1*z2*u = B + e*A + e^2*P.
+    pub fn verify_small_zk<S: Field, G: Group<ScalarField = S>>(
+        proof: &BulletproofProofSmallZK<S, G>,
+        generators: &BulletproofGenerators<G>,
+        challenge: S,
+    ) -> bool {
+        if generators.g.len() != 1 || generators.h.len() != 1 {
+            return false;
+        }
+
+        let lhs = (generators.g[0].mul(proof.z1) + generators.h[0].mul(proof.z2)).mul(challenge) + generators.u.mul(proof.z1 * proof.z2);
+        let rhs = proof.b_value + proof.a_value.mul(challenge) + proof.pedersen_commitment.mul(challenge.square());
+        ct_eq(&lhs, &rhs)
     }
 }
\ No newline at end of file
@@ -1,58 +1,115 @@
 use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
-use ark_crypto_primitives::sponge::poseidon::PoseidonSponge;
 use ark_crypto_primitives::sponge::Absorb;
-use ark_crypto_primitives::sponge::CryptographicSponge;
-use ark_ec::AffineRepr;
-use ark_ec::CurveGroup;
 use ark_ec::Group;
 use ark_ff::Field;
 use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use crate::util::poseidon::secure_config;
+use crate::util::{first_nonzero_challenge, PoseidonTranscript, Transcript};
 
 use super::BulletproofRecProof;
 
-pub struct DefaultVerifierChallenger;
+/// Fiat-Shamir challenger backed by a single transcript that absorbs every round, so each
+/// challenge is bound to all the rounds before it. A Poseidon sponge unless built with
+/// `with_transcript`.
+#[derive(Clone)]
+pub struct DefaultVerifierChallenger<S: PrimeField, T = PoseidonTranscript<S>> {
+    transcript: T,
+    _phantom: PhantomData<S>,
+}
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct BulletproofVerifierChallenge<S: Field + Clone> {
     pub random_challenge: S,
 }
 
+/// A round's challenge x with the powers the verifier folds its commitment with: x^-1, x^2 and
+/// x^-2. Computed once, they can be reused by every proof that has the same challenge, such as
+/// those of a constant challenger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChallengePowers<S: Field> {
+    pub x: S,
+    pub x_inv: S,
+    pub x_square: S,
+    pub x_inv_square: S,
+}
+
+impl<S: Field> ChallengePowers<S> {
+    /// The powers of the challenge, or `None` for zero, which has no inverse.
+    pub fn new(x: S) -> Option<Self> {
+        let x_inv = x.inverse()?;
+        Some(Self { x, x_inv, x_square: x.square(), x_inv_square: x_inv.square() })
+    }
+
+    /// Whether the powers are those of x, checked without inverting it.
+    pub fn is_consistent(&self) -> bool {
+        self.x * self.x_inv == S::one() && self.x_square == self.x.square() && self.x_inv_square == self.x_inv.square()
+    }
+}
+
+/// Generates the challenges of a bulletproof, one per recursive round, in order.
+///
+/// Challengers may keep a transcript across calls, so `prove` and `verify` each run on a fresh
+/// clone of the system's challenger.
 pub trait VerifierChallenger<S: Field + Clone, G: Group<ScalarField = S> + Clone> {
-    fn generate_challenge(&self, proof: &BulletproofRecProof<S, G>) -> S;
+    fn generate_challenge(&mut self, proof: &BulletproofRecProof<S, G>) -> S;
+
+    /// Derives the `counter`-th candidate challenge for a round, the first being the plain challenge.
+    fn generate_challenge_with_counter(&mut self, proof: &BulletproofRecProof<S, G>, counter: u64) -> S {
+        self.generate_challenge(proof) + S::from(counter)
+    }
+
+    /// Generates a challenge that is guaranteed to be invertible, as the generator and vector updates require.
+    fn generate_nonzero_challenge(&mut self, proof: &BulletproofRecProof<S, G>) -> S {
+        first_nonzero_challenge(|counter| self.generate_challenge_with_counter(proof, counter))
+    }
 }
 
-impl<S, G> VerifierChallenger<S, G> for DefaultVerifierChallenger
+impl<S: PrimeField + Absorb> DefaultVerifierChallenger<S> {
+    pub fn new(params: &PoseidonConfig<S>) -> Self {
+        Self::with_transcript(PoseidonTranscript::new(params))
+    }
+}
+
+/// A Poseidon sponge with `secure_config` of rate 2.
+impl<S: PrimeField + Absorb> Default for DefaultVerifierChallenger<S> {
+    fn default() -> Self {
+        Self::new(&secure_config(2, 1))
+    }
+}
+
+impl<S: PrimeField, T: Transcript<S>> DefaultVerifierChallenger<S, T> {
+    /// Draws the challenges from the transcript, which may already hold the messages of a larger
+    /// protocol the bulletproof is part of.
+    pub fn with_transcript(transcript: T) -> Self {
+        Self { transcript, _phantom: PhantomData }
+    }
+}
+
+impl<S, G, T> VerifierChallenger<S, G> for DefaultVerifierChallenger<S, T>
 where
-    S: PrimeField + Absorb + Clone,
-    G: CurveGroup<ScalarField = S, BaseField = S> + Clone,
-    G::Affine: Absorb ,
+    S: PrimeField,
+    G: Group<ScalarField = S> + Clone,
+    T: Transcript<S>,
 {
-    fn generate_challenge(&self, proof: &BulletproofRecProof<S, G>) -> S {
-        // Obtain Poseidon parameters for field S
-        let params = PoseidonConfig::<S>::new(
-            8,  // full_rounds
-            57, // partial_rounds
-            5,  // alpha (exponent)
-            vec![vec![S::one(); 3]; 3], // mds matrix (placeholder)
-            vec![vec![S::zero(); 3]; 65], // ark (placeholder)
-            2,  // rate
-            1   // capacity
-        );
-        let mut sponge = PoseidonSponge::<S>::new(&params);
-        
-        let pedersen_commitment_affine = proof.pedersen_commitment.into_affine();
-        sponge.absorb(&pedersen_commitment_affine.x());
-        sponge.absorb(&pedersen_commitment_affine.y());
-
-        let l_value_affine = proof.l_value.into_affine();
-        sponge.absorb(&l_value_affine.x());
-        sponge.absorb(&l_value_affine.y());
-
-        let r_value_affine = proof.r_value.into_affine();
-        sponge.absorb(&r_value_affine.x());
-        sponge.absorb(&r_value_affine.y());
-
-        sponge.squeeze_field_elements(1)[0]
-    }
-}
\ No newline at end of file
+    fn generate_challenge(&mut self, proof: &BulletproofRecProof<S, G>) -> S {
+        // The coordinates live in the base field, so absorb the compressed encodings of the points
+        self.transcript.absorb_group(b"bulletproof/P", &proof.pedersen_commitment);
+        self.transcript.absorb_group(b"bulletproof/L", &proof.l_value);
+        self.transcript.absorb_group(b"bulletproof/R", &proof.r_value);
+        self.transcript.squeeze_field(b"bulletproof/x")
+    }
+
+    fn generate_challenge_with_counter(&mut self, proof: &BulletproofRecProof<S, G>, counter: u64) -> S {
+        if counter == 0 {
+            self.generate_challenge(proof)
+        } else {
+            // The round is already absorbed, squeeze again after absorbing the counter
+            self.transcript.absorb_bytes(b"bulletproof/counter", &counter.to_le_bytes());
+            self.transcript.squeeze_field(b"bulletproof/x")
+        }
+    }
+}
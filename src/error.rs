@@ -0,0 +1,62 @@
+use std::fmt;
+
+use crate::bulletproofs::BulletproofError;
+use crate::fri::error::{FriError, MerkleError};
+use crate::kzg::KZGError;
+
+/// Any error of the crate, for callers that combine several of its systems, e.g. a KZG opening
+/// with an inner product argument. Each system's own entry points return its own error, which
+/// converts into this one with `?`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    Bulletproof(BulletproofError),
+    KZG(KZGError),
+    Fri(FriError),
+    Merkle(MerkleError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Bulletproof(error) => write!(f, "bulletproof: {}", error),
+            Error::KZG(error) => write!(f, "KZG: {}", error),
+            Error::Fri(error) => write!(f, "FRI: {}", error),
+            Error::Merkle(error) => write!(f, "Merkle tree: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Bulletproof(error) => Some(error),
+            Error::KZG(error) => Some(error),
+            Error::Fri(error) => Some(error),
+            Error::Merkle(error) => Some(error),
+        }
+    }
+}
+
+impl From<BulletproofError> for Error {
+    fn from(error: BulletproofError) -> Self {
+        Error::Bulletproof(error)
+    }
+}
+
+impl From<KZGError> for Error {
+    fn from(error: KZGError) -> Self {
+        Error::KZG(error)
+    }
+}
+
+impl From<FriError> for Error {
+    fn from(error: FriError) -> Self {
+        Error::Fri(error)
+    }
+}
+
+impl From<MerkleError> for Error {
+    fn from(error: MerkleError) -> Self {
+        Error::Merkle(error)
+    }
+}
@@ -0,0 +1,206 @@
+use ark_ff::{FftField, Field, PrimeField};
+use ark_serialize::CanonicalSerialize;
+
+use super::error::FriError;
+use super::utils::{check_two_adicity, domain_log_size};
+
+/// Parameters of the FRI low degree test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FriConfig {
+    /// Folding stops once the polynomial has at most this degree; the final polynomial is sent in the clear
+    pub max_degree: usize,
+    /// Number of queries opened in each round
+    pub num_queries: usize,
+    /// Ratio between the evaluation domain size and the number of coefficients (rounded up to a power of two)
+    pub blowup: usize,
+    /// Number of evaluations folded into one in each round
+    pub folding_factor: usize,
+    /// Number of evaluations packed into each Merkle leaf
+    pub leaf_width: usize,
+    /// Number of levels below each Merkle root sent once per round as a cap, where the
+    /// authentication paths stop
+    pub cap_height: usize,
+    /// Number of leading zero bits of proof of work the prover finds before the queries are
+    /// sampled, each adding a bit of security to the query phase
+    pub grinding_bits: usize,
+    /// The preset the configuration was built from, if any
+    pub preset: Option<SecurityPreset>,
+}
+
+/// A target level of security with parameters chosen to reach it, so that a configuration can be
+/// picked without working out the soundness bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SecurityPreset {
+    Bits80,
+    Bits100,
+    Bits128,
+}
+
+impl SecurityPreset {
+    /// The bits of security the preset reaches.
+    pub fn bits(&self) -> usize {
+        match self {
+            SecurityPreset::Bits80 => 80,
+            SecurityPreset::Bits100 => 100,
+            SecurityPreset::Bits128 => 128,
+        }
+    }
+}
+
+/// Size and soundness of a FRI proof, predicted from the configuration alone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FriEstimate {
+    /// Number of folding rounds
+    pub rounds: usize,
+    /// Number of queries opened in each round
+    pub queries: usize,
+    /// Size of the compressed canonical serialization of the proof
+    pub proof_bytes: usize,
+    /// Bits of security against a cheating prover
+    pub soundness_bits: f64,
+}
+
+impl FriConfig {
+    pub fn new(max_degree: usize, num_queries: usize) -> Self {
+        Self {
+            max_degree,
+            num_queries,
+            blowup: 1,
+            folding_factor: 2,
+            leaf_width: 1,
+            cap_height: 0,
+            grinding_bits: 0,
+            preset: None,
+        }
+    }
+
+    /// The configuration reaching the preset's level of security by the bound `estimate` uses,
+    /// folding down to a constant final polynomial.
+    ///
+    /// The unique decoding bound gives about `log2(2 / (1 + ρ))` bits per query, e.g. 0.83 at
+    /// blowup 8 and 0.91 at blowup 16, and grinding covers what the queries leave over.
+    pub fn preset(level: SecurityPreset) -> Self {
+        let (blowup, num_queries, grinding_bits) = match level {
+            SecurityPreset::Bits80 => (8, 78, 16),
+            SecurityPreset::Bits100 => (16, 88, 20),
+            SecurityPreset::Bits128 => (16, 119, 20),
+        };
+        Self {
+            blowup,
+            grinding_bits,
+            preset: Some(level),
+            ..Self::new(0, num_queries)
+        }
+    }
+
+    /// The parameters the soundness depends on as field elements, the preset last, which the
+    /// transcript starts from so that a proof only verifies under the security it was made for.
+    /// The leaf width and cap height only change how the openings are laid out, and a verifier
+    /// still accepts proofs with a lower cap than its own.
+    pub fn to_field_elements<F: Field>(&self) -> Vec<F> {
+        [
+            self.max_degree,
+            self.num_queries,
+            self.blowup,
+            self.folding_factor,
+            self.grinding_bits,
+            self.preset.map_or(0, |preset| preset.bits()),
+        ]
+        .into_iter()
+        .map(|parameter| F::from(parameter as u64))
+        .collect()
+    }
+
+    /// Checks that F has an evaluation domain for a polynomial of the given degree at this blowup,
+    /// which needs roots of unity of order the domain size.
+    pub fn check_domain<F: FftField>(&self, degree: usize) -> Result<(), FriError> {
+        check_two_adicity::<F>(domain_log_size(degree, self.blowup))
+    }
+
+    /// The number of folding rounds for a polynomial of the given degree.
+    pub fn num_rounds(&self, initial_degree: usize) -> usize {
+        let mut degree = initial_degree;
+        let mut rounds = 0;
+        while degree > self.max_degree {
+            degree /= self.folding_factor;
+            rounds += 1;
+        }
+        rounds
+    }
+
+    /// Estimates the proof for a polynomial of the given degree, over the field `F` with Merkle
+    /// digests of type `H`.
+    ///
+    /// The byte count follows the layout of `FRIProtocolProof`: per round and query, the leaves
+    /// holding the queried value and its `folding_factor - 1` siblings are opened in the current
+    /// tree and the leaf holding the folded value in the next, each Merkle proof carrying one
+    /// digest per level below the cap and each leaf `leaf_width` values.
+    ///
+    /// The soundness uses the standard bound in the unique decoding regime: with rate
+    /// `ρ = 1 / blowup`, each query lets a far-from-low-degree word pass with probability at most
+    /// `(1 + ρ) / 2`, grinding divides that by `2^grinding_bits`, and the commit phase adds about
+    /// `rounds · |D| / |F|`.
+    pub fn estimate<F: Field, H: Default + CanonicalSerialize>(&self, initial_degree: usize) -> FriEstimate {
+        let field_bytes = F::zero().compressed_size();
+        let digest_bytes = H::default().compressed_size();
+        // Indices and vector lengths are both serialized as u64
+        let length_bytes = 0usize.compressed_size();
+        let leaf_index_bytes = length_bytes + field_bytes;
+        // Root, path, leaf index and the values of the opened leaf
+        let merkle_proof_bytes = |path_length: usize, width: usize| {
+            digest_bytes + length_bytes + path_length * digest_bytes + leaf_index_bytes + length_bytes + width * field_bytes
+        };
+        let domain_size = |degree: usize| (degree + 1).next_power_of_two() * self.blowup;
+        // Width and height of the tree over the domain for the given degree
+        let tree_shape = |degree: usize| {
+            let width = self.leaf_width.min(domain_size(degree));
+            (width, (domain_size(degree) / width).trailing_zeros() as usize)
+        };
+        // Length of the authentication paths and size of the cap of a tree of the given height
+        let capped = |height: usize| {
+            let cap_height = self.cap_height.min(height);
+            (height - cap_height, length_bytes + (1 << cap_height) * digest_bytes)
+        };
+
+        let mut degree = initial_degree;
+        let mut rounds = 0;
+        let mut round_proof_bytes = 0;
+        while degree > self.max_degree {
+            let (width, height) = tree_shape(degree);
+            let (next_width, next_height) = tree_shape(degree / self.folding_factor);
+            let (path_length, cap_bytes) = capped(height);
+            let (next_path_length, next_cap_bytes) = capped(next_height);
+            let leaves = self.folding_factor.div_ceil(width);
+            // The folded value is left out of the next leaf
+            let query_bytes = leaf_index_bytes
+                + length_bytes + leaves * merkle_proof_bytes(path_length, width)
+                + merkle_proof_bytes(next_path_length, next_width - 1);
+            // Both caps and the openings
+            round_proof_bytes += cap_bytes + next_cap_bytes + length_bytes + self.num_queries * query_bytes;
+
+            degree /= self.folding_factor;
+            rounds += 1;
+        }
+
+        let proof_bytes = digest_bytes
+            + (length_bytes + rounds * digest_bytes)
+            + (length_bytes + round_proof_bytes)
+            + (length_bytes + (degree + 1) * field_bytes)
+            + digest_bytes
+            + length_bytes;
+
+        let rate = 1.0 / self.blowup as f64;
+        let query_error = ((1.0 + rate) / 2.0).powi(self.num_queries as i32) / 2f64.powi(self.grinding_bits as i32);
+        let field_bits = F::BasePrimeField::MODULUS_BIT_SIZE as f64 * F::extension_degree() as f64;
+        let commit_error = rounds as f64 * domain_size(initial_degree) as f64 / 2f64.powf(field_bits);
+
+        FriEstimate {
+            rounds,
+            queries: self.num_queries,
+            proof_bytes,
+            soundness_bits: (-(query_error + commit_error).log2()).max(0.0),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use ark_crypto_primitives::crh::{CRHScheme, TwoToOneCRHScheme};
+use ark_ff::{batch_inversion, FftField, Field};
+use ark_poly::{DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::util::VerifierChallenge;
+
+use super::error::FriError;
+use super::merkle_tree::{MerkleProof, MerkleTree, MerkleTreeOperator};
+use super::protocol::{FRIProtocol, FRIProtocolProof, FRISystemImpl};
+use super::prover::Prover;
+use super::utils::check_fits;
+use super::verifier::verifier::Verifier;
+
+/// The domain of a polynomial's first layer, its evaluations there and their tree.
+type OodLayer<F, H> = (GeneralEvaluationDomain<F>, Vec<F>, MerkleTree<F, H>);
+
+/// Samples the out-of-domain point `z` of DEEP-FRI from the commitment, trying the challenger's
+/// counters in order until the candidate is not an element of the domain.
+///
+/// Drawing `z` after the commitment keeps the prover from choosing `f(z)` ahead of time. It has to
+/// lie outside the domain: at a domain point `x - z` vanishes so the quotient is undefined, and the
+/// claimed `f(z)` would just be one of the committed evaluations rather than a check on the
+/// polynomial they are close to.
+pub fn sample_ood_point<F: FftField, VC: VerifierChallenge<Challenge = F>>(
+    challenger: &VC,
+    commitment: &VC::Commitment,
+    domain: &GeneralEvaluationDomain<F>,
+) -> F {
+    (0..)
+        .map(|counter| challenger.generate_challenge_with_counter(commitment, counter))
+        .find(|z| !domain.evaluate_vanishing_polynomial(*z).is_zero())
+        .expect("Some challenge lies outside the domain")
+}
+
+/// Computes the evaluations of the DEEP quotient `q(x) = (f(x) - f(z)) / (x - z)` over the domain.
+///
+/// `evals` are the evaluations of `f` over `domain` and `f_at_z` the claimed value of `f` at the
+/// out-of-domain point `z`. When the claim is correct, `q` is a polynomial of degree one less than `f`,
+/// so its evaluations can be used as the first layer of a DEEP-FRI low degree test.
+///
+/// Panics if `z` is in the domain, since `x - z` would vanish at `x = z`.
+pub fn deep_quotient<F: FftField>(
+    evals: &[F],
+    domain: &GeneralEvaluationDomain<F>,
+    z: F,
+    f_at_z: F,
+) -> Vec<F> {
+    assert_eq!(evals.len(), domain.size(), "Expected one evaluation per domain element");
+    assert!(
+        !domain.evaluate_vanishing_polynomial(z).is_zero(),
+        "The point z must be outside of the evaluation domain"
+    );
+
+    // Invert all the denominators x - z at once
+    let mut denominators: Vec<F> = domain.elements().map(|x| x - z).collect();
+    batch_inversion(&mut denominators);
+
+    evals
+        .iter()
+        .zip(denominators)
+        .map(|(&f_x, denominator_inv)| (f_x - f_at_z) * denominator_inv)
+        .collect()
+}
+
+/// A proof that the polynomial committed to by `commitment` evaluates to a claimed value at an
+/// out-of-domain point `z`, made with `prove_ood_eval`.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct OodEvalProof<F: Field, H: CanonicalSerialize + CanonicalDeserialize> {
+    /// The root of the tree over the evaluations of `f`, laid out as FRI lays out its first layer
+    pub commitment: H,
+    /// The low degree test of the DEEP quotient `q(x) = (f(x) - f(z)) / (x - z)`
+    pub quotient: FRIProtocolProof<F, H>,
+    /// The leaves of `f` at the positions of the leaves the quotient's first round opens, in order
+    pub openings: Vec<MerkleProof<F, H>>,
+}
+
+impl<F, P, LCH, INCH, MT, VC> FRISystemImpl<F, P, MT, VC, INCH, LCH>
+where
+    F: FftField,
+    P: DenseUVPolynomial<F>,
+    LCH: CRHScheme<Input = [F], Output = INCH::Output>,
+    INCH: TwoToOneCRHScheme,
+    MT: MerkleTreeOperator<F, INCH> + Clone,
+    VC: VerifierChallenge<Commitment = INCH::Output, Challenge = F>,
+{
+    /// Commits to the polynomial of at most the given degree and proves its value at `z`, which
+    /// it returns with the proof. The quotient is committed to over the same domain as the
+    /// polynomial, so the leaves its queries open are those of the polynomial to open as well.
+    ///
+    /// `z` must be outside the domain, and drawn once the commitment is known, e.g. with
+    /// `sample_ood_point` from a commitment made with `commit_evaluations`. Fails with
+    /// `FriError::PointInDomain` if it is in the domain.
+    pub fn prove_ood_eval(&self, polynomial: &P, degree: usize, z: F) -> Result<(F, OodEvalProof<F, INCH::Output>), FriError> {
+        let (domain, evals, tree) = self.ood_layer(polynomial, degree)?;
+        if domain.evaluate_vanishing_polynomial(z).is_zero() {
+            return Err(FriError::PointInDomain);
+        }
+        let f_at_z = polynomial.evaluate(&z);
+        let quotient = self.prove_from_evals(&deep_quotient(&evals, &domain, z, f_at_z), &domain)?;
+
+        let openings = quotient
+            .round_proofs
+            .first()
+            .map_or(Vec::new(), |round| round.openings.iter().flat_map(|opening| &opening.current).collect())
+            .into_iter()
+            .map(|leaf| self.tree_operator.create_proof(&tree, &leaf.leaf_index))
+            .collect::<Result<_, _>>()
+            .expect("The quotient's leaves are leaves of the polynomial's tree over the same domain");
+
+        Ok((f_at_z, OodEvalProof { commitment: tree.root(), quotient, openings }))
+    }
+
+    /// The commitment `prove_ood_eval` makes to the polynomial of at most the given degree, for
+    /// its proofs to be checked against a commitment received beforehand.
+    pub fn ood_commitment(&self, polynomial: &P, degree: usize) -> Result<INCH::Output, FriError> {
+        let (_, _, tree) = self.ood_layer(polynomial, degree)?;
+        Ok(tree.root())
+    }
+
+    /// The domain of the polynomial's first layer, its evaluations there and their tree.
+    fn ood_layer(&self, polynomial: &P, degree: usize) -> Result<OodLayer<F, INCH::Output>, FriError> {
+        let domain = *self.cached_domains().clone().initial(degree, self.config.blowup, F::one())?;
+        check_fits(polynomial.coeffs().len(), domain.size())?;
+
+        let evals = domain.fft(polynomial.coeffs());
+        let (tree, _) = Prover::<F, P>::commit_evaluations_rec::<LCH, INCH, MT>(&evals, &domain, degree, &self.tree_operator);
+        Ok((domain, evals, tree))
+    }
+
+    /// Verifies that the polynomial committed to in the proof evaluates to `claimed` at `z`.
+    ///
+    /// The quotient must pass the low degree test, and at every point of every leaf its first
+    /// round opens, `q(x)·(x - z) = f(x) - claimed` must hold for the value of `f` opened from
+    /// the commitment. When `f(z)` is not the claimed value, `(f(x) - claimed) / (x - z)` has a
+    /// pole at `z` and is far from any low degree polynomial, so either the test or the
+    /// consistency check fails. A quotient small enough to skip every round opens nothing, and is
+    /// rejected as the committed polynomial is then never looked at.
+    pub fn verify_ood_eval(&self, proof: &OodEvalProof<F, INCH::Output>, z: F, claimed: F) -> bool {
+        let Some(first_round) = proof.quotient.round_proofs.first() else {
+            return false;
+        };
+        let quotient_leaves: Vec<_> = first_round.openings.iter().flat_map(|opening| &opening.current).collect();
+        if quotient_leaves.is_empty() || quotient_leaves.len() != proof.openings.len() || !self.verify(&proof.quotient) {
+            return false;
+        }
+
+        // The points of a leaf are its first point times successive powers of ω^leaf_count
+        let Some(domain_size) = Verifier::<F, P, LCH, INCH, MT>::opened_domain_size(first_round) else {
+            return false;
+        };
+        let Some(generator) = F::get_root_of_unity(domain_size as u64) else {
+            return false;
+        };
+        let leaf_width = quotient_leaves[0].values.len();
+        let step = generator.pow([(domain_size / leaf_width) as u64]);
+
+        let consistent = quotient_leaves.iter().zip(&proof.openings).all(|(quotient_leaf, f_leaf)| {
+            f_leaf.leaf_index.index == quotient_leaf.leaf_index.index
+                && f_leaf.leaf_index.point == quotient_leaf.leaf_index.point
+                && f_leaf.values.len() == leaf_width
+                && std::iter::successors(Some(f_leaf.leaf_index.point), |x| Some(*x * step))
+                    .zip(quotient_leaf.values.iter().zip(&f_leaf.values))
+                    .all(|(x, (q_x, f_x))| *q_x * (x - z) == *f_x - claimed)
+        });
+
+        consistent
+            && self
+                .tree_operator
+                .verify_proofs_batch_with_cap(&proof.openings.iter().collect::<Vec<_>>(), std::slice::from_ref(&proof.commitment))
+    }
+}
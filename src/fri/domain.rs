@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use ark_ff::FftField;
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+
+use super::error::FriError;
+use super::utils::{check_two_adicity, domain_log_size};
+
+/// The evaluation domains of a proof, built once each and looked up by their log-size and coset
+/// offset.
+///
+/// Building a `GeneralEvaluationDomain` computes its generator, their inverses and the inverse of
+/// its size. The rounds of a proof only ever use the initial domain and its folds, and the
+/// verifier recomputes the points of the same few domains for every query, so each is kept the
+/// first time it is asked for.
+#[derive(Clone, Debug, Default)]
+pub struct DomainCache<F: FftField> {
+    domains: HashMap<(u32, F), GeneralEvaluationDomain<F>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<F: FftField> DomainCache<F> {
+    pub fn new() -> Self {
+        Self { domains: HashMap::new(), hits: 0, misses: 0 }
+    }
+
+    /// The domain `offset · H` for H the subgroup of size `2^log_size`, failing with
+    /// `FriError::DomainTooLarge` when F has no subgroup that large.
+    pub fn domain(&mut self, log_size: u32, offset: F) -> Result<&GeneralEvaluationDomain<F>, FriError> {
+        check_two_adicity::<F>(log_size)?;
+        if self.domains.contains_key(&(log_size, offset)) {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        Ok(self.domains.entry((log_size, offset)).or_insert_with(|| {
+            GeneralEvaluationDomain::<F>::new(1 << log_size)
+                .and_then(|domain| domain.get_coset(offset))
+                .expect("The field has a subgroup of every size up to its two-adicity")
+        }))
+    }
+
+    /// The domain `Prover::create_domain_with_offset` gives for the degree and blowup.
+    pub fn initial(&mut self, degree: usize, blowup: usize, offset: F) -> Result<&GeneralEvaluationDomain<F>, FriError> {
+        self.domain(domain_log_size(degree, blowup), offset)
+    }
+
+    /// The domain the evaluations over `domain` fold onto, as `Prover::fold_domain` gives it.
+    pub fn fold(&mut self, domain: &GeneralEvaluationDomain<F>) -> &GeneralEvaluationDomain<F> {
+        let log_size = domain.size().trailing_zeros();
+        assert!(log_size > 0, "A domain of size 1 cannot be folded");
+        self.domain(log_size - 1, domain.coset_offset().square())
+            .expect("A fold is smaller than the domain it folds")
+    }
+
+    /// The generator of the subgroup of the given size, if the size is a power of two F has a
+    /// subgroup of.
+    pub fn generator(&mut self, domain_size: usize) -> Option<F> {
+        if !domain_size.is_power_of_two() {
+            return None;
+        }
+        self.domain(domain_size.trailing_zeros(), F::one()).ok().map(|domain| domain.group_gen())
+    }
+
+    /// How many lookups found their domain already built.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// How many lookups had to build their domain, one per distinct domain.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+}
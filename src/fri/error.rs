@@ -0,0 +1,165 @@
+use std::fmt;
+
+/// Why a FRI proof was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FriError {
+    /// The proof does not have the shape the configuration implies, so it was rejected before any
+    /// cryptographic check
+    ProofShape(ProofShapeError),
+    /// The proof is well formed but one of its Merkle or folding checks failed
+    VerificationFailed,
+    /// A domain of `2^requested` elements is needed, but the field only has roots of unity of
+    /// order up to `2^supported`, its two-adicity
+    DomainTooLarge { requested: u32, supported: u32 },
+    /// A root of unity of order 1 was asked for, which is 1 and generates no domain worth folding
+    TrivialRootOfUnity,
+    /// Evaluations were given over a domain of `actual` points where the configuration commits to
+    /// the polynomial over one of `expected`
+    DomainMismatch { expected: usize, actual: usize },
+    /// Evaluations were given over a coset of the subgroup, where the configuration commits to the
+    /// polynomial over the subgroup itself
+    CosetMismatch,
+    /// The proof was stopped through its `CancellationToken` before it was complete
+    Cancelled,
+    /// A polynomial of `coefficients` coefficients does not fit in the domain of `domain_size`
+    /// points it is committed to over
+    PolynomialTooLarge { coefficients: usize, domain_size: usize },
+    /// `actual` evaluations were given over a domain of `expected` points
+    EvaluationCountMismatch { expected: usize, actual: usize },
+    /// The configuration folds by a factor other than 2, the only one supported
+    UnsupportedFoldingFactor { folding_factor: usize },
+    /// The configuration packs `configured` evaluations to a leaf where the tree operator packs
+    /// `operator`
+    LeafWidthMismatch { configured: usize, operator: usize },
+    /// An opening was asked for at a point of the domain, where the DEEP quotient is undefined
+    PointInDomain,
+}
+
+/// Why a Merkle proof could not be created.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MerkleError {
+    /// The leaf index is not below the number of leaves of the tree
+    LeafOutOfRange { index: usize, leaf_count: usize },
+    /// The point of the leaf index is not the point of the tree's domain at that index
+    InconsistentLeafPoint { index: usize },
+    /// The stored tree the proof is read from could not be read
+    Storage(String),
+    /// A tree was asked for without any leaf
+    NoLeaves,
+    /// The leaves hold no values
+    EmptyLeaves,
+    /// The leaf at the index holds `actual` values where the first leaf holds `expected`
+    LeafWidthMismatch { index: usize, expected: usize, actual: usize },
+    /// The hash function failed on a leaf or a pair of nodes
+    Hash(String),
+    /// The leaves `start..end` are not a non-empty range of a tree of `leaf_count` leaves
+    InvalidRange { start: usize, end: usize, leaf_count: usize },
+}
+
+/// A mismatch between the shape of a proof and the one the configuration implies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofShapeError {
+    RoundCount { expected: usize, actual: usize },
+    CommitmentCount { expected: usize, actual: usize },
+    QueryCount { round: usize, expected: usize, actual: usize },
+    /// A round whose claimed degree is not the previous one folded once
+    RoundDegree { round: usize, expected: usize, actual: usize },
+    /// A final polynomial of more than the `max_degree + 1` coefficients the configuration allows
+    FinalDegree { max_degree: usize, coefficients: usize },
+}
+
+impl fmt::Display for FriError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FriError::ProofShape(error) => write!(f, "malformed proof: {}", error),
+            FriError::VerificationFailed => write!(f, "proof verification failed"),
+            FriError::DomainTooLarge { requested, supported } => write!(
+                f,
+                "a domain of 2^{} elements is needed, but the field only has roots of unity of order up to 2^{}",
+                requested, supported
+            ),
+            FriError::TrivialRootOfUnity => write!(f, "the root of unity of order 1 is trivial"),
+            FriError::DomainMismatch { expected, actual } => write!(
+                f,
+                "the evaluations are over a domain of {} points, where one of {} is expected",
+                actual, expected
+            ),
+            FriError::CosetMismatch => write!(f, "the evaluations are over a coset rather than the subgroup"),
+            FriError::Cancelled => write!(f, "the proof was cancelled"),
+            FriError::PolynomialTooLarge { coefficients, domain_size } => write!(
+                f,
+                "a polynomial of {} coefficients does not fit in a domain of {} points",
+                coefficients, domain_size
+            ),
+            FriError::EvaluationCountMismatch { expected, actual } => {
+                write!(f, "expected {} evaluations, one per domain point, got {}", expected, actual)
+            }
+            FriError::UnsupportedFoldingFactor { folding_factor } => {
+                write!(f, "folding by {} is not supported, only by 2", folding_factor)
+            }
+            FriError::LeafWidthMismatch { configured, operator } => write!(
+                f,
+                "the configuration packs {} evaluations to a leaf, but the tree operator packs {}",
+                configured, operator
+            ),
+            FriError::PointInDomain => write!(f, "the point is in the domain, where the DEEP quotient is undefined"),
+        }
+    }
+}
+
+impl fmt::Display for ProofShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofShapeError::RoundCount { expected, actual } => {
+                write!(f, "expected {} rounds, got {}", expected, actual)
+            }
+            ProofShapeError::CommitmentCount { expected, actual } => {
+                write!(f, "expected {} round commitments, got {}", expected, actual)
+            }
+            ProofShapeError::QueryCount { round, expected, actual } => {
+                write!(f, "expected {} openings in round {}, got {}", expected, round, actual)
+            }
+            ProofShapeError::RoundDegree { round, expected, actual } => {
+                write!(f, "expected degree {} in round {}, got {}", expected, round, actual)
+            }
+            ProofShapeError::FinalDegree { max_degree, coefficients } => {
+                write!(f, "expected a final polynomial of degree at most {}, got {} coefficients", max_degree, coefficients)
+            }
+        }
+    }
+}
+
+impl fmt::Display for MerkleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MerkleError::LeafOutOfRange { index, leaf_count } => {
+                write!(f, "leaf {} is out of range for a tree of {} leaves", index, leaf_count)
+            }
+            MerkleError::InconsistentLeafPoint { index } => {
+                write!(f, "the point of leaf {} is not the domain point at that index", index)
+            }
+            MerkleError::Storage(reason) => write!(f, "the stored tree could not be read: {}", reason),
+            MerkleError::NoLeaves => write!(f, "a tree needs at least one leaf"),
+            MerkleError::EmptyLeaves => write!(f, "the leaves hold no values"),
+            MerkleError::LeafWidthMismatch { index, expected, actual } => write!(
+                f,
+                "leaf {} holds {} values, where the first leaf holds {}",
+                index, actual, expected
+            ),
+            MerkleError::Hash(reason) => write!(f, "hashing failed: {}", reason),
+            MerkleError::InvalidRange { start, end, leaf_count } => {
+                write!(f, "{}..{} is not a non-empty range of a tree of {} leaves", start, end, leaf_count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FriError {}
+
+impl std::error::Error for MerkleError {}
+
+impl From<ProofShapeError> for FriError {
+    fn from(error: ProofShapeError) -> Self {
+        FriError::ProofShape(error)
+    }
+}
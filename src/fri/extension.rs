@@ -0,0 +1,283 @@
+use std::marker::PhantomData;
+
+use ark_bls12_381::Fr;
+use ark_crypto_primitives::crh::{CRHScheme, TwoToOneCRHScheme};
+use ark_ff::{batch_inversion, Field, Fp2, Fp2Config, MontFp, PrimeField};
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::fri::config::FriConfig;
+use crate::fri::error::FriError;
+use crate::fri::fold;
+use crate::fri::merkle_tree::{LeafIndex, MerkleProof, MerkleTree, MerkleTreeOperator};
+use crate::fri::protocol::QUERY_COUNTER_OFFSET;
+use crate::fri::prover::{FRIRecProof, Prover};
+use crate::fri::transcript::{Transcript, TranscriptSeed};
+use crate::fri::utils::{challenge_to_index, check_fits, check_two_adicity, domain_log_size};
+use crate::util::VerifierChallenge;
+
+/// The quadratic extension `Fr[u] / (u^2 - 7)` of the BLS12-381 scalar field, 7 being the
+/// generator of its multiplicative group and so not a square.
+pub struct Fr2Config;
+
+impl Fp2Config for Fr2Config {
+    type Fp = Fr;
+
+    const NONRESIDUE: Fr = MontFp!("7");
+
+    // u^p = 7^((p - 1) / 2) · u = -u
+    const FROBENIUS_COEFF_FP2_C1: &'static [Fr] = &[MontFp!("1"), MontFp!("-1")];
+}
+
+pub type Fr2 = Fp2<Fr2Config>;
+
+/// A FRI proof over an extension E, laid out as `FRIProtocolProof`. The last layer is not opened:
+/// the verifier commits to the final polynomial's evaluations itself and compares the roots.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ExtensionFRIProof<E: Field, H: CanonicalSerialize + CanonicalDeserialize> {
+    pub initial_commitment: H,
+    pub round_commitments: Vec<H>,
+    pub round_proofs: Vec<FRIRecProof<E, H>>,
+    pub final_polynomial: Vec<E>,
+}
+
+/// The FRI low degree test for a polynomial with coefficients in an extension E of F, e.g. the
+/// composition of constraints over a quadratic extension of the trace's field.
+///
+/// Extensions have no large two-adic subgroups of their own (ark-ff does not make them an
+/// `FftField`), so the domains are subgroups of F, with their points embedded in E to label the
+/// leaves, and the folding challenges are drawn from E. The polynomial is folded by two with one
+/// evaluation per leaf, without grinding and with full Merkle paths: of the configuration, only
+/// `max_degree`, `num_queries` and `blowup` apply.
+pub struct ExtensionFRI<F, E, MT, VC, INCH, LCH> {
+    pub config: FriConfig,
+    pub tree_operator: MT,
+    pub verifier_challenge: VC,
+    _phantom: PhantomData<(F, E, INCH, LCH)>,
+}
+
+impl<F, E, LCH, INCH, MT, VC> ExtensionFRI<F, E, MT, VC, INCH, LCH>
+where
+    F: PrimeField,
+    E: Field<BasePrimeField = F>,
+    LCH: CRHScheme<Input = [E], Output = INCH::Output>,
+    INCH: TwoToOneCRHScheme,
+    MT: MerkleTreeOperator<E, INCH>,
+    VC: VerifierChallenge<Commitment = INCH::Output, Challenge = E>,
+{
+    pub fn new(max_degree: usize, num_queries: usize, tree_operator: MT, verifier_challenge: VC) -> Self {
+        Self::with_config(FriConfig::new(max_degree, num_queries), tree_operator, verifier_challenge)
+    }
+
+    pub fn with_config(config: FriConfig, tree_operator: MT, verifier_challenge: VC) -> Self {
+        Self { config, tree_operator, verifier_challenge, _phantom: PhantomData }
+    }
+
+    /// Proves the polynomial has at most the given degree. Fails when F has no domain for the
+    /// degree or the polynomial does not fit in it.
+    pub fn prove(&self, polynomial: &DensePolynomial<E>, degree: usize) -> Result<ExtensionFRIProof<E, INCH::Output>, FriError> {
+        let mut domain = self.initial_domain(degree)?;
+        check_fits(polynomial.coeffs.len(), domain.size())?;
+
+        let mut evals = extension_fft(&domain, &polynomial.coeffs);
+        let mut tree = self.commit(&evals, &domain, degree);
+        let initial_commitment = tree.root();
+        let mut transcript = Transcript::new(&self.tree_operator, &TranscriptSeed::default());
+        transcript.absorb(&self.tree_operator, &initial_commitment);
+        let query_indices = self.query_indices(transcript.state(), domain.size());
+
+        let mut current_degree = degree;
+        let mut round_commitments = Vec::new();
+        let mut round_proofs = Vec::new();
+        while current_degree > self.config.max_degree {
+            let challenge = self.verifier_challenge.generate_nonzero_challenge(transcript.state());
+            evals = fold_extension_evaluations(&evals, &domain, challenge);
+            domain = fold::fold_domain(&domain, 2);
+            current_degree /= 2;
+            let next_tree = self.commit(&evals, &domain, current_degree);
+
+            let queries: Vec<LeafIndex<E>> = query_indices.iter().map(|index| tree.leaf_of(index % tree.domain_size())).collect();
+            round_proofs.push(Prover::<E, DensePolynomial<E>>::open_rec::<LCH, INCH, MT>(&tree, &next_tree, &queries, 0, &self.tree_operator));
+            round_commitments.push(next_tree.root());
+            transcript.absorb(&self.tree_operator, &next_tree.root());
+            tree = next_tree;
+        }
+
+        let mut final_polynomial = extension_ifft(&domain, &evals);
+        final_polynomial.truncate(current_degree + 1);
+        Ok(ExtensionFRIProof {
+            initial_commitment,
+            round_commitments,
+            round_proofs,
+            final_polynomial,
+        })
+    }
+
+    /// Verifies a proof that the committed polynomial has at most the given degree.
+    pub fn verify(&self, proof: &ExtensionFRIProof<E, INCH::Output>, degree: usize) -> bool {
+        let Ok(mut domain) = self.initial_domain(degree) else {
+            return false;
+        };
+        let rounds = std::iter::successors(Some(degree), |degree| Some(degree / 2))
+            .take_while(|degree| *degree > self.config.max_degree)
+            .count();
+        if proof.round_commitments.len() != rounds || proof.round_proofs.len() != rounds {
+            return false;
+        }
+
+        let mut transcript = Transcript::new(&self.tree_operator, &TranscriptSeed::default());
+        transcript.absorb(&self.tree_operator, &proof.initial_commitment);
+        let query_indices = self.query_indices(transcript.state(), domain.size());
+
+        let mut current_degree = degree;
+        let mut current_root = &proof.initial_commitment;
+        for (round_proof, next_root) in proof.round_proofs.iter().zip(&proof.round_commitments) {
+            let challenge = self.verifier_challenge.generate_nonzero_challenge(transcript.state());
+            if !self.verify_round(round_proof, current_root, next_root, &domain, challenge, &query_indices) {
+                return false;
+            }
+            transcript.absorb(&self.tree_operator, next_root);
+            domain = fold::fold_domain(&domain, 2);
+            current_degree /= 2;
+            current_root = next_root;
+        }
+
+        proof.final_polynomial.len() <= current_degree + 1
+            && self.commit(&extension_fft(&domain, &proof.final_polynomial), &domain, current_degree).root() == *current_root
+    }
+
+    /// Checks every query of a round: `f(y)` and `f(-y)` open under the current root at the
+    /// sampled position and half a domain further, and their fold opens under the next root at `y^2`.
+    fn verify_round(
+        &self,
+        round_proof: &FRIRecProof<E, INCH::Output>,
+        current_root: &INCH::Output,
+        next_root: &INCH::Output,
+        domain: &GeneralEvaluationDomain<F>,
+        challenge: E,
+        query_indices: &[usize],
+    ) -> bool {
+        let (size, half) = (domain.size(), domain.size() / 2);
+        let leaf = |index: usize| LeafIndex { index, point: E::from_base_prime_field(domain.element(index)) };
+        let (current_cap, next_cap) = (std::slice::from_ref(current_root), std::slice::from_ref(next_root));
+
+        round_proof.openings.len() == query_indices.len()
+            && round_proof.openings.iter().zip(query_indices).all(|(opening, query_index)| {
+                let index = query_index % size;
+                let [f_y, f_minus_y] = &opening.current[..] else {
+                    return false;
+                };
+                if opening.query != leaf(index)
+                    || f_y.leaf_index != leaf(index)
+                    || f_minus_y.leaf_index != leaf((index + half) % size)
+                    || f_y.values.len() != 1
+                    || f_minus_y.values.len() != 1
+                    || !opening.next.values.is_empty()
+                    || opening.next.leaf_index != (LeafIndex { index: index % half, point: leaf(index).point.square() })
+                {
+                    return false;
+                }
+
+                let Some(y_inv) = domain.element(index).inverse() else {
+                    return false;
+                };
+                let folded = MerkleProof {
+                    values: vec![fold_pair(f_y.values[0], f_minus_y.values[0], y_inv, challenge)],
+                    ..opening.next.clone()
+                };
+                self.tree_operator.verify_proof_with_cap(f_y, current_cap)
+                    && self.tree_operator.verify_proof_with_cap(f_minus_y, current_cap)
+                    && self.tree_operator.verify_proof_with_cap(&folded, next_cap)
+            })
+    }
+
+    /// The subgroup of F a polynomial of the given degree is committed over.
+    fn initial_domain(&self, degree: usize) -> Result<GeneralEvaluationDomain<F>, FriError> {
+        let log_size = domain_log_size(degree, self.config.blowup);
+        check_two_adicity::<F>(log_size)?;
+        Ok(GeneralEvaluationDomain::new(1 << log_size).expect("The field has a subgroup of every size up to its two-adicity"))
+    }
+
+    /// Commits to evaluations over the domain with one per leaf, labelled by its point in E.
+    fn commit(&self, evals: &[E], domain: &GeneralEvaluationDomain<F>, degree: usize) -> MerkleTree<E, INCH::Output> {
+        let leaves = domain
+            .elements()
+            .zip(evals)
+            .enumerate()
+            .map(|(index, (point, value))| (LeafIndex { index, point: E::from_base_prime_field(point) }, vec![*value]))
+            .collect();
+        self.tree_operator
+            .create_tree(leaves, E::from_base_prime_field(domain.group_gen()), E::from_base_prime_field(domain.coset_offset()), degree)
+            .expect("The leaves of a domain all hold the same number of values")
+    }
+
+    /// The queried positions of the initial domain, sampled as `FRISystemImpl` samples them.
+    fn query_indices(&self, transcript_state: &INCH::Output, domain_size: usize) -> Vec<usize> {
+        (0..self.config.num_queries as u64)
+            .map(|i| challenge_to_index(self.verifier_challenge.generate_challenge_with_counter(transcript_state, QUERY_COUNTER_OFFSET + i), domain_size))
+            .collect()
+    }
+}
+
+/// A polynomial over F as one over its extension E, e.g. a trace column to be folded with
+/// challenges from E.
+pub fn lift_polynomial<F: PrimeField, E: Field<BasePrimeField = F>>(polynomial: &DensePolynomial<F>) -> DensePolynomial<E> {
+    DensePolynomial::from_coefficients_vec(polynomial.coeffs.iter().map(|coeff| E::from_base_prime_field(*coeff)).collect())
+}
+
+/// The evaluations over a domain of F of a polynomial with coefficients in E. The FFT is linear
+/// over F, so it is run on each coordinate of the coefficients.
+pub fn extension_fft<F: PrimeField, E: Field<BasePrimeField = F>>(domain: &GeneralEvaluationDomain<F>, coeffs: &[E]) -> Vec<E> {
+    let coordinates: Vec<Vec<F>> = coordinates(coeffs).iter().map(|coordinate| domain.fft(coordinate)).collect();
+    from_coordinates(&coordinates, domain.size())
+}
+
+/// The coefficients of the polynomial with the given evaluations over a domain of F, see
+/// `extension_fft`.
+pub fn extension_ifft<F: PrimeField, E: Field<BasePrimeField = F>>(domain: &GeneralEvaluationDomain<F>, evals: &[E]) -> Vec<E> {
+    let coordinates: Vec<Vec<F>> = coordinates(evals).iter().map(|coordinate| domain.ifft(coordinate)).collect();
+    from_coordinates(&coordinates, domain.size())
+}
+
+/// `fold::fold_evaluations` at arity 2 for evaluations in E over a domain of F, folded with a
+/// challenge from E.
+pub fn fold_extension_evaluations<F: PrimeField, E: Field<BasePrimeField = F>>(
+    evals: &[E],
+    domain: &GeneralEvaluationDomain<F>,
+    challenge: E,
+) -> Vec<E> {
+    assert_eq!(evals.len(), domain.size(), "Expected one evaluation per domain element");
+    let half = domain.size() / 2;
+    let mut y_invs: Vec<F> = domain.elements().take(half).collect();
+    batch_inversion(&mut y_invs);
+    y_invs
+        .into_iter()
+        .enumerate()
+        .map(|(i, y_inv)| fold_pair(evals[i], evals[i + half], y_inv, challenge))
+        .collect()
+}
+
+/// f_next(y^2) = (f(y) + f(-y)) / 2 + challenge · (f(y) - f(-y)) / (2y)
+fn fold_pair<F: PrimeField, E: Field<BasePrimeField = F>>(f_y: E, f_minus_y: E, y_inv: F, challenge: E) -> E {
+    (f_y + f_minus_y + challenge * (f_y - f_minus_y) * E::from_base_prime_field(y_inv)) / E::from(2u64)
+}
+
+/// The values' coordinates over F, one vector per degree of the extension.
+fn coordinates<F: PrimeField, E: Field<BasePrimeField = F>>(values: &[E]) -> Vec<Vec<F>> {
+    let mut coordinates = vec![Vec::with_capacity(values.len()); E::extension_degree() as usize];
+    for value in values {
+        for (coordinate, element) in coordinates.iter_mut().zip(value.to_base_prime_field_elements()) {
+            coordinate.push(element);
+        }
+    }
+    coordinates
+}
+
+fn from_coordinates<F: PrimeField, E: Field<BasePrimeField = F>>(coordinates: &[Vec<F>], len: usize) -> Vec<E> {
+    (0..len)
+        .map(|i| {
+            let elements: Vec<F> = coordinates.iter().map(|coordinate| coordinate[i]).collect();
+            E::from_base_prime_field_elems(&elements).expect("One coordinate per degree of the extension")
+        })
+        .collect()
+}
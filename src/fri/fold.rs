@@ -0,0 +1,69 @@
+use ark_ff::{batch_inversion, FftField, Field};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+
+/// Folds a polynomial given by its coefficients, lowest degree first.
+///
+/// Writing `f(x) = Σ_{j < arity} x^j f_j(x^arity)`, the folded polynomial is
+/// `g(x) = Σ_{j < arity} challenge^j f_j(x)`, so the coefficient of `x^i` in g is
+/// `Σ_j challenge^j · coeffs[arity · i + j]`. For arity 2 this is `f_even(x) + challenge · f_odd(x)`.
+pub fn fold_polynomial<F: Field>(coeffs: &[F], challenge: F, arity: usize) -> Vec<F> {
+    assert!(arity >= 2 && arity.is_power_of_two(), "The arity must be a power of two");
+    coeffs
+        .chunks(arity)
+        .map(|chunk| chunk.iter().rev().fold(F::zero(), |acc, coeff| acc * challenge + coeff))
+        .collect()
+}
+
+/// Folds the evaluations of a polynomial over the domain into the evaluations of
+/// `fold_polynomial` of it over `fold_domain(domain, arity)`.
+///
+/// The element `y = domain.element(i)` of the first `n / arity` shares its fiber with
+/// `y · ζ^t = domain.element(i + t · n / arity)`, where ζ is the primitive `arity`-th root of unity
+/// `ω^(n / arity)`. The inverse DFT of the fiber gives `y^j f_j(y^arity) = (1 / arity) Σ_t f(y ζ^t) ζ^(-tj)`,
+/// which is weighted by `(challenge / y)^j`. For arity 2:
+///
+/// g(y^2) = (f(y) + f(-y)) / 2 + challenge · (f(y) - f(-y)) / (2y)
+pub fn fold_evaluations<F: FftField>(evals: &[F], domain: &GeneralEvaluationDomain<F>, challenge: F, arity: usize) -> Vec<F> {
+    let mut folded = evals.to_vec();
+    fold_evaluations_in_place(&mut folded, domain, challenge, arity);
+    folded
+}
+
+/// Same as `fold_evaluations`, but writes the folded evaluations over the first `n / arity` of the
+/// buffer, which only the fiber of the position itself reads, and truncates it.
+pub fn fold_evaluations_in_place<F: FftField>(evals: &mut Vec<F>, domain: &GeneralEvaluationDomain<F>, challenge: F, arity: usize) {
+    assert!(arity >= 2 && arity.is_power_of_two(), "The arity must be a power of two");
+    assert_eq!(evals.len(), domain.size(), "Expected one evaluation per domain element");
+    assert_eq!(domain.size() % arity, 0, "The arity must divide the domain size");
+    let stride = domain.size() / arity;
+
+    // ζ^(-t) for every t, and 1 / arity
+    let zeta_inv = domain.group_gen_inv().pow([stride as u64]);
+    let zeta_inv_powers: Vec<F> = std::iter::successors(Some(F::one()), |power| Some(*power * zeta_inv)).take(arity).collect();
+    let arity_inv = F::from(arity as u64).inverse().expect("The arity should be invertible");
+
+    let mut y_invs: Vec<F> = domain.elements().take(stride).collect();
+    batch_inversion(&mut y_invs);
+
+    for (i, y_inv) in y_invs.into_iter().enumerate() {
+        let weight = challenge * y_inv;
+        let mut weight_power = arity_inv;
+        let mut folded = F::zero();
+        for j in 0..arity {
+            let coefficient: F = (0..arity).map(|t| evals[i + t * stride] * zeta_inv_powers[(t * j) % arity]).sum();
+            folded += weight_power * coefficient;
+            weight_power *= weight;
+        }
+        evals[i] = folded;
+    }
+    evals.truncate(stride);
+    evals.shrink_to_fit();
+}
+
+/// The domain the evaluations are folded onto: the `arity`-th powers of the elements of `domain`,
+/// of `1 / arity` the size and with the offset raised to the `arity`.
+pub fn fold_domain<F: FftField>(domain: &GeneralEvaluationDomain<F>, arity: usize) -> GeneralEvaluationDomain<F> {
+    GeneralEvaluationDomain::<F>::new(domain.size() / arity)
+        .and_then(|d| d.get_coset(domain.coset_offset().pow([arity as u64])))
+        .expect("Failed to create evaluation domain")
+}
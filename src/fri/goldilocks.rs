@@ -0,0 +1,18 @@
+// The derive of ark-ff 0.4 implements the config inside a function
+#![allow(non_local_definitions)]
+
+use ark_ff::fields::{Fp64, MontBackend, MontConfig};
+
+/// The Goldilocks prime `p = 2^64 - 2^32 + 1`, whose multiplicative group has two-adicity 32
+/// with 7 as a generator.
+///
+/// Over a 64-bit field a single element is too small for a folding challenge or a digest to be
+/// sound on its own: the commit phase error `rounds · |D| / p` is what `FriConfig::estimate`
+/// reports, and the Merkle trees should hash with `sha256` rather than a Poseidon sponge whose
+/// capacity is a single element. `check_two_adicity` bounds the domains to `2^32` elements.
+#[derive(MontConfig)]
+#[modulus = "18446744069414584321"]
+#[generator = "7"]
+pub struct GoldilocksConfig;
+
+pub type Goldilocks = Fp64<MontBackend<GoldilocksConfig, 1>>;
@@ -1,23 +1,48 @@
 // merkle_tree.rs
 use ark_crypto_primitives::crh::{CRHScheme, TwoToOneCRHScheme};
-use ark_ff::Field;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use std::{borrow::Borrow, collections::HashMap};
+use ark_ff::{FftField, Field, PrimeField};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::{cfg_chunks, cfg_into_iter};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{borrow::Borrow, collections::{BTreeMap, HashMap}};
 use std::fmt::Debug;
-use std::hash::Hash;
+use std::io::{Read, Seek, SeekFrom, Write};
 
-#[derive(Clone, Debug)]
+use super::error::MerkleError;
+use crate::util::parallel::ThreadSafe;
+
+/// The element the input of every leaf hash starts with, see `leaf_input`.
+const LEAF_TAG: u64 = 0;
+
+/// A position in an evaluation domain and the point of the domain at that position.
+///
+/// In a Merkle proof it identifies a leaf instead: the leaf's position in the tree and the first of
+/// the points whose values it packs.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct LeafIndex<F: Field> {
     pub index: usize,
     pub point: F,
 }
 
+impl<F: FftField> LeafIndex<F> {
+    /// The position in the domain with the point of the domain at that position, the pair a
+    /// tree over the domain expects.
+    pub fn from_index(index: usize, domain: &GeneralEvaluationDomain<F>) -> Self {
+        Self { index, point: domain.element(index) }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum MerkleNode<F: Field, H> {
+    /// A leaf holding one or more values, the same number for every leaf of a tree, hashed with the
+    /// point labelling it by the leaf hash. Other data is stored as the values `pack_payload`
+    /// gives.
     Leaf {
         hash: H,
         index: LeafIndex<F>,
-        value: F,
+        values: Vec<F>,
     },
     Internal {
         hash: H,
@@ -26,43 +51,715 @@ pub enum MerkleNode<F: Field, H> {
     },
 }
 
-#[derive(Clone, Debug)]
-pub struct MerkleProof<F: Field, H> {
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MerkleProof<F: Field, H: CanonicalSerialize + CanonicalDeserialize> {
     pub root_hash: H,
     pub path: Vec<H>,
     pub leaf_index: LeafIndex<F>,
+    /// The values stored in the opened leaf
+    pub values: Vec<F>,
+}
+
+/// A `MerkleProof` in the form it is serialized in, for a verifier that already holds the root.
+///
+/// At a level with an odd number of nodes, the last node is paired with itself, so the sibling in
+/// its proofs is the node the path has just hashed up to. Such siblings are left out of `path` and
+/// flagged instead, bit `level` of `duplicated` standing for the sibling at that level, and the
+/// root is left out too. See `MerkleTreeOperator::compact_proof` and `expand_proof`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CompactMerkleProof<F: Field, H: CanonicalSerialize + CanonicalDeserialize> {
+    /// The siblings that are not duplicates, bottom-up
+    pub path: Vec<H>,
+    pub duplicated: u64,
+    pub leaf_index: LeafIndex<F>,
+    pub values: Vec<F>,
+}
+
+/// A proof for the contiguous leaves `start..start + leaves.len()` of a tree.
+///
+/// The in-range leaves hash up to the root on their own except at the edges: at each level, the
+/// node left of the range is needed when the range starts at a right child, and the node right
+/// of it when the range ends at a left child.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MerkleRangeProof<F: Field, H: CanonicalSerialize + CanonicalDeserialize> {
+    pub root_hash: H,
+    pub height: usize,
+    pub start: usize,
+    /// The leaves of the range, in order, with the values they hold
+    pub leaves: Vec<(LeafIndex<F>, Vec<F>)>,
+    /// The nodes left of the range, bottom-up, at the levels the range starts at a right child
+    pub left_siblings: Vec<H>,
+    /// The nodes right of the range, bottom-up, at the levels the range ends at a left child
+    pub right_siblings: Vec<H>,
 }
 
+/// A Merkle tree over the evaluations of a polynomial on the domain `coset_offset · <primitive_root>`.
+///
+/// Each leaf packs `leaf_width` evaluations: with `m` leaves, leaf `j` holds the evaluations at
+/// positions `j, j + m, j + 2m, ...`, which form a coset of the subgroup of order `leaf_width`. In
+/// particular `y` and `-y`, half a domain apart, share a leaf as soon as `leaf_width >= 2`.
 #[derive(Clone)]
 pub struct MerkleTree<F: Field, H> {
     pub root: MerkleNode<F, H>,
     pub height: usize,
+    pub leaf_width: usize,
     pub primitive_root: F,
+    pub coset_offset: F,
     pub degree: usize,
     // For proof generation
     nodes_map: HashMap<usize, MerkleNode<F, H>>,
 }
 
+impl<F: Field, H> MerkleTree<F, H> {
+    /// The hash at the root of the tree, the commitment to the evaluations.
+    pub fn root(&self) -> H
+    where
+        H: Clone,
+    {
+        self.root.get_hash()
+    }
+
+    /// The number of levels between the leaves and the root.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The number of leaves, each holding `leaf_width` evaluations.
+    pub fn leaf_count(&self) -> usize {
+        1 << self.height
+    }
+
+    /// The number of evaluations committed to.
+    pub fn domain_size(&self) -> usize {
+        self.leaf_width << self.height
+    }
+
+    /// The leaf holding the evaluation at the given position of the domain.
+    pub fn leaf_of(&self, position: usize) -> LeafIndex<F> {
+        let index = position % self.leaf_count();
+        LeafIndex {
+            index,
+            point: self.coset_offset * self.primitive_root.pow([index as u64]),
+        }
+    }
+
+    /// Checks the leaf index is one of the tree's leaves, with the point of the domain at its
+    /// position.
+    pub fn check_leaf_index(&self, leaf_index: &LeafIndex<F>) -> Result<(), MerkleError> {
+        if leaf_index.index >= self.leaf_count() {
+            return Err(MerkleError::LeafOutOfRange { index: leaf_index.index, leaf_count: self.leaf_count() });
+        }
+        if self.leaf_of(leaf_index.index).point != leaf_index.point {
+            return Err(MerkleError::InconsistentLeafPoint { index: leaf_index.index });
+        }
+        Ok(())
+    }
+
+    /// The hashes of the nodes `cap_height` levels below the root, from left to right, or of the
+    /// leaves if the tree is not that high.
+    pub fn cap(&self, cap_height: usize) -> Vec<H>
+    where
+        H: Clone,
+    {
+        let mut level = vec![&self.root];
+        for _ in 0..cap_height.min(self.height) {
+            level = level
+                .into_iter()
+                .flat_map(|node| match node {
+                    MerkleNode::Internal { left, right, .. } => [left.as_ref(), right.as_ref()],
+                    MerkleNode::Leaf { .. } => unreachable!("Leaf reached above the bottom level of the tree"),
+                })
+                .collect();
+        }
+        level.into_iter().map(|node| node.get_hash()).collect()
+    }
+
+    /// The node at the given index among the nodes `level` levels above the leaves.
+    fn node_at(&self, level: usize, index: usize) -> &MerkleNode<F, H> {
+        let mut node = &self.root;
+        for depth in (level..self.height).rev() {
+            node = match node {
+                MerkleNode::Internal { left, right, .. } if (index >> (depth - level)) & 1 == 0 => left,
+                MerkleNode::Internal { right, .. } => right,
+                MerkleNode::Leaf { .. } => unreachable!("Leaf reached above the bottom level of the tree"),
+            };
+        }
+        node
+    }
+
+    /// The evaluations committed to in the leaf at the given index, in the order of `leaf_points`.
+    pub fn leaf_values(&self, leaf: usize) -> Option<&[F]> {
+        match self.nodes_map.get(&leaf)? {
+            MerkleNode::Leaf { values, .. } => Some(values),
+            MerkleNode::Internal { .. } => None,
+        }
+    }
+
+    /// The point at the given position of the domain and the evaluation committed to there, or
+    /// `None` past the end of the domain.
+    pub fn get_leaf(&self, position: usize) -> Option<(F, F)> {
+        if position >= self.domain_size() {
+            return None;
+        }
+        let leaf = self.leaf_of(position);
+        let offset = position >> self.height;
+        Some((self.leaf_points(&leaf)[offset], self.leaf_values(leaf.index)?[offset]))
+    }
+
+    /// The domain points whose evaluations the leaf holds, in order.
+    pub fn leaf_points(&self, leaf: &LeafIndex<F>) -> Vec<F> {
+        let step = self.primitive_root.pow([1u64 << self.height]);
+        std::iter::successors(Some(leaf.point), |point| Some(*point * step))
+            .take(self.leaf_width)
+            .collect()
+    }
+}
+
+/// How much the paths from some leaves of a tree up to its root overlap, counting the internal
+/// nodes they pass through: a batch of openings only needs each node once.
+#[cfg(feature = "metrics")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OverlapStats {
+    /// The internal nodes of every path, counted once per path
+    pub total_nodes: usize,
+    /// The distinct internal nodes of all the paths together
+    pub unique_nodes: usize,
+    /// The distinct internal nodes on more than one path
+    pub shared_nodes: usize,
+}
+
+#[cfg(feature = "metrics")]
+impl OverlapStats {
+    /// The fraction of the nodes of the paths that another path already passes through, 0 when
+    /// the paths are disjoint and `1 - 1/k` when all k of them are the same.
+    pub fn overlap(&self) -> f64 {
+        if self.total_nodes == 0 {
+            return 0.0;
+        }
+        (self.total_nodes - self.unique_nodes) as f64 / self.total_nodes as f64
+    }
+}
+
+/// The overlap of the paths from the leaves holding the given positions of the domain, as queries
+/// index it, up to the root.
+#[cfg(feature = "metrics")]
+pub fn path_overlap_stats<F: Field, H>(tree: &MerkleTree<F, H>, positions: &[usize]) -> OverlapStats {
+    // The node at `level` above the leaves on the path of leaf j is the (j >> level)-th of its level
+    let mut paths_through: HashMap<(usize, usize), usize> = HashMap::new();
+    for position in positions {
+        let leaf = tree.leaf_of(*position).index;
+        for level in 1..=tree.height {
+            *paths_through.entry((level, leaf >> level)).or_default() += 1;
+        }
+    }
+
+    OverlapStats {
+        total_nodes: positions.len() * tree.height,
+        unique_nodes: paths_through.len(),
+        shared_nodes: paths_through.values().filter(|paths| **paths > 1).count(),
+    }
+}
+
+/// The fixed part of a stored tree, before its leaves and hashes.
+struct StoredHeader<F> {
+    height: usize,
+    leaf_width: usize,
+    leaf_count: usize,
+    degree: usize,
+    primitive_root: F,
+    coset_offset: F,
+}
+
+impl<F: Field> StoredHeader<F> {
+    fn write<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        for size in [self.height, self.leaf_width, self.leaf_count, self.degree] {
+            (size as u64).serialize_compressed(&mut writer)?;
+        }
+        self.primitive_root.serialize_compressed(&mut writer)?;
+        self.coset_offset.serialize_compressed(&mut writer)
+    }
+
+    fn read<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let mut sizes = [0usize; 4];
+        for size in &mut sizes {
+            *size = usize::try_from(u64::deserialize_compressed(&mut reader)?).map_err(|_| SerializationError::InvalidData)?;
+        }
+        let [height, leaf_width, leaf_count, degree] = sizes;
+        if leaf_count == 0 || height >= usize::BITS as usize || leaf_count > 1 << height || leaf_width == 0 {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(Self {
+            height,
+            leaf_width,
+            leaf_count,
+            degree,
+            primitive_root: F::deserialize_compressed(&mut reader)?,
+            coset_offset: F::deserialize_compressed(&mut reader)?,
+        })
+    }
+
+    /// The number of nodes at each level, the leaves first and the root last. An odd node out is
+    /// paired with itself, so each level has half as many nodes as the one below, rounded up.
+    fn level_sizes(&self) -> Vec<usize> {
+        std::iter::successors(Some(self.leaf_count), |&size| Some(size.div_ceil(2)))
+            .take(self.height + 1)
+            .collect()
+    }
+}
+
+impl<F: Field, H: Clone + CanonicalSerialize + CanonicalDeserialize> MerkleTree<F, H> {
+    /// Writes the tree level by level: a header with its shape and domain, the point and values of
+    /// every leaf in order, then the hashes of every level from the leaves up to the root. Leaf
+    /// points, values and hashes are all of a fixed size, so `MerkleTreeView` can seek to any of
+    /// them. Nodes duplicated to pair an odd node out of a level are not written.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        let header = self.stored_header();
+        header.write(&mut writer)?;
+        for leaf in 0..header.leaf_count {
+            let (index, values) = match &self.nodes_map[&leaf] {
+                MerkleNode::Leaf { index, values, .. } => (index, values),
+                MerkleNode::Internal { .. } => unreachable!("Internal node among the leaves"),
+            };
+            index.point.serialize_compressed(&mut writer)?;
+            for value in values {
+                value.serialize_compressed(&mut writer)?;
+            }
+        }
+
+        // Walk down from the root, dropping the duplicates past the end of each level
+        let sizes = header.level_sizes();
+        let mut levels = vec![vec![&self.root]];
+        for &size in sizes.iter().rev().skip(1) {
+            let children = levels
+                .last()
+                .expect("Starts from the root")
+                .iter()
+                .flat_map(|node| match node {
+                    MerkleNode::Internal { left, right, .. } => [left.as_ref(), right.as_ref()],
+                    MerkleNode::Leaf { .. } => unreachable!("Leaf reached above the bottom level of the tree"),
+                })
+                .take(size)
+                .collect();
+            levels.push(children);
+        }
+        for level in levels.iter().rev() {
+            for node in level {
+                node.get_hash().serialize_compressed(&mut writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads back a tree written by `write_to`. The hashes are taken as they are rather than
+    /// recomputed, so a tree read from a corrupted file gives proofs that fail to verify.
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let header = StoredHeader::<F>::read(&mut reader)?;
+        let mut leaves = Vec::with_capacity(header.leaf_count);
+        for index in 0..header.leaf_count {
+            let point = F::deserialize_compressed(&mut reader)?;
+            let values = (0..header.leaf_width)
+                .map(|_| F::deserialize_compressed(&mut reader))
+                .collect::<Result<Vec<F>, _>>()?;
+            leaves.push((LeafIndex { index, point }, values));
+        }
+
+        let sizes = header.level_sizes();
+        let mut level = Vec::with_capacity(header.leaf_count);
+        for (leaf_index, values) in leaves {
+            level.push(MerkleNode::Leaf { hash: H::deserialize_compressed(&mut reader)?, index: leaf_index, values });
+        }
+        let nodes_map = level.iter().cloned().enumerate().collect();
+        for &size in &sizes[1..] {
+            let mut children = level.into_iter();
+            let mut next_level = Vec::with_capacity(size);
+            for _ in 0..size {
+                let left = children.next().ok_or(SerializationError::InvalidData)?;
+                let right = children.next().unwrap_or_else(|| left.clone());
+                let hash = H::deserialize_compressed(&mut reader)?;
+                next_level.push(MerkleNode::Internal { hash, left: Box::new(left), right: Box::new(right) });
+            }
+            level = next_level;
+        }
+
+        Ok(MerkleTree {
+            root: level.pop().ok_or(SerializationError::InvalidData)?,
+            height: header.height,
+            leaf_width: header.leaf_width,
+            primitive_root: header.primitive_root,
+            coset_offset: header.coset_offset,
+            degree: header.degree,
+            nodes_map,
+        })
+    }
+
+    fn stored_header(&self) -> StoredHeader<F> {
+        StoredHeader {
+            height: self.height,
+            leaf_width: self.leaf_width,
+            leaf_count: self.nodes_map.len(),
+            degree: self.degree,
+            primitive_root: self.primitive_root,
+            coset_offset: self.coset_offset,
+        }
+    }
+}
+
+/// A tree written by `MerkleTree::write_to`, read from its storage only as proofs need it: the
+/// leaf being opened and the siblings along its path, rather than every level.
+///
+/// Any `Read + Seek` source works, e.g. a `File`, or a `Cursor` over a memory-mapped file so that
+/// only the pages of the nodes read are loaded. The proofs are those the tree it was written from
+/// gives.
+pub struct MerkleTreeView<F: Field, H, R> {
+    reader: R,
+    header: StoredHeader<F>,
+    root: H,
+    /// Where the leaves start, right after the header
+    leaves_offset: u64,
+    /// Where the hashes of each level start, the leaves first
+    level_offsets: Vec<u64>,
+    level_sizes: Vec<usize>,
+    field_size: u64,
+    hash_size: u64,
+}
+
+impl<F: Field, H: Clone + CanonicalSerialize + CanonicalDeserialize, R: Read + Seek> MerkleTreeView<F, H, R> {
+    /// Reads the header of the stored tree and its root, which is all that is kept in memory.
+    pub fn open(mut reader: R) -> Result<Self, SerializationError> {
+        reader.seek(SeekFrom::Start(0))?;
+        let header = StoredHeader::<F>::read(&mut reader)?;
+        let leaves_offset = reader.stream_position()?;
+        let field_size = header.primitive_root.compressed_size() as u64;
+        let leaf_size = (header.leaf_width as u64 + 1) * field_size;
+
+        // Digests are of a fixed size, measured on the first one
+        let first_hash_offset = leaves_offset + header.leaf_count as u64 * leaf_size;
+        reader.seek(SeekFrom::Start(first_hash_offset))?;
+        let hash_size = H::deserialize_compressed(&mut reader)?.compressed_size() as u64;
+
+        let level_sizes = header.level_sizes();
+        let level_offsets = level_sizes
+            .iter()
+            .scan(first_hash_offset, |offset, &size| {
+                let start = *offset;
+                *offset += size as u64 * hash_size;
+                Some(start)
+            })
+            .collect::<Vec<_>>();
+        reader.seek(SeekFrom::Start(*level_offsets.last().expect("A tree has a root")))?;
+        let root = H::deserialize_compressed(&mut reader)?;
+
+        Ok(Self { reader, header, root, leaves_offset, level_offsets, level_sizes, field_size, hash_size })
+    }
+
+    pub fn root(&self) -> H {
+        self.root.clone()
+    }
+
+    pub fn height(&self) -> usize {
+        self.header.height
+    }
+
+    /// Creates the proof `MerkleTreeOperator::create_proof` gives for the leaf in the tree.
+    pub fn create_proof(&mut self, leaf_index: &LeafIndex<F>) -> Result<MerkleProof<F, H>, MerkleError> {
+        self.create_proof_with_cap(leaf_index, 0)
+    }
+
+    /// Creates the proof `MerkleTreeOperator::create_proof_with_cap` gives for the leaf in the tree.
+    pub fn create_proof_with_cap(&mut self, leaf_index: &LeafIndex<F>, cap_height: usize) -> Result<MerkleProof<F, H>, MerkleError> {
+        let header = &self.header;
+        let leaf_count = 1 << header.height;
+        if leaf_index.index >= leaf_count {
+            return Err(MerkleError::LeafOutOfRange { index: leaf_index.index, leaf_count });
+        }
+        if header.coset_offset * header.primitive_root.pow([leaf_index.index as u64]) != leaf_index.point {
+            return Err(MerkleError::InconsistentLeafPoint { index: leaf_index.index });
+        }
+        self.read_proof(leaf_index, cap_height).map_err(|error| MerkleError::Storage(error.to_string()))
+    }
+
+    fn read_proof(&mut self, leaf_index: &LeafIndex<F>, cap_height: usize) -> Result<MerkleProof<F, H>, SerializationError> {
+        // Walk down from the root following the bits of the index, as the tree does. A node past
+        // the end of its level is the copy of the last one pairing it, so its subtree is that of the
+        // last node and the nodes under it are found there.
+        let height = self.header.height;
+        let mut positions = vec![0; height + 1];
+        for level in (0..height).rev() {
+            let child = 2 * positions[level + 1] + ((leaf_index.index >> level) & 1);
+            positions[level] = child.min(self.level_sizes[level] - 1);
+        }
+
+        let leaf_offset = self.leaves_offset + positions[0] as u64 * (self.header.leaf_width as u64 + 1) * self.field_size;
+        self.reader.seek(SeekFrom::Start(leaf_offset + self.field_size))?;
+        let values = (0..self.header.leaf_width)
+            .map(|_| F::deserialize_compressed(&mut self.reader))
+            .collect::<Result<Vec<F>, _>>()?;
+
+        // The sibling at each level up to the cap, or the node itself when it is paired with itself
+        let levels = height - cap_height.min(height);
+        let mut path = Vec::with_capacity(levels);
+        for (level, &node) in positions.iter().enumerate().take(levels) {
+            let sibling = if node ^ 1 < self.level_sizes[level] { node ^ 1 } else { node };
+            self.reader.seek(SeekFrom::Start(self.level_offsets[level] + sibling as u64 * self.hash_size))?;
+            path.push(H::deserialize_compressed(&mut self.reader)?);
+        }
+
+        Ok(MerkleProof { root_hash: self.root.clone(), path, leaf_index: leaf_index.clone(), values })
+    }
+}
+
 pub trait MerkleTreeOperator<F: Field, INCH: TwoToOneCRHScheme>
 {
+    /// The maximum number of evaluations packed in a leaf. Domains smaller than this are
+    /// committed to with a single leaf.
+    fn leaf_width(&self) -> usize;
+
+    /// Creates a tree whose leaves each hash a point and the values stored at the leaf. There must
+    /// be at least one leaf, and every leaf must hold the same non-zero number of values.
     fn create_tree(
-        self: &Self,
-        points: Vec<(LeafIndex<F>, F)>,
+        &self,
+        leaves: Vec<(LeafIndex<F>, Vec<F>)>,
         primitive_root: F,
+        coset_offset: F,
         degree: usize,
-    ) -> MerkleTree<F, INCH::Output>;
+    ) -> Result<MerkleTree<F, INCH::Output>, MerkleError>;
 
-    fn create_proof(&self, tree: &MerkleTree<F, INCH::Output>, leaf_index: &LeafIndex<F>) -> MerkleProof<F, INCH::Output>;
-    fn verify_proof(
-        &self,
-        proof: &MerkleProof<F, INCH::Output>,
-        value: F
-    ) -> bool;
+    /// Creates a proof for the leaf, which must be consistent with the tree, see
+    /// `MerkleTree::check_leaf_index`.
+    fn create_proof(&self, tree: &MerkleTree<F, INCH::Output>, leaf_index: &LeafIndex<F>) -> Result<MerkleProof<F, INCH::Output>, MerkleError> {
+        self.create_proof_with_cap(tree, leaf_index, 0)
+    }
+    /// Creates a proof whose path stops `cap_height` levels below the root, at a node of
+    /// `tree.cap(cap_height)`.
+    fn create_proof_with_cap(&self, tree: &MerkleTree<F, INCH::Output>, leaf_index: &LeafIndex<F>, cap_height: usize) -> Result<MerkleProof<F, INCH::Output>, MerkleError>;
+    /// Creates a proof for the leaves `start..end`, which must be a non-empty range of the tree.
+    fn create_range_proof(&self, tree: &MerkleTree<F, INCH::Output>, start: usize, end: usize) -> Result<MerkleRangeProof<F, INCH::Output>, MerkleError> {
+        if start >= end || end > tree.leaf_count() {
+            return Err(MerkleError::InvalidRange { start, end, leaf_count: tree.leaf_count() });
+        }
+
+        let leaves = (start..end)
+            .map(|leaf| match tree.node_at(0, leaf) {
+                MerkleNode::Leaf { index, values, .. } => (index.clone(), values.clone()),
+                MerkleNode::Internal { .. } => unreachable!("Internal node at the bottom level of the tree"),
+            })
+            .collect();
+        let (mut left_siblings, mut right_siblings) = (Vec::new(), Vec::new());
+        let (mut first, mut last) = (start, end - 1);
+        for level in 0..tree.height {
+            if first % 2 == 1 {
+                left_siblings.push(tree.node_at(level, first - 1).get_hash());
+            }
+            if last % 2 == 0 {
+                right_siblings.push(tree.node_at(level, last + 1).get_hash());
+            }
+            first /= 2;
+            last /= 2;
+        }
+
+        Ok(MerkleRangeProof { root_hash: tree.root(), height: tree.height, start, leaves, left_siblings, right_siblings })
+    }
+    /// Checks that the leaves in the proof are those of its range in the tree with the given root.
+    fn verify_range_proof(&self, proof: &MerkleRangeProof<F, INCH::Output>, expected_root: &INCH::Output) -> bool {
+        let end = proof.start + proof.leaves.len();
+        if proof.leaves.is_empty()
+            || proof.height >= usize::BITS as usize
+            || end > 1 << proof.height
+            || proof.leaves.iter().enumerate().any(|(k, (leaf_index, _))| leaf_index.index != proof.start + k)
+        {
+            return false;
+        }
+
+        // Hash the range up level by level, completing it at the edges with the siblings
+        let mut level: Vec<INCH::Output> = proof.leaves.iter().map(|(leaf_index, values)| self.hash_leaf(leaf_index, values)).collect();
+        let (mut left_siblings, mut right_siblings) = (proof.left_siblings.iter(), proof.right_siblings.iter());
+        let (mut first, mut last) = (proof.start, end - 1);
+        for _ in 0..proof.height {
+            if first % 2 == 1 {
+                let Some(sibling) = left_siblings.next() else {
+                    return false;
+                };
+                level.insert(0, sibling.clone());
+            }
+            if last % 2 == 0 {
+                let Some(sibling) = right_siblings.next() else {
+                    return false;
+                };
+                level.push(sibling.clone());
+            }
+            level = level.chunks(2).map(|pair| self.hash_pair(&pair[0], &pair[1])).collect();
+            first /= 2;
+            last /= 2;
+        }
+
+        left_siblings.next().is_none()
+            && right_siblings.next().is_none()
+            && proof.root_hash == *expected_root
+            && level.len() == 1
+            && level[0] == *expected_root
+    }
+    /// The compact form of the proof, flagging the siblings that duplicate the node they are
+    /// hashed with rather than storing them.
+    fn compact_proof(&self, proof: &MerkleProof<F, INCH::Output>) -> CompactMerkleProof<F, INCH::Output> {
+        let mut current = self.hash_leaf(&proof.leaf_index, &proof.values);
+        let mut path = Vec::with_capacity(proof.path.len());
+        let mut duplicated = 0;
+        for (level, sibling) in proof.path.iter().enumerate() {
+            if *sibling == current && level < u64::BITS as usize {
+                duplicated |= 1 << level;
+            } else {
+                path.push(sibling.clone());
+            }
+            current = self.hash_path_step(proof.leaf_index.index >> level, &current, sibling);
+        }
+        CompactMerkleProof { path, duplicated, leaf_index: proof.leaf_index.clone(), values: proof.values.clone() }
+    }
+    /// The proof the compact one was made from, given the root it was made under, or `None` if
+    /// `duplicated` flags a level past the end of the path.
+    fn expand_proof(&self, proof: &CompactMerkleProof<F, INCH::Output>, root: &INCH::Output) -> Option<MerkleProof<F, INCH::Output>> {
+        let height = proof.path.len() + proof.duplicated.count_ones() as usize;
+        if height < u64::BITS as usize && proof.duplicated >> height != 0 {
+            return None;
+        }
+
+        let mut current = self.hash_leaf(&proof.leaf_index, &proof.values);
+        let mut stored = proof.path.iter();
+        let mut path = Vec::with_capacity(height);
+        for level in 0..height {
+            let sibling = if level < u64::BITS as usize && (proof.duplicated >> level) & 1 == 1 {
+                current.clone()
+            } else {
+                stored.next()?.clone()
+            };
+            current = self.hash_path_step(proof.leaf_index.index >> level, &current, &sibling);
+            path.push(sibling);
+        }
+        Some(MerkleProof { root_hash: root.clone(), path, leaf_index: proof.leaf_index.clone(), values: proof.values.clone() })
+    }
+    /// Checks a compact proof against the root by expanding it.
+    fn verify_compact_proof(&self, proof: &CompactMerkleProof<F, INCH::Output>, root: &INCH::Output) -> bool {
+        self.expand_proof(proof, root).is_some_and(|proof| self.verify_proof(&proof))
+    }
+    /// Checks that the values in the proof are those of its leaf under its root.
+    fn verify_proof(&self, proof: &MerkleProof<F, INCH::Output>) -> bool {
+        self.verify_proof_with_cap(proof, std::slice::from_ref(&proof.root_hash))
+    }
+    /// Checks that the values in the proof are those of its leaf under the node of the cap its
+    /// path ends at.
+    fn verify_proof_with_cap(&self, proof: &MerkleProof<F, INCH::Output>, cap: &[INCH::Output]) -> bool;
+    /// Checks that the cap, whose length must be a power of two, hashes up to the root.
+    fn verify_cap(&self, cap: &[INCH::Output], root: &INCH::Output) -> bool;
+
+    /// Checks many proofs at once, each against its own root. Proofs under the same root share the
+    /// hashing of the nodes their paths have in common, see `verify_proofs_batch_with_cap`.
+    fn verify_proofs_batch(&self, proofs: &[&MerkleProof<F, INCH::Output>]) -> bool {
+        let mut roots: Vec<&INCH::Output> = Vec::new();
+        for proof in proofs {
+            if !roots.contains(&&proof.root_hash) {
+                roots.push(&proof.root_hash);
+            }
+        }
+        roots.into_iter().all(|root| {
+            let group: Vec<_> = proofs.iter().copied().filter(|proof| proof.root_hash == *root).collect();
+            self.verify_proofs_batch_with_cap(&group, std::slice::from_ref(root))
+        })
+    }
+
+    /// Checks proofs whose paths all end at the cap, accepting exactly when every one of them
+    /// would pass `verify_proof_with_cap`.
+    ///
+    /// The paths are merged bottom-up: each node above the leaves is hashed once, however many
+    /// paths go through it, and a sibling a path claims must match the node computed or claimed
+    /// by the others.
+    fn verify_proofs_batch_with_cap(&self, proofs: &[&MerkleProof<F, INCH::Output>], cap: &[INCH::Output]) -> bool {
+        let Some(first) = proofs.first() else {
+            return true;
+        };
+        let height = first.path.len();
+        if proofs.iter().any(|proof| proof.path.len() != height) {
+            return false;
+        }
+
+        // The nodes computed at the current level, by index
+        let mut nodes = BTreeMap::new();
+        for proof in proofs {
+            let hash = self.hash_leaf(&proof.leaf_index, &proof.values);
+            if nodes.insert(proof.leaf_index.index, hash.clone()).is_some_and(|other| other != hash) {
+                return false;
+            }
+        }
+
+        for level in 0..height {
+            // The siblings only known from the paths
+            let mut siblings = BTreeMap::new();
+            for proof in proofs {
+                let sibling = (proof.leaf_index.index >> level) ^ 1;
+                let claimed = &proof.path[level];
+                if let Some(known) = nodes.get(&sibling).or(siblings.get(&sibling)) {
+                    if known != claimed {
+                        return false;
+                    }
+                } else {
+                    siblings.insert(sibling, claimed.clone());
+                }
+            }
+
+            let mut parents = BTreeMap::new();
+            for (&index, hash) in &nodes {
+                if parents.contains_key(&(index >> 1)) {
+                    continue;
+                }
+                let sibling = nodes.get(&(index ^ 1)).or(siblings.get(&(index ^ 1))).expect("Every path claims its sibling");
+                let (left, right) = if index % 2 == 0 { (hash, sibling) } else { (sibling, hash) };
+                parents.insert(index >> 1, self.hash_pair(left, right));
+            }
+            nodes = parents;
+        }
+
+        nodes.iter().all(|(index, hash)| cap.get(*index) == Some(hash))
+    }
+
+    /// Hashes a leaf: the point labelling it followed by its values.
+    fn hash_leaf(&self, leaf_index: &LeafIndex<F>, values: &[F]) -> INCH::Output;
+    /// Hashes values the way leaves are hashed.
+    fn hash_values(&self, values: &[F]) -> INCH::Output;
+    /// Hashes two digests the way sibling nodes are combined.
+    fn hash_pair(&self, left: &INCH::Output, right: &INCH::Output) -> INCH::Output;
+    /// Hashes the node at the given index of its level with its sibling into their parent.
+    fn hash_path_step(&self, index: usize, node: &INCH::Output, sibling: &INCH::Output) -> INCH::Output {
+        if index.is_multiple_of(2) {
+            self.hash_pair(node, sibling)
+        } else {
+            self.hash_pair(sibling, node)
+        }
+    }
 }
 
 pub struct MerkleTreeOperatorImpl<LCH: CRHScheme, INCH: TwoToOneCRHScheme> {
     leaf_crh_params: LCH::Parameters,
     two_to_one_crh_params: INCH::Parameters,
+    leaf_width: usize,
+}
+
+impl<LCH: CRHScheme, INCH: TwoToOneCRHScheme> MerkleTreeOperatorImpl<LCH, INCH> {
+    pub fn new(leaf_crh_params: LCH::Parameters, two_to_one_crh_params: INCH::Parameters) -> Self {
+        Self::with_leaf_width(leaf_crh_params, two_to_one_crh_params, 1)
+    }
+
+    pub fn with_leaf_width(leaf_crh_params: LCH::Parameters, two_to_one_crh_params: INCH::Parameters, leaf_width: usize) -> Self {
+        assert!(leaf_width.is_power_of_two(), "Leaf width must be a power of two");
+        Self {
+            leaf_crh_params,
+            two_to_one_crh_params,
+            leaf_width,
+        }
+    }
+}
+
+impl<LCH: CRHScheme, INCH: TwoToOneCRHScheme> Clone for MerkleTreeOperatorImpl<LCH, INCH> {
+    fn clone(&self) -> Self {
+        Self::with_leaf_width(self.leaf_crh_params.clone(), self.two_to_one_crh_params.clone(), self.leaf_width)
+    }
 }
 
 
@@ -70,30 +767,44 @@ pub struct MerkleTreeOperatorImpl<LCH: CRHScheme, INCH: TwoToOneCRHScheme> {
 impl<F: Field, LCH, INCH> MerkleTreeOperator<F, INCH> for MerkleTreeOperatorImpl<LCH, INCH>
 where
     LCH: CRHScheme<Input = [F], Output = INCH::Output>,
-    INCH: TwoToOneCRHScheme + Clone,
-    INCH::Output: Clone,
+    INCH: TwoToOneCRHScheme,
+    INCH::Output: Clone + ThreadSafe,
+    LCH::Parameters: ThreadSafe,
+    INCH::Parameters: ThreadSafe,
     for<'a> &'a INCH::Output: Borrow<INCH::Input>,
 {
+    fn leaf_width(&self) -> usize {
+        self.leaf_width
+    }
+
     fn create_tree(
         &self,
-        points: Vec<(LeafIndex<F>, F)>,
+        leaves: Vec<(LeafIndex<F>, Vec<F>)>,
         primitive_root: F,
+        coset_offset: F,
         degree: usize,
-    ) -> MerkleTree<F, INCH::Output> {
+    ) -> Result<MerkleTree<F, INCH::Output>, MerkleError> {
+        let leaf_width = leaves.first().ok_or(MerkleError::NoLeaves)?.1.len();
+        if leaf_width == 0 {
+            return Err(MerkleError::EmptyLeaves);
+        }
+        if let Some(index) = leaves.iter().position(|(_, values)| values.len() != leaf_width) {
+            return Err(MerkleError::LeafWidthMismatch { index, expected: leaf_width, actual: leaves[index].1.len() });
+        }
+
         // Create leaf nodes
-        let leaves: Vec<MerkleNode<F, INCH::Output>> = points
-            .iter()
-            .map(|(idx, val)| {
+        let leaves: Vec<MerkleNode<F, INCH::Output>> = cfg_into_iter!(leaves)
+            .map(|(idx, values)| {
                 // Hash the leaf data using CRHScheme
-                let leaf_input = vec![idx.point, *val];
-                let leaf_hash = LCH::evaluate(&self.leaf_crh_params, leaf_input).unwrap();
-                MerkleNode::Leaf {
+                let leaf_hash = LCH::evaluate(&self.leaf_crh_params, leaf_input(&idx, &values))
+                    .map_err(|error| MerkleError::Hash(error.to_string()))?;
+                Ok(MerkleNode::Leaf {
                     hash: leaf_hash,
-                    index: idx.clone(),
-                    value: *val,
-                }
+                    index: idx,
+                    values,
+                })
             })
-            .collect();
+            .collect::<Result<_, MerkleError>>()?;
 
         let mut nodes_map = HashMap::new();
         for (i, leaf) in leaves.iter().enumerate() {
@@ -104,98 +815,74 @@ where
         let mut current_level = leaves;
         let mut height = 0;
         while current_level.len() > 1 {
-            let mut next_level = Vec::new();
-            for i in (0..current_level.len()).step_by(2) {
-                let left = Box::new(current_level[i].clone());
-                let right = if i + 1 < current_level.len() {
-                    Box::new(current_level[i + 1].clone())
-                } else {
-                    Box::new(current_level[i].clone())
-                };
-                // Hash the two child hashes using TwoToOneCRHScheme
-                
-                let combined_hash = INCH::evaluate(
-                    &self.two_to_one_crh_params,
-                    &left.get_hash(),
-                    &right.get_hash(),
-                )
-                .unwrap();
-                let parent = MerkleNode::Internal {
-                    hash: combined_hash,
-                    left,
-                    right,
-                };
-                next_level.push(parent);
-            }
-            current_level = next_level;
+            // The pairs of a level are hashed independently, an odd node out with itself
+            current_level = cfg_chunks!(current_level, 2)
+                .map(|pair| {
+                    let left = Box::new(pair[0].clone());
+                    let right = Box::new(pair.get(1).unwrap_or(&pair[0]).clone());
+                    // Hash the two child hashes using TwoToOneCRHScheme
+                    let combined_hash = INCH::evaluate(
+                        &self.two_to_one_crh_params,
+                        &left.get_hash(),
+                        &right.get_hash(),
+                    )
+                    .map_err(|error| MerkleError::Hash(error.to_string()))?;
+                    Ok(MerkleNode::Internal {
+                        hash: combined_hash,
+                        left,
+                        right,
+                    })
+                })
+                .collect::<Result<_, MerkleError>>()?;
             height += 1;
         }
 
-        let root = current_level.pop().unwrap();
-        MerkleTree {
+        let root = current_level.pop().expect("A tree with leaves has a root");
+        Ok(MerkleTree {
             root,
             height,
+            leaf_width,
             primitive_root,
+            coset_offset,
             degree,
             nodes_map,
-        }
+        })
     }
 
-    fn create_proof(&self, tree: &MerkleTree<F, INCH::Output>, leaf_index: &LeafIndex<F>) -> MerkleProof<F, INCH::Output> {
-        let mut path = Vec::new();
-        let mut index = leaf_index.index;
-        let mut current_hash = tree
-            .nodes_map
-            .get(&index)
-            .unwrap()
-            .get_hash()
-            .clone();
+    fn create_proof_with_cap(&self, tree: &MerkleTree<F, INCH::Output>, leaf_index: &LeafIndex<F>, cap_height: usize) -> Result<MerkleProof<F, INCH::Output>, MerkleError> {
+        tree.check_leaf_index(leaf_index)?;
 
-        let mut current_level_nodes = tree.nodes_map.clone();
-
-        for _ in 0..tree.height {
-            let sibling_index = if index % 2 == 0 { index + 1 } else { index - 1 };
-            let sibling_node = current_level_nodes.get(&sibling_index);
-
-            let sibling_hash = if let Some(node) = sibling_node {
-                node.get_hash()
-            } else {
-                current_hash.clone()
-            };
-            path.push(sibling_hash.clone());
-
-            if index % 2 == 0 {
-                current_hash = INCH::evaluate(
-                    &self.two_to_one_crh_params,
-                    &current_hash,
-                    &sibling_hash,
-                )
-                .unwrap();
-            } else {
-                current_hash = INCH::evaluate(
-                    &self.two_to_one_crh_params,
-                    &sibling_hash,
-                    &current_hash,
-                )
-                .unwrap();
+        // Walk down from the root following the bits of the index, collecting the siblings
+        let mut path = Vec::with_capacity(tree.height);
+        let mut node = &tree.root;
+        for level in (0..tree.height).rev() {
+            match node {
+                MerkleNode::Internal { left, right, .. } => {
+                    if (leaf_index.index >> level) & 1 == 0 {
+                        path.push(right.get_hash());
+                        node = left;
+                    } else {
+                        path.push(left.get_hash());
+                        node = right;
+                    }
+                }
+                MerkleNode::Leaf { .. } => unreachable!("Leaf reached above the bottom level of the tree"),
             }
-            index /= 2;
         }
+        // The path is verified bottom-up, up to the cap
+        path.reverse();
+        path.truncate(tree.height - cap_height.min(tree.height));
 
-        MerkleProof {
-            root_hash: tree.root.get_hash(),
+        Ok(MerkleProof {
+            root_hash: tree.root(),
             path,
             leaf_index: leaf_index.clone(),
-        }
+            values: node.values().expect("Walked down to a leaf").to_vec(),
+        })
     }
 
-    fn verify_proof(
-        &self,
-        proof: &MerkleProof<F, INCH::Output>,
-        value: F
-    ) -> bool {
-        let leaf_input = vec![proof.leaf_index.point, value];
-        let mut current_hash = LCH::evaluate(&self.leaf_crh_params, leaf_input).unwrap();
+    fn verify_proof_with_cap(&self, proof: &MerkleProof<F, INCH::Output>, cap: &[INCH::Output]) -> bool {
+        let mut current_hash = self.hash_leaf(&proof.leaf_index, &proof.values);
         let mut index = proof.leaf_index.index;
 
         for sibling_hash in &proof.path {
@@ -217,7 +904,34 @@ where
             index /= 2;
         }
 
-        current_hash == proof.root_hash
+        cap.get(index).is_some_and(|node| *node == current_hash)
+    }
+
+    fn verify_cap(&self, cap: &[INCH::Output], root: &INCH::Output) -> bool {
+        if !cap.len().is_power_of_two() {
+            return false;
+        }
+
+        let mut level = cap.to_vec();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| INCH::evaluate(&self.two_to_one_crh_params, &pair[0], &pair[1]).unwrap())
+                .collect();
+        }
+        level[0] == *root
+    }
+
+    fn hash_leaf(&self, leaf_index: &LeafIndex<F>, values: &[F]) -> INCH::Output {
+        LCH::evaluate(&self.leaf_crh_params, leaf_input(leaf_index, values)).unwrap()
+    }
+
+    fn hash_values(&self, values: &[F]) -> INCH::Output {
+        LCH::evaluate(&self.leaf_crh_params, values).unwrap()
+    }
+
+    fn hash_pair(&self, left: &INCH::Output, right: &INCH::Output) -> INCH::Output {
+        INCH::evaluate(&self.two_to_one_crh_params, left, right).unwrap()
     }
 }
 
@@ -229,11 +943,42 @@ impl<F: Field, H: Clone> MerkleNode<F, H> {
         }
     }
 
-    pub fn value(&self) -> Option<F> {
-        if let MerkleNode::Leaf { value, .. } = self {
-            Some(*value)
+    pub fn values(&self) -> Option<&[F]> {
+        if let MerkleNode::Leaf { values, .. } = self {
+            Some(values)
         } else {
             None
         }
     }
+}
+
+/// The values a leaf stores for arbitrary data: the length of its compressed serialization
+/// followed by its bytes, packed little-endian into as many elements as needed, each holding fewer
+/// bits than the modulus so that distinct payloads give distinct values.
+///
+/// The leaves of a tree all hold the same number of values, so the payloads of a tree should have
+/// serializations of the same length.
+pub fn pack_payload<F: PrimeField, T: CanonicalSerialize>(payload: &T) -> Vec<F> {
+    let mut bytes = Vec::with_capacity(payload.compressed_size());
+    payload.serialize_compressed(&mut bytes).expect("Serializing to a vector does not fail");
+
+    let chunk_size = (F::MODULUS_BIT_SIZE as usize - 1) / 8;
+    std::iter::once(F::from(bytes.len() as u64))
+        .chain(bytes.chunks(chunk_size).map(F::from_le_bytes_mod_order))
+        .collect()
+}
+
+/// The data hashed into a leaf: the leaf tag, its first point, then its values.
+///
+/// Internal nodes hash exactly two digests, while a leaf hashes the tag and at least two elements
+/// after it, so the two never share an input even when the leaf and two-to-one hashes are the same
+/// sponge, as the Poseidon ones are. Without the tag, the leaf of width 1 whose point and value are
+/// the two children of a node would hash to that node, and its proof up from there would verify.
+/// The SHA-256 hashes tag their inputs themselves.
+fn leaf_input<F: Field>(leaf_index: &LeafIndex<F>, values: &[F]) -> Vec<F> {
+    let mut input = Vec::with_capacity(values.len() + 2);
+    input.push(F::from(LEAF_TAG));
+    input.push(leaf_index.point);
+    input.extend_from_slice(values);
+    input
 }
\ No newline at end of file
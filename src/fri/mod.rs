@@ -4,4 +4,17 @@ pub mod utils;
 pub mod prover;
 pub mod verifier;
 pub mod protocol;
+pub mod config;
+pub mod deep;
+pub mod error;
+pub mod transcript;
+pub mod oracle;
+pub mod fold;
+pub mod domain;
+pub mod progress;
+pub mod sha256;
+pub mod extension;
+#[cfg(feature = "small-field")]
+pub mod goldilocks;
+mod test;
 
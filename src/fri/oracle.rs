@@ -0,0 +1,189 @@
+use std::borrow::Borrow;
+use std::fmt::Debug;
+
+use ark_crypto_primitives::crh::{CRHScheme, TwoToOneCRHScheme};
+use ark_ff::FftField;
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::util::parallel::ThreadSafe;
+use crate::util::VerifierChallenge;
+
+use super::config::FriConfig;
+use super::merkle_tree::{MerkleProof, MerkleTree, MerkleTreeOperator, MerkleTreeOperatorImpl};
+use super::prover::Prover;
+
+/// A commitment to the evaluations of a function over a domain, which can be opened one position
+/// at a time. FRI only needs this much from the committed oracle to test it is close to a
+/// polynomial of low degree.
+pub trait CommitmentOracle<F: FftField> {
+    type Commitment: Clone + PartialEq + Debug + CanonicalSerialize + CanonicalDeserialize;
+    /// What the prover keeps to open the commitment later
+    type Committed;
+    type OpeningProof: Clone + Debug + CanonicalSerialize + CanonicalDeserialize;
+
+    /// Commits to the evaluations over the domain, given in the order of its elements.
+    fn commit(&self, evaluations: &[F], domain: &GeneralEvaluationDomain<F>) -> (Self::Committed, Self::Commitment);
+
+    /// Opens the evaluation at the given position of the domain.
+    fn open(&self, committed: &Self::Committed, index: usize) -> (F, Self::OpeningProof);
+
+    /// Whether the proof opens the commitment to the value at the given position.
+    fn verify_open(&self, commitment: &Self::Commitment, index: usize, value: F, proof: &Self::OpeningProof) -> bool;
+}
+
+impl<F: FftField, LCH, INCH> CommitmentOracle<F> for MerkleTreeOperatorImpl<LCH, INCH>
+where
+    LCH: CRHScheme<Input = [F], Output = INCH::Output>,
+    INCH: TwoToOneCRHScheme,
+    INCH::Output: ThreadSafe,
+    LCH::Parameters: ThreadSafe,
+    INCH::Parameters: ThreadSafe,
+    for<'a> &'a INCH::Output: Borrow<INCH::Input>,
+{
+    type Commitment = INCH::Output;
+    type Committed = MerkleTree<F, INCH::Output>;
+    type OpeningProof = MerkleProof<F, INCH::Output>;
+
+    fn commit(&self, evaluations: &[F], domain: &GeneralEvaluationDomain<F>) -> (Self::Committed, Self::Commitment) {
+        let (tree, commitment) = Prover::<F, DensePolynomial<F>>::commit_evaluations_rec::<LCH, INCH, Self>(
+            evaluations,
+            domain,
+            domain.size() - 1,
+            self,
+        );
+        (tree, commitment.merkle_root)
+    }
+
+    fn open(&self, tree: &Self::Committed, index: usize) -> (F, Self::OpeningProof) {
+        let proof = self.create_proof(tree, &tree.leaf_of(index)).expect("leaf_of gives leaves of the tree");
+        (proof.values[index >> tree.height()], proof)
+    }
+
+    fn verify_open(&self, commitment: &Self::Commitment, index: usize, value: F, proof: &Self::OpeningProof) -> bool {
+        let leaf_count = 1 << proof.path.len();
+        proof.root_hash == *commitment
+            && proof.leaf_index.index == index % leaf_count
+            && proof.values.get(index / leaf_count) == Some(&value)
+            && self.verify_proof(proof)
+    }
+}
+
+/// The values at `y` and `-y` of a layer, each with the proof of its opening.
+pub type PairOpening<F, O> = [(F, <O as CommitmentOracle<F>>::OpeningProof); 2];
+
+/// A FRI proof over an abstract oracle: the commitment of every folded layer, the initial one
+/// first, the openings of `f(y)` and `f(-y)` for every round and query, and the final polynomial.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct OracleFRIProof<F: FftField, O: CommitmentOracle<F>> {
+    pub commitments: Vec<O::Commitment>,
+    pub openings: Vec<Vec<PairOpening<F, O>>>,
+    pub final_polynomial: Vec<F>,
+}
+
+/// FRI as a low-degree test of a committed oracle, generic over how the layers are committed to.
+///
+/// Each folding challenge is derived from the commitment of the layer it folds, and the queries
+/// are supplied by the caller. Unlike `FRISystemImpl`, nothing is packed or capped: every
+/// opening is of a single position.
+pub struct OracleFRI<F, P, O, VC>
+where
+    F: FftField,
+    P: DenseUVPolynomial<F>,
+    O: CommitmentOracle<F>,
+    VC: VerifierChallenge<Commitment = O::Commitment, Challenge = F>,
+{
+    pub config: FriConfig,
+    pub oracle: O,
+    pub verifier_challenge: VC,
+    _phantom: std::marker::PhantomData<P>,
+}
+
+impl<F, P, O, VC> OracleFRI<F, P, O, VC>
+where
+    F: FftField,
+    P: DenseUVPolynomial<F>,
+    O: CommitmentOracle<F>,
+    VC: VerifierChallenge<Commitment = O::Commitment, Challenge = F>,
+{
+    pub fn new(config: FriConfig, oracle: O, verifier_challenge: VC) -> Self {
+        Self { config, oracle, verifier_challenge, _phantom: std::marker::PhantomData }
+    }
+
+    /// Proves the polynomial has at most the given degree, opening every round at the queries,
+    /// which are positions in the initial domain.
+    pub fn prove(&self, polynomial: &P, degree: usize, query_indices: &[usize]) -> OracleFRIProof<F, O> {
+        let mut domain = Prover::<F, P>::create_domain_with_offset(degree, self.config.blowup, F::one())
+            .expect("The field must have a domain for the degree");
+        let mut evaluations = domain.fft(polynomial.coeffs());
+        let mut commitments = Vec::new();
+        let mut openings = Vec::new();
+
+        for _ in 0..self.config.num_rounds(degree) {
+            let (committed, commitment) = self.oracle.commit(&evaluations, &domain);
+            let n = domain.size();
+            openings.push(
+                query_indices
+                    .iter()
+                    .map(|query| {
+                        let index = query % n;
+                        [self.oracle.open(&committed, index), self.oracle.open(&committed, (index + n / 2) % n)]
+                    })
+                    .collect(),
+            );
+
+            let challenge = self.verifier_challenge.generate_nonzero_challenge(&commitment);
+            commitments.push(commitment);
+            domain = Prover::<F, P>::reduce_evaluations_in_place(&mut evaluations, &domain, challenge);
+        }
+
+        let final_polynomial = Prover::prove_small(&P::from_coefficients_vec(domain.ifft(&evaluations)));
+        OracleFRIProof { commitments, openings, final_polynomial }
+    }
+
+    /// Verifies a proof that a polynomial of at most the given degree was committed to, opened at
+    /// the given queries.
+    pub fn verify(&self, proof: &OracleFRIProof<F, O>, degree: usize, query_indices: &[usize]) -> bool {
+        let rounds = self.config.num_rounds(degree);
+        if proof.commitments.len() != rounds
+            || proof.openings.len() != rounds
+            || proof.openings.iter().any(|round| round.len() != query_indices.len())
+            || proof.final_polynomial.len() > self.config.max_degree + 1
+        {
+            return false;
+        }
+
+        let challenges: Vec<F> = proof
+            .commitments
+            .iter()
+            .map(|commitment| self.verifier_challenge.generate_nonzero_challenge(commitment))
+            .collect();
+        let final_polynomial = P::from_coefficients_slice(&proof.final_polynomial);
+        let Ok(initial_domain) = Prover::<F, P>::create_domain_with_offset(degree, self.config.blowup, F::one()) else {
+            return false;
+        };
+
+        query_indices.iter().enumerate().all(|(q, query)| {
+            // The value each round folds to, which the next round must open at y^2
+            let mut domain = initial_domain;
+            let mut folded = None;
+            for (round, challenge) in challenges.iter().enumerate() {
+                let n = domain.size();
+                let index = query % n;
+                let [(f_y, y_proof), (f_neg_y, neg_y_proof)] = &proof.openings[round][q];
+                if !self.oracle.verify_open(&proof.commitments[round], index, *f_y, y_proof)
+                    || !self.oracle.verify_open(&proof.commitments[round], (index + n / 2) % n, *f_neg_y, neg_y_proof)
+                    || folded.is_some_and(|folded| folded != *f_y)
+                {
+                    return false;
+                }
+
+                let y_inv = domain.element(index).inverse().expect("Domain elements are non-zero");
+                folded = Some((*f_y + f_neg_y + *challenge * (*f_y - f_neg_y) * y_inv) / F::from(2u32));
+                domain = Prover::<F, P>::fold_domain(&domain);
+            }
+
+            folded.is_none_or(|folded| final_polynomial.evaluate(&domain.element(query % domain.size())) == folded)
+        })
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::error::FriError;
+
+/// Receives the progress of a proof, round by round. Round 0 is the commitment to the
+/// evaluations of the polynomial itself, and round i that to the layer the i-th fold gives.
+///
+/// Every method does nothing by default, and `()` is the sink that ignores everything, which the
+/// provers that do not report progress use.
+pub trait ProgressSink<H> {
+    /// The round is about to evaluate its layer over a domain of the given size.
+    fn on_round_start(&mut self, _round: usize, _domain_size: usize) {}
+
+    /// The round has built the tree of its layer, with the given root.
+    fn on_commit(&mut self, _round: usize, _root: &H) {}
+
+    /// Every layer is committed to and every query opened.
+    fn on_complete(&mut self) {}
+}
+
+impl<H> ProgressSink<H> for () {}
+
+/// Asks a proof running elsewhere to stop. The prover checks it between phases and then fails
+/// with `FriError::Cancelled`, so a round that has started is finished first.
+///
+/// Clones share their state: cancelling any of them cancels the proof holding another.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Fails with `FriError::Cancelled` once the token, if any, is cancelled.
+    pub fn check(token: Option<&Self>) -> Result<(), FriError> {
+        match token {
+            Some(token) if token.is_cancelled() => Err(FriError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+}
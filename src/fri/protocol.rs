@@ -1,44 +1,261 @@
 
-use ark_ff::{FftField, Field};
-use ark_poly::{DenseUVPolynomial, EvaluationDomain, Polynomial};
+use ark_ff::{FftField, Field, PrimeField};
+use ark_poly::{DenseUVPolynomial, EvaluationDomain, Evaluations, GeneralEvaluationDomain, Polynomial};
 use ark_crypto_primitives::crh::{CRHScheme, TwoToOneCRHScheme};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::Rng;
 
 use crate::fri::merkle_tree::{MerkleTree, MerkleTreeOperator};
 use crate::fri::prover::Prover;
-use crate::util::VerifierChallenge;
+use crate::util::folding::FoldingChallenges;
+use crate::util::secret::Secret;
+use crate::util::{TranscriptDigest, VerifierChallenge};
 
+use super::config::FriConfig;
+use super::domain::DomainCache;
+use super::error::{FriError, ProofShapeError};
 use super::merkle_tree::LeafIndex;
-use super::prover::{FRIRecCommitment, FRIRecProof};
+use super::progress::{CancellationToken, ProgressSink};
+use super::prover::{FRIRecCommitment, FRIRecProof, QueryOpening};
+use super::transcript::{ChallengerTranscript, RoundTranscript, SharedTranscript, Transcript, TranscriptSeed};
+use super::utils::{challenge_to_index, check_fits, domain_log_size};
 use super::verifier::verifier::Verifier;
 
+/// Query challenges are drawn from the transcript right after the initial commitment, with
+/// counters far above those `generate_nonzero_challenge` tries for the folding challenge of the
+/// first round.
+pub(crate) const QUERY_COUNTER_OFFSET: u64 = 1 << 32;
+
 // Define the FRI proof structure
-pub struct FRIProtocolProof<F: Field, H> {
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct FRIProtocolProof<F: Field, H: CanonicalSerialize + CanonicalDeserialize> {
     pub initial_commitment: H,
     pub round_commitments: Vec<H>,
     pub round_proofs: Vec<FRIRecProof<F, H>>,
     pub final_polynomial: Vec<F>,
+    transcript_state: H,
+    grinding_nonce: u64,
+}
+
+impl<F: Field, H: CanonicalSerialize + CanonicalDeserialize> FRIProtocolProof<F, H> {
+    /// The state of the transcript after the last commitment, which two implementations proving
+    /// from the same seed can compare.
+    pub fn transcript_state(&self) -> &H {
+        &self.transcript_state
+    }
+
+    /// The proof of work found right after the initial commitment, zero without grinding.
+    pub fn grinding_nonce(&self) -> u64 {
+        self.grinding_nonce
+    }
+}
+
+impl<F: FftField, H: CanonicalSerialize + CanonicalDeserialize> FRIProtocolProof<F, H> {
+    /// The evaluations of the final polynomial over the domain, which must have at least as many
+    /// points as the polynomial has coefficients.
+    pub fn final_evaluations(&self, domain: GeneralEvaluationDomain<F>) -> Evaluations<F, GeneralEvaluationDomain<F>> {
+        assert!(
+            self.final_polynomial.len() <= domain.size(),
+            "The final polynomial has {} coefficients, more than the {} points of the domain",
+            self.final_polynomial.len(),
+            domain.size()
+        );
+        Evaluations::from_vec_and_domain(domain.fft(&self.final_polynomial), domain)
+    }
+}
+
+impl<F: Field, H: CanonicalSerialize + CanonicalDeserialize> TranscriptDigest for FRIProtocolProof<F, H> {}
+
+impl<F: Field, H: CanonicalSerialize + CanonicalDeserialize + Clone> FRIProtocolProof<F, H> {
+    /// The same proof with the cap of every layer stored once, see `CompactFRIProof`.
+    pub fn compact(&self) -> CompactFRIProof<F, H> {
+        let caps = match self.round_proofs.first() {
+            Some(first) => std::iter::once(first.current_cap.clone())
+                .chain(self.round_proofs.iter().map(|round| round.next_cap.clone()))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        CompactFRIProof {
+            initial_commitment: self.initial_commitment.clone(),
+            round_commitments: self.round_commitments.clone(),
+            caps,
+            round_openings: self.round_proofs.iter().map(|round| round.openings.clone()).collect(),
+            final_polynomial: self.final_polynomial.clone(),
+            transcript_state: self.transcript_state.clone(),
+            grinding_nonce: self.grinding_nonce,
+        }
+    }
+}
+
+/// A `FRIProtocolProof` without the caps it repeats: the tree round i folds into is the one round
+/// i + 1 folds, so the next cap of a round is the current cap of the one after it. Here the cap of
+/// every layer is stored once, from the initial one to the one the last round folds into.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CompactFRIProof<F: Field, H: CanonicalSerialize + CanonicalDeserialize> {
+    pub initial_commitment: H,
+    pub round_commitments: Vec<H>,
+    /// The cap of every layer, one more than there are rounds, or none without rounds
+    pub caps: Vec<Vec<H>>,
+    /// The openings of every round
+    pub round_openings: Vec<Vec<QueryOpening<F, H>>>,
+    pub final_polynomial: Vec<F>,
+    transcript_state: H,
+    grinding_nonce: u64,
 }
 
+impl<F: Field, H: CanonicalSerialize + CanonicalDeserialize + Clone> CompactFRIProof<F, H> {
+    /// The full proof with the round structures rebuilt, or `None` if there is not one cap per
+    /// layer.
+    pub fn expand(&self) -> Option<FRIProtocolProof<F, H>> {
+        let expected_caps = if self.round_openings.is_empty() { 0 } else { self.round_openings.len() + 1 };
+        if self.caps.len() != expected_caps {
+            return None;
+        }
+
+        let round_proofs = self
+            .round_openings
+            .iter()
+            .zip(self.caps.windows(2))
+            .map(|(openings, caps)| FRIRecProof {
+                current_cap: caps[0].clone(),
+                next_cap: caps[1].clone(),
+                openings: openings.clone(),
+            })
+            .collect();
+
+        Some(FRIProtocolProof {
+            initial_commitment: self.initial_commitment.clone(),
+            round_commitments: self.round_commitments.clone(),
+            round_proofs,
+            final_polynomial: self.final_polynomial.clone(),
+            transcript_state: self.transcript_state.clone(),
+            grinding_nonce: self.grinding_nonce,
+        })
+    }
+}
+
+impl<F: Field, H: CanonicalSerialize + CanonicalDeserialize> TranscriptDigest for CompactFRIProof<F, H> {}
+
 // Define the FRI system implementation
 #[derive(Clone)]
 pub struct FRISystemImpl<F, P, MT, VC, INCH, LCH>
 where
-    F: Field,
+    F: FftField,
     P: DenseUVPolynomial<F>,
     MT: MerkleTreeOperator<F, INCH> + Clone,
     VC: VerifierChallenge,
     INCH: TwoToOneCRHScheme,
 {
-    pub max_degree: usize,
-    pub num_queries: usize,
+    pub config: FriConfig,
     pub tree_operator: MT,
     pub verifier_challenge: VC,
+    /// The domains every proof starts from, see `with_cached_domains`
+    domains: DomainCache<F>,
     _phantom: std::marker::PhantomData<(F, P, INCH, LCH)>,
 }
 
-pub trait FRIProtocol<F: Field, P: Polynomial<F>, INCH: TwoToOneCRHScheme> {
-    fn prove(&self, polynomial: &P, degree: usize) -> FRIProtocolProof<F, INCH::Output>;
-    fn verify(&self, proof: &FRIProtocolProof<F, INCH::Output>) -> bool;
+/// What the prover keeps from the commit phase to answer any number of query phases.
+///
+/// Only the Merkle trees are kept: their leaves hold the committed evaluations, which the openings
+/// read back by position instead of evaluating the polynomials again.
+pub struct ProverState<F: FftField, H> {
+    /// The Merkle tree of every committed layer, the initial one first
+    pub trees: Vec<MerkleTree<F, H>>,
+    pub final_polynomial: Vec<F>,
+    /// The transcript right after the initial commitment and the grinding nonce, which the queries
+    /// are sampled from
+    pub query_transcript: Transcript<H>,
+    pub grinding_nonce: u64,
+    /// The transcript after the last commitment
+    pub transcript: Transcript<H>,
+    /// The domains of every layer, the initial one and its folds
+    pub domains: DomainCache<F>,
+}
+
+/// The commitment of every round with the state the queries are answered from, as the commit
+/// phase returns them.
+pub type CommitPhaseOutput<F, H> = (Vec<FRIRecCommitment<H>>, ProverState<F, H>);
+
+/// The Merkle tree of a committed layer with its commitment, as `commit_evaluations` returns them.
+pub type CommittedLayer<F, H> = (MerkleTree<F, H>, FRIRecCommitment<H>);
+
+/// A proof with the polynomial committed to in every round, as `prove_with_trace` returns them.
+#[cfg(feature = "debug-trace")]
+pub type TracedProof<F, H> = (FRIProtocolProof<F, H>, Vec<ark_poly::univariate::DensePolynomial<F>>);
+
+/// The round state of the commit phase, with what its rounds share: the layer last committed to
+/// is over `domain`, the folding challenges are drawn from the transcript, and every round is
+/// reported to the sink once the token is checked.
+struct FoldRounds<'a, F: FftField, H, T, S> {
+    domain: GeneralEvaluationDomain<F>,
+    /// The tree of every layer committed to so far, the initial one first
+    trees: Vec<MerkleTree<F, H>>,
+    domains: &'a mut DomainCache<F>,
+    transcript: &'a mut T,
+    progress: &'a mut S,
+    cancellation: Option<&'a CancellationToken>,
+}
+
+/// The tree of every layer, the initial one first, with the final polynomial, as the folds of the
+/// commit phase leave them.
+type FoldedLayers<F, H> = (Vec<MerkleTree<F, H>>, Vec<F>);
+
+pub trait FRIProtocol<F: FftField, P: Polynomial<F>, INCH: TwoToOneCRHScheme> {
+    /// Proves with the all-zero seed.
+    fn prove(&self, polynomial: &P, degree: usize) -> Result<FRIProtocolProof<F, INCH::Output>, FriError> {
+        self.prove_with_seed(polynomial, degree, &TranscriptSeed::default())
+    }
+    /// Runs `commit_phase_with_seed` followed by `query_phase` at the indices sampled from the
+    /// transcript. Fails when F has no domain for the degree or the polynomial does not fit in it.
+    fn prove_with_seed(&self, polynomial: &P, degree: usize, seed: &TranscriptSeed) -> Result<FRIProtocolProof<F, INCH::Output>, FriError>;
+    /// Commits with the all-zero seed.
+    fn commit_phase(&self, polynomial: &P, degree: usize) -> Result<CommitPhaseOutput<F, INCH::Output>, FriError> {
+        self.commit_phase_with_seed(polynomial, degree, &TranscriptSeed::default())
+    }
+    /// Commits to the polynomial and all of its folds, returning the commitment of every layer
+    /// (the initial one first) and the state needed to open them later. The folding challenges
+    /// come from a transcript started from the seed.
+    fn commit_phase_with_seed(
+        &self,
+        polynomial: &P,
+        degree: usize,
+        seed: &TranscriptSeed,
+    ) -> Result<CommitPhaseOutput<F, INCH::Output>, FriError>;
+    /// Opens every round at the given indices into the initial domain, each reduced modulo the
+    /// size of the round's domain.
+    fn query_phase(&self, state: &ProverState<F, INCH::Output>, query_indices: &[usize]) -> FRIProtocolProof<F, INCH::Output>;
+    /// Proves that the evaluations over `domain` come from a low degree polynomial, folding in
+    /// evaluation space instead of going through the coefficient form. There must be one
+    /// evaluation per element of the domain, which must be the subgroup `prove` commits to a
+    /// polynomial of the degree it holds over, not a coset of it.
+    fn prove_from_evals(&self, evals: &[F], domain: &GeneralEvaluationDomain<F>) -> Result<FRIProtocolProof<F, INCH::Output>, FriError>;
+    /// Produces the same proof as `prove` while holding a single evaluation buffer and a single
+    /// Merkle tree at a time. See `FRISystemImpl::prove_low_memory`.
+    fn prove_low_memory(&self, polynomial: P, degree: usize) -> Result<FRIProtocolProof<F, INCH::Output>, FriError>;
+    /// Verifies a proof made with the all-zero seed, as `verify_degree` does for the largest
+    /// degree the configuration folds down in the proof's number of rounds.
+    fn verify(&self, proof: &FRIProtocolProof<F, INCH::Output>) -> bool {
+        self.verify_with_seed(proof, &TranscriptSeed::default())
+    }
+    /// Verifies a proof of the polynomial committed to at `index` of a log of commitments made
+    /// before their proofs, rejecting a proof of any other commitment, even a valid one from
+    /// elsewhere in the log, and an index past its end.
+    fn verify_against_log(&self, proof: &FRIProtocolProof<F, INCH::Output>, log: &[INCH::Output], index: usize) -> bool {
+        log.get(index) == Some(&proof.initial_commitment) && self.verify(proof)
+    }
+    /// Verifies a proof whose transcript started from the seed and whose queries were sampled
+    /// from it, as `prove_with_seed` does. A proof whose shape is not the one the configuration
+    /// implies is rejected before any hash is computed.
+    fn verify_with_seed(&self, proof: &FRIProtocolProof<F, INCH::Output>, seed: &TranscriptSeed) -> bool;
+    /// Verifies a proof made with the all-zero seed and opened at the given indices into the
+    /// initial domain, for query phases whose indices come from elsewhere, e.g. an outer transcript.
+    fn verify_with_queries(&self, proof: &FRIProtocolProof<F, INCH::Output>, query_indices: &[usize]) -> bool;
+    /// Verifies a proof made with the all-zero seed for a polynomial of the given degree, telling
+    /// a proof without the number of rounds, commitments and openings the configuration implies
+    /// apart from one failing a cryptographic check. `verify` is the same check with the degree
+    /// taken from the number of rounds.
+    fn verify_degree(&self, proof: &FRIProtocolProof<F, INCH::Output>, degree: usize) -> Result<(), FriError>;
 }
 
 // Implement the FRIProtocol trait for FRISystemImpl
@@ -47,120 +264,926 @@ where
     F: FftField,
     P: DenseUVPolynomial<F>,
     LCH: CRHScheme<Input = [F], Output = INCH::Output>,
-    INCH: TwoToOneCRHScheme + PartialEq<INCH::Output>,
+    INCH: TwoToOneCRHScheme,
     MT: MerkleTreeOperator<F, INCH> + Clone,
     VC: VerifierChallenge<Commitment = INCH::Output, Challenge = F>,
-    INCH::Output: PartialEq, // Add this bound
 {
-    fn prove(&self, polynomial: &P, degree: usize) -> FRIProtocolProof<F, INCH::Output> {
-        let domain = Prover::<F, P>::create_domain(degree);
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "fri::prove", skip_all, fields(degree = degree)))]
+    fn prove_with_seed(&self, polynomial: &P, degree: usize, seed: &TranscriptSeed) -> Result<FRIProtocolProof<F, INCH::Output>, FriError> {
+        let (_, state) = self.commit_phase_with_seed(polynomial, degree, seed)?;
+        let query_indices = self.query_indices(state.query_transcript.state(), state.trees[0].domain_size());
+        Ok(self.query_phase(&state, &query_indices))
+    }
+
+    fn commit_phase_with_seed(
+        &self,
+        polynomial: &P,
+        degree: usize,
+        seed: &TranscriptSeed,
+    ) -> Result<CommitPhaseOutput<F, INCH::Output>, FriError> {
+        self.commit_phase_with_progress(polynomial, degree, seed, &mut (), None)
+    }
+
+    fn query_phase(&self, state: &ProverState<F, INCH::Output>, query_indices: &[usize]) -> FRIProtocolProof<F, INCH::Output> {
+        let round_proofs = state
+            .trees
+            .windows(2)
+            .map(|trees| {
+                let (current_merkle_tree, next_merkle_tree) = (&trees[0], &trees[1]);
+                let leaf_indices = Self::leaf_indices(current_merkle_tree, query_indices);
+
+                Prover::<F, P>::open_rec::<LCH, INCH, MT>(
+                    current_merkle_tree,
+                    next_merkle_tree,
+                    &leaf_indices,
+                    self.config.cap_height,
+                    &self.tree_operator,
+                )
+            })
+            .collect();
+
+        FRIProtocolProof {
+            initial_commitment: state.trees[0].root(),
+            round_commitments: state.trees[1..].iter().map(|tree| tree.root()).collect(),
+            round_proofs,
+            final_polynomial: state.final_polynomial.clone(),
+            transcript_state: state.transcript.state().clone(),
+            grinding_nonce: state.grinding_nonce,
+        }
+    }
+
+    fn prove_from_evals(&self, evals: &[F], domain: &GeneralEvaluationDomain<F>) -> Result<FRIProtocolProof<F, INCH::Output>, FriError> {
+        if evals.len() != domain.size() {
+            return Err(FriError::EvaluationCountMismatch { expected: domain.size(), actual: evals.len() });
+        }
+        // The verifier folds the subgroup of the size the configuration implies, so a proof over
+        // any other domain could never verify
+        let degree = (domain.size() / self.config.blowup.next_power_of_two()).saturating_sub(1);
+        self.check_evaluation_domain(domain, degree)?;
 
         // Initial commitment
-        let mut current_poly = polynomial.clone();
-        let mut current_merkle_tree = Prover::commit_rec::<LCH, INCH, MT>(
-            &current_poly,
-            domain.group_gen(),
+        let mut current_domain = *domain;
+        let mut current_evals = evals.to_vec();
+        let mut current_merkle_tree = Prover::<F, P>::commit_evaluations_rec::<LCH, INCH, MT>(
+            &current_evals,
+            &current_domain,
+            current_domain.size() - 1,
             &self.tree_operator,
         ).0;
-        let mut initial_merkle_hash = current_merkle_tree.root.get_hash().clone();
+        let initial_merkle_hash = current_merkle_tree.root();
+        let mut transcript = self.new_transcript(&TranscriptSeed::default());
+        transcript.absorb(&self.tree_operator, &initial_merkle_hash);
+        let grinding_nonce = transcript.grind(&self.tree_operator, &self.verifier_challenge, self.config.grinding_bits);
+        let query_indices = self.query_indices(transcript.state(), current_domain.size());
         let mut round_commitments = Vec::new();
         let mut round_proofs = Vec::new();
 
-        // FRI rounds
-        while current_poly.degree() > self.max_degree {
-            let challenge = self.verifier_challenge.generate_challenge(&current_merkle_tree.root.get_hash());
-            
-            let (next_poly, next_merkle_tree) = Prover::reduce::<LCH, INCH, MT>(
-                &current_poly,
-                challenge,
+        // FRI rounds, folding until the evaluations fit a polynomial of at most max_degree
+        while current_domain.size() > (self.config.max_degree + 1) * self.config.blowup {
+            let challenge = self.verifier_challenge.generate_nonzero_challenge(transcript.state());
+
+            let (next_evals, next_domain) = Prover::<F, P>::reduce_evaluations(&current_evals, &current_domain, challenge);
+            let next_merkle_tree = Prover::<F, P>::commit_evaluations_rec::<LCH, INCH, MT>(
+                &next_evals,
+                &next_domain,
+                next_domain.size() - 1,
                 &self.tree_operator,
-            );
+            ).0;
 
-            let queries :Vec<F>  = (0..self.num_queries)
-                .map(|_| self.verifier_challenge.generate_challenge(&next_merkle_tree.root.get_hash()))
-                .collect::<Vec<_>>();
+            let leaf_indices = Self::leaf_indices(&current_merkle_tree, &query_indices);
 
-            // Choose the correct leaf indices for the queries
-            let leaf_indices: Vec<LeafIndex<F>> = queries.iter().enumerate().map(|(i, x)| {
-                LeafIndex {
-                    index: i,
-                    point: *x,
-                }
-            }).collect();
-
-            let round_proof = Prover::open_rec::<LCH, INCH, MT>(
-                &current_poly,
+            let round_proof = Prover::<F, P>::open_rec::<LCH, INCH, MT>(
                 &current_merkle_tree,
-                &next_poly,
                 &next_merkle_tree,
-                F::one(), // coset shift
                 &leaf_indices,
+                self.config.cap_height,
                 &self.tree_operator,
             );
 
-            round_commitments.push(current_merkle_tree.root.get_hash().clone());
+            round_commitments.push(next_merkle_tree.root());
+            transcript.absorb(&self.tree_operator, &next_merkle_tree.root());
             round_proofs.push(round_proof);
-            current_poly = next_poly;
+            current_evals = next_evals;
+            current_domain = next_domain;
             current_merkle_tree = next_merkle_tree;
         }
 
-        // Final small polynomial
-        let final_polynomial = Prover::prove_small(&current_poly);
+        // Final small polynomial, interpolated from the remaining evaluations
+        let final_polynomial = Prover::prove_small(&P::from_coefficients_vec(current_domain.ifft(&current_evals)));
 
-        FRIProtocolProof {
+        Ok(FRIProtocolProof {
             initial_commitment: initial_merkle_hash,
             round_commitments,
             round_proofs,
             final_polynomial,
+            transcript_state: transcript.state().clone(),
+            grinding_nonce,
+        })
+    }
+
+    /// `prove` keeps the current and next polynomials, their evaluations inside `commit_rec` and both
+    /// rounds' trees alive at once. Here the coefficients are evaluated in place and dropped, each
+    /// round's queries are fixed and opened before its tree is freed, and the evaluations are folded
+    /// in place, so only one evaluation buffer and one tree are alive at any time.
+    ///
+    /// Measured with a counting allocator on a degree 2^16 - 1 polynomial over BLS12-381 Fr with
+    /// Poseidon hashing, the allocation high-water mark drops from about 59 MiB with `prove` to about
+    /// 48 MiB. What remains is dominated by building the first round's Merkle tree.
+    fn prove_low_memory(&self, polynomial: P, degree: usize) -> Result<FRIProtocolProof<F, INCH::Output>, FriError> {
+        let mut current_domain = *self.domains.clone().initial(degree, self.config.blowup, F::one())?;
+        let mut current_degree = polynomial.degree();
+        check_fits(polynomial.coeffs().len(), current_domain.size())?;
+
+        // Evaluate in place, dropping the coefficient form
+        let mut evals = polynomial.coeffs().to_vec();
+        drop(polynomial);
+        current_domain.fft_in_place(&mut evals);
+
+        let mut current_merkle_tree = Prover::<F, P>::commit_evaluations_rec::<LCH, INCH, MT>(
+            &evals,
+            &current_domain,
+            current_degree,
+            &self.tree_operator,
+        ).0;
+        let initial_merkle_hash = current_merkle_tree.root();
+        let mut transcript = self.new_transcript(&TranscriptSeed::default());
+        transcript.absorb(&self.tree_operator, &initial_merkle_hash);
+        let grinding_nonce = transcript.grind(&self.tree_operator, &self.verifier_challenge, self.config.grinding_bits);
+        let query_indices = self.query_indices(transcript.state(), current_domain.size());
+        let mut round_commitments = Vec::new();
+        let mut round_proofs = Vec::new();
+
+        while current_degree > self.config.max_degree {
+            let current_cap = current_merkle_tree.cap(self.config.cap_height);
+            let challenge = self.verifier_challenge.generate_nonzero_challenge(transcript.state());
+
+            // Fix the queries and open the current layer before it is folded away
+            let leaf_indices = Self::leaf_indices(&current_merkle_tree, &query_indices);
+            let current_merkle_proofs = Prover::<F, P>::open_current_layer::<LCH, INCH, MT>(
+                &current_merkle_tree,
+                &leaf_indices,
+                self.config.cap_height,
+                &self.tree_operator,
+            );
+            drop(current_merkle_tree);
+
+            let domain_size = current_domain.size();
+            current_domain = Prover::<F, P>::reduce_evaluations_in_place(&mut evals, &current_domain, challenge);
+            current_degree /= 2;
+            current_merkle_tree = Prover::<F, P>::commit_evaluations_rec::<LCH, INCH, MT>(
+                &evals,
+                &current_domain,
+                current_degree,
+                &self.tree_operator,
+            ).0;
+
+            let next_merkle_root = current_merkle_tree.root();
+            let next_merkle_proofs = Prover::<F, P>::open_next_layer::<LCH, INCH, MT>(
+                &current_merkle_tree,
+                domain_size,
+                &leaf_indices,
+                self.config.cap_height,
+                &self.tree_operator,
+            );
+
+            round_commitments.push(next_merkle_root.clone());
+            transcript.absorb(&self.tree_operator, &next_merkle_root);
+            round_proofs.push(Prover::<F, P>::assemble_rec_proof(
+                current_cap,
+                current_merkle_tree.cap(self.config.cap_height),
+                &leaf_indices,
+                current_merkle_proofs,
+                next_merkle_proofs,
+            ));
         }
+
+        // Final small polynomial, interpolated from the remaining evaluations
+        let final_polynomial = Prover::prove_small(&P::from_coefficients_vec(current_domain.ifft(&evals)));
+
+        Ok(FRIProtocolProof {
+            initial_commitment: initial_merkle_hash,
+            round_commitments,
+            round_proofs,
+            final_polynomial,
+            transcript_state: transcript.state().clone(),
+            grinding_nonce,
+        })
     }
-    fn verify(&self, proof: &FRIProtocolProof<F, INCH::Output>) -> bool {
-        let verifier = Verifier::<F, P, LCH, INCH, MT>::create(self.tree_operator.clone());
-
-        // Verify initial commitment
-        if !verifier.verify_rec(
-            &FRIRecCommitment { merkle_root: proof.initial_commitment.clone(), degree: self.max_degree },
-            &proof.round_proofs[0],
-            &FRIRecCommitment { merkle_root: proof.round_commitments[0].clone(), degree: self.max_degree / 2 },
-            self.verifier_challenge.generate_challenge(&proof.initial_commitment),
-        ) {
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "fri::verify", skip_all, fields(rounds = proof.round_proofs.len())))]
+    fn verify_with_seed(&self, proof: &FRIProtocolProof<F, INCH::Output>, seed: &TranscriptSeed) -> bool {
+        self.degree_bound(proof.round_commitments.len())
+            .is_some_and(|degree| self.verify_for_degree(proof, degree, seed).is_ok())
+    }
+
+    fn verify_with_queries(&self, proof: &FRIProtocolProof<F, INCH::Output>, query_indices: &[usize]) -> bool {
+        self.implied_domain_size(proof)
+            .is_some_and(|domain_size| self.verify_transcript(proof, query_indices, domain_size, &TranscriptSeed::default()))
+    }
+
+    fn verify_degree(&self, proof: &FRIProtocolProof<F, INCH::Output>, degree: usize) -> Result<(), FriError> {
+        self.verify_for_degree(proof, degree, &TranscriptSeed::default())
+    }
+}
+
+impl<F, P, LCH, INCH, MT, VC> FRISystemImpl<F, P, MT, VC, INCH, LCH>
+where
+    F: FftField,
+    P: DenseUVPolynomial<F>,
+    LCH: CRHScheme<Input = [F], Output = INCH::Output>,
+    INCH: TwoToOneCRHScheme,
+    MT: MerkleTreeOperator<F, INCH> + Clone,
+    VC: VerifierChallenge<Commitment = INCH::Output, Challenge = F>,
+{
+    /// Proves that the committed codeword is close to a polynomial of degree at most
+    /// `d + zk_blinding_terms()`, where d is the degree of the polynomial, without revealing its
+    /// evaluations.
+    ///
+    /// What is committed to is the blinded polynomial `f(x) + x^(d+1) r(x)`, for r with
+    /// `zk_blinding_terms()` uniformly random coefficients, and the protocol then runs unchanged
+    /// on it. Any that many evaluations of `x^(d+1) r(x)` at distinct non-zero points are uniform
+    /// and independent, so the values opened from the initial layer say nothing about f; those of
+    /// later layers are folds of them. The verifier learns the degree bound `d + zk_blinding_terms()`
+    /// rather than d, and checks it with `verify` or `verify_degree` as for any other proof. The
+    /// Merkle leaves are not salted, so this is only honest-verifier zero knowledge once the
+    /// commitments are made hiding.
+    pub fn prove_zk<R: Rng>(&self, polynomial: &P, rng: &mut R) -> Result<FRIProtocolProof<F, INCH::Output>, FriError> {
+        let degree = polynomial.degree();
+        let mut coeffs = Secret::new(polynomial.coeffs().to_vec());
+        coeffs.resize(degree + 1, F::zero());
+        coeffs.extend((0..self.zk_blinding_terms()).map(|_| F::rand(rng)));
+        self.prove(&P::from_coefficients_slice(&coeffs), degree + self.zk_blinding_terms())
+    }
+
+    /// Proves like `prove`, also returning the polynomial committed to in every round for
+    /// inspecting a proof that fails to verify: the polynomial itself, then each fold of it down
+    /// to the final polynomial. The folds are recomputed with the challenges replayed from the
+    /// proof, which is the one `prove` gives.
+    #[cfg(feature = "debug-trace")]
+    pub fn prove_with_trace(
+        &self,
+        polynomial: &P,
+        degree: usize,
+    ) -> Result<TracedProof<F, INCH::Output>, FriError> {
+        let proof = self.prove(polynomial, degree)?;
+
+        let mut transcript = self.new_transcript(&TranscriptSeed::default());
+        transcript.absorb(&self.tree_operator, &proof.initial_commitment);
+        let nonce_accepted =
+            transcript.absorb_grinding_nonce(&self.tree_operator, &self.verifier_challenge, self.config.grinding_bits, proof.grinding_nonce);
+        debug_assert!(nonce_accepted, "The prover's grinding nonce is accepted");
+        let challenges = Self::replay_challenges(
+            &proof,
+            &mut ChallengerTranscript::new(&mut transcript, &self.tree_operator, &self.verifier_challenge),
+        );
+
+        let mut folds = vec![polynomial.clone()];
+        for challenge in challenges.challenges() {
+            let next = Prover::reduce(folds.last().expect("The trace starts with the polynomial"), *challenge);
+            folds.push(next);
+        }
+        let trace = folds.iter().map(|fold| ark_poly::univariate::DensePolynomial::from_coefficients_slice(fold.coeffs())).collect();
+        Ok((proof, trace))
+    }
+
+    /// Proves like `prove`, reporting every round to the sink and stopping with
+    /// `FriError::Cancelled` once the token is cancelled, which is checked before each round and
+    /// before the queries are opened.
+    pub fn prove_with_progress(
+        &self,
+        polynomial: &P,
+        degree: usize,
+        progress: &mut impl ProgressSink<INCH::Output>,
+        cancellation: Option<&CancellationToken>,
+    ) -> Result<FRIProtocolProof<F, INCH::Output>, FriError> {
+        let (_, state) = self.commit_phase_with_progress(polynomial, degree, &TranscriptSeed::default(), progress, cancellation)?;
+        CancellationToken::check(cancellation)?;
+        let query_indices = self.query_indices(state.query_transcript.state(), state.trees[0].domain_size());
+        let proof = self.query_phase(&state, &query_indices);
+        progress.on_complete();
+        Ok(proof)
+    }
+
+    /// `commit_phase_with_seed`, reporting every round to the sink and checking the token before each.
+    fn commit_phase_with_progress(
+        &self,
+        polynomial: &P,
+        degree: usize,
+        seed: &TranscriptSeed,
+        progress: &mut impl ProgressSink<INCH::Output>,
+        cancellation: Option<&CancellationToken>,
+    ) -> Result<CommitPhaseOutput<F, INCH::Output>, FriError> {
+        let mut domains = self.domains.clone();
+        let domain = *domains.initial(degree, self.config.blowup, F::one())?;
+        check_fits(polynomial.coeffs().len(), domain.size())?;
+
+        // Initial commitment
+        CancellationToken::check(cancellation)?;
+        progress.on_round_start(0, domain.size());
+        let initial_merkle_tree = Prover::commit_rec::<LCH, INCH, MT>(polynomial, &domain, &self.tree_operator).0;
+        progress.on_commit(0, &initial_merkle_tree.root());
+        let mut transcript = self.new_transcript(seed);
+        transcript.absorb(&self.tree_operator, &initial_merkle_tree.root());
+        let grinding_nonce = transcript.grind(&self.tree_operator, &self.verifier_challenge, self.config.grinding_bits);
+        let query_transcript = transcript.clone();
+
+        // FRI rounds and the final small polynomial
+        let (trees, final_polynomial) = self.commit_folds(
+            polynomial,
+            FoldRounds {
+                domain,
+                trees: vec![initial_merkle_tree],
+                domains: &mut domains,
+                transcript: &mut ChallengerTranscript::new(&mut transcript, &self.tree_operator, &self.verifier_challenge),
+                progress,
+                cancellation,
+            },
+        )?;
+        let commitments = trees
+            .iter()
+            .map(|tree| FRIRecCommitment { merkle_root: tree.root(), degree: tree.degree })
+            .collect();
+
+        Ok((commitments, ProverState { trees, final_polynomial, query_transcript, grinding_nonce, transcript, domains }))
+    }
+
+    /// Commits to the evaluations of a polynomial of at most the given degree, checking they are
+    /// over the domain `prove` commits to it over: the subgroup of the size the degree and blowup
+    /// give, not a coset of it.
+    pub fn commit_evaluations(
+        &self,
+        evals: &Evaluations<F, GeneralEvaluationDomain<F>>,
+        degree: usize,
+    ) -> Result<CommittedLayer<F, INCH::Output>, FriError> {
+        self.check_evaluation_domain(&evals.domain(), degree)?;
+        Ok(Prover::<F, P>::commit_evaluations_rec::<LCH, INCH, MT>(&evals.evals, &evals.domain(), degree, &self.tree_operator))
+    }
+
+    /// Proves the evaluations are those of a polynomial of at most the given degree, with
+    /// `prove_from_evals` once their domain is checked as in `commit_evaluations`.
+    pub fn prove_evaluations(
+        &self,
+        evals: &Evaluations<F, GeneralEvaluationDomain<F>>,
+        degree: usize,
+    ) -> Result<FRIProtocolProof<F, INCH::Output>, FriError> {
+        self.check_evaluation_domain(&evals.domain(), degree)?;
+        self.prove_from_evals(&evals.evals, &evals.domain())
+    }
+
+    /// Checks the domain is the subgroup `prove` commits to a polynomial of the degree over.
+    fn check_evaluation_domain(&self, domain: &GeneralEvaluationDomain<F>, degree: usize) -> Result<(), FriError> {
+        self.config.check_domain::<F>(degree)?;
+        let expected = 1 << domain_log_size(degree, self.config.blowup);
+        if domain.size() != expected {
+            return Err(FriError::DomainMismatch { expected, actual: domain.size() });
+        }
+        if domain.coset_offset() != F::one() {
+            return Err(FriError::CosetMismatch);
+        }
+        Ok(())
+    }
+
+    /// The number of random coefficients `prove_zk` blinds with, one per value the queries can
+    /// open from the initial layer: the leaves of both `y` and `-y`, each packing `leaf_width` values.
+    pub fn zk_blinding_terms(&self) -> usize {
+        2 * self.config.num_queries * self.config.leaf_width
+    }
+
+    /// Proves like `prove`, but with the final polynomial masked, as it is a fold of every
+    /// coefficient of the polynomial and otherwise sent in the clear.
+    ///
+    /// After r rounds the j-th coefficient of the final polynomial combines coefficients
+    /// `j·2^r` to `(j+1)·2^r - 1` of the committed one, each weighted by a product of the non-zero
+    /// folding challenges. The polynomial of degree d is padded with uniformly random coefficients
+    /// from `d + 1` up to `final_blinding_degree(d)`, which puts one in each of these ranges, so
+    /// that the coefficients of the final polynomial are uniform and independent. The padding
+    /// leaves the first d + 1 coefficients alone; the verifier checks the degree bound
+    /// `final_blinding_degree(d)` rather than d.
+    pub fn prove_with_blinded_final<R: Rng>(&self, polynomial: &P, rng: &mut R) -> Result<FRIProtocolProof<F, INCH::Output>, FriError> {
+        let degree = polynomial.degree();
+        let blinded_degree = self.final_blinding_degree(degree);
+        let mut coeffs = Secret::new(polynomial.coeffs().to_vec());
+        coeffs.resize(degree + 1, F::zero());
+        coeffs.extend((degree..blinded_degree).map(|_| F::rand(rng)));
+        self.prove(&P::from_coefficients_slice(&coeffs), blinded_degree)
+    }
+
+    /// The degree `prove_with_blinded_final` pads a polynomial of the given degree to:
+    /// `(max_degree + 1)·2^r - 1` for the fewest rounds r with `2^r > degree + 1`, the first
+    /// range folding into the constant coefficient then reaching past the degree.
+    pub fn final_blinding_degree(&self, degree: usize) -> usize {
+        (self.config.max_degree + 1) * (degree + 2).next_power_of_two() - 1
+    }
+
+    /// Reconstructs the polynomial a proof made with the default seed commits to, from every
+    /// evaluation of every layer the prover kept in its state rather than the few the queries open.
+    ///
+    /// Each layer is interpolated over its domain and must be the one before folded with the
+    /// challenge replayed from the proof's commitments, the last one must be the final polynomial,
+    /// and the first must have at most the degree its domain holds at the blowup. Returns `None`
+    /// as soon as a round is inconsistent, which a folding bug makes it.
+    #[cfg(test)]
+    pub(crate) fn reconstruct_poly_from_rounds(
+        &self,
+        proof: &FRIProtocolProof<F, INCH::Output>,
+        state: &ProverState<F, INCH::Output>,
+    ) -> Option<ark_poly::univariate::DensePolynomial<F>> {
+        use ark_poly::univariate::DensePolynomial;
+
+        let roots: Vec<_> = state.trees.iter().map(MerkleTree::root).collect();
+        if roots.first() != Some(&proof.initial_commitment) || roots[1..] != proof.round_commitments[..] {
+            return None;
+        }
+
+        let mut transcript = self.new_transcript(&TranscriptSeed::default());
+        transcript.absorb(&self.tree_operator, &proof.initial_commitment);
+        if !transcript.absorb_grinding_nonce(&self.tree_operator, &self.verifier_challenge, self.config.grinding_bits, proof.grinding_nonce) {
+            return None;
+        }
+        let challenges = Self::replay_challenges(
+            proof,
+            &mut ChallengerTranscript::new(&mut transcript, &self.tree_operator, &self.verifier_challenge),
+        );
+
+        // Interpolate every layer from all of its evaluations
+        let mut domains = DomainCache::new();
+        let mut layers = Vec::with_capacity(state.trees.len());
+        for tree in &state.trees {
+            let domain = *domains.domain(tree.domain_size().trailing_zeros(), tree.coset_offset).ok()?;
+            let evals = (0..domain.size()).map(|position| tree.get_leaf(position).map(|(_, value)| value)).collect::<Option<Vec<F>>>()?;
+            layers.push(DensePolynomial::from_coefficients_vec(domain.ifft(&evals)));
+        }
+
+        let folds_agree = layers
+            .windows(2)
+            .zip(challenges.challenges())
+            .all(|(pair, challenge)| Prover::<F, DensePolynomial<F>>::reduce(&pair[0], *challenge) == pair[1]);
+        let final_agrees = layers.last()? == &DensePolynomial::from_coefficients_vec(proof.final_polynomial.clone());
+        let initial = layers.swap_remove(0);
+        let degree_bound = (state.trees[0].domain_size() / self.config.blowup.next_power_of_two()).checked_sub(1)?;
+        (folds_agree && final_agrees && initial.degree() <= degree_bound).then_some(initial)
+    }
+
+    /// Verifies a proof sent without its repeated caps, rebuilding the rounds first.
+    pub fn verify_compact(&self, proof: &CompactFRIProof<F, INCH::Output>) -> bool {
+        proof.expand().is_some_and(|proof| self.verify(&proof))
+    }
+
+    /// Verifies openings of a single round against commitments the caller already trusts, for a
+    /// light client that only cares about some positions and does not hold the whole proof.
+    ///
+    /// `commitments` is the chain of roots of a proof made with the default seed: the initial
+    /// commitment followed by the round commitments. The challenge of the round is replayed from
+    /// them and the grinding nonce, then every opening must be in the trees the round folds from
+    /// and into, and fold consistently at its query. The openings must come from a proof without a
+    /// cap, since only the roots are known.
+    ///
+    /// This does not establish that the committed codeword is close to a low-degree polynomial:
+    /// only `verify` does, by checking every round and the final polynomial at queries the prover
+    /// could not choose. Here the caller picks the openings, and the others are never looked at.
+    pub fn verify_openings(
+        &self,
+        commitments: &[INCH::Output],
+        grinding_nonce: u64,
+        round: usize,
+        openings: &[QueryOpening<F, INCH::Output>],
+    ) -> bool {
+        let (Some(current_root), Some(next_root)) = (commitments.get(round), commitments.get(round + 1)) else {
+            return false;
+        };
+        if openings.is_empty() {
+            return false;
+        }
+
+        let mut transcript = self.new_transcript(&TranscriptSeed::default());
+        transcript.absorb(&self.tree_operator, &commitments[0]);
+        if !transcript.absorb_grinding_nonce(&self.tree_operator, &self.verifier_challenge, self.config.grinding_bits, grinding_nonce) {
             return false;
         }
+        let mut challenger = ChallengerTranscript::new(&mut transcript, &self.tree_operator, &self.verifier_challenge);
+        let mut challenge = challenger.folding_challenge();
+        for commitment in &commitments[1..=round] {
+            challenger.absorb_commitment(commitment);
+            challenge = challenger.folding_challenge();
+        }
+
+        // The round's domain is the initial one of a proof with that many rounds, folded once per
+        // round before it
+        let Some(domain_size) = self
+            .degree_bound(commitments.len() - 1)
+            .and_then(|degree| self.initial_domain_size(degree))
+            .map(|domain_size| domain_size >> round)
+        else {
+            return false;
+        };
+        let round_proof = FRIRecProof { current_cap: vec![current_root.clone()], next_cap: vec![next_root.clone()], openings: openings.to_vec() };
+        let degree = (domain_size / self.config.blowup.next_power_of_two()).saturating_sub(1);
+        let query_indices: Vec<usize> = openings.iter().map(|opening| opening.query.index).collect();
+
+        Verifier::<F, P, LCH, INCH, MT>::create(self.tree_operator.clone()).with_blowup(self.config.blowup).verify_rec(
+            &FRIRecCommitment { merkle_root: current_root.clone(), degree },
+            &round_proof,
+            &FRIRecCommitment { merkle_root: next_root.clone(), degree: degree / 2 },
+            challenge,
+            &query_indices,
+            F::one(),
+        )
+    }
+
+    /// Verifies a proof for a polynomial of the given degree whose transcript started from the
+    /// seed: its shape is checked against the configuration first, then every round at the
+    /// queries sampled over the domain of the degree.
+    fn verify_for_degree(&self, proof: &FRIProtocolProof<F, INCH::Output>, degree: usize, seed: &TranscriptSeed) -> Result<(), FriError> {
+        self.config.check_domain::<F>(degree)?;
+        self.check_shape(proof, degree)?;
+        let domain_size = self.initial_domain_size(degree).ok_or(FriError::VerificationFailed)?;
+
+        let mut transcript = self.new_transcript(seed);
+        transcript.absorb(&self.tree_operator, &proof.initial_commitment);
+        if !transcript.absorb_grinding_nonce(&self.tree_operator, &self.verifier_challenge, self.config.grinding_bits, proof.grinding_nonce) {
+            return Err(FriError::VerificationFailed);
+        }
+        // Every query is sampled whatever the proof opens, so each round must open all of them
+        let query_indices = self.query_indices(transcript.state(), domain_size);
+        if self.verify_transcript(proof, &query_indices, domain_size, seed) {
+            Ok(())
+        } else {
+            Err(FriError::VerificationFailed)
+        }
+    }
+
+    /// Replays the transcript started from the seed and verifies every round at the given
+    /// queries into the initial domain of the given size, then the final polynomial and the
+    /// transcript state.
+    fn verify_transcript(&self, proof: &FRIProtocolProof<F, INCH::Output>, query_indices: &[usize], domain_size: usize, seed: &TranscriptSeed) -> bool {
+        // Each round's challenge only depends on the commitments before it
+        let mut transcript = self.new_transcript(seed);
+        transcript.absorb(&self.tree_operator, &proof.initial_commitment);
+        if !transcript.absorb_grinding_nonce(&self.tree_operator, &self.verifier_challenge, self.config.grinding_bits, proof.grinding_nonce) {
+            return false;
+        }
+        let challenges = Self::replay_challenges(
+            proof,
+            &mut ChallengerTranscript::new(&mut transcript, &self.tree_operator, &self.verifier_challenge),
+        );
+
+        self.verify_folding(proof, query_indices, domain_size, &challenges) && *transcript.state() == proof.transcript_state
+    }
 
-        // Verify intermediate rounds
-        for i in 1..proof.round_proofs.len() {
-            let challenge = self.verifier_challenge.generate_challenge(&proof.round_commitments[i-1]);
+    /// Draws the challenge of every round from the transcript, absorbing the commitment of the
+    /// layer it folds into after each.
+    fn replay_challenges(proof: &FRIProtocolProof<F, INCH::Output>, transcript: &mut impl RoundTranscript<F, INCH::Output>) -> FoldingChallenges<F> {
+        let mut challenges = Vec::with_capacity(proof.round_commitments.len());
+        for commitment in &proof.round_commitments {
+            challenges.push(transcript.folding_challenge());
+            transcript.absorb_commitment(commitment);
+        }
+        FoldingChallenges::new(challenges)
+    }
+
+    /// Verifies every round at the given queries with the given challenges, then the final
+    /// polynomial, whichever transcript the challenges were drawn from. The rounds fold the
+    /// subgroup of the given size, the initial domain the caller derived from the configuration,
+    /// and its squares, never a coset, so the opened leaves cannot move the rounds onto another
+    /// domain.
+    fn verify_folding(
+        &self,
+        proof: &FRIProtocolProof<F, INCH::Output>,
+        query_indices: &[usize],
+        domain_size: usize,
+        challenges: &FoldingChallenges<F>,
+    ) -> bool {
+        let verifier = Verifier::<F, P, LCH, INCH, MT>::with_cap_height(self.tree_operator.clone(), self.config.cap_height)
+            .with_blowup(self.config.blowup);
+
+        if proof.round_commitments.len() != proof.round_proofs.len() || challenges.challenges().len() != proof.round_proofs.len() {
+            return false;
+        }
+
+        // The tree round i folds into is the one round i + 1 folds, so both must show the same cap
+        // of it. Their roots are linked through the commitments: round i is checked against the
+        // i-th and (i + 1)-th roots of the chain starting from the initial commitment.
+        if proof.round_proofs.windows(2).any(|rounds| rounds[0].next_cap != rounds[1].current_cap) {
+            return false;
+        }
+
+        // The largest degree the initial domain holds at the blowup, halved by every round
+        let initial_degree = (domain_size / self.config.blowup.next_power_of_two()).saturating_sub(1);
+
+        // Verify each round, starting from the initial commitment
+        for (i, challenge) in challenges.challenges().iter().enumerate() {
+            let current_root = if i == 0 { &proof.initial_commitment } else { &proof.round_commitments[i-1] };
             if !verifier.verify_rec(
-                &FRIRecCommitment { merkle_root: proof.round_commitments[i-1].clone(), degree: self.max_degree / (2_usize.pow(i as u32)) },
+                &FRIRecCommitment { merkle_root: current_root.clone(), degree: initial_degree >> i },
                 &proof.round_proofs[i],
-                &FRIRecCommitment { merkle_root: proof.round_commitments[i].clone(), degree: self.max_degree / (2_usize.pow((i+1) as u32)) },
-                challenge,
+                &FRIRecCommitment { merkle_root: proof.round_commitments[i].clone(), degree: initial_degree >> (i + 1) },
+                *challenge,
+                query_indices,
+                F::one(),
             ) {
                 return false;
             }
         }
 
+        // The last layer the rounds fold into must hold the evaluations of the final polynomial,
+        // over the initial domain folded once per round. Without rounds that layer is the initial
+        // one, so the final polynomial must be the committed polynomial itself.
+        let final_root = proof.round_commitments.last().unwrap_or(&proof.initial_commitment);
+        if !self.commits_to_final_polynomial(&proof.final_polynomial, domain_size >> proof.round_proofs.len(), F::one(), final_root) {
+            return false;
+        }
+
         // Verify final small polynomial
-        Verifier::<F, P, LCH, INCH, MT>::verify_small(&proof.final_polynomial, self.max_degree / (2_usize.pow(proof.round_proofs.len() as u32)))
+        Verifier::<F, P, LCH, INCH, MT>::verify_small(&proof.final_polynomial, self.config.max_degree)
+    }
+
+    /// Commits to every fold of the polynomial, whose layer over `rounds.domain` is already
+    /// committed to, until its degree is at most `max_degree`. Each folding challenge is drawn
+    /// from the transcript once the layer before is absorbed. Each fold is reported to the sink as
+    /// the round after the last tree, and the token is checked before it. Returns the tree of
+    /// every layer, the initial one first, and the final polynomial.
+    fn commit_folds<T, S>(&self, polynomial: &P, mut rounds: FoldRounds<'_, F, INCH::Output, T, S>) -> Result<FoldedLayers<F, INCH::Output>, FriError>
+    where
+        T: RoundTranscript<F, INCH::Output>,
+        S: ProgressSink<INCH::Output>,
+    {
+        let mut current_poly = polynomial.clone();
+        while current_poly.degree() > self.config.max_degree {
+            CancellationToken::check(rounds.cancellation)?;
+            let challenge = rounds.transcript.folding_challenge();
+            rounds.domain = *rounds.domains.fold(&rounds.domain);
+            #[cfg(feature = "tracing")]
+            let _round = tracing::debug_span!("fri::round", round = rounds.trees.len(), domain_size = rounds.domain.size()).entered();
+            rounds.progress.on_round_start(rounds.trees.len(), rounds.domain.size());
+
+            let next_poly = Prover::reduce(&current_poly, challenge);
+            let (next_merkle_tree, _) = Prover::commit_rec::<LCH, INCH, MT>(&next_poly, &rounds.domain, &self.tree_operator);
+            rounds.progress.on_commit(rounds.trees.len(), &next_merkle_tree.root());
+
+            rounds.transcript.absorb_commitment(&next_merkle_tree.root());
+            rounds.trees.push(next_merkle_tree);
+            current_poly = next_poly;
+        }
+
+        Ok((rounds.trees, Prover::prove_small(&current_poly)))
+    }
+
+    /// Whether committing to the final polynomial over `coset_offset · H`, for H of the given
+    /// size, gives the root the last round folded into.
+    fn commits_to_final_polynomial(&self, final_polynomial: &[F], domain_size: usize, coset_offset: F, root: &INCH::Output) -> bool {
+        let Some(domain) = GeneralEvaluationDomain::<F>::new(domain_size).and_then(|domain| domain.get_coset(coset_offset)) else {
+            return false;
+        };
+        if final_polynomial.len() > domain.size() {
+            return false;
+        }
+        let polynomial = P::from_coefficients_slice(final_polynomial);
+        Prover::commit_rec::<LCH, INCH, MT>(&polynomial, &domain, &self.tree_operator).0.root() == *root
+    }
+}
+
+impl<F, P, LCH, INCH, MT, VC> FRISystemImpl<F, P, MT, VC, INCH, LCH>
+where
+    F: PrimeField,
+    P: DenseUVPolynomial<F>,
+    LCH: CRHScheme<Input = [F], Output = INCH::Output>,
+    INCH: TwoToOneCRHScheme,
+    MT: MerkleTreeOperator<F, INCH> + Clone,
+    VC: VerifierChallenge<Commitment = INCH::Output, Challenge = F>,
+{
+    /// Proves the polynomial has at most its degree with every challenge drawn from the caller's
+    /// transcript, which may already have absorbed the commitments of a larger protocol, e.g. the
+    /// trace of a STARK, and is left having absorbed the whole proof.
+    ///
+    /// The configuration, the initial commitment, the commitment of every fold and the final
+    /// polynomial are absorbed in order, each folding challenge is squeezed right before the layer
+    /// it folds into is committed to, and the queries are squeezed last. `verifier_challenge` is
+    /// not used, the proof has no grinding nonce and its transcript state is left at the default:
+    /// the state is the caller's.
+    pub fn prove_with_transcript<T: crate::util::Transcript<F>>(
+        &self,
+        polynomial: &P,
+        transcript: &mut T,
+    ) -> Result<FRIProtocolProof<F, INCH::Output>, FriError> {
+        let mut domains = self.domains.clone();
+        let domain = *domains.initial(polynomial.degree(), self.config.blowup, F::one())?;
+
+        let initial_merkle_tree = Prover::commit_rec::<LCH, INCH, MT>(polynomial, &domain, &self.tree_operator).0;
+        self.absorb_statement(transcript, &initial_merkle_tree.root());
+        let rounds = FoldRounds {
+            domain,
+            trees: vec![initial_merkle_tree],
+            domains: &mut domains,
+            transcript: &mut SharedTranscript(&mut *transcript),
+            progress: &mut (),
+            cancellation: None,
+        };
+        let (trees, final_polynomial) = self.commit_folds(polynomial, rounds).expect("A proof without a cancellation token is never cancelled");
+        absorb_final_polynomial(transcript, &final_polynomial);
+
+        let query_indices = if trees.len() > 1 { self.squeeze_query_indices(transcript, domain.size()) } else { Vec::new() };
+        let state = ProverState {
+            trees,
+            final_polynomial,
+            query_transcript: Transcript::from_state(INCH::Output::default()),
+            grinding_nonce: 0,
+            transcript: Transcript::from_state(INCH::Output::default()),
+            domains,
+        };
+        Ok(self.query_phase(&state, &query_indices))
+    }
+
+    /// Verifies a proof made with `prove_with_transcript`, replaying its absorptions and squeezes
+    /// on the caller's transcript, which must be in the state the prover's was in.
+    pub fn verify_with_transcript<T: crate::util::Transcript<F>>(&self, proof: &FRIProtocolProof<F, INCH::Output>, transcript: &mut T) -> bool {
+        if proof.grinding_nonce != 0 || proof.transcript_state != INCH::Output::default() {
+            return false;
+        }
+
+        self.absorb_statement(transcript, &proof.initial_commitment);
+        let challenges = Self::replay_challenges(proof, &mut SharedTranscript(&mut *transcript));
+        absorb_final_polynomial(transcript, &proof.final_polynomial);
+
+        let Some(degree) = self.degree_bound(proof.round_commitments.len()) else {
+            return false;
+        };
+        let (Ok(()), Some(domain_size)) = (self.check_shape(proof, degree), self.initial_domain_size(degree)) else {
+            return false;
+        };
+        let query_indices = if proof.round_proofs.is_empty() { Vec::new() } else { self.squeeze_query_indices(transcript, domain_size) };
+        self.verify_folding(proof, &query_indices, domain_size, &challenges)
+    }
+
+    /// Absorbs the configuration, which fixes what the proof claims, and the initial commitment.
+    fn absorb_statement<T: crate::util::Transcript<F>>(&self, transcript: &mut T, initial_commitment: &INCH::Output) {
+        for parameter in self.config.to_field_elements::<F>() {
+            transcript.absorb_field(b"fri/config", &parameter);
+        }
+        transcript.absorb_group(b"fri/commitment", initial_commitment);
+    }
+
+    /// Squeezes an index into a domain of the given size for every query.
+    fn squeeze_query_indices<T: crate::util::Transcript<F>>(&self, transcript: &mut T, domain_size: usize) -> Vec<usize> {
+        (0..self.config.num_queries).map(|_| transcript.squeeze_usize(b"fri/query", domain_size)).collect()
+    }
+}
+
+fn absorb_final_polynomial<F: PrimeField, T: crate::util::Transcript<F>>(transcript: &mut T, final_polynomial: &[F]) {
+    for coefficient in final_polynomial {
+        transcript.absorb_field(b"fri/final", coefficient);
+    }
+}
+
+impl<F, P, MT, VC, INCH, LCH> FRISystemImpl<F, P, MT, VC, INCH, LCH>
+where
+    F: FftField,
+    P: DenseUVPolynomial<F>,
+    MT: MerkleTreeOperator<F, INCH> + Clone,
+    VC: VerifierChallenge<Commitment = INCH::Output, Challenge = F>,
+    INCH: TwoToOneCRHScheme,
+{
+    /// Checks the number of rounds, commitments and openings of a proof for a polynomial of the
+    /// given degree, and that its final polynomial is within `max_degree`.
+    fn check_shape(&self, proof: &FRIProtocolProof<F, INCH::Output>, degree: usize) -> Result<(), ProofShapeError> {
+        let rounds = self.config.num_rounds(degree);
+        if proof.round_proofs.len() != rounds {
+            return Err(ProofShapeError::RoundCount { expected: rounds, actual: proof.round_proofs.len() });
+        }
+        if proof.round_commitments.len() != rounds {
+            return Err(ProofShapeError::CommitmentCount { expected: rounds, actual: proof.round_commitments.len() });
+        }
+        for (round, round_proof) in proof.round_proofs.iter().enumerate() {
+            if round_proof.openings.len() != self.config.num_queries {
+                return Err(ProofShapeError::QueryCount {
+                    round,
+                    expected: self.config.num_queries,
+                    actual: round_proof.openings.len(),
+                });
+            }
+        }
+        if proof.final_polynomial.len() > self.config.max_degree + 1 {
+            return Err(ProofShapeError::FinalDegree { max_degree: self.config.max_degree, coefficients: proof.final_polynomial.len() });
+        }
+        Ok(())
+    }
+
+    /// The transcript every proof starts from: the seed, then the configuration.
+    pub fn new_transcript(&self, seed: &TranscriptSeed) -> Transcript<INCH::Output> {
+        let mut transcript = Transcript::new(&self.tree_operator, seed);
+        transcript.absorb_values(&self.tree_operator, &self.config.to_field_elements::<F>());
+        transcript
+    }
+
+    /// Checks that the degrees claimed by the round commitments, the initial one first, halve
+    /// every round, as folding a polynomial of degree d gives one of degree `d / folding_factor`.
+    pub fn check_round_degrees(&self, commitments: &[FRIRecCommitment<INCH::Output>]) -> Result<(), ProofShapeError> {
+        for (round, pair) in commitments.windows(2).enumerate() {
+            let expected = pair[0].degree / self.config.folding_factor;
+            if pair[1].degree != expected {
+                return Err(ProofShapeError::RoundDegree { round: round + 1, expected, actual: pair[1].degree });
+            }
+        }
+        Ok(())
+    }
+
+    /// The largest degree the configuration folds down to `max_degree` in the given number of
+    /// rounds.
+    fn degree_bound(&self, rounds: usize) -> Option<usize> {
+        let factor = self.config.folding_factor.checked_pow(u32::try_from(rounds).ok()?)?;
+        self.config.max_degree.checked_add(1)?.checked_mul(factor).map(|bound| bound - 1)
+    }
+
+    /// The size of the domain a polynomial of the given degree is committed to over, the subgroup
+    /// `commit_phase` picks at the configured blowup.
+    fn initial_domain_size(&self, degree: usize) -> Option<usize> {
+        1_usize.checked_shl(domain_log_size(degree, self.config.blowup))
+    }
+
+    /// The initial domain `verify` checks a proof over: that of a polynomial of `degree_bound` for
+    /// its number of rounds. It is fixed by the configuration and the round count alone, not read
+    /// off the openings, which would let the prover pick the domain and how many queries it opens.
+    fn implied_domain_size(&self, proof: &FRIProtocolProof<F, INCH::Output>) -> Option<usize> {
+        self.initial_domain_size(self.degree_bound(proof.round_commitments.len())?)
+    }
+
+    /// The indices into a domain of the given size that are queried, sampled from the transcript
+    /// right after the initial commitment so that a round can be opened as soon as the next one
+    /// is committed.
+    fn query_indices(&self, transcript_state: &INCH::Output, domain_size: usize) -> Vec<usize> {
+        (0..self.config.num_queries as u64)
+            .map(|i| {
+                let challenge = self.verifier_challenge.generate_challenge_with_counter(transcript_state, QUERY_COUNTER_OFFSET + i);
+                challenge_to_index(challenge, domain_size)
+            })
+            .collect()
+    }
+
+    /// The leaves of a round's tree that are opened, one per query.
+    fn leaf_indices(tree: &MerkleTree<F, INCH::Output>, query_indices: &[usize]) -> Vec<LeafIndex<F>> {
+        let domain_size = tree.domain_size();
+        query_indices
+            .iter()
+            .map(|query| {
+                let index = query % domain_size;
+                LeafIndex {
+                    index,
+                    point: tree.coset_offset * tree.primitive_root.pow([index as u64]),
+                }
+            })
+            .collect()
     }
 }
 
 // Implement a constructor for FRISystemImpl
 impl<F, P, MT, VC, INCH, LCH> FRISystemImpl<F, P, MT, VC, INCH, LCH>
 where
-    F: Field,
+    F: FftField,
     P: DenseUVPolynomial<F>,
     MT: MerkleTreeOperator<F, INCH> + Clone,
-    VC: VerifierChallenge<Commitment = MerkleTree<F, INCH>, Challenge = F>,
+    VC: VerifierChallenge<Commitment = INCH::Output, Challenge = F>,
     INCH: TwoToOneCRHScheme,
 {
     pub fn new(max_degree: usize, num_queries: usize, tree_operator: MT, verifier_challenge: VC) -> Self {
-        Self {
-            max_degree,
-            num_queries,
+        let config = FriConfig {
+            leaf_width: tree_operator.leaf_width(),
+            ..FriConfig::new(max_degree, num_queries)
+        };
+        Self::with_config(config, tree_operator, verifier_challenge)
+            .expect("FriConfig::new folds by 2, and the leaf width is the operator's")
+    }
+
+    /// Fails when the configuration folds by anything but 2, or packs leaves differently from the
+    /// tree operator.
+    pub fn with_config(config: FriConfig, tree_operator: MT, verifier_challenge: VC) -> Result<Self, FriError> {
+        if config.folding_factor != 2 {
+            return Err(FriError::UnsupportedFoldingFactor { folding_factor: config.folding_factor });
+        }
+        if config.leaf_width != tree_operator.leaf_width() {
+            return Err(FriError::LeafWidthMismatch { configured: config.leaf_width, operator: tree_operator.leaf_width() });
+        }
+        Ok(Self {
+            config,
             tree_operator,
             verifier_challenge,
+            domains: DomainCache::new(),
             _phantom: std::marker::PhantomData,
+        })
+    }
+
+    /// Builds the domain of a polynomial of the given degree and each of its folds once, for every
+    /// later proof to start from instead of building its own. The domains are those a proof would
+    /// build, so the proofs are the same; polynomials of other degrees still get theirs built per
+    /// proof.
+    pub fn with_cached_domains(mut self, degree: usize) -> Result<Self, FriError> {
+        let mut domain = *self.domains.initial(degree, self.config.blowup, F::one())?;
+        for _ in 0..self.config.num_rounds(degree) {
+            domain = *self.domains.fold(&domain);
         }
+        Ok(self)
+    }
+
+    /// The domains every proof starts from, empty unless built with `with_cached_domains`.
+    pub fn cached_domains(&self) -> &DomainCache<F> {
+        &self.domains
     }
 }
@@ -4,39 +4,79 @@
 use ark_ff::{FftField, Field};
 use ark_poly::{DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain};
 use ark_crypto_primitives::crh::{CRHScheme, TwoToOneCRHScheme};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use crate::fri::error::FriError;
+use crate::fri::fold;
+use crate::fri::utils::{check_two_adicity, domain_log_size};
 use crate::fri::merkle_tree::{LeafIndex, MerkleProof, MerkleTree, MerkleTreeOperator};
 
 // Define the structures here
-#[derive(Clone, Debug)]
-pub struct FRIRecCommitment<H> {
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct FRIRecCommitment<H: CanonicalSerialize + CanonicalDeserialize> {
     pub merkle_root: H,
     pub degree: usize,
 }
 
-#[derive(Clone, Debug)]
-pub struct VerifierQuery<F: Field> {
-    pub leaf_indices: Vec<LeafIndex<F>>,
+/// Everything opened for one query `y` in a round.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct QueryOpening<F: Field, H: CanonicalSerialize + CanonicalDeserialize> {
+    /// The queried position and point `y` of the current domain
+    pub query: LeafIndex<F>,
+    /// The leaves of the current tree holding `f(y)` and `f(-y)`, a single one when they are packed together
+    pub current: Vec<MerkleProof<F, H>>,
+    /// The leaf of the next tree holding `f_next(y^2)`, without that value: the verifier computes it
+    /// from `f(y)` and `f(-y)` and checks it against the Merkle proof
+    pub next: MerkleProof<F, H>,
 }
 
-#[derive(Clone, Debug)]
-pub struct FRIRecProof<F: Field, H> {
-    pub current_merkle_root: H,
-    pub next_merkle_root: H,
-    pub current_merkle_proofs: Vec<(MerkleProof<F, H>, MerkleProof<F, H>)>,
-    pub next_merkle_proofs: Vec<MerkleProof<F, H>>,
-    pub current_evaluations: Vec<(F, F)>,
-    pub next_evaluations: Vec<F>,
-    pub query: VerifierQuery<F>,
+/// The openings of one round. The roots of both trees are not repeated here: the protocol proof
+/// holds every root once, and the caps are checked against them.
+/// The row of an interleaved tree at a query: the evaluation of every column at the query's
+/// point, authenticated by the Merkle proof of the leaf holding them.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RowOpening<F: Field, H: CanonicalSerialize + CanonicalDeserialize> {
+    pub proof: MerkleProof<F, H>,
+}
+
+impl<F: Field, H: CanonicalSerialize + CanonicalDeserialize> RowOpening<F, H> {
+    /// The queried position and point of the domain
+    pub fn query(&self) -> &LeafIndex<F> {
+        &self.proof.leaf_index
+    }
+
+    /// The evaluations of the columns at the queried point, in the order they were committed in
+    pub fn row(&self) -> &[F] {
+        &self.proof.values
+    }
+}
+
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct FRIRecProof<F: Field, H: CanonicalSerialize + CanonicalDeserialize> {
+    /// The top of the current tree, where the Merkle paths of the current leaves stop
+    pub current_cap: Vec<H>,
+    /// The top of the next tree, where the Merkle paths of the folded leaves stop
+    pub next_cap: Vec<H>,
+    pub openings: Vec<QueryOpening<F, H>>,
 }
 
 pub struct Prover<F, P>
 where
-    F: FftField,
+    F: Field,
     P: DenseUVPolynomial<F>,
 {
     _phantom: std::marker::PhantomData<(F, P)>,
 }
 
+impl<F, P> Default for Prover<F, P>
+where
+    F: FftField,
+    P: DenseUVPolynomial<F>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<F, P> Prover<F, P>
 where
     F: FftField,
@@ -48,16 +88,25 @@ where
         }
     }
     /// Creates an evaluation domain for the given degree.
-    pub fn create_domain(degree: usize) -> GeneralEvaluationDomain<F> {
-        let domain_size = (degree + 1).next_power_of_two();
-        GeneralEvaluationDomain::<F>::new(domain_size)
-            .expect("Failed to create evaluation domain")
+    pub fn create_domain(degree: usize) -> Result<GeneralEvaluationDomain<F>, FriError> {
+        Self::create_domain_with_offset(degree, 1, F::one())
+    }
+
+    /// Creates the evaluation domain `offset · H` for the given degree, where `H` is the
+    /// multiplicative subgroup of size `(degree + 1).next_power_of_two() * blowup`, failing with
+    /// `FriError::DomainTooLarge` when F has no subgroup that large.
+    pub fn create_domain_with_offset(degree: usize, blowup: usize, offset: F) -> Result<GeneralEvaluationDomain<F>, FriError> {
+        check_two_adicity::<F>(domain_log_size(degree, blowup))?;
+        let domain_size = (degree + 1).next_power_of_two() * blowup;
+        Ok(GeneralEvaluationDomain::<F>::new(domain_size)
+            .and_then(|domain| domain.get_coset(offset))
+            .expect("The field has a subgroup of every size up to its two-adicity"))
     }
 
-    /// Commits to a polynomial using the provided Merkle tree operator.
+    /// Commits to a polynomial over the given evaluation domain using the provided Merkle tree operator.
     pub fn commit_rec<LCH, INCH, MT>(
         polynomial: &P,
-        root_of_unity: F,
+        domain: &GeneralEvaluationDomain<F>,
         tree_operator: &MT,
     ) -> (MerkleTree<F, INCH::Output>, FRIRecCommitment<INCH::Output>)
     where
@@ -66,37 +115,168 @@ where
         MT: MerkleTreeOperator<F, INCH>,
     {
         let degree = polynomial.degree();
-        let domain = Self::create_domain(degree);
+        assert!(
+            polynomial.coeffs().len() <= domain.size(),
+            "Polynomial of degree {} does not fit in a domain of size {}",
+            degree,
+            domain.size()
+        );
 
         // Evaluate the polynomial over the domain using FFT
-        let evaluations = domain.fft(&polynomial.coeffs());
+        let evaluations = domain.fft(polynomial.coeffs());
 
-        // Collect the domain elements (points) and their corresponding evaluations
-        let points: Vec<(LeafIndex<F>, F)> = domain
+        Self::commit_evaluations_rec::<LCH, INCH, MT>(&evaluations, domain, degree, tree_operator)
+    }
+
+    /// Commits to the evaluations of a polynomial of at most the given degree over the domain.
+    ///
+    /// The evaluations are packed `tree_operator.leaf_width()` to a leaf (the whole domain in one
+    /// leaf if it is smaller), see `MerkleTree` for the layout.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", name = "fri::build_tree", skip_all, fields(domain_size = domain.size(), leaf_width = tree_operator.leaf_width())))]
+    pub fn commit_evaluations_rec<LCH, INCH, MT>(
+        evaluations: &[F],
+        domain: &GeneralEvaluationDomain<F>,
+        degree: usize,
+        tree_operator: &MT,
+    ) -> (MerkleTree<F, INCH::Output>, FRIRecCommitment<INCH::Output>)
+    where
+        LCH: CRHScheme<Input = [F], Output = INCH::Output>,
+        INCH: TwoToOneCRHScheme,
+        MT: MerkleTreeOperator<F, INCH>,
+    {
+        assert_eq!(evaluations.len(), domain.size(), "Expected one evaluation per domain element");
+
+        // Pack the evaluations `leaf_count` positions apart into the same leaf, labelled by the
+        // first of its domain points
+        let leaf_width = tree_operator.leaf_width().min(domain.size());
+        let leaf_count = domain.size() / leaf_width;
+        let leaves: Vec<(LeafIndex<F>, Vec<F>)> = domain
             .elements()
+            .take(leaf_count)
             .enumerate()
             .map(|(i, point)| {
                 (
                     LeafIndex { index: i, point },
-                    evaluations[i],
+                    (0..leaf_width).map(|k| evaluations[i + k * leaf_count]).collect(),
                 )
             })
             .collect();
 
         // Create the Merkle tree from the evaluations
-        let merkle_tree = tree_operator.create_tree(points, root_of_unity, degree);
+        let merkle_tree = tree_operator
+            .create_tree(leaves, domain.group_gen(), domain.coset_offset(), degree)
+            .expect("The leaves of a domain all hold the same number of values");
 
         let commitment = FRIRecCommitment {
-            merkle_root: merkle_tree.root.get_hash(),
+            merkle_root: merkle_tree.root(),
             degree,
         };
 
         (merkle_tree, commitment)
     }
 
-    /// Proves the evaluation of the polynomial at a given point.
+    /// Commits to several polynomials, e.g. the columns of a trace, with a single tree over the
+    /// domain, see `commit_interleaved`. The commitment carries the largest degree of the
+    /// polynomials.
+    pub fn commit_rec_batched<LCH, INCH, MT>(
+        polynomials: &[P],
+        domain: &GeneralEvaluationDomain<F>,
+        tree_operator: &MT,
+    ) -> (MerkleTree<F, INCH::Output>, FRIRecCommitment<INCH::Output>)
+    where
+        LCH: CRHScheme<Input = [F], Output = INCH::Output>,
+        INCH: TwoToOneCRHScheme,
+        MT: MerkleTreeOperator<F, INCH>,
+    {
+        let degree = polynomials.iter().map(|polynomial| polynomial.degree()).max().unwrap_or(0);
+        assert!(
+            polynomials.iter().all(|polynomial| polynomial.coeffs().len() <= domain.size()),
+            "Polynomial of degree {} does not fit in a domain of size {}",
+            degree,
+            domain.size()
+        );
+
+        let columns: Vec<Vec<F>> = polynomials.iter().map(|polynomial| domain.fft(polynomial.coeffs())).collect();
+        Self::commit_interleaved::<LCH, INCH, MT>(&columns, domain, degree, tree_operator)
+    }
+
+    /// Commits to the evaluations of several polynomials of at most the given degree over the
+    /// domain with a single tree, whose leaf `i` holds the row of their evaluations at the i-th
+    /// point, in the order of the columns. One path then authenticates every column at a query,
+    /// however many there are.
+    ///
+    /// Rows are not packed together whatever the operator's leaf width, so the tree has a leaf
+    /// width of 1 and `leaf_values` gives a whole row.
+    pub fn commit_interleaved<LCH, INCH, MT>(
+        columns: &[Vec<F>],
+        domain: &GeneralEvaluationDomain<F>,
+        degree: usize,
+        tree_operator: &MT,
+    ) -> (MerkleTree<F, INCH::Output>, FRIRecCommitment<INCH::Output>)
+    where
+        LCH: CRHScheme<Input = [F], Output = INCH::Output>,
+        INCH: TwoToOneCRHScheme,
+        MT: MerkleTreeOperator<F, INCH>,
+    {
+        assert!(!columns.is_empty(), "Expected at least one column");
+        assert!(
+            columns.iter().all(|column| column.len() == domain.size()),
+            "Expected one evaluation per domain element in every column"
+        );
+
+        let leaves = domain
+            .elements()
+            .enumerate()
+            .map(|(i, point)| (LeafIndex { index: i, point }, columns.iter().map(|column| column[i]).collect()))
+            .collect();
+
+        let mut merkle_tree = tree_operator
+            .create_tree(leaves, domain.group_gen(), domain.coset_offset(), degree)
+            .expect("The leaves of a domain all hold the same number of values");
+        merkle_tree.leaf_width = 1;
+        let commitment = FRIRecCommitment {
+            merkle_root: merkle_tree.root(),
+            degree,
+        };
+
+        (merkle_tree, commitment)
+    }
+
+}
+
+/// The openings only read the committed trees back, so they work over any field, e.g. an
+/// extension of the field the domains are subgroups of, see `fri::extension`.
+impl<F, P> Prover<F, P>
+where
+    F: Field,
+    P: DenseUVPolynomial<F>,
+{
+    /// Opens every column of a tree made with `commit_interleaved` at each query, with one Merkle
+    /// proof per query.
+    pub fn open_rec_batched<LCH, INCH, MT>(
+        merkle_tree: &MerkleTree<F, INCH::Output>,
+        queries: &[LeafIndex<F>],
+        cap_height: usize,
+        tree_operator: &MT,
+    ) -> Vec<RowOpening<F, INCH::Output>>
+    where
+        LCH: CRHScheme<Input = [F], Output = INCH::Output>,
+        INCH: TwoToOneCRHScheme,
+        MT: MerkleTreeOperator<F, INCH>,
+    {
+        queries
+            .iter()
+            .map(|query| RowOpening {
+                proof: tree_operator
+                    .create_proof_with_cap(merkle_tree, &merkle_tree.leaf_of(query.index), cap_height)
+                    .expect("leaf_of gives leaves of the tree"),
+            })
+            .collect()
+    }
+
+    /// Proves the evaluation of the committed polynomial at a given point of the domain, returning
+    /// the evaluation read back from the leaf holding it and the Merkle proof of that leaf.
     pub fn prove_evaluation<LCH, INCH, MT>(
-        polynomial: &P,
         merkle_tree: &MerkleTree<F, INCH::Output>,
         point: LeafIndex<F>,
         tree_operator: &MT,
@@ -106,19 +286,21 @@ where
         INCH: TwoToOneCRHScheme,
         MT: MerkleTreeOperator<F, INCH>,
     {
-        let evaluation = polynomial.evaluate(&point.point);
-        let proof = tree_operator.create_proof(merkle_tree, &point);
+        let proof = tree_operator.create_proof(merkle_tree, &merkle_tree.leaf_of(point.index)).expect("leaf_of gives leaves of the tree");
+        let evaluation = proof.values[point.index >> merkle_tree.height()];
         (evaluation, proof)
     }
 
-    /// Opens the recursive proof for the FRI protocol.
+    /// Opens the recursive proof for the FRI protocol: for each query, the leaves holding `f(y)` and
+    /// `f(-y)` in the current round's tree and the leaf holding `f_next(y^2)` in the next round's.
+    ///
+    /// The opened values are the committed leaves themselves, so each query costs a walk down the
+    /// trees rather than an evaluation of the round polynomials.
     pub fn open_rec<LCH, INCH, MT>(
-        current_polynomial: &P,
         current_merkle_tree: &MerkleTree<F, INCH::Output>,
-        next_polynomial: &P,
         next_merkle_tree: &MerkleTree<F, INCH::Output>,
-        coset_shift: F,
         queries: &[LeafIndex<F>],
+        cap_height: usize,
         tree_operator: &MT,
     ) -> FRIRecProof<F, INCH::Output>
     where
@@ -126,91 +308,147 @@ where
         INCH: TwoToOneCRHScheme,
         MT: MerkleTreeOperator<F, INCH>,
     {
-        let current_evaluations: Vec<(F, F)> = queries
-            .iter()
-            .map(|q| (
-                current_polynomial.evaluate(&q.point),
-                current_polynomial.evaluate(&(q.point * coset_shift)),
-            ))
-            .collect();
+        let domain_size = current_merkle_tree.domain_size();
+        let current_merkle_proofs = Self::open_current_layer::<LCH, INCH, MT>(current_merkle_tree, queries, cap_height, tree_operator);
+        let next_merkle_proofs = Self::open_next_layer::<LCH, INCH, MT>(next_merkle_tree, domain_size, queries, cap_height, tree_operator);
 
-        let next_evaluations: Vec<F> = queries
-            .iter()
-            .map(|q| next_polynomial.evaluate(&q.point))
-            .collect();
+        Self::assemble_rec_proof(
+            current_merkle_tree.cap(cap_height),
+            next_merkle_tree.cap(cap_height),
+            queries,
+            current_merkle_proofs,
+            next_merkle_proofs,
+        )
+    }
 
-        let current_merkle_proofs: Vec<(MerkleProof<F, INCH::Output>, MerkleProof<F, INCH::Output>)> = queries
+    /// Pairs up the openings of both layers with their queries. Each layer comes with the cap of
+    /// its tree.
+    pub fn assemble_rec_proof<H: CanonicalSerialize + CanonicalDeserialize>(
+        current_cap: Vec<H>,
+        next_cap: Vec<H>,
+        queries: &[LeafIndex<F>],
+        current_merkle_proofs: Vec<Vec<MerkleProof<F, H>>>,
+        next_merkle_proofs: Vec<MerkleProof<F, H>>,
+    ) -> FRIRecProof<F, H> {
+        let openings = queries
             .iter()
-            .map(|q| {
-                let query_proof = tree_operator.create_proof(current_merkle_tree, q);
-                let coset_leaf_index = LeafIndex {
-                    index: q.index,
-                    point: q.point * coset_shift,
-                };
-                let coset_proof = tree_operator.create_proof(current_merkle_tree, &coset_leaf_index);
-                (query_proof, coset_proof)
+            .zip(current_merkle_proofs)
+            .zip(next_merkle_proofs)
+            .map(|((query, current), next)| QueryOpening {
+                query: query.clone(),
+                current,
+                next,
             })
             .collect();
 
-        let next_merkle_proofs: Vec<MerkleProof<F, INCH::Output>> = queries
-            .iter()
-            .map(|q| tree_operator.create_proof(next_merkle_tree, q))
-            .collect();
-
         FRIRecProof {
-            current_merkle_root: current_merkle_tree.root.get_hash(),
-            next_merkle_root: next_merkle_tree.root.get_hash(),
-            current_merkle_proofs,
-            next_merkle_proofs,
-            current_evaluations,
-            next_evaluations,
-            query: VerifierQuery {
-                leaf_indices: queries.to_vec(),
-            },
+            current_cap,
+            next_cap,
+            openings,
         }
     }
 
-    /// Reduces the polynomial for the next round of the FRI protocol.
-    pub fn reduce<LCH, INCH, MT>(
-        polynomial: &P,
-        challenge: F,
+    /// Creates the Merkle proofs for the leaves opened for each query in the current round's tree:
+    /// the leaf holding `y` and, when `-y` is not packed with it, the leaf holding `-y`.
+    pub fn open_current_layer<LCH, INCH, MT>(
+        current_merkle_tree: &MerkleTree<F, INCH::Output>,
+        queries: &[LeafIndex<F>],
+        cap_height: usize,
         tree_operator: &MT,
-    ) -> (P, MerkleTree<F, INCH::Output>)
+    ) -> Vec<Vec<MerkleProof<F, INCH::Output>>>
     where
         LCH: CRHScheme<Input = [F], Output = INCH::Output>,
         INCH: TwoToOneCRHScheme,
         MT: MerkleTreeOperator<F, INCH>,
     {
-        let degree = polynomial.degree();
-        let half_degree = degree / 2;
-
-        let mut even_coeffs = Vec::with_capacity(half_degree + 1);
-        let mut odd_coeffs = Vec::with_capacity(half_degree);
+        queries
+            .iter()
+            .map(|q| {
+                current_leaves(current_merkle_tree, q)
+                    .iter()
+                    .map(|leaf| tree_operator.create_proof_with_cap(current_merkle_tree, leaf, cap_height).expect("leaf_of gives leaves of the tree"))
+                    .collect()
+            })
+            .collect()
+    }
 
-        for (i, coeff) in polynomial.coeffs().iter().enumerate() {
-            if i % 2 == 0 {
-                even_coeffs.push(*coeff);
-            } else {
-                odd_coeffs.push(*coeff);
-            }
-        }
+    /// Creates the Merkle proofs for the folded queries in the next round's tree, where
+    /// `domain_size` is the size of the current (unfolded) round's domain. The folded value itself
+    /// is left out of each proof.
+    pub fn open_next_layer<LCH, INCH, MT>(
+        next_merkle_tree: &MerkleTree<F, INCH::Output>,
+        domain_size: usize,
+        queries: &[LeafIndex<F>],
+        cap_height: usize,
+        tree_operator: &MT,
+    ) -> Vec<MerkleProof<F, INCH::Output>>
+    where
+        LCH: CRHScheme<Input = [F], Output = INCH::Output>,
+        INCH: TwoToOneCRHScheme,
+        MT: MerkleTreeOperator<F, INCH>,
+    {
+        queries
+            .iter()
+            .map(|q| {
+                let folded_index = q.index % (domain_size / 2);
+                let mut proof = tree_operator
+                    .create_proof_with_cap(next_merkle_tree, &next_merkle_tree.leaf_of(folded_index), cap_height)
+                    .expect("leaf_of gives leaves of the tree");
+                proof.values.remove(folded_index >> next_merkle_tree.height());
+                proof
+            })
+            .collect()
+    }
 
-        // Construct the reduced polynomial: f_reduced(x) = f_even(x^2) + challenge * x * f_odd(x^2)
-        let even_poly = P::from_coefficients_vec(even_coeffs);
-        let scaled_odd_coeffs: Vec<F> = odd_coeffs.iter().map(|coeff| *coeff * challenge).collect();
-        let scaled_odd_poly = P::from_coefficients_vec(scaled_odd_coeffs);
+}
 
-        let reduced_poly = even_poly.add(scaled_odd_poly);
+impl<F, P> Prover<F, P>
+where
+    F: FftField,
+    P: DenseUVPolynomial<F>,
+{
+    /// Returns the domain of the next round: the squares of the elements of `domain`, which has half
+    /// the size and the offset squared.
+    pub fn fold_domain(domain: &GeneralEvaluationDomain<F>) -> GeneralEvaluationDomain<F> {
+        fold::fold_domain(domain, 2)
+    }
 
-        let domain = Self::create_domain(half_degree);
+    /// Reduces the polynomial for the next round of the FRI protocol:
+    /// f_reduced(x) = f_even(x) + challenge * f_odd(x). A polynomial of degree d reduces to one of
+    /// degree at most ⌊d/2⌋, the degree of f_even, and exactly that unless the challenge cancels
+    /// its leading coefficient.
+    pub fn reduce(polynomial: &P, challenge: F) -> P {
+        P::from_coefficients_vec(fold::fold_polynomial(polynomial.coeffs(), challenge, 2))
+    }
 
-        let (merkle_tree, _) = Self::commit_rec::<LCH, INCH, MT>(
-            &reduced_poly,
-            domain.group_gen(),
-            tree_operator,
-        );
+    /// Reduces the evaluations of the polynomial over `domain` for the next round of the FRI protocol.
+    ///
+    /// Folds directly in evaluation space with `fold::fold_evaluations` at arity 2: for
+    /// `y = domain.element(i)` with `i < n / 2`, the sibling `-y` sits at index `i + n / 2`, and
+    ///
+    /// f_reduced(y^2) = (f(y) + f(-y)) / 2 + challenge * (f(y) - f(-y)) / (2y)
+    ///
+    /// which agrees with `reduce` on the coefficients. The returned evaluations are over the squared
+    /// domain, of half the size and with the offset squared.
+    pub fn reduce_evaluations(
+        evaluations: &[F],
+        domain: &GeneralEvaluationDomain<F>,
+        challenge: F,
+    ) -> (Vec<F>, GeneralEvaluationDomain<F>) {
+        let mut reduced_evaluations = evaluations.to_vec();
+        let reduced_domain = Self::reduce_evaluations_in_place(&mut reduced_evaluations, domain, challenge);
+        (reduced_evaluations, reduced_domain)
+    }
 
-        (reduced_poly, merkle_tree)
+    /// Same as `reduce_evaluations`, but folds into the first half of the buffer and truncates it,
+    /// so no second evaluation vector is allocated.
+    pub fn reduce_evaluations_in_place(
+        evaluations: &mut Vec<F>,
+        domain: &GeneralEvaluationDomain<F>,
+        challenge: F,
+    ) -> GeneralEvaluationDomain<F> {
+        fold::fold_evaluations_in_place(evaluations, domain, challenge, 2);
+        Self::fold_domain(domain)
     }
 
     /// Proves the small degree polynomial at the end of the FRI protocol.
@@ -218,3 +456,16 @@ where
         polynomial.coeffs().to_vec()
     }
 }
+
+/// The leaves of the current round's tree opened for the query `y`: the leaf holding `y`, followed
+/// by the leaf holding `-y` half a domain away when leaves are not packed.
+fn current_leaves<F: Field, H>(tree: &MerkleTree<F, H>, query: &LeafIndex<F>) -> Vec<LeafIndex<F>> {
+    let domain_size = tree.domain_size();
+    let leaf = tree.leaf_of(query.index);
+    if tree.leaf_width == 1 {
+        vec![leaf, tree.leaf_of((query.index + domain_size / 2) % domain_size)]
+    } else {
+        vec![leaf]
+    }
+}
+
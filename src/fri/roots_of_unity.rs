@@ -1,11 +1,39 @@
 use ark_bls12_377::Fr; 
-use ark_ff::{Field, FftField};
+use ark_ff::{Field, FftField, One};
 use ark_poly::domain::{EvaluationDomain, GeneralEvaluationDomain};
 
-pub fn get_root_of_unity(k: u32) -> Option<Fr> {
-    let two_adic_root = Fr::TWO_ADIC_ROOT_OF_UNITY;
-    let exponent = 1u64 << (Fr::TWO_ADICITY as u32 - k);
-    Some(two_adic_root.pow([exponent]))
+use super::error::FriError;
+use super::utils::check_two_adicity;
+
+/// A primitive root of unity of order `2^k`, which only exists up to the two-adicity of the field.
+/// `k = 0` is rejected with `FriError::TrivialRootOfUnity`, the only root of order 1 being 1.
+pub fn get_root_of_unity(k: u32) -> Result<Fr, FriError> {
+    if k == 0 {
+        return Err(FriError::TrivialRootOfUnity);
+    }
+    check_two_adicity::<Fr>(k)?;
+
+    // The two-adic root has order 2^TWO_ADICITY, so raising it to 2^(TWO_ADICITY - k) leaves order 2^k
+    let root = Fr::TWO_ADIC_ROOT_OF_UNITY.pow([1u64 << (Fr::TWO_ADICITY - k)]);
+    debug_assert!(has_order(root, k), "The root of unity for k = {} does not have order 2^k", k);
+    Ok(root)
+}
+
+/// A primitive root of unity generating a subgroup of at least `n` elements, with the size of
+/// that subgroup: `n` rounded up to a power of two, and to 2 below that.
+pub fn primitive_root_for_size(n: usize) -> Result<(Fr, usize), FriError> {
+    let size = n
+        .max(2)
+        .checked_next_power_of_two()
+        .ok_or(FriError::DomainTooLarge { requested: usize::BITS, supported: Fr::TWO_ADICITY })?;
+    Ok((get_root_of_unity(size.trailing_zeros())?, size))
+}
+
+/// Whether the element has order exactly `2^k`, for `k >= 1`: it is a `2^k`-th root of unity but
+/// not a `2^(k-1)`-th one.
+pub fn has_order(element: Fr, k: u32) -> bool {
+    let half_order = element.pow([1u64 << (k - 1)]);
+    half_order != Fr::one() && half_order.square() == Fr::one()
 }
 
 pub fn get_evaluation_domain(size: usize) -> Option<GeneralEvaluationDomain<Fr>> {
@@ -0,0 +1,119 @@
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+
+use ark_crypto_primitives::crh::{CRHScheme, TwoToOneCRHScheme};
+use ark_ff::{FftField, Field, PrimeField};
+use ark_poly::univariate::DensePolynomial;
+use ark_std::rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::util::VerifierChallenge;
+
+use super::config::FriConfig;
+use super::error::FriError;
+use super::merkle_tree::MerkleTreeOperatorImpl;
+use super::protocol::FRISystemImpl;
+
+/// The tags hashed first, so that leaves, inner nodes and challenges never share an input.
+const LEAF_TAG: u8 = 0;
+const NODE_TAG: u8 = 1;
+const CHALLENGE_TAG: u8 = 2;
+
+pub type Sha256Digest = [u8; 32];
+
+/// A FRI system over F whose Merkle trees and challenges both use SHA-256.
+pub type Sha256FRISystem<F> = FRISystemImpl<F, DensePolynomial<F>, MerkleTreeOperatorImpl<Sha256LeafHash<F>, Sha256TwoToOne>, Sha256Challenger<F>, Sha256TwoToOne, Sha256LeafHash<F>>;
+
+/// A SHA-256 FRI system under the configuration, e.g. `FriConfig::preset`, with a tree operator
+/// packing `config.leaf_width` values per leaf.
+pub fn sha256_system<F: FftField>(config: FriConfig) -> Result<Sha256FRISystem<F>, FriError> {
+    let tree_operator = MerkleTreeOperatorImpl::with_leaf_width((), (), config.leaf_width);
+    FRISystemImpl::with_config(config, tree_operator, Sha256Challenger::new())
+}
+
+/// Hashes the values of a Merkle leaf with SHA-256, each as its canonical serialization. Unlike
+/// the Poseidon hashes the digest does not depend on the size of the field, so it is the one to
+/// use over small fields.
+pub struct Sha256LeafHash<F>(PhantomData<F>);
+
+impl<F: Field> CRHScheme for Sha256LeafHash<F> {
+    type Input = [F];
+    type Output = Sha256Digest;
+    type Parameters = ();
+
+    fn setup<R: Rng>(_rng: &mut R) -> Result<Self::Parameters, ark_crypto_primitives::Error> {
+        Ok(())
+    }
+
+    fn evaluate<T: Borrow<Self::Input>>(_parameters: &Self::Parameters, input: T) -> Result<Self::Output, ark_crypto_primitives::Error> {
+        let mut hasher = Sha256::new().chain_update([LEAF_TAG]);
+        for value in input.borrow() {
+            let mut bytes = Vec::new();
+            value.serialize_compressed(&mut bytes)?;
+            hasher.update(&bytes);
+        }
+        Ok(hasher.finalize().into())
+    }
+}
+
+/// Hashes two SHA-256 digests into their parent.
+pub struct Sha256TwoToOne;
+
+impl TwoToOneCRHScheme for Sha256TwoToOne {
+    type Input = Sha256Digest;
+    type Output = Sha256Digest;
+    type Parameters = ();
+
+    fn setup<R: Rng>(_rng: &mut R) -> Result<Self::Parameters, ark_crypto_primitives::Error> {
+        Ok(())
+    }
+
+    fn evaluate<T: Borrow<Self::Input>>(parameters: &Self::Parameters, left: T, right: T) -> Result<Self::Output, ark_crypto_primitives::Error> {
+        Self::compress(parameters, left, right)
+    }
+
+    fn compress<T: Borrow<Self::Output>>(_parameters: &Self::Parameters, left: T, right: T) -> Result<Self::Output, ark_crypto_primitives::Error> {
+        Ok(Sha256::new().chain_update([NODE_TAG]).chain_update(left.borrow()).chain_update(right.borrow()).finalize().into())
+    }
+}
+
+/// Derives challenges from a SHA-256 digest by hashing it with the counter and reducing the 256
+/// bits modulo the field, which is off uniform by at most `p / 2^256`. Over an extension, each
+/// coefficient after the first is hashed with its position too.
+#[derive(Clone, Default)]
+pub struct Sha256Challenger<F>(PhantomData<F>);
+
+impl<F: Field> Sha256Challenger<F> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<F: Field> VerifierChallenge for Sha256Challenger<F> {
+    type Commitment = Sha256Digest;
+    type Challenge = F;
+
+    fn generate_challenge(&self, commitment: &Self::Commitment) -> Self::Challenge {
+        self.generate_challenge_with_counter(commitment, 0)
+    }
+
+    fn verify_challenge_generation(&self, commitment: &Self::Commitment, challenge: &Self::Challenge) -> bool {
+        self.generate_challenge(commitment) == *challenge
+    }
+
+    fn generate_challenge_with_counter(&self, commitment: &Self::Commitment, counter: u64) -> Self::Challenge {
+        let coefficients: Vec<F::BasePrimeField> = (0..F::extension_degree())
+            .map(|position| {
+                let mut hasher = Sha256::new()
+                    .chain_update([CHALLENGE_TAG])
+                    .chain_update(commitment)
+                    .chain_update(counter.to_le_bytes());
+                if position > 0 {
+                    hasher.update(position.to_le_bytes());
+                }
+                F::BasePrimeField::from_le_bytes_mod_order(&hasher.finalize())
+            })
+            .collect();
+        F::from_base_prime_field_elems(&coefficients).expect("One coefficient per degree of the extension")
+    }
+}
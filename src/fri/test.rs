@@ -0,0 +1,2127 @@
+#![allow(unused_imports)]
+#![allow(dead_code)]
+
+use std::borrow::Borrow;
+use std::cell::Cell;
+use std::str::FromStr;
+
+use ark_bls12_381::Fr;
+use ark_crypto_primitives::crh::poseidon::{TwoToOneCRH, CRH};
+use ark_crypto_primitives::crh::TwoToOneCRHScheme;
+use ark_crypto_primitives::sponge::poseidon::{PoseidonConfig, PoseidonSponge};
+use crate::util::poseidon::secure_config;
+use ark_crypto_primitives::sponge::CryptographicSponge;
+use ark_ff::{FftField, Field, One, UniformRand, Zero};
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, EvaluationDomain, Evaluations, GeneralEvaluationDomain, Polynomial};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::thread_rng;
+
+use crate::fri::config::{FriConfig, SecurityPreset};
+use crate::fri::deep::{deep_quotient, sample_ood_point, OodEvalProof};
+use crate::fri::fold;
+use crate::fri::error::{FriError, MerkleError, ProofShapeError};
+use crate::fri::merkle_tree::{
+    pack_payload, CompactMerkleProof, LeafIndex, MerkleNode, MerkleProof, MerkleTree, MerkleTreeOperator, MerkleTreeOperatorImpl, MerkleTreeView,
+};
+use crate::fri::oracle::{CommitmentOracle, OracleFRI};
+use crate::fri::progress::{CancellationToken, ProgressSink};
+use crate::fri::roots_of_unity::{get_root_of_unity, has_order, primitive_root_for_size};
+use crate::fri::protocol::{FRIProtocol, FRIProtocolProof, FRISystemImpl};
+use crate::fri::prover::{FRIRecCommitment, FRIRecProof, Prover, QueryOpening};
+use crate::fri::transcript::{Transcript, TranscriptSeed};
+use crate::fri::utils::{challenge_to_index, hash_field_elements};
+use crate::fri::verifier::verifier::Verifier;
+use crate::util::{PoseidonTranscript, Sha256Transcript, Transcript as _, TranscriptDigest, VerifierChallenge};
+
+// Named after the generic parameters they fill in throughout the crate
+#[allow(clippy::upper_case_acronyms)]
+type LCH = CRH<Fr>;
+#[allow(clippy::upper_case_acronyms)]
+type INCH = TwoToOneCRH<Fr>;
+type TreeOperator = MerkleTreeOperatorImpl<LCH, INCH>;
+type FRIProver = Prover<Fr, DensePolynomial<Fr>>;
+type FRIVerifier = Verifier<Fr, DensePolynomial<Fr>, LCH, INCH, TreeOperator>;
+type FRISystem = FRISystemImpl<Fr, DensePolynomial<Fr>, TreeOperator, PoseidonChallenger, INCH, LCH>;
+
+/// Derives challenges by hashing the Merkle root with a Poseidon sponge
+pub struct PoseidonChallenger {
+    config: PoseidonConfig<Fr>,
+}
+
+impl VerifierChallenge for PoseidonChallenger {
+    type Commitment = Fr;
+    type Challenge = Fr;
+
+    fn generate_challenge(&self, commitment: &Self::Commitment) -> Self::Challenge {
+        let mut sponge = PoseidonSponge::new(&self.config);
+        sponge.absorb(commitment);
+        sponge.squeeze_field_elements(1)[0]
+    }
+
+    fn verify_challenge_generation(&self, commitment: &Self::Commitment, challenge: &Self::Challenge) -> bool {
+        self.generate_challenge(commitment) == *challenge
+    }
+
+    fn generate_challenge_with_counter(&self, commitment: &Self::Commitment, counter: u64) -> Self::Challenge {
+        let mut sponge = PoseidonSponge::new(&self.config);
+        sponge.absorb(commitment);
+        if counter > 0 {
+            sponge.absorb(&counter);
+        }
+        sponge.squeeze_field_elements(1)[0]
+    }
+}
+
+/// Returns zero for the first candidate challenge, then the Poseidon challenge
+pub struct ZeroFirstChallenger {
+    inner: PoseidonChallenger,
+}
+
+impl VerifierChallenge for ZeroFirstChallenger {
+    type Commitment = Fr;
+    type Challenge = Fr;
+
+    fn generate_challenge(&self, _commitment: &Self::Commitment) -> Self::Challenge {
+        Fr::zero()
+    }
+
+    fn verify_challenge_generation(&self, commitment: &Self::Commitment, challenge: &Self::Challenge) -> bool {
+        self.generate_challenge(commitment) == *challenge
+    }
+
+    fn generate_challenge_with_counter(&self, commitment: &Self::Commitment, counter: u64) -> Self::Challenge {
+        if counter == 0 {
+            self.generate_challenge(commitment)
+        } else {
+            self.inner.generate_challenge_with_counter(commitment, counter)
+        }
+    }
+}
+
+/// Draws the first few challenges from the domain, the way an unlucky challenger could.
+pub struct InDomainFirstChallenger {
+    inner: PoseidonChallenger,
+    domain: GeneralEvaluationDomain<Fr>,
+    in_domain: u64,
+}
+
+impl VerifierChallenge for InDomainFirstChallenger {
+    type Commitment = Fr;
+    type Challenge = Fr;
+
+    fn generate_challenge(&self, commitment: &Self::Commitment) -> Self::Challenge {
+        self.generate_challenge_with_counter(commitment, 0)
+    }
+
+    fn verify_challenge_generation(&self, commitment: &Self::Commitment, challenge: &Self::Challenge) -> bool {
+        self.generate_challenge(commitment) == *challenge
+    }
+
+    fn generate_challenge_with_counter(&self, commitment: &Self::Commitment, counter: u64) -> Self::Challenge {
+        if counter < self.in_domain {
+            self.domain.element(counter as usize * 5)
+        } else {
+            self.inner.generate_challenge_with_counter(commitment, counter)
+        }
+    }
+}
+
+thread_local! {
+    static TWO_TO_ONE_HASHES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The Poseidon two-to-one hash, counting its invocations on the current thread
+pub struct CountingTwoToOneCRH;
+
+impl TwoToOneCRHScheme for CountingTwoToOneCRH {
+    type Input = Fr;
+    type Output = Fr;
+    type Parameters = PoseidonConfig<Fr>;
+
+    fn setup<R: ark_std::rand::Rng>(r: &mut R) -> Result<Self::Parameters, ark_crypto_primitives::Error> {
+        INCH::setup(r)
+    }
+
+    fn evaluate<T: Borrow<Self::Input>>(parameters: &Self::Parameters, left: T, right: T) -> Result<Self::Output, ark_crypto_primitives::Error> {
+        TWO_TO_ONE_HASHES.with(|count| count.set(count.get() + 1));
+        INCH::evaluate(parameters, left, right)
+    }
+
+    fn compress<T: Borrow<Self::Output>>(parameters: &Self::Parameters, left: T, right: T) -> Result<Self::Output, ark_crypto_primitives::Error> {
+        TWO_TO_ONE_HASHES.with(|count| count.set(count.get() + 1));
+        INCH::compress(parameters, left, right)
+    }
+}
+
+fn poseidon_config() -> PoseidonConfig<Fr> {
+    secure_config::<Fr>(2, 1)
+}
+
+fn setup_tree_operator() -> TreeOperator {
+    TreeOperator::new(poseidon_config(), poseidon_config())
+}
+
+fn setup_tree_operator_with_leaf_width(leaf_width: usize) -> TreeOperator {
+    TreeOperator::with_leaf_width(poseidon_config(), poseidon_config(), leaf_width)
+}
+
+fn setup_system(max_degree: usize, num_queries: usize) -> FRISystem {
+    FRISystem::new(
+        max_degree,
+        num_queries,
+        setup_tree_operator(),
+        PoseidonChallenger { config: poseidon_config() },
+    )
+}
+
+fn setup_system_with_config(config: FriConfig) -> FRISystem {
+    FRISystem::with_config(
+        config,
+        setup_tree_operator_with_leaf_width(config.leaf_width),
+        PoseidonChallenger { config: poseidon_config() },
+    ).unwrap()
+}
+
+#[test]
+fn test_create_domain_with_offset() {
+    let offset = Fr::GENERATOR;
+    let domain = FRIProver::create_domain_with_offset(7, 4, offset).unwrap();
+
+    assert_eq!(domain.size(), 32);
+    assert_eq!(domain.coset_offset(), offset);
+    assert_eq!(domain.element(0), offset);
+
+    // Without blowup or offset we get back the plain subgroup domain
+    let plain = FRIProver::create_domain(7).unwrap();
+    let plain_with_offset = FRIProver::create_domain_with_offset(7, 1, Fr::one()).unwrap();
+    assert_eq!(plain.size(), 8);
+    assert_eq!(plain.group_gen(), plain_with_offset.group_gen());
+    assert_eq!(plain.coset_offset(), plain_with_offset.coset_offset());
+}
+
+#[test]
+fn test_domains_beyond_the_two_adicity_are_rejected() {
+    // BLS12-381 Fr has roots of unity of order up to 2^32, and the domains are not allocated
+    assert_eq!(Fr::TWO_ADICITY, 32);
+    let too_large = |requested| FriError::DomainTooLarge { requested, supported: 32 };
+
+    assert_eq!(FRIProver::create_domain((1 << 32) - 1).unwrap().size(), 1 << 32);
+    assert_eq!(FRIProver::create_domain(1 << 32).map(|domain| domain.size()), Err(too_large(33)));
+    assert_eq!(FRIProver::create_domain_with_offset((1 << 31) - 1, 2, Fr::GENERATOR).unwrap().size(), 1 << 32);
+    assert_eq!(FRIProver::create_domain_with_offset((1 << 31) - 1, 4, Fr::GENERATOR).map(|domain| domain.size()), Err(too_large(33)));
+    assert_eq!(FRIProver::create_domain(usize::MAX).map(|domain| domain.size()), Err(too_large(usize::BITS)));
+
+    let config = FriConfig { blowup: 8, ..FriConfig::new(1, 4) };
+    assert_eq!(config.check_domain::<Fr>((1 << 29) - 1), Ok(()));
+    assert_eq!(config.check_domain::<Fr>(1 << 29), Err(too_large(33)));
+
+    // A proof claiming such a degree is rejected before it is looked at
+    let system = setup_system(1, 4);
+    let proof = system.prove(&DensePolynomial::<Fr>::rand(15, &mut thread_rng()), 15).unwrap();
+    assert_eq!(system.verify_degree(&proof, 1 << 40), Err(too_large(41)));
+
+    // BLS12-377 Fr has roots of unity of order up to 2^47
+    let root = get_root_of_unity(47).unwrap();
+    assert_eq!(root.pow([1u64 << 47]), ark_bls12_377::Fr::one());
+    assert_eq!(get_root_of_unity(48), Err(FriError::DomainTooLarge { requested: 48, supported: 47 }));
+}
+
+#[test]
+fn test_roots_of_unity_have_their_order() {
+    type Fr377 = ark_bls12_377::Fr;
+    for k in 1..=Fr377::TWO_ADICITY {
+        let root = get_root_of_unity(k).unwrap();
+        assert_eq!(root.pow([1u64 << k]), Fr377::one());
+        assert_ne!(root.pow([1u64 << (k - 1)]), Fr377::one());
+        assert!(has_order(root, k));
+    }
+    assert_eq!(get_root_of_unity(1).unwrap(), -Fr377::one());
+    assert!(!has_order(Fr377::one(), 1));
+    assert!(!has_order(get_root_of_unity(3).unwrap(), 4));
+
+    // Both ends of the valid range
+    assert_eq!(get_root_of_unity(0), Err(FriError::TrivialRootOfUnity));
+    assert_eq!(get_root_of_unity(u32::MAX), Err(FriError::DomainTooLarge { requested: u32::MAX, supported: 47 }));
+
+    // Sizes are rounded up to the subgroup actually used
+    for (n, size) in [(0, 2_usize), (1, 2), (2, 2), (5, 8), (1024, 1024), (1025, 2048), (1 << 47, 1 << 47)] {
+        assert_eq!(primitive_root_for_size(n), Ok((get_root_of_unity(size.trailing_zeros()).unwrap(), size)));
+    }
+    assert_eq!(primitive_root_for_size((1 << 47) + 1), Err(FriError::DomainTooLarge { requested: 48, supported: 47 }));
+    assert_eq!(primitive_root_for_size(usize::MAX), Err(FriError::DomainTooLarge { requested: usize::BITS, supported: 47 }));
+}
+
+#[test]
+fn test_commit_rec_uses_given_domain() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(7, &mut rng);
+    let tree_operator = setup_tree_operator();
+
+    for (blowup, offset) in [(1, Fr::one()), (2, Fr::GENERATOR)] {
+        let domain = FRIProver::create_domain_with_offset(polynomial.degree(), blowup, offset).unwrap();
+        let (tree, commitment) = FRIProver::commit_rec::<LCH, INCH, _>(&polynomial, &domain, &tree_operator);
+
+        assert_eq!(tree.primitive_root, domain.group_gen());
+        assert_eq!(tree.coset_offset, domain.coset_offset());
+        assert_eq!(tree.leaf_count(), domain.size());
+        assert_eq!(commitment.merkle_root, tree.root());
+    }
+}
+
+#[test]
+fn test_merkle_tree_accessors() {
+    let mut rng = thread_rng();
+
+    for (degree, leaf_width) in [(3, 1), (7, 1), (15, 2), (31, 4)] {
+        let polynomial = DensePolynomial::<Fr>::rand(degree, &mut rng);
+        let tree_operator = setup_tree_operator_with_leaf_width(leaf_width);
+        let domain = FRIProver::create_domain_with_offset(degree, 2, Fr::GENERATOR).unwrap();
+        let (tree, commitment) = FRIProver::commit_rec::<LCH, INCH, _>(&polynomial, &domain, &tree_operator);
+
+        assert_eq!(tree.root(), commitment.merkle_root);
+        assert_eq!(tree.leaf_count(), domain.size() / leaf_width);
+        assert_eq!(tree.leaf_count(), 1 << tree.height());
+        for position in 0..domain.size() {
+            let point = domain.element(position);
+            assert_eq!(tree.get_leaf(position), Some((point, polynomial.evaluate(&point))));
+        }
+        assert_eq!(tree.get_leaf(domain.size()), None);
+        assert_eq!(tree.get_leaf(usize::MAX), None);
+    }
+}
+
+#[test]
+fn test_commit_rec_batched_opens_rows() {
+    let mut rng = thread_rng();
+    let columns: Vec<_> = [7, 5, 3].iter().map(|&degree| DensePolynomial::<Fr>::rand(degree, &mut rng)).collect();
+    let tree_operator = setup_tree_operator();
+    let verifier = Verifier::<Fr, DensePolynomial<Fr>, LCH, INCH, TreeOperator>::with_cap_height(tree_operator.clone(), 1);
+    let domain = FRIProver::create_domain_with_offset(7, 2, Fr::GENERATOR).unwrap();
+
+    let (tree, commitment) = FRIProver::commit_rec_batched::<LCH, INCH, _>(&columns, &domain, &tree_operator);
+    assert_eq!(commitment.degree, 7);
+    assert_eq!(tree.leaf_count(), domain.size());
+
+    // Each opening holds the evaluations of every column at the query's point
+    let queries = [LeafIndex::from_index(3, &domain), LeafIndex::from_index(12, &domain)];
+    let openings = FRIProver::open_rec_batched::<LCH, INCH, _>(&tree, &queries, 1, &tree_operator);
+    for (query, opening) in queries.iter().zip(&openings) {
+        let row: Vec<_> = columns.iter().map(|column| column.evaluate(&query.point)).collect();
+        assert_eq!(opening.row(), row);
+    }
+    let cap = tree.cap(1);
+    assert!(verifier.verify_rec_batched(&commitment, &cap, &queries, &openings, 3));
+
+    // Openings of another row, or missing a column, are rejected
+    assert!(!verifier.verify_rec_batched(&commitment, &cap, &queries[..1], &openings[1..], 3));
+    let mut tampered = openings.clone();
+    tampered[0].proof.values[2] += Fr::one();
+    assert!(!verifier.verify_rec_batched(&commitment, &cap, &queries, &tampered, 3));
+    tampered[0].proof.values.pop();
+    assert!(!verifier.verify_rec_batched(&commitment, &cap, &queries, &tampered, 2));
+}
+
+#[test]
+fn test_interleaved_columns_share_one_path() {
+    let mut rng = thread_rng();
+    let domain = FRIProver::create_domain_with_offset(15, 1, Fr::one()).unwrap();
+    let columns: Vec<Vec<Fr>> = (0..4).map(|_| (0..domain.size()).map(|_| Fr::rand(&mut rng)).collect()).collect();
+    let tree_operator = setup_tree_operator();
+    let verifier = Verifier::<Fr, DensePolynomial<Fr>, LCH, INCH, TreeOperator>::create(tree_operator.clone());
+
+    let (tree, commitment) = FRIProver::commit_interleaved::<LCH, INCH, _>(&columns, &domain, 15, &tree_operator);
+    let cap = tree.cap(0);
+    let queries: Vec<_> = [0, 5, 15].iter().map(|&i| LeafIndex::from_index(i, &domain)).collect();
+    let openings = FRIProver::open_rec_batched::<LCH, INCH, _>(&tree, &queries, 0, &tree_operator);
+    for (query, opening) in queries.iter().zip(&openings) {
+        assert_eq!(opening.query(), query);
+        assert_eq!(opening.row(), columns.iter().map(|column| column[query.index]).collect::<Vec<_>>());
+        // The path is that of a single column
+        assert_eq!(opening.proof.path.len(), tree.height());
+    }
+    assert!(verifier.verify_rec_batched(&commitment, &cap, &queries, &openings, 4));
+
+    // Altering any single cell is detected
+    for (i, column) in (0..openings.len()).flat_map(|i| (0..4).map(move |column| (i, column))) {
+        let mut tampered = openings.clone();
+        tampered[i].proof.values[column] += Fr::one();
+        assert!(!verifier.verify_rec_batched(&commitment, &cap, &queries, &tampered, 4));
+    }
+}
+
+#[test]
+fn test_prove_verify() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(15, &mut rng);
+    let system = setup_system(1, 4);
+
+    let proof = system.prove(&polynomial, polynomial.degree()).unwrap();
+    assert_eq!(proof.round_proofs.len(), 3);
+    assert!(system.verify(&proof));
+
+    // The final polynomial is bound to the last committed layer
+    let mut tampered = proof;
+    tampered.final_polynomial[0] += Fr::one();
+    assert!(!system.verify(&tampered));
+}
+
+#[test]
+fn test_domain_cache_is_hit_per_query() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(31, &mut rng);
+    let system = setup_system(0, 4);
+
+    // The initial domain and its five folds are each built once
+    let (_, state) = system.commit_phase(&polynomial, 31).unwrap();
+    assert_eq!(state.trees.len(), 6);
+    assert_eq!((state.domains.hits(), state.domains.misses()), (0, 6));
+
+    let proof = system.prove(&polynomial, 31).unwrap();
+    assert_eq!(proof.round_proofs.len(), 5);
+    assert!(system.verify(&proof));
+
+    // Replay the rounds through a single verifier
+    let mut transcript = system.new_transcript(&TranscriptSeed::default());
+    transcript.absorb(&system.tree_operator, &proof.initial_commitment);
+    assert!(transcript.absorb_grinding_nonce(&system.tree_operator, &system.verifier_challenge, 0, proof.grinding_nonce()));
+    let query_indices: Vec<usize> = proof.round_proofs[0].openings.iter().map(|opening| opening.query.index).collect();
+    let verifier = Verifier::<Fr, DensePolynomial<Fr>, LCH, INCH, TreeOperator>::create(system.tree_operator.clone());
+    let mut coset_offset = Fr::one();
+    let mut current_root = proof.initial_commitment;
+    for (i, (round_proof, next_root)) in proof.round_proofs.iter().zip(&proof.round_commitments).enumerate() {
+        let challenge = system.verifier_challenge.generate_nonzero_challenge(transcript.state());
+        transcript.absorb(&system.tree_operator, next_root);
+        assert!(verifier.verify_rec(
+            &FRIRecCommitment { merkle_root: current_root, degree: 31 >> i },
+            round_proof,
+            &FRIRecCommitment { merkle_root: *next_root, degree: 31 >> (i + 1) },
+            challenge,
+            &query_indices,
+            coset_offset,
+        ));
+        coset_offset.square_in_place();
+        current_root = *next_root;
+    }
+
+    // Every query looks up the generator of the round's domain for the query and both current
+    // leaves, and that of the next domain for the folded leaf: one miss per domain, hits otherwise
+    let cache = verifier.domain_cache();
+    assert_eq!(cache.misses(), 6);
+    assert_eq!(cache.hits() + cache.misses(), 5 * 4 * 4);
+}
+
+#[test]
+fn test_fri_instances_share_a_transcript() {
+    let mut rng = thread_rng();
+    let system = setup_system(1, 4);
+    let (first, second) = (DensePolynomial::<Fr>::rand(15, &mut rng), DensePolynomial::<Fr>::rand(15, &mut rng));
+
+    // The outer protocol absorbed a commitment of its own before running both instances
+    let outer_transcript = || {
+        let mut transcript = PoseidonTranscript::new(&poseidon_config());
+        transcript.absorb_field(b"outer", &Fr::from(42u64));
+        transcript
+    };
+    let mut transcript = outer_transcript();
+    let first_proof = system.prove_with_transcript(&first, &mut transcript).unwrap();
+    let second_proof = system.prove_with_transcript(&second, &mut transcript).unwrap();
+    assert_eq!(first_proof.round_proofs.len(), 3);
+
+    let mut transcript = outer_transcript();
+    assert!(system.verify_with_transcript(&first_proof, &mut transcript));
+    assert!(system.verify_with_transcript(&second_proof, &mut transcript));
+
+    // Each instance's challenges depend on everything absorbed before it
+    let mut transcript = outer_transcript();
+    assert!(!system.verify_with_transcript(&second_proof, &mut transcript));
+    let mut transcript = outer_transcript();
+    transcript.absorb_field(b"outer", &Fr::from(43u64));
+    assert!(!system.verify_with_transcript(&first_proof, &mut transcript));
+
+    // The standalone verifier draws its challenges elsewhere
+    assert!(!system.verify(&first_proof));
+}
+
+#[test]
+fn test_prover_and_verifier_transcripts_agree() {
+    let mut rng = thread_rng();
+    let system = setup_system(1, 4);
+    let polynomial = DensePolynomial::<Fr>::rand(31, &mut rng);
+
+    // After the proof both sides have absorbed the same messages and squeezed the same challenges
+    let mut prover_transcript = Sha256Transcript::new(b"fri test");
+    let proof = system.prove_with_transcript(&polynomial, &mut prover_transcript).unwrap();
+    let mut verifier_transcript = Sha256Transcript::new(b"fri test");
+    assert!(system.verify_with_transcript(&proof, &mut verifier_transcript));
+    assert_eq!(prover_transcript, verifier_transcript);
+    assert_eq!(
+        crate::util::Transcript::<Fr>::squeeze_field(&mut prover_transcript, b"next"),
+        crate::util::Transcript::<Fr>::squeeze_field(&mut verifier_transcript, b"next")
+    );
+
+    // Another domain separator gives other challenges
+    assert!(!system.verify_with_transcript(&proof, &mut Sha256Transcript::new(b"other test")));
+    let mut poseidon_transcript = PoseidonTranscript::new(&poseidon_config());
+    assert!(!system.verify_with_transcript(&proof, &mut poseidon_transcript));
+}
+
+/// Records the callbacks of a proof, cancelling its token once the given round is committed to
+#[derive(Default)]
+struct CountingSink {
+    round_starts: Vec<(usize, usize)>,
+    commits: Vec<(usize, Fr)>,
+    completions: usize,
+    cancel_after: Option<(usize, CancellationToken)>,
+}
+
+impl ProgressSink<Fr> for CountingSink {
+    fn on_round_start(&mut self, round: usize, domain_size: usize) {
+        self.round_starts.push((round, domain_size));
+    }
+
+    fn on_commit(&mut self, round: usize, root: &Fr) {
+        self.commits.push((round, *root));
+        if let Some((_, token)) = self.cancel_after.as_ref().filter(|(last_round, _)| *last_round == round) {
+            token.cancel();
+        }
+    }
+
+    fn on_complete(&mut self) {
+        self.completions += 1;
+    }
+}
+
+#[test]
+fn test_progress_is_reported_per_round() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(15, &mut rng);
+    let system = setup_system(0, 4);
+
+    // The initial layer and four folds, each reported once with the root the proof commits to
+    let mut sink = CountingSink::default();
+    let proof = system.prove_with_progress(&polynomial, 15, &mut sink, None).unwrap();
+    assert_eq!(proof.round_proofs.len(), 4);
+    assert!(system.verify(&proof));
+    assert_eq!(sink.round_starts, vec![(0, 16), (1, 8), (2, 4), (3, 2), (4, 1)]);
+    let roots: Vec<Fr> = std::iter::once(proof.initial_commitment).chain(proof.round_commitments.iter().copied()).collect();
+    assert_eq!(sink.commits, roots.into_iter().enumerate().collect::<Vec<_>>());
+    assert_eq!(sink.completions, 1);
+
+    // Reporting progress does not change the proof
+    let plain = system.prove(&polynomial, 15).unwrap();
+    assert_eq!(plain.transcript_digest(), proof.transcript_digest());
+
+    // Cancelling once round 1 is committed to stops before round 2 starts
+    let token = CancellationToken::new();
+    let mut sink = CountingSink { cancel_after: Some((1, token.clone())), ..CountingSink::default() };
+    assert_eq!(system.prove_with_progress(&polynomial, 15, &mut sink, Some(&token)).err(), Some(FriError::Cancelled));
+    assert_eq!(sink.round_starts.len(), 2);
+    assert_eq!(sink.commits.len(), 2);
+    assert_eq!(sink.completions, 0);
+
+    // A token cancelled up front stops the proof before anything is evaluated
+    let mut sink = CountingSink::default();
+    assert_eq!(system.prove_with_progress(&polynomial, 15, &mut sink, Some(&token)).err(), Some(FriError::Cancelled));
+    assert!(sink.round_starts.is_empty());
+}
+
+#[test]
+fn test_transcript_digest_changes_with_the_proof() {
+    let polynomial = DensePolynomial::from_coefficients_vec((1..=16u64).map(Fr::from).collect());
+    let system = setup_system(1, 4);
+    let digest = system.prove(&polynomial, 15).unwrap().transcript_digest();
+    assert_eq!(digest, system.prove(&polynomial, 15).unwrap().transcript_digest());
+
+    let mut proof = system.prove(&polynomial, 15).unwrap();
+    proof.final_polynomial[0] += Fr::one();
+    assert_ne!(proof.transcript_digest(), digest);
+    let mut proof = system.prove(&polynomial, 15).unwrap();
+    proof.round_proofs[1].openings[2].current[0].values[0] += Fr::one();
+    assert_ne!(proof.transcript_digest(), digest);
+    let proof = system.prove_with_seed(&polynomial, 15, &TranscriptSeed([1; 32])).unwrap();
+    assert_ne!(proof.transcript_digest(), digest);
+}
+
+#[test]
+fn test_verify_small_exact_rejects_lower_degree() {
+    let coeffs: Vec<Fr> = (1..=4u64).map(Fr::from).collect();
+    assert!(FRIVerifier::verify_small(&coeffs, 3));
+    assert!(FRIVerifier::verify_small_exact(&coeffs, 3));
+
+    // Degree 2 where 3 is expected, with the leading coefficient missing or zero
+    assert!(FRIVerifier::verify_small(&coeffs[..3], 3));
+    assert!(!FRIVerifier::verify_small_exact(&coeffs[..3], 3));
+    let zero_leading = [&coeffs[..3], &[Fr::zero()]].concat();
+    assert!(FRIVerifier::verify_small(&zero_leading, 3));
+    assert!(!FRIVerifier::verify_small_exact(&zero_leading, 3));
+    assert!(!FRIVerifier::verify_small_exact(&[coeffs.clone(), vec![Fr::one()]].concat(), 3));
+
+    // The final polynomial of a polynomial of full degree has exactly the folded degree
+    let system = setup_system(3, 8);
+    let proof = system.prove(&DensePolynomial::<Fr>::rand(63, &mut thread_rng()), 63).unwrap();
+    assert!(FRIVerifier::verify_small_exact(&proof.final_polynomial, 3));
+}
+
+#[test]
+fn test_compact_proof_verifies_and_is_smaller() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(63, &mut rng);
+    for cap_height in [0, 1] {
+        let system = setup_system_with_config(FriConfig { cap_height, ..FriConfig::new(3, 8) });
+        let proof = system.prove(&polynomial, 63).unwrap();
+        let compact = proof.compact();
+        assert_eq!(compact.caps.len(), proof.round_proofs.len() + 1);
+        assert!(system.verify_compact(&compact));
+        assert!(compact.compressed_size() < proof.compressed_size());
+
+        let expanded = compact.expand().unwrap();
+        assert!(expanded.round_proofs.iter().zip(&proof.round_proofs).all(|(expanded, round)| {
+            expanded.current_cap == round.current_cap && expanded.next_cap == round.next_cap
+        }));
+
+        // A missing or altered cap does not verify
+        let mut missing = compact.clone();
+        missing.caps.pop();
+        assert!(missing.expand().is_none());
+        assert!(!system.verify_compact(&missing));
+        let mut altered = compact.clone();
+        altered.caps[1][0] += Fr::one();
+        assert!(!system.verify_compact(&altered));
+    }
+}
+
+#[test]
+fn test_verify_against_log() {
+    let mut rng = thread_rng();
+    let system = setup_system(3, 8);
+    let proofs: Vec<_> = (0..3).map(|_| system.prove(&DensePolynomial::<Fr>::rand(63, &mut rng), 63).unwrap()).collect();
+    let log: Vec<_> = proofs.iter().map(|proof| proof.initial_commitment).collect();
+
+    // Each proof verifies at its own entry only
+    for (index, proof) in proofs.iter().enumerate() {
+        for entry in 0..log.len() {
+            assert_eq!(system.verify_against_log(proof, &log, entry), entry == index);
+        }
+        assert!(!system.verify_against_log(proof, &log, log.len()));
+    }
+
+    // The entry must also hold a valid proof
+    let mut forged = system.prove(&DensePolynomial::<Fr>::rand(63, &mut rng), 63).unwrap();
+    forged.initial_commitment = log[0];
+    assert!(!system.verify_against_log(&forged, &log, 0));
+
+    // A proof without rounds reveals the committed polynomial, so one of a polynomial small
+    // enough verifies against its entry
+    let small = system.prove(&DensePolynomial::<Fr>::rand(3, &mut rng), 3).unwrap();
+    assert!(small.round_proofs.is_empty());
+    assert!(system.verify_against_log(&small, std::slice::from_ref(&small.initial_commitment), 0));
+
+    // but one claiming any other polynomial for an entry is rejected, even sent with the
+    // transcript replayed to the state the verifier reaches
+    let mut transcript = system.new_transcript(&TranscriptSeed::default());
+    transcript.absorb(&system.tree_operator, &log[0]);
+    let no_rounds: (Vec<Fr>, Vec<FRIRecProof<Fr, Fr>>) = (Vec::new(), Vec::new());
+    let mut bytes = Vec::new();
+    ((log[0], no_rounds), (vec![Fr::one()], *transcript.state(), 0u64)).serialize_compressed(&mut bytes).unwrap();
+    let zero_round = FRIProtocolProof::<Fr, Fr>::deserialize_compressed(bytes.as_slice()).unwrap();
+    assert!(!system.verify(&zero_round));
+    assert!(!system.verify_against_log(&zero_round, &log, 0));
+    let mut other_final = small.clone();
+    other_final.final_polynomial[0] += Fr::one();
+    assert!(!system.verify_against_log(&other_final, std::slice::from_ref(&small.initial_commitment), 0));
+}
+
+#[cfg(feature = "debug-trace")]
+#[test]
+fn test_prove_with_trace() {
+    let polynomial = DensePolynomial::<Fr>::rand(63, &mut thread_rng());
+    let system = setup_system(3, 8);
+    let (proof, trace) = system.prove_with_trace(&polynomial, 63).unwrap();
+    assert_eq!(proof.transcript_digest(), system.prove(&polynomial, 63).unwrap().transcript_digest());
+    assert!(system.verify(&proof));
+
+    // The polynomial, then one fold per round halving its degree, down to the final polynomial
+    assert_eq!(trace.len(), proof.round_commitments.len() + 1);
+    assert_eq!(trace[0], polynomial);
+    assert!(trace.windows(2).all(|folds| folds[1].degree() < folds[0].degree()));
+    assert_eq!(trace.iter().map(|fold| fold.degree()).collect::<Vec<_>>(), [63, 31, 15, 7, 3]);
+    assert_eq!(trace.last().unwrap(), &DensePolynomial::from_coefficients_slice(&proof.final_polynomial));
+}
+
+#[test]
+fn test_prove_verify_zk() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(15, &mut rng);
+    let system = setup_system(1, 4);
+    let blinded_degree = 15 + system.zk_blinding_terms();
+
+    let proof = system.prove_zk(&polynomial, &mut rng).unwrap();
+    let other = system.prove_zk(&polynomial, &mut rng).unwrap();
+    assert!(system.verify(&proof));
+    assert!(system.verify(&other));
+    assert_eq!(system.verify_degree(&proof, blinded_degree), Ok(()));
+
+    // The same polynomial is committed to with different blinders, so the openings differ
+    assert_ne!(proof.initial_commitment, other.initial_commitment);
+    let opened_values = |proof: &FRIProtocolProof<Fr, Fr>| -> Vec<Fr> {
+        proof.round_proofs[0].openings.iter().flat_map(|opening| opening.current.iter().flat_map(|leaf| leaf.values.clone())).collect()
+    };
+    let unblinded = system.prove(&polynomial, 15).unwrap();
+    assert_ne!(opened_values(&proof), opened_values(&other));
+    assert_ne!(opened_values(&proof), opened_values(&unblinded));
+}
+
+#[test]
+fn test_prove_from_evals_matches_coefficient_form() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(15, &mut rng);
+    let system = setup_system(1, 4);
+
+    // Evaluations over the same domain `prove` commits to
+    let domain = FRIProver::create_domain(polynomial.degree()).unwrap();
+    let evals = domain.fft(&polynomial.coeffs);
+
+    let proof = system.prove(&polynomial, polynomial.degree()).unwrap();
+    let proof_from_evals = system.prove_from_evals(&evals, &domain).unwrap();
+
+    assert_eq!(proof_from_evals.initial_commitment, proof.initial_commitment);
+    assert_eq!(proof_from_evals.round_commitments, proof.round_commitments);
+    assert_eq!(proof_from_evals.final_polynomial, proof.final_polynomial);
+    assert!(system.verify(&proof_from_evals));
+}
+
+#[test]
+fn test_proofs_reconstruct_to_the_committed_polynomial() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(31, &mut rng);
+    let system = setup_system_with_config(FriConfig { blowup: 2, ..FriConfig::new(1, 4) });
+
+    let (_, state) = system.commit_phase(&polynomial, 31).unwrap();
+    let proof = system.prove(&polynomial, 31).unwrap();
+    assert_eq!(system.reconstruct_poly_from_rounds(&proof, &state), Some(polynomial.clone()));
+
+    // A final polynomial that is not the last layer
+    let mut tampered = proof.clone();
+    tampered.final_polynomial[0] += Fr::one();
+    assert_eq!(system.reconstruct_poly_from_rounds(&tampered, &state), None);
+
+    // A layer that is not the fold of the one before, as a folding bug would commit to
+    let (_, mut state) = system.commit_phase(&polynomial, 31).unwrap();
+    let other = DensePolynomial::<Fr>::rand(7, &mut rng);
+    let domain = GeneralEvaluationDomain::<Fr>::new(16).unwrap();
+    state.trees[2] = FRIProver::commit_rec::<LCH, INCH, _>(&other, &domain, &system.tree_operator).0;
+    let mut tampered = proof.clone();
+    tampered.round_commitments[1] = state.trees[2].root();
+    assert_eq!(system.reconstruct_poly_from_rounds(&tampered, &state), None);
+
+    // Layers committed for another proof
+    let (_, other_state) = system.commit_phase(&DensePolynomial::<Fr>::rand(31, &mut rng), 31).unwrap();
+    assert_eq!(system.reconstruct_poly_from_rounds(&proof, &other_state), None);
+}
+
+#[test]
+fn test_final_polynomial_blinding() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(15, &mut rng);
+    let system = setup_system(1, 4);
+    let blinded_degree = system.final_blinding_degree(15);
+    assert_eq!(blinded_degree, 63);
+
+    let proof = system.prove_with_blinded_final(&polynomial, &mut rng).unwrap();
+    let other = system.prove_with_blinded_final(&polynomial, &mut rng).unwrap();
+    assert!(system.verify(&proof));
+    assert!(system.verify(&other));
+    assert_eq!(system.verify_degree(&proof, blinded_degree), Ok(()));
+    assert_eq!(proof.round_proofs.len(), 5);
+
+    // Fresh padding reveals a different final polynomial each time
+    assert_eq!(proof.final_polynomial.len(), 2);
+    assert_ne!(proof.final_polynomial, other.final_polynomial);
+    assert_ne!(proof.final_polynomial, system.prove(&polynomial, 15).unwrap().final_polynomial);
+
+    // Even a constant, which `prove` sends as it is, is padded to be folded
+    let constant = DensePolynomial::<Fr>::rand(0, &mut rng);
+    assert_eq!(system.final_blinding_degree(0), 3);
+    let proof = system.prove_with_blinded_final(&constant, &mut rng).unwrap();
+    assert_eq!(proof.round_proofs.len(), 1);
+    assert!(system.verify(&proof));
+}
+
+#[test]
+fn test_commit_evaluations_matches_interpolated_polynomial() {
+    let mut rng = thread_rng();
+    let system = setup_system_with_config(FriConfig { blowup: 2, ..FriConfig::new(1, 4) });
+    let polynomial = DensePolynomial::<Fr>::rand(15, &mut rng);
+    let domain = FRIProver::create_domain_with_offset(15, 2, Fr::one()).unwrap();
+    let evals = Evaluations::from_vec_and_domain(domain.fft(&polynomial.coeffs), domain);
+
+    let (_, commitment) = system.commit_evaluations(&evals, 15).unwrap();
+    let (_, expected) = FRIProver::commit_rec::<LCH, INCH, _>(&evals.interpolate_by_ref(), &domain, &system.tree_operator);
+    assert_eq!(commitment.merkle_root, expected.merkle_root);
+
+    let proof = system.prove_evaluations(&evals, 15).unwrap();
+    assert_eq!(proof.initial_commitment, system.prove(&polynomial, 15).unwrap().initial_commitment);
+    assert!(system.verify(&proof));
+
+    // The final polynomial comes back from its evaluations
+    let final_domain = GeneralEvaluationDomain::<Fr>::new(4).unwrap();
+    let final_evals = proof.final_evaluations(final_domain);
+    assert_eq!(final_evals.interpolate().coeffs, DensePolynomial::from_coefficients_vec(proof.final_polynomial.clone()).coeffs);
+
+    // Evaluations over a domain of another size, or over a coset, are rejected
+    let small_domain = FRIProver::create_domain_with_offset(15, 1, Fr::one()).unwrap();
+    let small_evals = Evaluations::from_vec_and_domain(small_domain.fft(&polynomial.coeffs), small_domain);
+    assert_eq!(system.commit_evaluations(&small_evals, 15).err(), Some(FriError::DomainMismatch { expected: 32, actual: 16 }));
+    assert_eq!(system.prove_evaluations(&evals, 7).err(), Some(FriError::DomainMismatch { expected: 16, actual: 32 }));
+    let coset = FRIProver::create_domain_with_offset(15, 2, Fr::GENERATOR).unwrap();
+    let coset_evals = Evaluations::from_vec_and_domain(coset.fft(&polynomial.coeffs), coset);
+    assert_eq!(system.commit_evaluations(&coset_evals, 15).err(), Some(FriError::CosetMismatch));
+}
+
+#[test]
+fn test_prove_from_evals_of_low_degree_extension() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(15, &mut rng);
+    let system = setup_system(3, 4);
+
+    let domain = FRIProver::create_domain_with_offset(polynomial.degree(), 4, Fr::one()).unwrap();
+    let evals = domain.fft(&polynomial.coeffs);
+
+    let proof = system.prove_from_evals(&evals, &domain).unwrap();
+    assert!(system.verify(&proof));
+
+    // The verifier fixes the domain to the subgroup, so the same extension over a coset of it is
+    // refused before anything is committed to
+    let coset = FRIProver::create_domain_with_offset(polynomial.degree(), 4, Fr::GENERATOR).unwrap();
+    assert_eq!(system.prove_from_evals(&coset.fft(&polynomial.coeffs), &coset).err(), Some(FriError::CosetMismatch));
+}
+
+#[test]
+fn test_malformed_prover_inputs_are_rejected() {
+    let mut rng = thread_rng();
+    let system = setup_system_with_config(FriConfig { blowup: 2, ..FriConfig::new(1, 4) });
+
+    // The polynomial must fit in the domain of the degree it is proven for
+    let polynomial = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let too_large = Some(FriError::PolynomialTooLarge { coefficients: 64, domain_size: 32 });
+    assert_eq!(system.prove(&polynomial, 15).err(), too_large);
+    assert_eq!(system.commit_phase(&polynomial, 15).err(), too_large);
+    assert_eq!(system.prove_low_memory(polynomial.clone(), 15).err(), too_large);
+    assert!(system.prove(&polynomial, 63).is_ok());
+
+    // One evaluation per domain point
+    let domain = FRIProver::create_domain(15).unwrap();
+    let evals = domain.fft(&polynomial.coeffs[..16]);
+    assert_eq!(
+        system.prove_from_evals(&evals[..15], &domain).err(),
+        Some(FriError::EvaluationCountMismatch { expected: 16, actual: 15 })
+    );
+
+    // The configuration must match what the system supports
+    let config = FriConfig { folding_factor: 4, ..FriConfig::new(1, 4) };
+    let system = FRISystem::with_config(config, setup_tree_operator(), PoseidonChallenger { config: poseidon_config() });
+    assert_eq!(system.err(), Some(FriError::UnsupportedFoldingFactor { folding_factor: 4 }));
+    let config = FriConfig { leaf_width: 2, ..FriConfig::new(1, 4) };
+    let system = FRISystem::with_config(config, setup_tree_operator(), PoseidonChallenger { config: poseidon_config() });
+    assert_eq!(system.err(), Some(FriError::LeafWidthMismatch { configured: 2, operator: 1 }));
+}
+
+/// Proves a random polynomial of `2^log_size` coefficients with both provers and compares the proofs.
+fn check_prove_low_memory_matches_prove(log_size: usize) {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand((1 << log_size) - 1, &mut rng);
+    let system = setup_system(1, 4);
+
+    let proof = system.prove(&polynomial, polynomial.degree()).unwrap();
+    let low_memory_proof = system.prove_low_memory(polynomial.clone(), polynomial.degree()).unwrap();
+
+    assert_eq!(low_memory_proof.initial_commitment, proof.initial_commitment);
+    assert_eq!(low_memory_proof.round_commitments, proof.round_commitments);
+    assert_eq!(low_memory_proof.final_polynomial, proof.final_polynomial);
+    for (low_memory_round, round) in low_memory_proof.round_proofs.iter().zip(&proof.round_proofs) {
+        for (low_memory_opening, opening) in low_memory_round.openings.iter().zip(&round.openings) {
+            let values = |opening: &QueryOpening<Fr, Fr>| -> Vec<Fr> {
+                opening.current.iter().chain([&opening.next]).flat_map(|proof| proof.values.clone()).collect()
+            };
+            assert_eq!(values(low_memory_opening), values(opening));
+        }
+        assert_eq!(low_memory_round.current_cap, round.current_cap);
+        assert_eq!(low_memory_round.next_cap, round.next_cap);
+    }
+    assert!(system.verify(&low_memory_proof));
+}
+
+#[test]
+fn test_prove_low_memory_matches_prove() {
+    check_prove_low_memory_matches_prove(10);
+}
+
+// Slow in debug builds, run with `cargo test --release -- --ignored`
+#[test]
+#[ignore]
+fn test_prove_low_memory_matches_prove_large() {
+    check_prove_low_memory_matches_prove(16);
+}
+
+#[test]
+fn test_ood_point_is_never_in_the_domain() {
+    let domain = FRIProver::create_domain_with_offset(15, 2, Fr::GENERATOR).unwrap();
+    let challenger = InDomainFirstChallenger { inner: PoseidonChallenger { config: poseidon_config() }, domain, in_domain: 3 };
+    let commitment = Fr::from(7u64);
+
+    let z = sample_ood_point(&challenger, &commitment, &domain);
+    assert_eq!(z, challenger.inner.generate_challenge_with_counter(&commitment, 3));
+    assert!(domain.elements().all(|x| x != z));
+
+    let challenger = PoseidonChallenger { config: poseidon_config() };
+    for commitment in 0..16u64 {
+        let z = sample_ood_point(&challenger, &Fr::from(commitment), &domain);
+        assert!(domain.elements().all(|x| x != z));
+    }
+}
+
+#[test]
+fn test_hash_field_elements() {
+    let elements = [Fr::from(1u64), Fr::from(2u64), Fr::from(3u64)];
+    let hash = hash_field_elements(b"test", &elements);
+    assert_eq!(hash, hash_field_elements(b"test", &elements));
+
+    // Permuting, padding or retagging the input changes the hash
+    assert_ne!(hash, hash_field_elements(b"test", &[elements[1], elements[0], elements[2]]));
+    assert_ne!(hash, hash_field_elements(b"test", &[elements[0], elements[1], elements[2], Fr::zero()]));
+    assert_ne!(hash, hash_field_elements(b"other", &elements));
+    assert_ne!(hash_field_elements::<Fr>(b"test", &[]), hash_field_elements(b"test", &[Fr::zero()]));
+
+    // Pinned so the parameters cannot change unnoticed
+    let pinned = "4317415896927939827537998859578131974345651509637305528310580079006626788170";
+    assert_eq!(hash, Fr::from_str(pinned).unwrap());
+}
+
+#[test]
+fn test_deep_quotient_has_degree_one_less() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(15, &mut rng);
+    let domain = FRIProver::create_domain_with_offset(polynomial.degree(), 2, Fr::one()).unwrap();
+    let evals = domain.fft(&polynomial.coeffs);
+
+    let z = Fr::rand(&mut rng);
+    let quotient_evals = deep_quotient(&evals, &domain, z, polynomial.evaluate(&z));
+    let quotient = DensePolynomial::from_coefficients_vec(domain.ifft(&quotient_evals));
+    assert_eq!(quotient.degree(), polynomial.degree() - 1);
+
+    // A wrong claimed value leaves a pole at z, so the quotient is no longer low degree
+    let wrong_evals = deep_quotient(&evals, &domain, z, polynomial.evaluate(&z) + Fr::one());
+    let wrong_quotient = DensePolynomial::from_coefficients_vec(domain.ifft(&wrong_evals));
+    assert!(wrong_quotient.degree() > polynomial.degree());
+}
+
+#[test]
+fn test_ood_evaluation_is_verified_against_the_commitment() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(31, &mut rng);
+
+    for leaf_width in [1, 2] {
+        let config = FriConfig { blowup: 4, leaf_width, ..FriConfig::new(3, 8) };
+        let system = setup_system_with_config(config);
+        let domain = FRIProver::create_domain_with_offset(polynomial.degree(), config.blowup, Fr::one()).unwrap();
+        let (tree, _) = system.commit_evaluations(&Evaluations::from_vec_and_domain(domain.fft(&polynomial.coeffs), domain), 31).unwrap();
+        let z = sample_ood_point(&system.verifier_challenge, &tree.root(), &domain);
+
+        let (claimed, proof) = system.prove_ood_eval(&polynomial, 31, z).unwrap();
+        assert_eq!(claimed, polynomial.evaluate(&z));
+        assert_eq!(proof.commitment, tree.root());
+        assert!(system.verify_ood_eval(&proof, z, claimed));
+
+        // A wrong claim, or the same claim at another point, is inconsistent with the quotient
+        assert!(!system.verify_ood_eval(&proof, z, claimed + Fr::one()));
+        assert!(!system.verify_ood_eval(&proof, z + Fr::one(), claimed));
+
+        // The openings must be of the committed polynomial, at the leaves of the quotient
+        let mut tampered = proof.clone();
+        tampered.openings[0].values[0] += Fr::one();
+        assert!(!system.verify_ood_eval(&tampered, z, claimed));
+        let mut tampered = proof.clone();
+        tampered.commitment = Fr::zero();
+        assert!(!system.verify_ood_eval(&tampered, z, claimed));
+        let tampered = OodEvalProof { openings: proof.openings[1..].to_vec(), ..proof.clone() };
+        assert!(!system.verify_ood_eval(&tampered, z, claimed));
+    }
+}
+
+#[test]
+fn test_estimate_matches_serialized_proof() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+
+    for (leaf_width, cap_height) in [(1, 0), (2, 0), (4, 0), (2, 2)] {
+        let config = FriConfig { blowup: 4, leaf_width, cap_height, ..FriConfig::new(3, 8) };
+        let system = setup_system_with_config(config);
+
+        let proof = system.prove(&poly, poly.degree()).unwrap();
+        assert!(system.verify(&proof));
+
+        let estimate = config.estimate::<Fr, Fr>(poly.degree());
+        assert_eq!(estimate.rounds, proof.round_proofs.len());
+        assert_eq!(estimate.queries, proof.round_proofs[0].openings.len());
+
+        let proof_bytes = proof.compressed_size();
+        let tolerance = proof_bytes / 100;
+        assert!(
+            estimate.proof_bytes.abs_diff(proof_bytes) <= tolerance,
+            "leaf width {}: estimated {} bytes, serialized {} bytes",
+            leaf_width,
+            estimate.proof_bytes,
+            proof_bytes
+        );
+
+        // Rate 1/4 gives log2(8/5) bits per query
+        assert!((estimate.soundness_bits - 8.0 * (8.0f64 / 5.0).log2()).abs() < 0.01);
+    }
+}
+
+#[test]
+fn test_security_presets_reach_their_level() {
+    for preset in [SecurityPreset::Bits80, SecurityPreset::Bits100, SecurityPreset::Bits128] {
+        let config = FriConfig::preset(preset);
+        assert_eq!(config.preset, Some(preset));
+        assert_eq!(config.folding_factor, 2);
+        for degree in [7, (1 << 10) - 1, (1 << 20) - 1] {
+            let estimate = config.estimate::<Fr, Fr>(degree);
+            assert!(
+                estimate.soundness_bits >= preset.bits() as f64,
+                "{:?} estimated at {} bits for degree {}",
+                preset,
+                estimate.soundness_bits,
+                degree
+            );
+        }
+    }
+}
+
+#[test]
+fn test_prove_verify_under_preset() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(7, &mut rng);
+    let system = setup_system_with_config(FriConfig::preset(SecurityPreset::Bits80));
+
+    let proof = system.prove(&poly, poly.degree()).unwrap();
+    assert!(system.verify(&proof));
+    assert_eq!(system.verify_degree(&proof, poly.degree()), Ok(()));
+
+    // The same parameters under another preset start from another transcript
+    let relabeled = setup_system_with_config(FriConfig { preset: Some(SecurityPreset::Bits128), ..system.config });
+    assert!(!relabeled.verify(&proof));
+
+    // So does the same preset without grinding, and the nonce must meet the target
+    let ungrinded = setup_system_with_config(FriConfig { grinding_bits: 0, ..system.config });
+    assert!(!ungrinded.verify(&proof));
+    let mut transcript = system.new_transcript(&TranscriptSeed::default());
+    transcript.absorb(&system.tree_operator, &proof.initial_commitment);
+    assert!(transcript.clone().absorb_grinding_nonce(&system.tree_operator, &system.verifier_challenge, 16, proof.grinding_nonce()));
+    assert!(!transcript.absorb_grinding_nonce(&system.tree_operator, &system.verifier_challenge, 16, proof.grinding_nonce() + 1));
+}
+
+#[test]
+fn test_generate_nonzero_challenge_skips_zero() {
+    let inner = PoseidonChallenger { config: poseidon_config() };
+    let commitment = Fr::from(42u64);
+    let expected = inner.generate_challenge_with_counter(&commitment, 1);
+    let challenger = ZeroFirstChallenger { inner };
+
+    assert!(challenger.generate_challenge(&commitment).is_zero());
+    assert_eq!(challenger.generate_nonzero_challenge(&commitment), expected);
+
+    // The FRI prover and verifier both fold with the non-zero challenge
+    let system = FRISystemImpl::<Fr, DensePolynomial<Fr>, TreeOperator, ZeroFirstChallenger, INCH, LCH>::new(
+        1,
+        4,
+        setup_tree_operator(),
+        challenger,
+    );
+    let poly = DensePolynomial::<Fr>::rand(15, &mut thread_rng());
+    let proof = system.prove(&poly, poly.degree()).unwrap();
+    assert!(system.verify(&proof));
+}
+
+#[test]
+fn test_cached_domains_give_the_same_proofs() {
+    let mut rng = thread_rng();
+    let config = FriConfig { blowup: 2, ..FriConfig::new(1, 4) };
+    let uncached = setup_system_with_config(config);
+    let cached = setup_system_with_config(config).with_cached_domains(31).unwrap();
+    assert_eq!(cached.cached_domains().misses(), 5);
+    assert_eq!(uncached.cached_domains().misses(), 0);
+
+    let serialize = |proof: &FRIProtocolProof<Fr, Fr>| {
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+    for _ in 0..8 {
+        let poly = DensePolynomial::<Fr>::rand(31, &mut rng);
+        let proof = cached.prove(&poly, 31).unwrap();
+        assert_eq!(serialize(&proof), serialize(&uncached.prove(&poly, 31).unwrap()));
+        assert_eq!(serialize(&proof), serialize(&cached.prove_low_memory(poly.clone(), 31).unwrap()));
+        assert!(uncached.verify(&proof));
+
+        // Every domain of the proof was already built
+        let (_, state) = cached.commit_phase(&poly, 31).unwrap();
+        assert_eq!((state.domains.hits(), state.domains.misses()), (5, 5));
+    }
+
+    // Other degrees build their own domains
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let (_, state) = cached.commit_phase(&poly, 63).unwrap();
+    assert_eq!(state.domains.misses(), 6);
+    assert_eq!(serialize(&cached.prove(&poly, 63).unwrap()), serialize(&uncached.prove(&poly, 63).unwrap()));
+}
+
+#[test]
+fn test_query_phases_reuse_commit_phase() {
+    let mut rng = thread_rng();
+    let system = setup_system(1, 4);
+    let poly = DensePolynomial::<Fr>::rand(15, &mut rng);
+
+    let (commitments, state) = system.commit_phase(&poly, poly.degree()).unwrap();
+    assert_eq!(commitments.len(), 4);
+    assert_eq!(commitments[0].degree, 15);
+    assert_eq!(commitments[3].degree, 1);
+
+    let first = system.query_phase(&state, &[0, 5, 9, 14]);
+    let second = system.query_phase(&state, &[3, 7, 11]);
+    assert!(system.verify_with_queries(&first, &[0, 5, 9, 14]));
+    assert!(system.verify_with_queries(&second, &[3, 7, 11]));
+    assert!(!system.verify_with_queries(&second, &[3, 8, 11]));
+
+    assert_eq!(first.initial_commitment, commitments[0].merkle_root);
+    assert_eq!(first.initial_commitment, second.initial_commitment);
+    assert_eq!(first.round_commitments, second.round_commitments);
+    let round_roots: Vec<_> = commitments[1..].iter().map(|c| c.merkle_root).collect();
+    assert_eq!(first.round_commitments, round_roots);
+    assert_eq!(second.round_proofs[0].openings[1].query.index, 7);
+}
+
+#[test]
+fn test_reduce_halves_the_degree() {
+    let mut rng = thread_rng();
+    for degree in [1, 2, 7, 30, 31] {
+        let poly = DensePolynomial::<Fr>::rand(degree, &mut rng);
+        let challenge = Fr::rand(&mut rng);
+        let reduced = FRIProver::reduce(&poly, challenge);
+        assert_eq!(reduced.degree(), degree / 2);
+
+        // f(x) = f_even(x^2) + x f_odd(x^2) and the reduction keeps f_even + challenge · f_odd
+        let x = Fr::rand(&mut rng);
+        let f_even_plus_odd = (poly.evaluate(&x) + poly.evaluate(&-x)) / Fr::from(2u64)
+            + challenge * (poly.evaluate(&x) - poly.evaluate(&-x)) / (Fr::from(2u64) * x);
+        assert_eq!(reduced.evaluate(&x.square()), f_even_plus_odd);
+    }
+}
+
+#[test]
+fn test_folding_coefficients_and_evaluations_agree() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let challenge = Fr::rand(&mut rng);
+    let domain = FRIProver::create_domain_with_offset(poly.degree(), 2, Fr::GENERATOR).unwrap();
+    let evals = domain.fft(poly.coeffs());
+
+    for arity in [2, 4] {
+        let folded_coeffs = fold::fold_polynomial(poly.coeffs(), challenge, arity);
+        assert_eq!(folded_coeffs.len(), 64 / arity);
+
+        let folded_domain = fold::fold_domain(&domain, arity);
+        let folded_evals = fold::fold_evaluations(&evals, &domain, challenge, arity);
+        assert_eq!(folded_evals.len(), folded_domain.size());
+        assert_eq!(folded_domain.coset_offset(), Fr::GENERATOR.pow([arity as u64]));
+
+        let mut interpolated = folded_domain.ifft(&folded_evals);
+        assert!(interpolated[folded_coeffs.len()..].iter().all(Zero::is_zero));
+        interpolated.truncate(folded_coeffs.len());
+        assert_eq!(interpolated, folded_coeffs);
+    }
+
+    // Folding by 4 is folding by 2 twice with the challenge squared the second time
+    let twice = fold::fold_polynomial(&fold::fold_polynomial(poly.coeffs(), challenge, 2), challenge.square(), 2);
+    assert_eq!(fold::fold_polynomial(poly.coeffs(), challenge, 4), twice);
+}
+
+#[test]
+fn test_spliced_rounds_are_rejected() {
+    let mut rng = thread_rng();
+    for cap_height in [0, 1] {
+        let system = setup_system_with_config(FriConfig { cap_height, ..FriConfig::new(1, 4) });
+        let proof_a = system.prove(&DensePolynomial::<Fr>::rand(31, &mut rng), 31).unwrap();
+        let proof_b = system.prove(&DensePolynomial::<Fr>::rand(31, &mut rng), 31).unwrap();
+        assert!(system.verify(&proof_a));
+        assert!(system.verify(&proof_b));
+
+        // Round 2 of A opens trees B never committed to
+        let mut spliced = proof_b.clone();
+        spliced.round_proofs[2] = proof_a.round_proofs[2].clone();
+        assert!(!system.verify(&spliced));
+
+        // Taking A's root along with the round breaks the transcript and the neighbouring rounds
+        spliced.round_commitments[2] = proof_a.round_commitments[2];
+        assert!(!system.verify(&spliced));
+    }
+}
+
+#[test]
+fn test_round_degrees_must_halve() {
+    let mut rng = thread_rng();
+    let system = setup_system(1, 4);
+    let poly = DensePolynomial::<Fr>::rand(31, &mut rng);
+
+    let (mut commitments, _) = system.commit_phase(&poly, poly.degree()).unwrap();
+    assert_eq!(system.check_round_degrees(&commitments), Ok(()));
+
+    // A forged round claiming degree 8 after 15 does not fold the previous one
+    commitments[2].degree = 8;
+    assert_eq!(
+        system.check_round_degrees(&commitments),
+        Err(ProofShapeError::RoundDegree { round: 2, expected: 7, actual: 8 })
+    );
+    commitments[2].degree = 7;
+    commitments[4].degree = 0;
+    assert_eq!(
+        system.check_round_degrees(&commitments),
+        Err(ProofShapeError::RoundDegree { round: 4, expected: 1, actual: 0 })
+    );
+}
+
+#[test]
+fn test_degrees_must_match_the_committed_domains() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(31, &mut rng);
+    let system = setup_system_with_config(FriConfig { blowup: 2, ..FriConfig::new(0, 4) });
+    let proof = system.prove(&polynomial, 31).unwrap();
+    assert!(system.verify(&proof));
+
+    let query_indices: Vec<usize> = proof.round_proofs[0].openings.iter().map(|opening| opening.query.index).collect();
+    let mut transcript = system.new_transcript(&TranscriptSeed::default());
+    transcript.absorb(&system.tree_operator, &proof.initial_commitment);
+    let challenge = system.verifier_challenge.generate_nonzero_challenge(transcript.state());
+    let verify_first_round = |verifier: &Verifier<Fr, DensePolynomial<Fr>, LCH, INCH, TreeOperator>, degree: usize| {
+        verifier.verify_rec(
+            &FRIRecCommitment { merkle_root: proof.initial_commitment, degree },
+            &proof.round_proofs[0],
+            &FRIRecCommitment { merkle_root: proof.round_commitments[0], degree: degree / 2 },
+            challenge,
+            &query_indices,
+            Fr::one(),
+        )
+    };
+
+    // The openings are of a tree over 64 points, which holds degree 31 at a blowup of 2. Claiming
+    // degree 15 or 63 instead, or verifying with another blowup, is rejected before any folding.
+    let verifier = Verifier::create(system.tree_operator.clone()).with_blowup(2);
+    assert!(verify_first_round(&verifier, 31));
+    for degree in [15, 63] {
+        assert!(!verify_first_round(&verifier, degree));
+    }
+    assert!(!verify_first_round(&Verifier::create(system.tree_operator.clone()), 31));
+    assert!(verify_first_round(&Verifier::create(system.tree_operator.clone()).with_blowup(4), 15));
+}
+
+#[test]
+fn test_packed_leaves_prove_verify() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+
+    let mut path_lengths = Vec::new();
+    let mut proof_sizes = Vec::new();
+    for leaf_width in [1, 2, 4] {
+        let system = setup_system_with_config(FriConfig { leaf_width, ..FriConfig::new(1, 8) });
+        let proof = system.prove(&poly, poly.degree()).unwrap();
+        assert!(system.verify(&proof), "leaf width {} should verify", leaf_width);
+
+        // With leaves packed, y and -y are opened together
+        let expected_leaves = if leaf_width == 1 { 2 } else { 1 };
+        assert!(proof.round_proofs[0].openings.iter().all(|opening| opening.current.len() == expected_leaves));
+
+        path_lengths.push(
+            proof
+                .round_proofs
+                .iter()
+                .flat_map(|round| &round.openings)
+                .flat_map(|opening| opening.current.iter().chain([&opening.next]))
+                .map(|proof| proof.path.len())
+                .sum::<usize>(),
+        );
+        proof_sizes.push(proof.compressed_size());
+    }
+
+    // Every doubling of the width saves a level in every path. With 32 byte digests and 32 byte
+    // field elements, going from 2 to 4 trades each saved digest for two more values, so only the
+    // first step shrinks the serialized proof.
+    assert!(path_lengths[0] > path_lengths[1] && path_lengths[1] > path_lengths[2]);
+    assert!(proof_sizes[0] > proof_sizes[1]);
+}
+
+#[test]
+fn test_packed_leaves_reject_other_width() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let systems: Vec<FRISystem> = [1, 2, 4]
+        .into_iter()
+        .map(|leaf_width| setup_system_with_config(FriConfig { leaf_width, ..FriConfig::new(1, 8) }))
+        .collect();
+
+    for (i, prover) in systems.iter().enumerate() {
+        let proof = prover.prove(&poly, poly.degree()).unwrap();
+        for (j, verifier) in systems.iter().enumerate() {
+            assert_eq!(verifier.verify(&proof), i == j, "proof of width index {} checked at width index {}", i, j);
+        }
+    }
+}
+
+#[test]
+fn test_merkle_proof_rejects_swapped_value() {
+    let tree_operator = setup_tree_operator_with_leaf_width(2);
+    let domain = GeneralEvaluationDomain::<Fr>::new(16).unwrap();
+    let leaves = (0..8u64)
+        .map(|i| (LeafIndex::from_index(i as usize, &domain), vec![Fr::from(10 * i), Fr::from(10 * i + 1)]))
+        .collect();
+    let tree = tree_operator.create_tree(leaves, domain.group_gen(), Fr::one(), 15).unwrap();
+
+    let mut proof = tree_operator.create_proof(&tree, &LeafIndex::from_index(5, &domain)).unwrap();
+    assert_eq!(proof.values, vec![Fr::from(50u64), Fr::from(51u64)]);
+    assert!(tree_operator.verify_proof(&proof));
+
+    proof.values[1] += Fr::one();
+    assert!(!tree_operator.verify_proof(&proof));
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn test_path_overlap_stats() {
+    use crate::fri::merkle_tree::{path_overlap_stats, OverlapStats};
+
+    let tree_operator = setup_tree_operator();
+    let domain = GeneralEvaluationDomain::<Fr>::new(16).unwrap();
+    let leaves = (0..16).map(|i| (LeafIndex::from_index(i, &domain), vec![Fr::from(i as u64)])).collect();
+    let tree = tree_operator.create_tree(leaves, domain.group_gen(), Fr::one(), 15).unwrap();
+
+    // The same leaf twice shares its whole path
+    let same = path_overlap_stats(&tree, &[5, 5]);
+    assert_eq!(same, OverlapStats { total_nodes: 8, unique_nodes: 4, shared_nodes: 4 });
+    assert_eq!(same.overlap(), 0.5);
+
+    // Siblings share all but the leaves, opposite halves only the root
+    assert_eq!(path_overlap_stats(&tree, &[4, 5]), OverlapStats { total_nodes: 8, unique_nodes: 4, shared_nodes: 4 });
+    assert_eq!(path_overlap_stats(&tree, &[0, 15]), OverlapStats { total_nodes: 8, unique_nodes: 7, shared_nodes: 1 });
+
+    // Positions past the leaves wrap around to the leaf holding them
+    assert_eq!(path_overlap_stats(&tree, &[3, 3 + 16]), same);
+    assert_eq!(path_overlap_stats(&tree, &[]).overlap(), 0.0);
+    assert_eq!(path_overlap_stats(&tree, &[7]).overlap(), 0.0);
+}
+
+#[test]
+fn test_merkle_leaves_hold_vectors_and_payloads() {
+    let tree_operator = setup_tree_operator();
+    let domain = GeneralEvaluationDomain::<Fr>::new(8).unwrap();
+
+    // Every value of the leaf is hashed, and the proof needs all of them
+    let leaves = (0..8u64)
+        .map(|i| (LeafIndex::from_index(i as usize, &domain), vec![Fr::from(i), Fr::from(i * i), Fr::from(i + 100)]))
+        .collect();
+    let tree = tree_operator.create_tree(leaves, domain.group_gen(), Fr::one(), 7).unwrap();
+    let proof = tree_operator.create_proof(&tree, &LeafIndex::from_index(5, &domain)).unwrap();
+    assert_eq!(proof.values, vec![Fr::from(5u64), Fr::from(25u64), Fr::from(105u64)]);
+    assert_eq!(tree.leaf_values(5), Some(proof.values.as_slice()));
+    assert!(tree_operator.verify_proof(&proof));
+
+    let mut truncated = proof.clone();
+    truncated.values.pop();
+    assert!(!tree_operator.verify_proof(&truncated));
+    let mut swapped = proof.clone();
+    swapped.values.swap(0, 2);
+    assert!(!tree_operator.verify_proof(&swapped));
+
+    // Payloads of the same size pack into the same number of values
+    let payloads: Vec<(u64, Vec<u8>)> = (0..8u64).map(|i| (i, vec![i as u8; 70])).collect();
+    let leaves = payloads
+        .iter()
+        .enumerate()
+        .map(|(i, payload)| (LeafIndex::from_index(i, &domain), pack_payload::<Fr, _>(payload)))
+        .collect();
+    let tree = tree_operator.create_tree(leaves, domain.group_gen(), Fr::one(), 7).unwrap();
+    let proof = tree_operator.create_proof(&tree, &LeafIndex::from_index(3, &domain)).unwrap();
+    assert_eq!(proof.values, pack_payload::<Fr, _>(&payloads[3]));
+    assert!(tree_operator.verify_proof(&proof));
+    assert_ne!(pack_payload::<Fr, _>(&payloads[3]), pack_payload::<Fr, _>(&(3u64, vec![3u8; 69])));
+}
+
+#[test]
+fn test_internal_nodes_do_not_verify_as_leaves() {
+    // The leaf and two-to-one hashes are the same Poseidon sponge
+    let tree_operator = setup_tree_operator();
+    let domain = GeneralEvaluationDomain::<Fr>::new(4).unwrap();
+    let leaves = (0..4u64).map(|i| (LeafIndex::from_index(i as usize, &domain), vec![Fr::from(i)])).collect();
+    let tree = tree_operator.create_tree(leaves, domain.group_gen(), Fr::one(), 3).unwrap();
+    let (left, right) = match &tree.root {
+        MerkleNode::Internal { left, .. } => match left.as_ref() {
+            MerkleNode::Internal { left, right, .. } => (left.get_hash(), right.get_hash()),
+            MerkleNode::Leaf { .. } => unreachable!(),
+        },
+        MerkleNode::Leaf { .. } => unreachable!(),
+    };
+    assert_eq!(tree_operator.hash_pair(&left, &right), tree.cap(1)[0]);
+    assert_eq!(tree_operator.hash_values(&[left, right]), tree.cap(1)[0]);
+
+    // A leaf whose point and value are the children of the first node above the leaves, proven one
+    // level up with that node's sibling
+    let honest = tree_operator.create_proof(&tree, &LeafIndex::from_index(0, &domain)).unwrap();
+    let forged = MerkleProof {
+        root_hash: tree.root(),
+        path: vec![honest.path[1]],
+        leaf_index: LeafIndex { index: 0, point: left },
+        values: vec![right],
+    };
+    assert!(tree_operator.verify_proof(&honest));
+    assert!(!tree_operator.verify_proof(&forged));
+    assert_ne!(tree_operator.hash_leaf(&forged.leaf_index, &forged.values), tree.cap(1)[0]);
+}
+
+#[test]
+fn test_merkle_proofs_need_consistent_leaf_indices() {
+    let tree_operator = setup_tree_operator();
+    let domain = GeneralEvaluationDomain::<Fr>::new(16).unwrap().get_coset(Fr::GENERATOR).unwrap();
+    let leaves = (0..16).map(|i| (LeafIndex::from_index(i, &domain), vec![Fr::from(i as u64)])).collect();
+    let tree = tree_operator.create_tree(leaves, domain.group_gen(), Fr::GENERATOR, 15).unwrap();
+
+    for i in 0..16 {
+        let leaf_index = LeafIndex::from_index(i, &domain);
+        assert_eq!(tree.check_leaf_index(&leaf_index), Ok(()));
+        let proof = tree_operator.create_proof(&tree, &leaf_index).unwrap();
+        assert_eq!(proof.values, vec![Fr::from(i as u64)]);
+        assert!(tree_operator.verify_proof(&proof));
+    }
+
+    let mismatched = LeafIndex { index: 3, point: domain.element(4) };
+    assert_eq!(tree_operator.create_proof(&tree, &mismatched).unwrap_err(), MerkleError::InconsistentLeafPoint { index: 3 });
+    let out_of_range = LeafIndex { index: 16, point: domain.element(16) };
+    assert_eq!(
+        tree_operator.create_proof_with_cap(&tree, &out_of_range, 1).unwrap_err(),
+        MerkleError::LeafOutOfRange { index: 16, leaf_count: 16 }
+    );
+}
+
+#[test]
+fn test_fri_rejects_swapped_opening_value() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(31, &mut rng);
+
+    for leaf_width in [1, 4] {
+        let system = setup_system_with_config(FriConfig { leaf_width, ..FriConfig::new(1, 4) });
+        let proof = system.prove(&poly, poly.degree()).unwrap();
+        assert!(system.verify(&proof));
+
+        let mut current_tampered = proof.clone();
+        current_tampered.round_proofs[0].openings[0].current[0].values[0] += Fr::one();
+        assert!(!system.verify(&current_tampered), "leaf width {}", leaf_width);
+
+        // With single-value leaves, the next leaf only holds the folded value, which is left out
+        let mut next_tampered = proof.clone();
+        if let Some(value) = next_tampered.round_proofs[1].openings[2].next.values.first_mut() {
+            *value = Fr::rand(&mut rng);
+            assert!(!system.verify(&next_tampered), "leaf width {}", leaf_width);
+        }
+    }
+}
+
+#[test]
+fn test_verify_degree_rejects_malformed_proofs() {
+    let mut rng = thread_rng();
+    let system = setup_system(1, 4);
+    let poly = DensePolynomial::<Fr>::rand(15, &mut rng);
+    let proof = system.prove(&poly, poly.degree()).unwrap();
+    assert_eq!(system.verify_degree(&proof, poly.degree()), Ok(()));
+
+    let mut missing_round = proof.clone();
+    missing_round.round_proofs.pop();
+    missing_round.round_commitments.pop();
+    assert_eq!(
+        system.verify_degree(&missing_round, poly.degree()),
+        Err(FriError::ProofShape(ProofShapeError::RoundCount { expected: 3, actual: 2 }))
+    );
+
+    let mut missing_query = proof.clone();
+    missing_query.round_proofs[1].openings.pop();
+    assert_eq!(
+        system.verify_degree(&missing_query, poly.degree()),
+        Err(FriError::ProofShape(ProofShapeError::QueryCount { round: 1, expected: 4, actual: 3 }))
+    );
+
+    let mut extra_commitment = proof.clone();
+    extra_commitment.round_commitments.push(proof.initial_commitment);
+    assert_eq!(
+        system.verify_degree(&extra_commitment, poly.degree()),
+        Err(FriError::ProofShape(ProofShapeError::CommitmentCount { expected: 3, actual: 4 }))
+    );
+
+    let mut long_final = proof.clone();
+    long_final.final_polynomial.extend([Fr::one(), Fr::one()]);
+    assert_eq!(
+        system.verify_degree(&long_final, poly.degree()),
+        Err(FriError::ProofShape(ProofShapeError::FinalDegree { max_degree: 1, coefficients: 4 }))
+    );
+
+    // `verify` runs the same checks, for the degree the number of rounds implies
+    for malformed in [&missing_round, &missing_query, &extra_commitment, &long_final] {
+        assert!(!system.verify(malformed));
+    }
+
+    // A well formed proof with a wrong value fails the cryptographic checks instead
+    let mut tampered = proof.clone();
+    tampered.round_proofs[0].openings[0].current[0].values[0] += Fr::one();
+    assert_eq!(system.verify_degree(&tampered, poly.degree()), Err(FriError::VerificationFailed));
+    assert!(!system.verify(&tampered));
+}
+
+#[test]
+fn test_verify_rejects_stripped_openings() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(15, &mut rng);
+    let system = setup_system(1, 4);
+    let proof = system.prove(&poly, poly.degree()).unwrap();
+    assert!(system.verify(&proof));
+
+    // Without openings no query is checked, yet the queries are still sampled over the domain
+    // the configuration gives for this many rounds
+    let mut stripped = proof.clone();
+    stripped.round_proofs.iter_mut().for_each(|round| round.openings.clear());
+    assert!(!system.verify(&stripped));
+
+    // Nor can the queries be narrowed down to those the rounds still open
+    let mut first_only = proof.clone();
+    first_only.round_proofs.iter_mut().for_each(|round| round.openings.truncate(1));
+    assert!(!system.verify(&first_only));
+}
+
+#[test]
+fn test_verify_rejects_openings_of_the_wrong_length() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(15, &mut rng);
+
+    let system = setup_system(1, 4);
+    let proof = system.prove(&poly, poly.degree()).unwrap();
+    assert!(system.verify(&proof));
+
+    // A round with one query fewer than the transcript samples
+    let mut missing_query = proof.clone();
+    missing_query.round_proofs[0].openings.pop();
+    assert!(!system.verify(&missing_query));
+
+    // A query missing the leaf of f(-y), which a leaf of a single value cannot hold
+    let mut missing_leaf = proof.clone();
+    missing_leaf.round_proofs[0].openings[0].current.pop();
+    assert!(!system.verify(&missing_leaf));
+
+    // A leaf of the next tree with one value fewer than its width, besides the folded one
+    let system = setup_system_with_config(FriConfig { leaf_width: 4, ..FriConfig::new(1, 4) });
+    let proof = system.prove(&poly, poly.degree()).unwrap();
+    assert!(system.verify(&proof));
+    let mut short_next = proof.clone();
+    short_next.round_proofs[0].openings[0].next.values.pop();
+    assert!(!system.verify(&short_next));
+}
+
+#[test]
+fn test_queried_points_are_domain_points() {
+    assert_eq!(challenge_to_index(Fr::from(13u64), 8), 5);
+    // The scalar field of BLS12-381 has a 2^32 subgroup, so -1 is divisible by any small power of two
+    assert_eq!(challenge_to_index(-Fr::one(), 1 << 10), 0);
+
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(31, &mut rng);
+    for leaf_width in [1, 4] {
+        let system = setup_system_with_config(FriConfig { blowup: 2, leaf_width, ..FriConfig::new(1, 6) });
+        let proof = system.prove(&poly, poly.degree()).unwrap();
+        assert!(system.verify(&proof));
+
+        let initial_domain = FRIProver::create_domain_with_offset(poly.degree(), 2, Fr::one()).unwrap();
+        let mut transcript = system.new_transcript(&TranscriptSeed::default());
+        transcript.absorb(&system.tree_operator, &proof.initial_commitment);
+        let expected_indices: Vec<usize> = (0..6u64)
+            .map(|i| {
+                let challenge = system.verifier_challenge.generate_challenge_with_counter(transcript.state(), (1 << 32) + i);
+                challenge_to_index(challenge, initial_domain.size())
+            })
+            .collect();
+
+        let mut domain = initial_domain;
+        for round in &proof.round_proofs {
+            for (opening, expected_index) in round.openings.iter().zip(&expected_indices) {
+                assert_eq!(opening.query.index, expected_index % domain.size());
+                assert_eq!(opening.query.point, domain.element(opening.query.index));
+            }
+            domain = FRIProver::fold_domain(&domain);
+        }
+
+        // Moving a query to another point of the domain is rejected
+        let mut moved = proof.clone();
+        let query = &mut moved.round_proofs[0].openings[0].query;
+        query.index = (query.index + 1) % initial_domain.size();
+        query.point = initial_domain.element(query.index);
+        assert!(!system.verify(&moved));
+    }
+}
+
+#[test]
+fn test_light_client_verifies_openings_of_trusted_commitments() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let system = setup_system_with_config(FriConfig { blowup: 2, grinding_bits: 2, ..FriConfig::new(3, 8) });
+    let proof = system.prove(&poly, poly.degree()).unwrap();
+    assert!(system.verify(&proof));
+
+    let commitments: Vec<Fr> = std::iter::once(proof.initial_commitment).chain(proof.round_commitments.iter().copied()).collect();
+    let nonce = proof.grinding_nonce();
+    for (round, round_proof) in proof.round_proofs.iter().enumerate() {
+        // Any subset of the openings of a round, in any order
+        assert!(system.verify_openings(&commitments, nonce, round, &round_proof.openings));
+        assert!(system.verify_openings(&commitments, nonce, round, &round_proof.openings[2..5]));
+        assert!(system.verify_openings(&commitments, nonce, round, &[round_proof.openings[6].clone(), round_proof.openings[1].clone()]));
+        assert!(!system.verify_openings(&commitments, nonce, round, &[]));
+    }
+
+    // Against the commitments of another round, or another round's challenge
+    let openings = &proof.round_proofs[1].openings[..3];
+    assert!(!system.verify_openings(&commitments, nonce, 0, openings));
+    assert!(!system.verify_openings(&commitments, nonce, 2, openings));
+    assert!(!system.verify_openings(&commitments, nonce + 1, 1, openings));
+    let mut reordered = commitments.clone();
+    reordered.swap(1, 2);
+    assert!(!system.verify_openings(&reordered, nonce, 1, openings));
+    assert!(!system.verify_openings(&commitments[..2], nonce, 1, openings));
+
+    let mut tampered = openings.to_vec();
+    tampered[0].next.values.push(Fr::one());
+    assert!(!system.verify_openings(&commitments, nonce, 1, &tampered));
+}
+
+#[test]
+fn test_merkle_cap_shortens_proofs() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+
+    let uncapped_system = setup_system_with_config(FriConfig::new(1, 8));
+    let capped_system = setup_system_with_config(FriConfig { cap_height: 2, ..FriConfig::new(1, 8) });
+    let uncapped = uncapped_system.prove(&poly, poly.degree()).unwrap();
+    let capped = capped_system.prove(&poly, poly.degree()).unwrap();
+    assert!(capped_system.verify(&capped));
+    assert_eq!(capped.round_proofs[0].current_cap.len(), 4);
+
+    // Every path of the current trees, the smallest of which has height 2, is two levels shorter
+    for (capped_round, uncapped_round) in capped.round_proofs.iter().zip(&uncapped.round_proofs) {
+        let capped_path = &capped_round.openings[0].current[0].path;
+        let uncapped_path = &uncapped_round.openings[0].current[0].path;
+        assert_eq!(capped_path.len(), uncapped_path.len() - 2);
+    }
+    assert!(capped.compressed_size() < uncapped.compressed_size());
+
+    // A cap larger than the configured one is rejected, a smaller one is just a shorter cap
+    assert!(!uncapped_system.verify(&capped));
+    assert!(capped_system.verify(&uncapped));
+
+    let mut tampered = capped.clone();
+    tampered.round_proofs[1].current_cap[3] = Fr::rand(&mut rng);
+    assert!(!capped_system.verify(&tampered));
+}
+
+#[test]
+fn test_transcript_seed_determines_proof() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(31, &mut rng);
+    let system = setup_system(1, 8);
+    let seed = TranscriptSeed([7; 32]);
+    let other_seed = TranscriptSeed([9; 32]);
+
+    let serialize = |proof: &FRIProtocolProof<Fr, Fr>| {
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+    let proof = system.prove_with_seed(&poly, poly.degree(), &seed).unwrap();
+    assert_eq!(serialize(&proof), serialize(&system.prove_with_seed(&poly, poly.degree(), &seed).unwrap()));
+
+    let other_proof = system.prove_with_seed(&poly, poly.degree(), &other_seed).unwrap();
+    let queries = |proof: &FRIProtocolProof<Fr, Fr>| -> Vec<usize> {
+        proof.round_proofs[0].openings.iter().map(|opening| opening.query.index).collect()
+    };
+    assert_ne!(queries(&proof), queries(&other_proof));
+    assert_ne!(proof.transcript_state(), other_proof.transcript_state());
+
+    assert!(system.verify_with_seed(&proof, &seed));
+    assert!(system.verify_with_seed(&other_proof, &other_seed));
+    assert!(!system.verify_with_seed(&proof, &other_seed));
+    assert!(!system.verify_with_seed(&other_proof, &seed));
+
+    // The default seed is the all-zero one
+    let default_proof = system.prove(&poly, poly.degree()).unwrap();
+    assert!(system.verify_with_seed(&default_proof, &TranscriptSeed([0; 32])));
+    assert!(!system.verify(&proof));
+}
+
+#[test]
+fn test_openings_are_committed_leaves() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let system = setup_system_with_config(FriConfig { leaf_width: 2, ..FriConfig::new(1, 8) });
+
+    let (_, state) = system.commit_phase(&poly, poly.degree()).unwrap();
+    let initial_tree = &state.trees[0];
+    for leaf in 0..initial_tree.leaf_count() {
+        let points = initial_tree.leaf_points(&initial_tree.leaf_of(leaf));
+        let evaluations: Vec<Fr> = points.iter().map(|point| poly.evaluate(point)).collect();
+        assert_eq!(initial_tree.leaf_values(leaf).unwrap(), evaluations.as_slice());
+    }
+
+    let proof = system.query_phase(&state, &[1, 17, 40, 63]);
+    for (round, round_proof) in proof.round_proofs.iter().enumerate() {
+        for opening in &round_proof.openings {
+            for current in &opening.current {
+                let leaf_values = state.trees[round].leaf_values(current.leaf_index.index);
+                assert_eq!(leaf_values.unwrap(), current.values.as_slice());
+            }
+            // All but the folded value, which the verifier computes
+            let next_tree = &state.trees[round + 1];
+            let mut leaf_values = next_tree.leaf_values(opening.next.leaf_index.index).unwrap().to_vec();
+            leaf_values.remove((opening.query.index % next_tree.domain_size()) >> next_tree.height());
+            assert_eq!(leaf_values, opening.next.values);
+        }
+    }
+}
+
+#[test]
+fn test_sibling_leaves_sit_half_a_domain_apart() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(31, &mut rng);
+    let tree_operator = setup_tree_operator();
+    let system = setup_system(1, 8);
+
+    let (_, state) = system.commit_phase(&poly, poly.degree()).unwrap();
+    let proof = system.query_phase(&state, &[0, 3, 17, 40, 63]);
+    assert!(system.verify_with_queries(&proof, &[0, 3, 17, 40, 63]));
+    for (round, round_proof) in proof.round_proofs.iter().enumerate() {
+        let domain_size = state.trees[round].domain_size();
+        for opening in &round_proof.openings {
+            let [current, sibling] = &opening.current[..] else { panic!("Expected two leaves per query") };
+            assert_eq!(current.leaf_index.index, opening.query.index);
+            assert_eq!(sibling.leaf_index.index, (opening.query.index + domain_size / 2) % domain_size);
+        }
+    }
+
+    // A valid opening of any other leaf is not accepted as the sibling
+    let tree = &state.trees[0];
+    let mut tampered = proof.clone();
+    let opening = &mut tampered.round_proofs[0].openings[1];
+    opening.current[1] = tree_operator.create_proof(tree, &tree.leaf_of(opening.query.index + 1)).unwrap();
+    assert!(!system.verify_with_queries(&tampered, &[0, 3, 17, 40, 63]));
+}
+
+#[test]
+fn test_verifier_recomputes_folded_value() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(31, &mut rng);
+    let tree_operator = setup_tree_operator();
+    let system = setup_system(1, 8);
+    let query_indices = [2, 9, 33, 50];
+
+    let (_, state) = system.commit_phase(&poly, poly.degree()).unwrap();
+    let proof = system.query_phase(&state, &query_indices);
+    assert!(system.verify_with_queries(&proof, &query_indices));
+    assert!(proof.round_proofs.iter().flat_map(|round| &round.openings).all(|opening| opening.next.values.is_empty()));
+
+    // A valid proof of the next leaf over, claimed to be the folded leaf, holds a different value
+    let next_tree = &state.trees[1];
+    let mut tampered = proof.clone();
+    let opening = &mut tampered.round_proofs[0].openings[0];
+    let mut other_leaf = tree_operator.create_proof(next_tree, &next_tree.leaf_of(opening.next.leaf_index.index + 1)).unwrap();
+    other_leaf.values.clear();
+    other_leaf.leaf_index = opening.next.leaf_index.clone();
+    opening.next = other_leaf;
+    assert!(!system.verify_with_queries(&tampered, &query_indices));
+}
+
+#[test]
+fn test_fri_over_merkle_oracle() {
+    let mut rng = thread_rng();
+    let poly = DensePolynomial::<Fr>::rand(31, &mut rng);
+    let system = OracleFRI::<Fr, DensePolynomial<Fr>, _, _>::new(
+        FriConfig { blowup: 2, ..FriConfig::new(1, 4) },
+        setup_tree_operator_with_leaf_width(2),
+        PoseidonChallenger { config: poseidon_config() },
+    );
+    let query_indices = [1, 22, 40, 127];
+
+    let proof = system.prove(&poly, poly.degree(), &query_indices);
+    assert_eq!(proof.commitments.len(), 4);
+    assert!(system.verify(&proof, poly.degree(), &query_indices));
+    assert!(!system.verify(&proof, poly.degree(), &[1, 22, 40, 126]));
+
+    // The oracle opens single positions, whatever the width of its leaves
+    let domain = FRIProver::create_domain(63).unwrap();
+    let (tree, commitment) = system.oracle.commit(&domain.fft(poly.coeffs()), &domain);
+    let (value, opening) = system.oracle.open(&tree, 37);
+    assert_eq!(value, poly.evaluate(&domain.element(37)));
+    assert!(system.oracle.verify_open(&commitment, 37, value, &opening));
+    assert!(!system.oracle.verify_open(&commitment, 5, value, &opening));
+    assert!(!system.oracle.verify_open(&commitment, 37, value + Fr::one(), &opening));
+
+    let mut tampered = proof.clone();
+    tampered.openings[2][1][0].0 += Fr::one();
+    assert!(!system.verify(&tampered, poly.degree(), &query_indices));
+
+    // A polynomial of twice the degree does not fold down to one of degree 1
+    let high_degree = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let proof = system.prove(&high_degree, poly.degree(), &query_indices);
+    assert!(!system.verify(&proof, poly.degree(), &query_indices));
+}
+
+#[test]
+fn test_batch_merkle_verification_shares_hashes() {
+    let tree_operator = MerkleTreeOperatorImpl::<LCH, CountingTwoToOneCRH>::new(poseidon_config(), poseidon_config());
+    let domain = GeneralEvaluationDomain::<Fr>::new(16).unwrap();
+    let tree_of = |offset: u64| {
+        let leaves = (0..16u64)
+            .map(|i| (LeafIndex::from_index(i as usize, &domain), vec![Fr::from(i + offset)]))
+            .collect();
+        tree_operator.create_tree(leaves, domain.group_gen(), Fr::one(), 15).unwrap()
+    };
+    let tree = tree_of(0);
+    let proofs: Vec<_> = [0, 1, 2, 3, 8, 9, 9]
+        .into_iter()
+        .map(|i| tree_operator.create_proof(&tree, &LeafIndex::from_index(i, &domain)).unwrap())
+        .collect();
+    let proof_refs: Vec<_> = proofs.iter().collect();
+
+    let count_hashes = |verify: &dyn Fn() -> bool| {
+        TWO_TO_ONE_HASHES.with(|count| count.set(0));
+        assert!(verify());
+        TWO_TO_ONE_HASHES.with(|count| count.get())
+    };
+    let naive = count_hashes(&|| proofs.iter().all(|proof| tree_operator.verify_proof(proof)));
+    let batched = count_hashes(&|| tree_operator.verify_proofs_batch(&proof_refs));
+    assert_eq!(naive, 7 * 4);
+    // Leaves 0-3 and 8-9 meet in 3, 2, 2 and 1 distinct nodes on the way up
+    assert_eq!(batched, 8);
+
+    // A single bad leaf fails the batch, as does a sibling disagreeing with the computed node
+    let mut tampered = proofs.clone();
+    tampered[4].values[0] += Fr::one();
+    assert!(!tree_operator.verify_proofs_batch(&tampered.iter().collect::<Vec<_>>()));
+    let mut tampered = proofs.clone();
+    tampered[0].path[0] += Fr::one();
+    assert!(!tree_operator.verify_proofs_batch(&tampered.iter().collect::<Vec<_>>()));
+
+    // Proofs under different roots are checked against their own
+    let other_tree = tree_of(100);
+    let other_proof = tree_operator.create_proof(&other_tree, &LeafIndex::from_index(5, &domain)).unwrap();
+    assert!(tree_operator.verify_proofs_batch(&[&proofs[0], &other_proof, &proofs[5]]));
+    let mut wrong_root = other_proof.clone();
+    wrong_root.root_hash = tree.root();
+    assert!(!tree_operator.verify_proofs_batch(&[&proofs[0], &wrong_root]));
+}
+
+#[test]
+fn test_merkle_range_proofs() {
+    let tree_operator = setup_tree_operator_with_leaf_width(2);
+    let leaves = (0..16u64)
+        .map(|i| (LeafIndex { index: i as usize, point: Fr::from(i) }, vec![Fr::from(10 * i), Fr::from(10 * i + 1)]))
+        .collect();
+    let tree = tree_operator.create_tree(leaves, Fr::one(), Fr::one(), 31).unwrap();
+    let root = tree.root();
+
+    // The whole tree, a single leaf, ranges crossing subtrees of every size, and a whole subtree
+    for (start, end) in [(0, 16), (5, 6), (0, 1), (15, 16), (3, 11), (7, 9), (1, 15), (8, 12)] {
+        let proof = tree_operator.create_range_proof(&tree, start, end).unwrap();
+        assert_eq!(proof.leaves.len(), end - start);
+        assert_eq!(proof.leaves[0].1, vec![Fr::from(10 * start as u64), Fr::from(10 * start as u64 + 1)]);
+        assert!(tree_operator.verify_range_proof(&proof, &root), "range {}..{}", start, end);
+        assert!(!tree_operator.verify_range_proof(&proof, &Fr::from(1u64)), "range {}..{}", start, end);
+    }
+    assert!(tree_operator.create_range_proof(&tree, 0, 16).unwrap().left_siblings.is_empty());
+
+    // A tampered value, a shifted range and a missing boundary node are caught
+    let proof = tree_operator.create_range_proof(&tree, 3, 11).unwrap();
+    let mut tampered = proof.clone();
+    tampered.leaves[4].1[1] += Fr::one();
+    assert!(!tree_operator.verify_range_proof(&tampered, &root));
+    let mut shifted = proof.clone();
+    shifted.start = 4;
+    assert!(!tree_operator.verify_range_proof(&shifted, &root));
+    let mut truncated = proof.clone();
+    truncated.right_siblings.pop();
+    assert!(!tree_operator.verify_range_proof(&truncated, &root));
+
+    // Only non-empty ranges of the tree are proven
+    for (start, end) in [(4, 4), (5, 3), (8, 17)] {
+        assert_eq!(
+            tree_operator.create_range_proof(&tree, start, end).err(),
+            Some(MerkleError::InvalidRange { start, end, leaf_count: 16 })
+        );
+    }
+}
+
+#[test]
+fn test_malformed_merkle_leaves_are_rejected() {
+    let tree_operator = setup_tree_operator();
+    let leaf = |i: usize, width: usize| (LeafIndex { index: i, point: Fr::from(i as u64) }, vec![Fr::from(i as u64); width]);
+
+    assert_eq!(tree_operator.create_tree(Vec::new(), Fr::one(), Fr::one(), 0).err(), Some(MerkleError::NoLeaves));
+    assert_eq!(
+        tree_operator.create_tree(vec![leaf(0, 0), leaf(1, 0)], Fr::one(), Fr::one(), 1).err(),
+        Some(MerkleError::EmptyLeaves)
+    );
+    assert_eq!(
+        tree_operator.create_tree(vec![leaf(0, 2), leaf(1, 2), leaf(2, 3), leaf(3, 2)], Fr::one(), Fr::one(), 7).err(),
+        Some(MerkleError::LeafWidthMismatch { index: 2, expected: 2, actual: 3 })
+    );
+    assert!(tree_operator.create_tree(vec![leaf(0, 2), leaf(1, 2)], Fr::one(), Fr::one(), 3).is_ok());
+}
+
+#[test]
+fn test_stored_merkle_trees_give_the_same_proofs() {
+    let tree_operator = setup_tree_operator();
+    let mut rng = thread_rng();
+    fn serialize(proof: &impl CanonicalSerialize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    // A full tree, and one whose levels pair their last node with itself
+    for (leaf_count, domain_size) in [(1 << 12, 1 << 12), (1000, 1024)] {
+        let domain = GeneralEvaluationDomain::<Fr>::new(domain_size).unwrap().get_coset(Fr::GENERATOR).unwrap();
+        let leaves = (0..leaf_count).map(|i| (LeafIndex::from_index(i, &domain), vec![Fr::rand(&mut rng)])).collect();
+        let tree = tree_operator.create_tree(leaves, domain.group_gen(), Fr::GENERATOR, domain_size - 1).unwrap();
+        let mut bytes = Vec::new();
+        tree.write_to(&mut bytes).unwrap();
+
+        let reloaded = MerkleTree::<Fr, Fr>::read_from(bytes.as_slice()).unwrap();
+        let mut view = MerkleTreeView::<Fr, Fr, _>::open(std::io::Cursor::new(&bytes)).unwrap();
+        assert_eq!(reloaded.root(), tree.root());
+        assert_eq!(view.root(), tree.root());
+        assert_eq!(view.height(), tree.height());
+
+        let indices = (0..32).map(|_| usize::rand(&mut rng) % domain_size).chain([0, leaf_count - 1, domain_size - 1]);
+        for index in indices {
+            let leaf_index = LeafIndex::from_index(index, &domain);
+            for cap_height in [0, 3] {
+                let proof = tree_operator.create_proof_with_cap(&tree, &leaf_index, cap_height).unwrap();
+                let reloaded_proof = tree_operator.create_proof_with_cap(&reloaded, &leaf_index, cap_height).unwrap();
+                let view_proof = view.create_proof_with_cap(&leaf_index, cap_height).unwrap();
+                assert_eq!(serialize(&reloaded_proof), serialize(&proof));
+                assert_eq!(serialize(&view_proof), serialize(&proof));
+            }
+            // Past the last leaf, both open the copies pairing it, which hold another leaf's point
+            if index < leaf_count {
+                assert!(tree_operator.verify_proof(&view.create_proof(&leaf_index).unwrap()));
+            }
+        }
+
+        let out_of_range = LeafIndex { index: domain_size, point: domain.element(domain_size) };
+        assert_eq!(view.create_proof(&out_of_range).unwrap_err(), MerkleError::LeafOutOfRange { index: domain_size, leaf_count: domain_size });
+        let mismatched = LeafIndex { index: 3, point: domain.element(4) };
+        assert_eq!(view.create_proof(&mismatched).unwrap_err(), MerkleError::InconsistentLeafPoint { index: 3 });
+
+        // A file cut short loses its root
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(MerkleTree::<Fr, Fr>::read_from(truncated).is_err());
+        assert!(MerkleTreeView::<Fr, Fr, _>::open(std::io::Cursor::new(truncated)).is_err());
+    }
+}
+
+#[test]
+fn test_compact_merkle_proofs() {
+    let tree_operator = setup_tree_operator();
+    let domain = GeneralEvaluationDomain::<Fr>::new(1024).unwrap();
+    let leaves = (0..1000).map(|i| (LeafIndex::from_index(i, &domain), vec![Fr::from(i as u64)])).collect();
+    let tree = tree_operator.create_tree(leaves, domain.group_gen(), Fr::one(), 999).unwrap();
+    let root = tree.root();
+    fn serialize(proof: &impl CanonicalSerialize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    let (mut expanded_bytes, mut compact_bytes, mut duplicates) = (0, 0, 0);
+    for i in 0..1000 {
+        let proof = tree_operator.create_proof(&tree, &LeafIndex::from_index(i, &domain)).unwrap();
+        let compact = tree_operator.compact_proof(&proof);
+        assert!(tree_operator.verify_compact_proof(&compact, &root));
+        assert!(!tree_operator.verify_compact_proof(&compact, &Fr::one()));
+
+        // The expanded proof and the serialized compact one are the ones we started from
+        assert_eq!(serialize(&tree_operator.expand_proof(&compact, &root).unwrap()), serialize(&proof));
+        let bytes = serialize(&compact);
+        assert_eq!(CompactMerkleProof::deserialize_compressed(bytes.as_slice()).unwrap(), compact);
+
+        expanded_bytes += proof.compressed_size();
+        compact_bytes += bytes.len();
+        duplicates += compact.duplicated.count_ones();
+    }
+
+    // The levels of 125 and 63 nodes pair their last node with itself, which leaves 992..1000 go through
+    let proof = tree_operator.create_proof(&tree, &LeafIndex::from_index(999, &domain)).unwrap();
+    assert_eq!(tree_operator.compact_proof(&proof).duplicated, 0b11000);
+    assert_eq!(duplicates, 16);
+
+    // Every proof drops its root for the bitmask, and the duplicates drop a digest each
+    let digest_bytes = Fr::one().compressed_size();
+    assert_eq!(expanded_bytes - compact_bytes, 1000 * (digest_bytes - 0u64.compressed_size()) + 16 * digest_bytes);
+
+    // Flags past the end of the path are rejected
+    let mut compact = tree_operator.compact_proof(&proof);
+    compact.duplicated |= 1 << 20;
+    assert!(tree_operator.expand_proof(&compact, &root).is_none());
+}
+
+#[test]
+fn test_prove_verify_over_quadratic_extension() {
+    use crate::fri::extension::{extension_fft, fold_extension_evaluations, lift_polynomial, ExtensionFRI, Fr2};
+    use crate::fri::sha256::{Sha256Challenger, Sha256LeafHash, Sha256TwoToOne};
+
+    type ExtensionSystem = ExtensionFRI<
+        Fr,
+        Fr2,
+        MerkleTreeOperatorImpl<Sha256LeafHash<Fr2>, Sha256TwoToOne>,
+        Sha256Challenger<Fr2>,
+        Sha256TwoToOne,
+        Sha256LeafHash<Fr2>,
+    >;
+
+    let mut rng = thread_rng();
+    let system = ExtensionSystem::new(3, 8, MerkleTreeOperatorImpl::new((), ()), Sha256Challenger::new());
+
+    // A trace column over Fr folded with challenges from Fr2 leaves Fr after the first round
+    let column = lift_polynomial::<Fr, Fr2>(&DensePolynomial::rand(63, &mut rng));
+    let challenge = system.verifier_challenge.generate_nonzero_challenge(&[1; 32]);
+    assert!(!challenge.c1.is_zero());
+    let folded = fold::fold_polynomial(&column.coeffs, challenge, 2);
+    assert!(folded.iter().any(|coeff| !coeff.c1.is_zero()));
+
+    // Folding the evaluations agrees with folding the coefficients
+    let domain = GeneralEvaluationDomain::<Fr>::new(64).unwrap();
+    let folded_domain = fold::fold_domain(&domain, 2);
+    assert_eq!(
+        fold_extension_evaluations(&extension_fft(&domain, &column.coeffs), &domain, challenge),
+        extension_fft(&folded_domain, &folded)
+    );
+
+    let proof = system.prove(&column, 63).unwrap();
+    assert_eq!(proof.round_proofs.len(), 4);
+    assert!(system.verify(&proof, 63));
+    assert!(!system.verify(&proof, 31));
+
+    // A polynomial with coefficients all over the extension verifies too
+    let polynomial = DensePolynomial::<Fr2>::rand(63, &mut rng);
+    let proof = system.prove(&polynomial, 63).unwrap();
+    assert!(system.verify(&proof, 63));
+
+    let mut tampered = proof.clone();
+    tampered.final_polynomial[0] += Fr2::one();
+    assert!(!system.verify(&tampered, 63));
+    let mut tampered = proof.clone();
+    tampered.round_proofs[1].openings[0].current[0].values[0] += Fr2::one();
+    assert!(!system.verify(&tampered, 63));
+
+    // The polynomial does not fit in the domain of a lower degree
+    assert!(matches!(system.prove(&polynomial, 31), Err(FriError::PolynomialTooLarge { .. })));
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_parallel_tree_and_fft_match_serial() {
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    let mut rng = ChaCha20Rng::seed_from_u64(1464);
+    let tree_operator = setup_tree_operator();
+    let domain = GeneralEvaluationDomain::<Fr>::new(64).unwrap();
+    let polynomial = DensePolynomial::<Fr>::rand(63, &mut rng);
+
+    let evals = domain.fft(&polynomial.coeffs);
+    assert!(domain.elements().zip(&evals).all(|(x, y)| polynomial.evaluate(&x) == *y));
+
+    let leaves: Vec<_> = evals.iter().enumerate().map(|(i, y)| (LeafIndex::from_index(i, &domain), vec![*y])).collect();
+    let mut level: Vec<_> = leaves.iter().map(|(index, values)| tree_operator.hash_leaf(index, values)).collect();
+    while level.len() > 1 {
+        level = level.chunks(2).map(|pair| tree_operator.hash_pair(&pair[0], &pair[1])).collect();
+    }
+    let tree = tree_operator.create_tree(leaves, domain.group_gen(), Fr::one(), 63).unwrap();
+    assert_eq!(tree.root(), level[0]);
+}
+
+#[cfg(feature = "small-field")]
+mod small_field {
+    use super::*;
+
+    use crate::fri::goldilocks::Goldilocks;
+    use crate::fri::sha256::{Sha256Challenger, Sha256LeafHash, Sha256TwoToOne};
+
+    type GoldilocksTreeOperator = MerkleTreeOperatorImpl<Sha256LeafHash<Goldilocks>, Sha256TwoToOne>;
+    type GoldilocksFRISystem = FRISystemImpl<
+        Goldilocks,
+        DensePolynomial<Goldilocks>,
+        GoldilocksTreeOperator,
+        Sha256Challenger<Goldilocks>,
+        Sha256TwoToOne,
+        Sha256LeafHash<Goldilocks>,
+    >;
+
+    #[test]
+    fn test_prove_verify_over_goldilocks() {
+        assert_eq!(Goldilocks::TWO_ADICITY, 32);
+        let mut rng = thread_rng();
+        let config = FriConfig { blowup: 4, leaf_width: 2, cap_height: 1, ..FriConfig::new(3, 16) };
+        let system = GoldilocksFRISystem::with_config(config, GoldilocksTreeOperator::with_leaf_width((), (), 2), Sha256Challenger::new()).unwrap();
+
+        let polynomial = DensePolynomial::<Goldilocks>::rand(255, &mut rng);
+        let proof = system.prove(&polynomial, 255).unwrap();
+        assert_eq!(system.verify_degree(&proof, 255), Ok(()));
+
+        let mut tampered = proof.clone();
+        tampered.final_polynomial[0] += Goldilocks::one();
+        assert!(!system.verify(&tampered));
+
+        // The seed is split so that it is not reduced modulo the 64-bit prime
+        let seed = TranscriptSeed([0xff; 32]);
+        let other_seed = TranscriptSeed([[0xff; 16], [0; 16]].concat().try_into().unwrap());
+        assert_eq!(seed.to_field_elements::<Goldilocks>().len(), 5);
+        assert_ne!(seed.to_field_elements::<Goldilocks>(), other_seed.to_field_elements::<Goldilocks>());
+        let proof = system.prove_with_seed(&polynomial, 255, &seed).unwrap();
+        assert!(system.verify_with_seed(&proof, &seed));
+        assert!(!system.verify_with_seed(&proof, &other_seed));
+
+        // Poseidon over Goldilocks needs x^7, as x^5 is not a permutation of it
+        let hash = hash_field_elements(b"test", &[Goldilocks::from(1u64), Goldilocks::from(2u64)]);
+        assert_ne!(hash, hash_field_elements(b"test", &[Goldilocks::from(2u64), Goldilocks::from(1u64)]));
+    }
+
+    #[test]
+    fn test_goldilocks_challenges_cover_the_domain_uniformly() {
+        let challenger = Sha256Challenger::<Goldilocks>::new();
+        let domain_size = 64;
+        let samples = 1 << 16;
+        let mut counts = vec![0usize; domain_size];
+        for counter in 0..samples {
+            counts[challenge_to_index(challenger.generate_challenge_with_counter(&[7; 32], counter), domain_size)] += 1;
+        }
+
+        // The bias is at most 1 / p per index, far below the sampling noise: every index is hit
+        // within six standard deviations of the 1024 expected
+        let expected = samples as f64 / domain_size as f64;
+        let deviation = (expected * (1.0 - 1.0 / domain_size as f64)).sqrt();
+        assert!(counts.iter().all(|count| (*count as f64 - expected).abs() < 6.0 * deviation), "{:?}", counts);
+
+        // Elements at the top of the field map by their canonical value
+        let p_minus_one = -Goldilocks::one();
+        assert_eq!(challenge_to_index(p_minus_one, 1 << 32), 0);
+        assert_eq!(challenge_to_index(p_minus_one - Goldilocks::from(5u64), 1 << 32), (1 << 32) - 5);
+    }
+}
@@ -0,0 +1,162 @@
+use std::marker::PhantomData;
+
+use ark_crypto_primitives::crh::TwoToOneCRHScheme;
+use ark_ff::{Field, PrimeField};
+use ark_serialize::CanonicalSerialize;
+
+use crate::fri::merkle_tree::MerkleTreeOperator;
+use crate::fri::utils::challenge_to_index;
+use crate::util::VerifierChallenge;
+
+/// A caller-supplied seed the whole FRI transcript starts from, so that every challenge is
+/// determined by the seed and the commitments alone.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TranscriptSeed(pub [u8; 32]);
+
+impl TranscriptSeed {
+    /// The seed as field elements, each of at most 16 bytes and fewer than the modulus has, so
+    /// that none is reduced and distinct seeds never collide, even over small fields: two halves
+    /// over a 255-bit field, five elements over a 64-bit one.
+    pub fn to_field_elements<F: Field>(&self) -> Vec<F> {
+        let chunk_bytes = ((F::BasePrimeField::MODULUS_BIT_SIZE as usize - 1) / 8).min(16);
+        self.0
+            .chunks(chunk_bytes)
+            .map(|bytes| F::from_base_prime_field(F::BasePrimeField::from_le_bytes_mod_order(bytes)))
+            .collect()
+    }
+}
+
+/// The running state of a FRI transcript: the seed hashed as a leaf, then chained with every
+/// commitment through the two-to-one hash. Challenges are drawn from the state rather than from
+/// the last commitment alone, so they depend on the seed and everything committed before.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transcript<H> {
+    state: H,
+}
+
+impl<H: Clone> Transcript<H> {
+    pub fn new<F: Field, INCH: TwoToOneCRHScheme<Output = H>>(tree_operator: &impl MerkleTreeOperator<F, INCH>, seed: &TranscriptSeed) -> Self {
+        Self {
+            state: tree_operator.hash_values(&seed.to_field_elements::<F>()),
+        }
+    }
+
+    /// Starts from a state computed elsewhere, e.g. by a larger protocol the transcript is shared with.
+    pub fn from_state(state: H) -> Self {
+        Self { state }
+    }
+
+    /// Replaces the state by a hash of it computed outside the Merkle tree's hashes, for protocols
+    /// that absorb into a shared transcript with their own sponge.
+    pub fn absorb_with(&mut self, hash: impl FnOnce(&H) -> H) {
+        self.state = hash(&self.state);
+    }
+
+    pub fn absorb<F: Field, INCH: TwoToOneCRHScheme<Output = H>>(&mut self, tree_operator: &impl MerkleTreeOperator<F, INCH>, commitment: &H) {
+        self.state = tree_operator.hash_pair(&self.state, commitment);
+    }
+
+    /// Absorbs field elements, hashed as a leaf.
+    pub fn absorb_values<F: Field, INCH: TwoToOneCRHScheme<Output = H>>(&mut self, tree_operator: &impl MerkleTreeOperator<F, INCH>, values: &[F]) {
+        let hash = tree_operator.hash_values(values);
+        self.absorb(tree_operator, &hash);
+    }
+
+    /// Finds the first nonce that `meets_grinding` once absorbed, absorbs it and returns it.
+    /// Nothing is absorbed when no grinding is asked for.
+    pub fn grind<F: Field, INCH: TwoToOneCRHScheme<Output = H>, VC: VerifierChallenge<Commitment = H, Challenge = F>>(
+        &mut self,
+        tree_operator: &impl MerkleTreeOperator<F, INCH>,
+        challenger: &VC,
+        bits: usize,
+    ) -> u64 {
+        if bits == 0 {
+            return 0;
+        }
+        let nonce = (0..)
+            .find(|nonce| {
+                let mut candidate = self.clone();
+                candidate.absorb_values(tree_operator, &[F::from(*nonce)]);
+                candidate.meets_grinding(challenger, bits)
+            })
+            .expect("Some nonce meets the grinding target");
+        self.absorb_values(tree_operator, &[F::from(nonce)]);
+        nonce
+    }
+
+    /// Absorbs the prover's nonce and checks it meets the grinding target, the way `grind` found it.
+    pub fn absorb_grinding_nonce<F: Field, INCH: TwoToOneCRHScheme<Output = H>, VC: VerifierChallenge<Commitment = H, Challenge = F>>(
+        &mut self,
+        tree_operator: &impl MerkleTreeOperator<F, INCH>,
+        challenger: &VC,
+        bits: usize,
+        nonce: u64,
+    ) -> bool {
+        if bits == 0 {
+            return nonce == 0;
+        }
+        self.absorb_values(tree_operator, &[F::from(nonce)]);
+        self.meets_grinding(challenger, bits)
+    }
+
+    /// Whether the low `bits` bits of the challenge drawn from the state are all zero.
+    fn meets_grinding<F: Field, VC: VerifierChallenge<Commitment = H, Challenge = F>>(&self, challenger: &VC, bits: usize) -> bool {
+        challenge_to_index(challenger.generate_challenge(&self.state), 1 << bits) == 0
+    }
+
+    pub fn state(&self) -> &H {
+        &self.state
+    }
+}
+
+/// Where the folding rounds of a proof draw their challenges from and absorb their commitments
+/// into: FRI's own transcript, or the sponge of a larger protocol FRI runs inside of.
+pub trait RoundTranscript<F, H> {
+    /// The non-zero challenge folding the layer absorbed last.
+    fn folding_challenge(&mut self) -> F;
+    /// Absorbs the commitment to the next layer.
+    fn absorb_commitment(&mut self, commitment: &H);
+}
+
+/// A `Transcript` with the tree operator it hashes with and the challenger drawing from it.
+pub struct ChallengerTranscript<'a, H, MT, VC, INCH> {
+    transcript: &'a mut Transcript<H>,
+    tree_operator: &'a MT,
+    challenger: &'a VC,
+    _phantom: PhantomData<INCH>,
+}
+
+impl<'a, H, MT, VC, INCH> ChallengerTranscript<'a, H, MT, VC, INCH> {
+    pub fn new(transcript: &'a mut Transcript<H>, tree_operator: &'a MT, challenger: &'a VC) -> Self {
+        Self { transcript, tree_operator, challenger, _phantom: PhantomData }
+    }
+}
+
+impl<F, INCH, MT, VC> RoundTranscript<F, INCH::Output> for ChallengerTranscript<'_, INCH::Output, MT, VC, INCH>
+where
+    F: Field,
+    INCH: TwoToOneCRHScheme,
+    MT: MerkleTreeOperator<F, INCH>,
+    VC: VerifierChallenge<Commitment = INCH::Output, Challenge = F>,
+{
+    fn folding_challenge(&mut self) -> F {
+        self.challenger.generate_nonzero_challenge(self.transcript.state())
+    }
+
+    fn absorb_commitment(&mut self, commitment: &INCH::Output) {
+        self.transcript.absorb(self.tree_operator, commitment);
+    }
+}
+
+/// A transcript shared with a larger protocol, whose challenges are squeezed from it.
+pub struct SharedTranscript<'a, T>(pub &'a mut T);
+
+impl<F: PrimeField, H: CanonicalSerialize, T: crate::util::Transcript<F>> RoundTranscript<F, H> for SharedTranscript<'_, T> {
+    fn folding_challenge(&mut self) -> F {
+        self.0.squeeze_nonzero_field(b"fri/fold")
+    }
+
+    fn absorb_commitment(&mut self, commitment: &H) {
+        self.0.absorb_group(b"fri/commitment", commitment);
+    }
+}
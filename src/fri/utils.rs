@@ -1,4 +1,9 @@
-use ark_ff::Field;
+use ark_crypto_primitives::sponge::poseidon::PoseidonSponge;
+use ark_crypto_primitives::sponge::{Absorb, CryptographicSponge};
+use ark_ff::{FftField, Field, PrimeField};
+
+use super::error::FriError;
+use crate::util::poseidon::secure_config;
 
 pub fn compute_evaluations<F: Field>(
     polynomial_coeffs: &[F],
@@ -19,8 +24,64 @@ pub fn get_coset<F: Field>(domain: &[F], shift: F) -> Vec<F> {
     domain.iter().map(|&x| x * shift).collect()
 }
 
-pub fn hash_field_elements<F: Field>(elements: &[F]) -> F {
-    // Implement a simple hash function for field elements
-    // In practice, use a cryptographic hash function like Poseidon
-    elements.iter().fold(F::zero(), |acc, &x| acc + x)
-}
\ No newline at end of file
+/// The base two logarithm of the size of the domain for a polynomial of the given degree at the
+/// given blowup, rounded up, or `usize::BITS` when the size does not even fit in a usize.
+pub fn domain_log_size(degree: usize, blowup: usize) -> u32 {
+    degree
+        .checked_add(1)
+        .and_then(usize::checked_next_power_of_two)
+        .and_then(|size| size.checked_mul(blowup))
+        .and_then(usize::checked_next_power_of_two)
+        .map_or(usize::BITS, |size| size.trailing_zeros())
+}
+
+/// Checks that F has a multiplicative subgroup of size `2^log_size`, which it only has up to its
+/// two-adicity.
+pub fn check_two_adicity<F: FftField>(log_size: u32) -> Result<(), FriError> {
+    if log_size > F::TWO_ADICITY {
+        return Err(FriError::DomainTooLarge { requested: log_size, supported: F::TWO_ADICITY });
+    }
+    Ok(())
+}
+
+/// Checks that a polynomial with the given number of coefficients can be evaluated over a domain
+/// of the given size without wrapping around.
+pub fn check_fits(coefficients: usize, domain_size: usize) -> Result<(), FriError> {
+    if coefficients > domain_size {
+        return Err(FriError::PolynomialTooLarge { coefficients, domain_size });
+    }
+    Ok(())
+}
+
+/// Hashes field elements with a Poseidon sponge, separating uses of the hash by the domain tag.
+///
+/// The tag and the number of elements are absorbed before the elements, so neither a different
+/// tag nor trailing zeros give the same state. The sponge is `secure_config` with rate 2 and a
+/// single element of capacity, so over a 64-bit field the hash only has 32 bits of collision
+/// resistance.
+pub fn hash_field_elements<F: PrimeField + Absorb>(domain_tag: &[u8], elements: &[F]) -> F {
+    let mut sponge = PoseidonSponge::new(&secure_config::<F>(2, 1));
+    sponge.absorb(&domain_tag);
+    sponge.absorb(&(elements.len() as u64));
+    sponge.absorb(&elements);
+    sponge.squeeze_field_elements(1)[0]
+}
+
+/// Maps a challenge to a position in a domain of the given power of two size: its canonical
+/// representation reduced modulo the size.
+///
+/// For a uniform challenge of a prime field, each of the `n` positions is hit by `⌊p / n⌋` or
+/// `⌈p / n⌉` of the p elements, so its probability is within `1 / p` of `1 / n`: about `2^-64`
+/// over Goldilocks, for any domain it has.
+///
+/// Field elements serialize as their canonical representation in little endian, starting with the
+/// first base field coefficient for extensions, so the low bits are read straight from the bytes.
+pub fn challenge_to_index<F: Field>(challenge: F, domain_size: usize) -> usize {
+    assert!(domain_size.is_power_of_two(), "Domain size must be a power of two");
+    let mut bytes = Vec::new();
+    challenge.serialize_compressed(&mut bytes).expect("Serializing to a vector cannot fail");
+    let mut low_bytes = [0u8; 8];
+    let len = bytes.len().min(8);
+    low_bytes[..len].copy_from_slice(&bytes[..len]);
+    (u64::from_le_bytes(low_bytes) % domain_size as u64) as usize
+}
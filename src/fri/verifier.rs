@@ -1,39 +1,64 @@
+use std::cell::{Ref, RefCell};
+
+use ark_ff::FftField;
 use ark_poly::DenseUVPolynomial;
 use ark_crypto_primitives::crh::{CRHScheme, TwoToOneCRHScheme};
-use crate::fri::merkle_tree::MerkleTreeOperator;
-use crate::fri::prover::{FRIRecCommitment, FRIRecProof};
+use crate::fri::domain::DomainCache;
+use crate::fri::merkle_tree::{LeafIndex, MerkleProof, MerkleTreeOperator};
+use crate::fri::prover::{FRIRecCommitment, FRIRecProof, RowOpening};
+use crate::fri::utils::domain_log_size;
 
+#[allow(clippy::module_inception)]
 pub mod verifier {
-    use ark_ff::Field;
     use super::*;
 
     pub struct Verifier<F, P, LCH, INCH, MT>
     where
-        F: Field,
+        F: FftField,
         P: DenseUVPolynomial<F>,
         LCH: CRHScheme<Input = [F], Output = INCH::Output>,
         INCH: TwoToOneCRHScheme,
         MT: MerkleTreeOperator<F, INCH> ,
     {
         tree_operator: MT,
+        cap_height: usize,
+        /// The blowup the domains of the commitments were chosen with, tying their degrees to the
+        /// size of their trees
+        blowup: usize,
+        /// The subgroups the query points are recomputed in, shared by every query and round
+        domains: RefCell<DomainCache<F>>,
         _phantom: std::marker::PhantomData<(F, P, LCH, INCH)>,
     }
 
     impl<F, P, LCH, INCH, MT> Verifier<F, P, LCH, INCH, MT>
     where
-        F: Field,
+        F: FftField,
         P: DenseUVPolynomial<F>,
         LCH: CRHScheme<Input = [F], Output = INCH::Output>,
-        INCH: TwoToOneCRHScheme + PartialEq<INCH::Output>,
+        INCH: TwoToOneCRHScheme,
         MT: MerkleTreeOperator<F, INCH>,
     {
         pub fn create(tree_operator: MT) -> Self {
+            Self::with_cap_height(tree_operator, 0)
+        }
+
+        /// A verifier of proofs whose Merkle paths stop `cap_height` levels below the roots.
+        pub fn with_cap_height(tree_operator: MT, cap_height: usize) -> Self {
             Self {
                 tree_operator,
+                cap_height,
+                blowup: 1,
+                domains: RefCell::new(DomainCache::new()),
                 _phantom: std::marker::PhantomData,
             }
         }
 
+        /// The verifier for commitments over domains chosen with the given blowup, 1 by default.
+        pub fn with_blowup(mut self, blowup: usize) -> Self {
+            self.blowup = blowup;
+            self
+        }
+
 
         pub fn verify_small(
             final_polynomial: &[F],
@@ -47,51 +72,212 @@ pub mod verifier {
             // Verify that the highest-degree coefficient (the last non-zero element) is indeed non-zero
             final_polynomial.iter().rev().find(|&&coeff| coeff != F::zero()).is_some()
         }
+
+        /// Like `verify_small`, but the final polynomial must have exactly the expected degree:
+        /// `expected_degree + 1` coefficients with a non-zero leading one. A polynomial whose
+        /// degree every round halved exactly ends there, so a lower degree means a fold collapsed
+        /// it further than the degree the commitments claim.
+        pub fn verify_small_exact(
+            final_polynomial: &[F],
+            expected_degree: usize,
+        ) -> bool {
+            final_polynomial.len() == expected_degree + 1
+                && final_polynomial.last().is_some_and(|leading| !leading.is_zero())
+        }
         pub fn verify_rec(
             &self,
             current_commitment: &FRIRecCommitment<INCH::Output>,
             round_proof: &FRIRecProof<F, INCH::Output>,
             next_commitment: &FRIRecCommitment<INCH::Output>,
             challenge: F,
+            query_indices: &[usize],
+            coset_offset: F,
         ) -> bool {
-            // Verify Merkle proofs for both current and next polynomial evaluations
-            let current_proofs_valid = round_proof.current_merkle_proofs.iter()
-                .zip(&round_proof.current_evaluations)
-                .all(|((proof_x, proof_wx), &(value_x, value_wx))| {
-                    self.tree_operator.verify_proof(proof_x, value_x) &&
-                    self.tree_operator.verify_proof(proof_wx, value_wx)
-                });
-
-            let next_proofs_valid = round_proof.next_merkle_proofs.iter()
-                .zip(&round_proof.next_evaluations)
-                .all(|(proof, &value)| {
-                    self.tree_operator.verify_proof(proof, value)
-                });
-
-            if !current_proofs_valid || !next_proofs_valid {
+            if round_proof.openings.len() != query_indices.len()
+                || next_commitment.degree != current_commitment.degree / 2
+                || !self.is_valid_cap(&round_proof.current_cap, &current_commitment.merkle_root)
+                || !self.is_valid_cap(&round_proof.next_cap, &next_commitment.merkle_root)
+            {
                 return false;
             }
 
-            // Check the consistency equation
-            let consistency_check = round_proof.current_evaluations.iter()
-                .zip(&round_proof.next_evaluations)
-                .zip(&round_proof.query.leaf_indices)
-                .all(|(((f_x, f_wx), &f_next), leaf_index)| {
-                    let y_i = leaf_index.point;
-                    let s_r = F::one(); // Coset shift, typically 1 for the standard FRI
-                    let lhs = F::from(2u32) * f_next;
-                    let rhs = (F::one() + challenge) * f_x + (F::one() - challenge) * f_wx;
-                    lhs == rhs
-                });
-
-            if !consistency_check {
-                return false;
+            // Every opening is zipped with its query below, which the length check above keeps from
+            // stopping at a shorter vector of openings. The leaves of an opening have their counts
+            // checked against the leaf width by `open_current_leaves` and `folded_leaf`.
+
+            // The Merkle proofs of all the queries are checked together once the values are known
+            let mut current_proofs = Vec::new();
+            let mut next_proofs = Vec::new();
+            for (opening, query_index) in round_proof.openings.iter().zip(query_indices) {
+                let query = &opening.query;
+
+                // Read f(y) and f(-y) out of the opened leaves
+                let Some((f_x, f_wx, domain_size)) = self.open_current_leaves(query, &opening.current, &round_proof.current_cap) else {
+                    return false;
+                };
+
+                // The query must be the point of the sampled index, and the opened leaves must start
+                // at points of the same domain
+                if query.index != query_index % domain_size
+                    || !self.is_committed_degree(current_commitment.degree, domain_size)
+                    || !self.is_domain_point(query, coset_offset, domain_size)
+                    || !opening.current.iter().all(|proof| self.is_domain_point(&proof.leaf_index, coset_offset, domain_size))
+                    || !self.is_domain_point(&opening.next.leaf_index, coset_offset.square(), domain_size / 2)
+                {
+                    return false;
+                }
+
+                // Fold the pair and check the result is what the next tree commits to at y^2
+                // f_next(y^2) = (f(y) + f(-y)) / 2 + challenge * (f(y) - f(-y)) / (2y)
+                let Some(y_inv) = query.point.inverse() else {
+                    return false;
+                };
+                let f_next = (f_x + f_wx + challenge * (f_x - f_wx) * y_inv) / F::from(2u32);
+                let Some(next_proof) = self.folded_leaf(query, domain_size, f_next, &opening.next, &round_proof.next_cap) else {
+                    return false;
+                };
+                current_proofs.extend(&opening.current);
+                next_proofs.push(next_proof);
+            }
+
+            self.tree_operator.verify_proofs_batch_with_cap(&current_proofs, &round_proof.current_cap)
+                && self.tree_operator.verify_proofs_batch_with_cap(&next_proofs.iter().collect::<Vec<_>>(), &round_proof.next_cap)
+        }
+
+        /// Verifies openings made with `Prover::open_rec_batched` of a tree of `columns` polynomials
+        /// under the commitment: every opening must be the row of the query, with one value per
+        /// column, and hash up to the cap, which must hash up to the root.
+        pub fn verify_rec_batched(
+            &self,
+            commitment: &FRIRecCommitment<INCH::Output>,
+            cap: &[INCH::Output],
+            queries: &[LeafIndex<F>],
+            openings: &[RowOpening<F, INCH::Output>],
+            columns: usize,
+        ) -> bool {
+            openings.len() == queries.len()
+                && self.is_valid_cap(cap, &commitment.merkle_root)
+                && queries.iter().zip(openings).all(|(query, opening)| {
+                    opening.row().len() == columns
+                        && opening.query().index == query.index
+                        && opening.query().point == query.point
+                })
+                && self.tree_operator.verify_proofs_batch_with_cap(
+                    &openings.iter().map(|opening| &opening.proof).collect::<Vec<_>>(),
+                    cap,
+                )
+        }
+
+        /// The size of the domain the current tree of the round commits to, read off the shape of
+        /// its cap and of the Merkle proofs of its first opening.
+        pub fn opened_domain_size(round_proof: &FRIRecProof<F, INCH::Output>) -> Option<usize> {
+            let proof = round_proof.openings.first()?.current.first()?;
+            Some((proof.values.len() * round_proof.current_cap.len()) << proof.path.len())
+        }
+
+        /// The offset of the coset of the round's domain the first query point lies in.
+        pub fn opened_coset_offset(round_proof: &FRIRecProof<F, INCH::Output>) -> Option<F> {
+            let generator = F::get_root_of_unity(Self::opened_domain_size(round_proof)? as u64)?;
+            let query = &round_proof.openings.first()?.query;
+            Some(query.point * generator.pow([query.index as u64]).inverse()?)
+        }
+
+        /// The domains the verifier has looked up so far.
+        pub fn domain_cache(&self) -> Ref<'_, DomainCache<F>> {
+            self.domains.borrow()
+        }
+
+        /// Whether the cap has at most `2^cap_height` nodes and hashes up to the root.
+        fn is_valid_cap(&self, cap: &[INCH::Output], root: &INCH::Output) -> bool {
+            cap.len() <= 1 << self.cap_height && self.tree_operator.verify_cap(cap, root)
+        }
+
+        /// Whether a polynomial of the degree is committed to over a domain of the given size, the
+        /// one `Prover::create_domain` gives for the degree and the verifier's blowup.
+        fn is_committed_degree(&self, degree: usize, domain_size: usize) -> bool {
+            1_usize.checked_shl(domain_log_size(degree, self.blowup)) == Some(domain_size)
+        }
+
+        /// Whether the point is the one at the index in `coset_offset · <ω>` for `ω` of order `domain_size`.
+        fn is_domain_point(&self, leaf: &LeafIndex<F>, coset_offset: F, domain_size: usize) -> bool {
+            match self.domains.borrow_mut().generator(domain_size) {
+                Some(generator) => leaf.point == coset_offset * generator.pow([leaf.index as u64]),
+                None => false,
             }
+        }
 
-            // Verify that the provided Merkle roots match the commitments
-            current_commitment.merkle_root == round_proof.current_merkle_root &&
-            next_commitment.merkle_root == round_proof.next_merkle_root
+        /// The width the tree over a domain of the given size packs its leaves with.
+        fn expected_leaf_width(&self, domain_size: usize) -> usize {
+            self.tree_operator.leaf_width().min(domain_size)
         }
 
+        /// Checks the shape of the leaves opened in the current round for the query `y`, returning
+        /// `f(y)`, `f(-y)` and the size of the current domain. The Merkle proofs are left to the caller.
+        fn open_current_leaves(
+            &self,
+            query: &LeafIndex<F>,
+            proofs: &[MerkleProof<F, INCH::Output>],
+            cap: &[INCH::Output],
+        ) -> Option<(F, F, usize)> {
+            let first_proof = proofs.first()?;
+            let leaf_width = first_proof.values.len();
+            if proofs.iter().any(|proof| proof.values.len() != leaf_width) {
+                return None;
+            }
+            let leaf_count = cap.len() << first_proof.path.len();
+            let domain_size = leaf_width * leaf_count;
+            if leaf_width != self.expected_leaf_width(domain_size) || query.index >= domain_size {
+                return None;
+            }
+
+            // The queried value must sit in the first opened leaf
+            if first_proof.leaf_index.index != query.index % leaf_count {
+                return None;
+            }
+
+            let position = query.index / leaf_count;
+            let values = &first_proof.values;
+            match (leaf_width, proofs.len()) {
+                // f(-y) is in a leaf of its own, exactly half the domain further along
+                (1, 2) if proofs[1].leaf_index.index == (query.index + domain_size / 2) % domain_size => {
+                    Some((values[0], proofs[1].values[0], domain_size))
+                }
+                // f(-y) sits half a leaf further along
+                (_, 1) if leaf_width > 1 => Some((
+                    values[position],
+                    values[(position + leaf_width / 2) % leaf_width],
+                    domain_size,
+                )),
+                _ => None,
+            }
+        }
+
+        /// Checks the shape of the leaf opened in the next round for the query `y`, returning its
+        /// Merkle proof with the folded value `f_next(y^2)` put back in the place the prover left it
+        /// out of.
+        fn folded_leaf(
+            &self,
+            query: &LeafIndex<F>,
+            domain_size: usize,
+            f_next: F,
+            proof: &MerkleProof<F, INCH::Output>,
+            cap: &[INCH::Output],
+        ) -> Option<MerkleProof<F, INCH::Output>> {
+            let leaf_width = proof.values.len() + 1;
+            let next_domain_size = domain_size / 2;
+            let leaf_count = cap.len() << proof.path.len();
+            if leaf_width * leaf_count != next_domain_size || leaf_width != self.expected_leaf_width(next_domain_size) {
+                return None;
+            }
+
+            let folded_index = query.index % next_domain_size;
+            if proof.leaf_index.index != folded_index % leaf_count {
+                return None;
+            }
+
+            let mut values = proof.values.clone();
+            values.insert(folded_index / leaf_count, f_next);
+            Some(MerkleProof { values, ..proof.clone() })
+        }
     }
-}
\ No newline at end of file
+}
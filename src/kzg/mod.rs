@@ -1,14 +1,17 @@
 pub mod prover;
 pub mod verifier;
 pub mod system;
+pub mod permutation;
+pub mod pedersen;
 mod test;
 
-use std::marker::PhantomData;
+use std::fmt;
 
 use ark_ec::{pairing::Pairing, Group};
 use ark_ff::Field;
-use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
-use crate::util::VerifierChallenge;
+use ark_poly::Polynomial;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use crate::util::TranscriptDigest;
 
 /// Represents a KZG (Kate-Zaverucha-Goldberg) commitment to a polynomial.
 ///
@@ -28,6 +31,7 @@ use crate::util::VerifierChallenge;
 ///
 /// This is equivalent to evaluating f(s) in the exponent using the CRS (Common Reference String)
 /// values provided in the public parameters.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct KZGCommitment<F: Field, G: Group<ScalarField = F>> {
     pub value: G,
 }
@@ -61,35 +65,185 @@ pub struct KZGCommitment<F: Field, G: Group<ScalarField = F>> {
 ///
 /// The commitment is computed using the CRS values in PP_1, while the witness
 /// is computed using a subset of these values (up to degree d-1).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct KZGProof<F: Field, G: Group<ScalarField = F>> {
     pub commitment: G,           // g^(f(s))
     pub challenge: F,
     pub challenge_evaluation: G, // g^(f(alpha))
     pub witness: G,              // g^(q(s))
+    pub value: Option<F>,        // f(alpha), when the prover reveals it
 }
 
+impl<F: Field, G: Group<ScalarField = F>> KZGProof<F, G> {
+    /// The challenge and, if revealed, the value of the polynomial there, e.g. to index proofs by.
+    pub fn opening(&self) -> (F, Option<F>) {
+        (self.challenge, self.value)
+    }
+}
+
+impl<F: Field, G: Group<ScalarField = F>> TranscriptDigest for KZGProof<F, G> {}
+
+/// Openings of three committed polynomials f, g and h at a common point z, attesting to the
+/// relation f(x) = g(x) · h(x) by checking f(z) = g(z) · h(z).
+///
+/// By Schwartz–Zippel, two distinct polynomials of degree at most d agree at a random z with
+/// probability at most d / |F|, so z must only be chosen once all three commitments are fixed.
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ProductRelationProof<F: Field, G: Group<ScalarField = F>> {
+    pub f: KZGProof<F, G>,
+    pub g: KZGProof<F, G>,
+    pub h: KZGProof<F, G>,
+}
+
+/// A KZG opening of a bivariate polynomial f(x, y) at a point (x0, y0).
+///
+/// # Procedure
+/// f(x, y) - f(x0, y0) splits as q_x(x, y) * (x - x0) + q_y(y) * (y - y0) with
+///    q_x(x, y) = (f(x, y) - f(x0, y)) / (x - x0)
+///    q_y(y) = (f(x0, y) - f(x0, y0)) / (y - y0)
+///
+/// and the two witnesses are the commitments to these quotients, computed with the CRS in s and t:
+///    W_x = g_1^(q_x(s, t)), W_y = g_1^(q_y(t))
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BivariateKZGProof<F: Field, G: Group<ScalarField = F>> {
+    pub commitment: G,           // g^(f(s, t))
+    pub challenge: (F, F),
+    pub challenge_evaluation: G, // g^(f(x0, y0))
+    pub witness_x: G,            // g^(q_x(s, t))
+    pub witness_y: G,            // g^(q_y(t))
+}
+
+/// A proof that two committed polynomials f and g take the same value at a point z, without
+/// revealing it: C_f - C_g commits to f - g, which opens to 0 at z.
+///
+/// # Procedure
+/// f - g has a root at z exactly when f(z) = g(z), in which case the quotient
+///    q(x) = (f(x) - g(x)) / (x - z)
+///
+/// is a polynomial, and the witness is its commitment W = g_1^(q(s)).
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct EqualOpeningProof<F: Field, G: Group<ScalarField = F>> {
+    pub commitment_f: G, // g^(f(s))
+    pub commitment_g: G, // g^(g(s))
+    pub challenge: F,
+    pub witness: G,      // g^(q(s))
+}
+
+/// An opening at z of the combination Σ r_i f_i of committed polynomials with a single witness,
+/// revealing only the combined value. The verifier recombines the commitments C_i of the f_i
+/// into the commitment Σ r_i C_i of the combination.
+///
+/// # Procedure
+/// With y = Σ r_i f_i(z), the witness is the commitment W = g_1^(q(s)) to
+///    q(x) = (Σ r_i f_i(x) - y) / (x - z)
+///
+/// The r_i must be drawn after the commitments are fixed, e.g. with
+/// `KZGVerifierChallenger::folding_coefficients`, or the prover could choose them to cancel out a
+/// wrong commitment.
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct FoldedOpeningProof<F: Field, G: Group<ScalarField = F>> {
+    pub challenge: F,
+    pub value: F,   // Σ r_i f_i(z)
+    pub witness: G, // g^(q(s))
+}
+
+/// Openings of any number of committed polynomials, each at its own point, which
+/// `verify_aggregate` checks together with two pairings instead of two per opening.
+///
+/// The witnesses are all kept: the verifier combines them itself with randomness drawn from every
+/// opening, as combinations made by the prover could be forged from the CRS.
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregateKZGProof<F: Field, G: Group<ScalarField = F>> {
+    pub proofs: Vec<KZGProof<F, G>>,
+}
+
+impl<F: Field, G: Group<ScalarField = F>> TranscriptDigest for ProductRelationProof<F, G> {}
+
+impl<F: Field, G: Group<ScalarField = F>> TranscriptDigest for AggregateKZGProof<F, G> {}
+
+impl<F: Field, G: Group<ScalarField = F>> TranscriptDigest for EqualOpeningProof<F, G> {}
+
+impl<F: Field, G: Group<ScalarField = F>> TranscriptDigest for FoldedOpeningProof<F, G> {}
+
+impl<F: Field, G: Group<ScalarField = F>> TranscriptDigest for BivariateKZGProof<F, G> {}
+
 pub trait KZGSystem<F: Field, G1: Group<ScalarField = F>, G2: Group<ScalarField = F>> {
     type E: Pairing;
     type Poly: Polynomial<F>;
 
-    fn prove(&self, polynomial: &Self::Poly) -> KZGProof<F, G1>
+    fn prove(&self, polynomial: &Self::Poly) -> Result<KZGProof<F, G1>, KZGError>
     where
         <Self::E as Pairing>::ScalarField: From<F>,
         <Self::E as Pairing>::G1: From<G1>,
         <Self::E as Pairing>::G2: From<G2>;
 
-    fn verify(&self, proof: KZGProof<F, G1>) -> bool
+    /// Whether the proof is a valid opening, or an error when it is not even well formed.
+    fn verify(&self, proof: KZGProof<F, G1>) -> Result<bool, KZGError>
     where
         <Self::E as Pairing>::ScalarField: From<F>,
         <Self::E as Pairing>::G1: From<G1>,
         <Self::E as Pairing>::G2: From<G2>;
 }
 
+/// Why a KZG system cannot prove an opening, or verify one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KZGError {
+    /// The polynomial has a higher degree than the CRS supports
+    DegreeTooLarge { degree: usize, max_degree: usize },
+    /// Dividing f(x) - f(z) by x - z left a remainder, so the quotient is not a polynomial
+    NonZeroRemainder,
+    /// A point of the proof is not in the prime-order subgroup of G1
+    MalformedPoint,
+    /// A combination was given a different number of coefficients than polynomials
+    CoefficientCountMismatch { polynomials: usize, coefficients: usize },
+    /// σ is not a permutation of a power of two elements, so it permutes no subgroup
+    InvalidPermutation { size: usize },
+}
+
+impl fmt::Display for KZGError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KZGError::DegreeTooLarge { degree, max_degree } => {
+                write!(f, "the polynomial has degree {}, but the CRS supports at most {}", degree, max_degree)
+            }
+            KZGError::NonZeroRemainder => write!(f, "dividing by x - z left a non-zero remainder"),
+            KZGError::MalformedPoint => write!(f, "a point of the proof is not in the prime-order subgroup"),
+            KZGError::CoefficientCountMismatch { polynomials, coefficients } => {
+                write!(f, "{} polynomials are combined with {} coefficients", polynomials, coefficients)
+            }
+            KZGError::InvalidPermutation { size } => write!(f, "σ of {} elements is not a permutation of a power of two elements", size),
+        }
+    }
+}
+
+impl std::error::Error for KZGError {}
+
+/// Why a shifted polynomial cannot be committed to with a CRS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShiftError {
+    /// The shift would pair a non-zero coefficient with a negative power of s
+    NegativePower { shift: i32 },
+    /// The shifted polynomial has a higher degree than the CRS supports
+    DegreeTooLarge { degree: usize, max_degree: usize },
+}
+
+/// Why a polynomial given coefficient by coefficient cannot be committed to with a CRS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitError {
+    /// More coefficients were given than the CRS has powers for
+    TooManyCoefficients { max_degree: usize },
+}
+
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct CRS<G: Group> {
     pub g1_powers: Vec<G>,
 }
 
+/// Common Reference String for bivariate polynomials, with g1_powers[i][j] = g_1^(s^i * t^j)
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct BivariateCRS<G: Group> {
+    pub g1_powers: Vec<Vec<G>>,
+}
 
 /// Trait for generating Common Reference String (CRS) for KZG commitments
 pub trait CRSGenerator<F: Field, G: Group<ScalarField = F>> {
@@ -102,3 +256,9 @@ pub trait CRSGenerator<F: Field, G: Group<ScalarField = F>> {
     /// A `CRS` struct containing the generated G1 powers
     fn generate(&self, degree: usize) -> CRS<G>;
 }
+
+/// Trait for generating the two-dimensional Common Reference String for bivariate KZG commitments
+pub trait BivariateCRSGenerator<F: Field, G: Group<ScalarField = F>> {
+    /// Generates the CRS for polynomials of degree at most `degree_x` in x and `degree_y` in y
+    fn generate(&self, degree_x: usize, degree_y: usize) -> BivariateCRS<G>;
+}
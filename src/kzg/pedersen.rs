@@ -0,0 +1,120 @@
+use ark_bls12_381::{Bls12_381, Fr, G1Projective as G1};
+use ark_ec::Group;
+use ark_ff::{Field, Zero};
+use ark_poly::{univariate::DensePolynomial, Polynomial};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::bulletproofs::{BulletproofGenerators, BulletproofProof, BulletproofSystem};
+use crate::error::Error;
+use crate::util::msm::msm_projective;
+use crate::util::TranscriptDigest;
+
+use super::prover::prover;
+use super::system::KZGSystemImpl;
+use super::verifier::verifier;
+use super::{KZGError, KZGProof};
+
+/// A proof that a KZG commitment C = g_1^(f(s)) and a Pedersen vector commitment
+/// C_P = Σ f_i·g_i commit to the same coefficients f_0, ..., f_(n-1).
+///
+/// # Procedure
+/// With ζ drawn from both commitments, C is opened at ζ to v = f(ζ) as any KZG commitment, and
+/// C_P is opened to the same value with the inner product argument over the bulletproof
+/// generators: for b = (1, ζ, ..., ζ^(n-1)), the argument proves the commitment
+///    P = C_P + Σ b_i·h_i + v·u
+///
+/// which the verifier builds itself from C_P, ζ and v, holds vectors a and b with <a, b> = v.
+/// Two different polynomials of degree below n agree at ζ with probability at most n/|F|, and ζ
+/// is only known once both commitments are.
+#[derive(Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KZGPedersenProof<F: Field, G: Group<ScalarField = F>> {
+    /// The opening of the KZG commitment at ζ, revealing f(ζ)
+    pub kzg: KZGProof<F, G>,
+    /// C_P = Σ f_i·g_i
+    pub pedersen_commitment: G,
+    /// The inner product of the coefficients with the powers of ζ, under P
+    pub inner_product: BulletproofProof<F, G>,
+}
+
+impl<F: Field, G: Group<ScalarField = F>> TranscriptDigest for KZGPedersenProof<F, G> {}
+
+/// The Pedersen vector commitment Σ f_i·g_i to the coefficients of the polynomial, the one
+/// `prove_kzg_pedersen_equality` links to the KZG commitment.
+pub fn pedersen_commitment(polynomial: &DensePolynomial<Fr>, generators: &BulletproofGenerators<G1>) -> G1 {
+    assert!(polynomial.coeffs.len() <= generators.g.len(), "The polynomial has more coefficients than there are generators");
+    msm_projective(&generators.g[..polynomial.coeffs.len()], &polynomial.coeffs)
+}
+
+impl KZGSystemImpl {
+    /// Proves that the KZG commitment to the polynomial and its Pedersen commitment under the
+    /// generators hold the same coefficients. There must be a power of two generators in `g` and
+    /// `h`, at least as many as the polynomial has coefficients, and the CRS must support its degree.
+    pub fn prove_kzg_pedersen_equality<B: BulletproofSystem<Fr, G1>>(
+        &self,
+        polynomial: &DensePolynomial<Fr>,
+        generators: &BulletproofGenerators<G1>,
+        inner_product: &B,
+    ) -> Result<KZGPedersenProof<Fr, G1>, Error> {
+        let n = generators.g.len();
+        if polynomial.coeffs.len() > n {
+            return Err(KZGError::DegreeTooLarge { degree: polynomial.coeffs.len() - 1, max_degree: n.saturating_sub(1) }.into());
+        }
+
+        let max_degree = self.crs.g1_powers.len().saturating_sub(1);
+        if polynomial.degree() > max_degree {
+            return Err(KZGError::DegreeTooLarge { degree: polynomial.degree(), max_degree }.into());
+        }
+
+        let kzg_commitment = prover::prover_commit(&self.crs, polynomial);
+        let pedersen_commitment = pedersen_commitment(polynomial, generators);
+        let zeta = self.verifier_challenge.hash_commitments_after(&[], &[kzg_commitment, pedersen_commitment]);
+
+        let mut coeffs = polynomial.coeffs.clone();
+        coeffs.resize(n, Fr::zero());
+        Ok(KZGPedersenProof {
+            kzg: prover::try_prover_open(&self.crs, polynomial, &zeta, &kzg_commitment)?,
+            pedersen_commitment,
+            inner_product: inner_product.prove(generators.clone(), coeffs, powers(zeta, n))?,
+        })
+    }
+
+    /// Verifies that the KZG commitment and the Pedersen commitment of the proof hold the same
+    /// coefficients: both must open to the same value at the challenge drawn from them.
+    pub fn verify_kzg_pedersen_equality<B: BulletproofSystem<Fr, G1>>(
+        &self,
+        proof: KZGPedersenProof<Fr, G1>,
+        generators: &BulletproofGenerators<G1>,
+        inner_product: &B,
+    ) -> bool {
+        let n = generators.g.len();
+        if !n.is_power_of_two() || generators.h.len() != n {
+            return false;
+        }
+
+        let KZGPedersenProof { kzg, pedersen_commitment, inner_product: inner_product_proof } = proof;
+        let zeta = self.verifier_challenge.hash_commitments_after(&[], &[kzg.commitment, pedersen_commitment]);
+        let value = match kzg.value {
+            Some(value) if kzg.challenge == zeta && self.crs.g1_powers[0] * value == kzg.challenge_evaluation => value,
+            _ => return false,
+        };
+        if !verifier::verify::<Bls12_381>(kzg, zeta, self.g2, self.g2_s) {
+            return false;
+        }
+
+        // The inner product argument must open the commitment built from C_P, the powers of ζ
+        // and the KZG value, rather than one of the prover's choosing
+        let expected = pedersen_commitment
+            + powers(zeta, n).iter().zip(&generators.h).map(|(power, generator)| *generator * power).sum::<G1>()
+            + generators.u * value;
+        let opened = inner_product_proof
+            .rec_proofs
+            .first()
+            .map_or(inner_product_proof.small_proof.pedersen_commitment, |(round, _)| round.pedersen_commitment);
+        opened == expected && inner_product.verify(inner_product_proof, generators.clone())
+    }
+}
+
+/// 1, x, ..., x^(n-1).
+fn powers(x: Fr, n: usize) -> Vec<Fr> {
+    std::iter::successors(Some(Fr::from(1u64)), |power| Some(*power * x)).take(n).collect()
+}
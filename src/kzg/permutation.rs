@@ -0,0 +1,185 @@
+use ark_bls12_381::{Bls12_381, Fr, G1Projective as G1};
+use ark_ec::Group;
+use ark_ff::{batch_inversion, Field, One, Zero};
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain, Polynomial};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::error::Error;
+use crate::util::TranscriptDigest;
+
+use super::prover::prover;
+use super::system::KZGSystemImpl;
+use super::verifier::verifier;
+use super::{KZGError, KZGProof};
+
+/// A proof that g(ω^i) = f(ω^σ(i)) for every i, over the subgroup H = <ω> of size n = |σ|, with
+/// the grand-product argument of PLONK.
+///
+/// # Procedure
+/// With challenges β and γ drawn from the commitments to f and g, the accumulator z is the
+/// polynomial of degree below n with z(1) = 1 and
+///    z(ω^(i+1)) = z(ω^i) · (f(ω^i) + β·ω^i + γ) / (g(ω^i) + β·S_σ(ω^i) + γ)
+///
+/// where S_σ(ω^i) = ω^σ(i). The product of all n ratios is 1 exactly when the pairs (f(ω^i), ω^i)
+/// and (g(ω^i), ω^σ(i)) are the same multiset, so z wraps around to z(1) = 1. With a third
+/// challenge α drawn from the commitment to z, the quotient
+///    t(x) = [z(ωx)·(g(x) + β·S_σ(x) + γ) - z(x)·(f(x) + β·x + γ) + α·L_1(x)·(z(x) - 1)] / Z_H(x)
+///
+/// is a polynomial only if both constraints hold on all of H, where L_1 is the Lagrange basis
+/// polynomial of 1 and Z_H(x) = x^n - 1. The relation is checked at a point ζ drawn from the
+/// commitment to t, from the openings of f, g, z and t at ζ and of z at ωζ.
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PermutationProof<F: Field, G: Group<ScalarField = F>> {
+    pub f: KZGProof<F, G>,
+    pub g: KZGProof<F, G>,
+    pub z: KZGProof<F, G>,
+    /// z opened at ωζ, against the same commitment as `z`
+    pub z_shifted: KZGProof<F, G>,
+    pub t: KZGProof<F, G>,
+}
+
+impl<F: Field, G: Group<ScalarField = F>> TranscriptDigest for PermutationProof<F, G> {}
+
+/// The challenges of the argument, each drawn from the commitments fixed before it.
+struct PermutationChallenges {
+    beta: Fr,
+    gamma: Fr,
+    alpha: Fr,
+    zeta: Fr,
+}
+
+impl KZGSystemImpl {
+    /// Proves that the evaluations of g over the subgroup of size `sigma.len()` are those of f
+    /// permuted by σ, i.e. g(ω^i) = f(ω^σ(i)). f and g must have degree below the size of the
+    /// subgroup, which the CRS must support.
+    ///
+    /// When g is not a permutation of f the proof is still produced, but fails to verify. Fails
+    /// with `KZGError::InvalidPermutation` if σ permutes no subgroup, or with
+    /// `KZGError::DegreeTooLarge` if f, g or the CRS do not fit its size.
+    pub fn prove_permutation(&self, f: &DensePolynomial<Fr>, g: &DensePolynomial<Fr>, sigma: &[usize]) -> Result<PermutationProof<Fr, G1>, Error> {
+        let Some(domain) = GeneralEvaluationDomain::<Fr>::new(sigma.len()).filter(|domain| domain.size() == sigma.len() && is_permutation(sigma)) else {
+            return Err(KZGError::InvalidPermutation { size: sigma.len() }.into());
+        };
+        let n = domain.size();
+        let degree = f.degree().max(g.degree());
+        if degree >= n {
+            return Err(KZGError::DegreeTooLarge { degree, max_degree: n - 1 }.into());
+        }
+
+        let commit = |polynomial: &DensePolynomial<Fr>| prover::prover_commit(&self.crs, polynomial);
+        let (f_commitment, g_commitment) = (commit(f), commit(g));
+        let beta = self.verifier_challenge.hash_commitments_after(&[], &[f_commitment, g_commitment]);
+        let gamma = self.verifier_challenge.hash_commitments_after(&[beta], &[f_commitment, g_commitment]);
+
+        // The accumulator, from the running product of the ratios
+        let f_evals = domain.fft(&f.coeffs);
+        let g_evals = domain.fft(&g.coeffs);
+        let sigma_evals = sigma_evaluations(&domain, sigma);
+        let mut denominators: Vec<Fr> = (0..n).map(|i| g_evals[i] + beta * sigma_evals[i] + gamma).collect();
+        batch_inversion(&mut denominators);
+        let mut z_evals = Vec::with_capacity(n);
+        let mut accumulator = Fr::one();
+        for (i, (point, denominator_inv)) in domain.elements().zip(&denominators).enumerate() {
+            z_evals.push(accumulator);
+            accumulator *= (f_evals[i] + beta * point + gamma) * denominator_inv;
+        }
+        let z = DensePolynomial::from_coefficients_vec(domain.ifft(&z_evals));
+        let z_commitment = commit(&z);
+        let alpha = self.verifier_challenge.hash_commitments_after(&[gamma], &[z_commitment]);
+
+        // The quotient by the vanishing polynomial, where beta·x + gamma and beta·S_σ(x) + gamma
+        // are added to the constant and linear terms of f and g
+        let s_sigma = DensePolynomial::from_coefficients_vec(domain.ifft(&sigma_evals));
+        let f_term = f + &DensePolynomial::from_coefficients_vec(vec![gamma, beta]);
+        let g_term = &(g + &(&s_sigma * beta)) + &DensePolynomial::from_coefficients_vec(vec![gamma]);
+        let z_shifted = shift_argument(&z, domain.group_gen());
+        let lagrange_first = DensePolynomial::from_coefficients_vec(vec![domain.size_inv(); n]);
+        let numerator = &(&(&z_shifted * &g_term) - &(&z * &f_term))
+            + &(&(&lagrange_first * &(&z - &DensePolynomial::from_coefficients_vec(vec![Fr::one()]))) * alpha);
+        let (t, _) = numerator.divide_by_vanishing_poly(domain).ok_or(KZGError::NonZeroRemainder)?;
+        let t_commitment = commit(&t);
+        let zeta = self.verifier_challenge.hash_commitments_after(&[alpha], &[t_commitment]);
+
+        // Each opening fails if the CRS does not support the degree of its polynomial
+        Ok(PermutationProof {
+            f: prover::try_prover_open(&self.crs, f, &zeta, &f_commitment)?,
+            g: prover::try_prover_open(&self.crs, g, &zeta, &g_commitment)?,
+            z: prover::try_prover_open(&self.crs, &z, &zeta, &z_commitment)?,
+            z_shifted: prover::try_prover_open(&self.crs, &z, &(zeta * domain.group_gen()), &z_commitment)?,
+            t: prover::try_prover_open(&self.crs, &t, &zeta, &t_commitment)?,
+        })
+    }
+
+    /// Verifies a proof that g is f permuted by σ, rederiving the challenges from the commitments,
+    /// checking every opening, and then the relation of the quotient at ζ.
+    pub fn verify_permutation(&self, proof: PermutationProof<Fr, G1>, sigma: &[usize]) -> bool {
+        let Some(domain) = GeneralEvaluationDomain::<Fr>::new(sigma.len()).filter(|domain| domain.size() == sigma.len()) else {
+            return false;
+        };
+        if !is_permutation(sigma) || proof.z.commitment != proof.z_shifted.commitment {
+            return false;
+        }
+
+        let PermutationChallenges { beta, gamma, alpha, zeta } = self.permutation_challenges(&proof);
+        let openings = [
+            (&proof.f, zeta),
+            (&proof.g, zeta),
+            (&proof.z, zeta),
+            (&proof.z_shifted, zeta * domain.group_gen()),
+            (&proof.t, zeta),
+        ];
+        let mut values = [Fr::zero(); 5];
+        for (value, (opening, point)) in values.iter_mut().zip(openings) {
+            match opening.value {
+                Some(revealed) if opening.challenge == point && self.crs.g1_powers[0] * revealed == opening.challenge_evaluation => {
+                    *value = revealed;
+                }
+                _ => return false,
+            }
+            if !verifier::verify::<Bls12_381>(opening.clone(), point, self.g2, self.g2_s) {
+                return false;
+            }
+        }
+        let [f_zeta, g_zeta, z_zeta, z_shifted_zeta, t_zeta] = values;
+
+        let s_sigma = DensePolynomial::from_coefficients_vec(domain.ifft(&sigma_evaluations(&domain, sigma)));
+        let vanishing = domain.evaluate_vanishing_polynomial(zeta);
+        let Some(lagrange_first) = (Fr::from(sigma.len() as u64) * (zeta - Fr::one())).inverse().map(|inv| vanishing * inv) else {
+            return false;
+        };
+        z_shifted_zeta * (g_zeta + beta * s_sigma.evaluate(&zeta) + gamma) - z_zeta * (f_zeta + beta * zeta + gamma)
+            + alpha * lagrange_first * (z_zeta - Fr::one())
+            == t_zeta * vanishing
+    }
+
+    /// Draws β and γ from the commitments to f and g, α after the one to z and ζ after the one to t.
+    fn permutation_challenges(&self, proof: &PermutationProof<Fr, G1>) -> PermutationChallenges {
+        let inputs = [proof.f.commitment, proof.g.commitment];
+        let beta = self.verifier_challenge.hash_commitments_after(&[], &inputs);
+        let gamma = self.verifier_challenge.hash_commitments_after(&[beta], &inputs);
+        let alpha = self.verifier_challenge.hash_commitments_after(&[gamma], &[proof.z.commitment]);
+        let zeta = self.verifier_challenge.hash_commitments_after(&[alpha], &[proof.t.commitment]);
+        PermutationChallenges { beta, gamma, alpha, zeta }
+    }
+}
+
+/// The evaluations of S_σ over the domain, S_σ(ω^i) = ω^σ(i).
+fn sigma_evaluations(domain: &GeneralEvaluationDomain<Fr>, sigma: &[usize]) -> Vec<Fr> {
+    sigma.iter().map(|&index| domain.element(index)).collect()
+}
+
+/// p(c·x), whose i-th coefficient is c^i times that of p.
+fn shift_argument(polynomial: &DensePolynomial<Fr>, c: Fr) -> DensePolynomial<Fr> {
+    let coeffs = polynomial.coeffs.iter().scan(Fr::one(), |power, coeff| {
+        let shifted = *coeff * *power;
+        *power *= c;
+        Some(shifted)
+    });
+    DensePolynomial::from_coefficients_vec(coeffs.collect())
+}
+
+/// Whether σ maps 0..n onto itself.
+fn is_permutation(sigma: &[usize]) -> bool {
+    let mut seen = vec![false; sigma.len()];
+    sigma.iter().all(|&index| index < sigma.len() && !std::mem::replace(&mut seen[index], true))
+}
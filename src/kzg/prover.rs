@@ -1,27 +1,90 @@
-use ark_ec::Group;
+use ark_ec::{Group, VariableBaseMSM};
 use ark_ff::Field;
 use ark_poly::Polynomial;
 
+#[allow(clippy::module_inception)]
 pub mod prover {
     use std::ops::Div;
 
-    use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
+    use ark_ff::Zero;
+    use ark_poly::{univariate::{DenseOrSparsePolynomial, DensePolynomial}, DenseUVPolynomial};
 
-    use crate::kzg::{KZGProof, CRS};
+    use crate::util::msm::msm_projective;
+    use crate::kzg::{BivariateCRS, BivariateKZGProof, CommitError, EqualOpeningProof, FoldedOpeningProof, KZGError, KZGProof, ProductRelationProof, ShiftError, CRS};
 
     use super::*;
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", name = "kzg::commit", skip_all, fields(msm_size = polynomial.coeffs().len().min(crs.g1_powers.len()))))]
     pub fn prover_commit<F, G, P>(crs: &CRS<G>, polynomial: &P) -> G
+    where
+        F: Field,
+        G: Group<ScalarField = F> + VariableBaseMSM,
+        P: DenseUVPolynomial<F>,
+    {
+        let coeffs = polynomial.coeffs();
+        let terms = coeffs.len().min(crs.g1_powers.len());
+        msm_projective(&crs.g1_powers[..terms], &coeffs[..terms])
+    }
+
+    /// Commits to the polynomial like `prover_commit`, also returning the compressed canonical
+    /// serialization of the commitment, for absorbing it into a transcript without serializing
+    /// it again.
+    pub fn prover_commit_with_bytes<F, G, P>(crs: &CRS<G>, polynomial: &P) -> (G, Vec<u8>)
+    where
+        F: Field,
+        G: Group<ScalarField = F> + VariableBaseMSM,
+        P: DenseUVPolynomial<F>,
+    {
+        let commitment = prover_commit(crs, polynomial);
+        let mut bytes = Vec::with_capacity(commitment.compressed_size());
+        commitment.serialize_compressed(&mut bytes).expect("Serializing to a vector cannot fail");
+        (commitment, bytes)
+    }
+
+    /// Commits to the polynomial whose coefficients the iterator yields, lowest degree first,
+    /// adding each term to the commitment as it comes so the coefficients are never all in memory.
+    pub fn prover_commit_streaming<F, G>(crs: &CRS<G>, coeffs: impl Iterator<Item = F>) -> Result<G, CommitError>
+    where
+        F: Field,
+        G: Group<ScalarField = F>,
+    {
+        let mut powers = crs.g1_powers.iter();
+        let mut commitment = G::zero();
+        for coeff in coeffs {
+            let g1_power = powers
+                .next()
+                .ok_or_else(|| CommitError::TooManyCoefficients { max_degree: crs.g1_powers.len().saturating_sub(1) })?;
+            commitment += g1_power.mul(coeff);
+        }
+        Ok(commitment)
+    }
+
+    /// Commits to `x^shift · f(x)` by offsetting the CRS powers the coefficients are paired with.
+    ///
+    /// A negative shift divides by a power of x, which is only possible when the corresponding low
+    /// coefficients of f are zero, e.g. `f(x) / x` when `f(0) = 0`.
+    pub fn commit_shifted<F, G, P>(crs: &CRS<G>, polynomial: &P, shift: i32) -> Result<G, ShiftError>
     where
         F: Field,
         G: Group<ScalarField = F>,
         P: DenseUVPolynomial<F>,
     {
+        let max_degree = crs.g1_powers.len() as i64 - 1;
         polynomial
             .coeffs()
             .iter()
-            .zip(crs.g1_powers.iter())
-            .map(|(coeff, g1_power)| g1_power.mul(*coeff))
+            .enumerate()
+            .filter(|(_, coeff)| !coeff.is_zero())
+            .map(|(i, coeff)| {
+                let power = i as i64 + shift as i64;
+                if power < 0 {
+                    Err(ShiftError::NegativePower { shift })
+                } else if power > max_degree {
+                    Err(ShiftError::DegreeTooLarge { degree: power as usize, max_degree: max_degree as usize })
+                } else {
+                    Ok(crs.g1_powers[power as usize].mul(*coeff))
+                }
+            })
             .sum()
     }
 
@@ -34,7 +97,7 @@ pub mod prover {
     ) -> KZGProof<F, G1>
     where
         F: Field,
-        G1: Group<ScalarField = F>,
+        G1: Group<ScalarField = F> + VariableBaseMSM,
     {
         let mut quotient_poly = polynomial.clone();
 
@@ -49,12 +112,235 @@ pub mod prover {
         let divisor = DenseUVPolynomial::from_coefficients_vec(vec![-*challenge_point, F::one()]);
         let quotient = quotient_poly.div(&divisor); // The quotient should not have a remainder doing this division
 
-        let kzgproof = KZGProof {
+        KZGProof {
+            commitment: *commitment,
+            challenge: *challenge_point,
+            challenge_evaluation: crs.g1_powers[0].mul(eval_at_challenge),
+            witness: prover_commit(crs, &quotient),
+            value: Some(eval_at_challenge),
+        }
+    }
+
+    /// Opens the polynomial at the challenge like `prover_open`, but fails instead of committing to
+    /// a truncation of the polynomial or of its quotient when the CRS does not support its degree.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", name = "kzg::open", skip_all, fields(degree = polynomial.degree())))]
+    pub fn try_prover_open<F, G1>(
+        crs: &CRS<G1>,
+        polynomial: &DensePolynomial<F>,
+        challenge_point: &F,
+        commitment: &G1,
+    ) -> Result<KZGProof<F, G1>, KZGError>
+    where
+        F: Field,
+        G1: Group<ScalarField = F> + VariableBaseMSM,
+    {
+        let max_degree = crs.g1_powers.len().saturating_sub(1);
+        if polynomial.degree() > max_degree {
+            return Err(KZGError::DegreeTooLarge { degree: polynomial.degree(), max_degree });
+        }
+
+        // q(X) = (f(X) - f(z)) / (X - z), which z being a root of the numerator leaves no remainder
+        let eval_at_challenge = polynomial.evaluate(challenge_point);
+        let numerator = polynomial - &DensePolynomial::from_coefficients_vec(vec![eval_at_challenge]);
+        let divisor = DensePolynomial::from_coefficients_vec(vec![-*challenge_point, F::one()]);
+        let (quotient, remainder) = DenseOrSparsePolynomial::from(numerator)
+            .divide_with_q_and_r(&DenseOrSparsePolynomial::from(divisor))
+            .ok_or(KZGError::NonZeroRemainder)?;
+        if !remainder.is_zero() {
+            return Err(KZGError::NonZeroRemainder);
+        }
+
+        Ok(KZGProof {
             commitment: *commitment,
             challenge: *challenge_point,
             challenge_evaluation: crs.g1_powers[0].mul(eval_at_challenge),
             witness: prover_commit(crs, &quotient),
-        };
-        kzgproof
+            value: Some(eval_at_challenge),
+        })
+    }
+
+    /// Opens the polynomial at zero, where the value is the constant coefficient and the quotient
+    /// `(f(X) - f(0)) / X` is the other coefficients shifted down, without any division.
+    pub fn prove_at_zero<F, G1>(crs: &CRS<G1>, polynomial: &DensePolynomial<F>) -> KZGProof<F, G1>
+    where
+        F: Field,
+        G1: Group<ScalarField = F> + VariableBaseMSM,
+    {
+        let constant = polynomial.coeffs.first().copied().unwrap_or(F::zero());
+        let quotient = DensePolynomial::from_coefficients_slice(polynomial.coeffs.get(1..).unwrap_or(&[]));
+
+        KZGProof {
+            commitment: prover_commit(crs, polynomial),
+            challenge: F::zero(),
+            challenge_evaluation: crs.g1_powers[0].mul(constant),
+            witness: prover_commit(crs, &quotient),
+            value: Some(constant),
+        }
+    }
+
+    /// Opens f, g and h at the point z, which should be derived from their three commitments.
+    /// Fails with `KZGError::DegreeTooLarge` if the CRS does not support the degree of one of them.
+    pub fn prove_product_relation<F, G1>(
+        crs: &CRS<G1>,
+        f: &DensePolynomial<F>,
+        g: &DensePolynomial<F>,
+        h: &DensePolynomial<F>,
+        z: &F,
+    ) -> Result<ProductRelationProof<F, G1>, KZGError>
+    where
+        F: Field,
+        G1: Group<ScalarField = F> + VariableBaseMSM,
+    {
+        let open = |polynomial: &DensePolynomial<F>| try_prover_open(crs, polynomial, z, &prover_commit(crs, polynomial));
+        Ok(ProductRelationProof { f: open(f)?, g: open(g)?, h: open(h)? })
+    }
+
+    /// Proves f(z) = g(z) without revealing the value, see `EqualOpeningProof`. Fails if the CRS
+    /// does not support the degree of either polynomial, or with `KZGError::NonZeroRemainder` if
+    /// they differ at z, as z is then no root of f - g.
+    pub fn prove_equal_opening<F, G1>(
+        crs: &CRS<G1>,
+        f: &DensePolynomial<F>,
+        g: &DensePolynomial<F>,
+        z: &F,
+    ) -> Result<EqualOpeningProof<F, G1>, KZGError>
+    where
+        F: Field,
+        G1: Group<ScalarField = F> + VariableBaseMSM,
+    {
+        let max_degree = crs.g1_powers.len().saturating_sub(1);
+        let degree = f.degree().max(g.degree());
+        if degree > max_degree {
+            return Err(KZGError::DegreeTooLarge { degree, max_degree });
+        }
+
+        let (quotient, remainder) = divide_by_linear(&(f - g).coeffs, *z);
+        if !remainder.is_zero() {
+            return Err(KZGError::NonZeroRemainder);
+        }
+
+        Ok(EqualOpeningProof {
+            commitment_f: prover_commit(crs, f),
+            commitment_g: prover_commit(crs, g),
+            challenge: *z,
+            witness: prover_commit(crs, &DensePolynomial::from_coefficients_vec(quotient)),
+        })
+    }
+
+    /// Opens the combination Σ r_i f_i of the polynomials at z, see `FoldedOpeningProof`. Fails if
+    /// there are not as many coefficients as polynomials, or if the CRS does not support the degree
+    /// of one of them.
+    pub fn prove_folded_opening<F, G1>(
+        crs: &CRS<G1>,
+        polynomials: &[DensePolynomial<F>],
+        r_coeffs: &[F],
+        z: &F,
+    ) -> Result<FoldedOpeningProof<F, G1>, KZGError>
+    where
+        F: Field,
+        G1: Group<ScalarField = F> + VariableBaseMSM,
+    {
+        if polynomials.len() != r_coeffs.len() {
+            return Err(KZGError::CoefficientCountMismatch { polynomials: polynomials.len(), coefficients: r_coeffs.len() });
+        }
+        let max_degree = crs.g1_powers.len().saturating_sub(1);
+        let degree = polynomials.iter().map(|polynomial| polynomial.degree()).max().unwrap_or(0);
+        if degree > max_degree {
+            return Err(KZGError::DegreeTooLarge { degree, max_degree });
+        }
+
+        let mut folded = vec![F::zero(); degree + 1];
+        for (polynomial, r) in polynomials.iter().zip(r_coeffs) {
+            for (folded, coeff) in folded.iter_mut().zip(&polynomial.coeffs) {
+                *folded += *r * coeff;
+            }
+        }
+        let (quotient, value) = divide_by_linear(&folded, *z);
+
+        Ok(FoldedOpeningProof {
+            challenge: *z,
+            value,
+            witness: prover_commit(crs, &DensePolynomial::from_coefficients_vec(quotient)),
+        })
+    }
+
+    /// Commits to a bivariate polynomial given as coeffs[i][j], the coefficient of x^i * y^j.
+    /// The commitment is C = ∏ (g_1^(s^i t^j))^(f_ij) = g_1^(f(s, t)). Fails with
+    /// `KZGError::DegreeTooLarge` if the CRS does not support its degree in x or in y.
+    pub fn commit_bivariate<F, G>(crs: &BivariateCRS<G>, coeffs: &[Vec<F>]) -> Result<G, KZGError>
+    where
+        F: Field,
+        G: Group<ScalarField = F>,
+    {
+        if coeffs.len() > crs.g1_powers.len() {
+            return Err(KZGError::DegreeTooLarge { degree: coeffs.len() - 1, max_degree: crs.g1_powers.len().saturating_sub(1) });
+        }
+        coeffs
+            .iter()
+            .zip(crs.g1_powers.iter())
+            .map(|(row, g1_row)| {
+                if row.len() > g1_row.len() {
+                    return Err(KZGError::DegreeTooLarge { degree: row.len() - 1, max_degree: g1_row.len().saturating_sub(1) });
+                }
+                Ok(row.iter().zip(g1_row.iter()).map(|(coeff, g1_power)| g1_power.mul(*coeff)).sum::<G>())
+            })
+            .sum()
+    }
+
+    /// Opens a bivariate polynomial at (x0, y0), see `BivariateKZGProof` for the witnesses. Fails
+    /// like `commit_bivariate` if the CRS does not support its degree.
+    pub fn open_bivariate<F, G>(
+        crs: &BivariateCRS<G>,
+        coeffs: &[Vec<F>],
+        challenge_point: (F, F),
+    ) -> Result<BivariateKZGProof<F, G>, KZGError>
+    where
+        F: Field,
+        G: Group<ScalarField = F>,
+    {
+        let commitment = commit_bivariate(crs, coeffs)?;
+        let (x0, y0) = challenge_point;
+        let degree_y = coeffs.iter().map(|row| row.len()).max().unwrap_or(0);
+
+        // View f as a polynomial in x whose coefficients are polynomials in y, f(x, y) = Σ_j f_j(x) y^j,
+        // and divide each f_j(x) - f_j(x0) by (x - x0)
+        let mut quotient_x = vec![vec![F::zero(); degree_y]; coeffs.len().saturating_sub(1)];
+        let mut f_at_x0 = vec![F::zero(); degree_y];
+        for j in 0..degree_y {
+            let column: Vec<F> = coeffs.iter().map(|row| row.get(j).copied().unwrap_or(F::zero())).collect();
+            let (quotient, remainder) = divide_by_linear(&column, x0);
+            for (i, q) in quotient.into_iter().enumerate() {
+                quotient_x[i][j] = q;
+            }
+            f_at_x0[j] = remainder;
+        }
+
+        // Divide f(x0, y) - f(x0, y0) by (y - y0)
+        let (quotient_y, eval_at_challenge) = divide_by_linear(&f_at_x0, y0);
+
+        Ok(BivariateKZGProof {
+            commitment,
+            challenge: challenge_point,
+            challenge_evaluation: crs.g1_powers[0][0].mul(eval_at_challenge),
+            witness_x: commit_bivariate(crs, &quotient_x)?,
+            witness_y: quotient_y
+                .iter()
+                .zip(crs.g1_powers[0].iter())
+                .map(|(coeff, g1_power)| g1_power.mul(*coeff))
+                .sum(),
+        })
+    }
+
+    /// Synthetic division of p(X) by (X - z), returning the quotient and the remainder p(z).
+    fn divide_by_linear<F: Field>(coeffs: &[F], z: F) -> (Vec<F>, F) {
+        let mut quotient = vec![F::zero(); coeffs.len().saturating_sub(1)];
+        let mut carry = F::zero();
+        for i in (0..coeffs.len()).rev() {
+            carry = carry * z + coeffs[i];
+            if i > 0 {
+                quotient[i - 1] = carry;
+            }
+        }
+        (quotient, carry)
     }
-}
\ No newline at end of file
+}
@@ -1,15 +1,22 @@
-use ark_bls12_381::{Bls12_381, Fr, G1Projective as G1, G2Projective as G2};
-use ark_crypto_primitives::sponge::poseidon::{PoseidonConfig, PoseidonSponge};
-use ark_crypto_primitives::sponge::CryptographicSponge;
-use ark_ec::{AffineRepr, CurveGroup};
+use ark_bls12_381::{Bls12_381, Fr, G1Affine, G1Projective as G1, G2Projective as G2};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_ec::CurveGroup;
+use ark_ff::{One, UniformRand};
 use ark_poly::univariate::DensePolynomial;
-use crate::kzg::KZGProof;
-use crate::util::VerifierChallenge;
+use ark_std::rand::Rng;
+use crate::fri::transcript::Transcript;
+use crate::kzg::{AggregateKZGProof, EqualOpeningProof, FoldedOpeningProof, KZGError, KZGProof, ProductRelationProof};
+use crate::util::poseidon::secure_config;
+use crate::util::secret::{wipe, Secret};
+use crate::util::{PoseidonTranscript, Transcript as _, VerifierChallenge};
 
 use super::prover::prover;
 use super::verifier::verifier;
 use super::{KZGCommitment, KZGSystem, CRS};
 
+/// The commitment to a polynomial with a proof of its evaluation, as `commit_and_prove` returns them.
+pub type CommittedProof = (KZGCommitment<Fr, G1>, KZGProof<Fr, G1>);
+
 pub struct KZGVerifierChallenger {
     poseidon_config: PoseidonConfig<Fr>,
 }
@@ -26,7 +33,8 @@ impl KZGSystem<Fr, G1, G2> for KZGSystemImpl {
     type E = Bls12_381;
     type Poly = DensePolynomial<Fr>;
 
-    fn prove(&self, polynomial: &Self::Poly) -> KZGProof<Fr, G1> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "kzg::prove", skip_all, fields(coefficients = polynomial.coeffs.len())))]
+    fn prove(&self, polynomial: &Self::Poly) -> Result<KZGProof<Fr, G1>, KZGError> {
         // Compute the commitment
         let commitment = prover::prover_commit(&self.crs, polynomial);
 
@@ -34,21 +42,167 @@ impl KZGSystem<Fr, G1, G2> for KZGSystemImpl {
         let challenge = self.verifier_challenge.generate_challenge(&KZGCommitment { value: commitment });
 
         // Compute the proof
-        prover::prover_open(&self.crs, polynomial, &challenge, &commitment)
+        prover::try_prover_open(&self.crs, polynomial, &challenge, &commitment)
     }
 
-    fn verify(&self, proof: KZGProof<Fr, G1>) -> bool {
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "kzg::verify", skip_all))]
+    fn verify(&self, proof: KZGProof<Fr, G1>) -> Result<bool, KZGError> {
+        // Reject points outside the prime-order subgroup before pairing them
+        if ![proof.commitment, proof.witness, proof.challenge_evaluation].iter().all(is_in_prime_order_subgroup) {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("kzg proof holds a point outside the prime-order subgroup");
+            return Err(KZGError::MalformedPoint);
+        }
+
         // Generate the challenge
         let challenge = proof.challenge;
 
         // Verify that the challenge was generated correctly
         if !self.verifier_challenge.verify_challenge_generation(&KZGCommitment { value: proof.commitment }, &challenge) {
-            return false;
+            #[cfg(feature = "tracing")]
+            tracing::debug!("kzg challenge not derived from the commitment");
+            return Ok(false);
+        }
+
+        // A revealed value must be the one in the exponent
+        if let Some(value) = proof.value {
+            if self.crs.g1_powers[0] * value != proof.challenge_evaluation {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("kzg revealed value differs from the one in the exponent");
+                return Ok(false);
+            }
         }
 
-        println!("Challenge: {:?}", challenge);
         // Verify the proof
-        verifier::verify::<Bls12_381>(proof, challenge, self.g2, self.g2_s)
+        Ok(verifier::verify::<Bls12_381>(proof, challenge, self.g2, self.g2_s))
+    }
+}
+
+/// Whether the point is on the curve and in the subgroup of order r, rather than in one of the
+/// small-order cosets the cofactor of G1 leaves.
+pub(crate) fn is_in_prime_order_subgroup(point: &G1) -> bool {
+    let affine: G1Affine = point.into_affine();
+    affine.is_on_curve() && affine.is_in_correct_subgroup_assuming_on_curve()
+}
+
+impl KZGSystemImpl {
+    /// Runs a trusted setup for polynomials of degree at most `degree`: samples the secret s and
+    /// the generators, computes the powers of s in G1 and [s]G₂, then zeroizes s before
+    /// returning, as anyone who learns it can open commitments to any value.
+    pub fn setup<R: Rng>(degree: usize, verifier_challenge: KZGVerifierChallenger, rng: &mut R) -> Self {
+        let mut s = Fr::rand(rng);
+        let g1 = G1::rand(rng);
+        let g2 = G2::rand(rng);
+
+        let mut power = Secret::new(Fr::one());
+        let g1_powers = (0..=degree)
+            .map(|_| {
+                let point = g1 * *power;
+                *power *= s;
+                point
+            })
+            .collect();
+        let g2_s = g2 * s;
+        wipe(&mut s);
+
+        KZGSystemImpl {
+            crs: CRS { g1_powers },
+            degree,
+            g2,
+            g2_s,
+            verifier_challenge,
+        }
+    }
+
+    /// Proves an evaluation of the polynomial, also returning its commitment on its own.
+    pub fn commit_and_prove(&self, polynomial: &DensePolynomial<Fr>) -> Result<CommittedProof, KZGError> {
+        let proof = self.prove(polynomial)?;
+        Ok((KZGCommitment { value: proof.commitment }, proof))
+    }
+
+    /// Gathers openings of any polynomials at any points, e.g. made with `try_prover_open`, to be
+    /// checked together by `verify_aggregate`. This only copies them with `to_vec()`: the aggregate
+    /// is no smaller than its inputs and nothing in it is folded, so it saves the verifier
+    /// pairings, not proof size or work a circuit would have to redo.
+    pub fn aggregate_proofs(&self, proofs: &[KZGProof<Fr, G1>]) -> AggregateKZGProof<Fr, G1> {
+        AggregateKZGProof { proofs: proofs.to_vec() }
+    }
+
+    /// Verifies every opening of the aggregate at its own point with two pairings, combining them
+    /// with the powers of a challenge drawn from all of them. The points are taken as given rather
+    /// than rederived from the commitments as in `verify`.
+    pub fn verify_aggregate(&self, aggregate: &AggregateKZGProof<Fr, G1>) -> bool {
+        let g1 = self.crs.g1_powers[0];
+        let well_formed = aggregate.proofs.iter().all(|proof| {
+            [proof.commitment, proof.witness, proof.challenge_evaluation].iter().all(is_in_prime_order_subgroup)
+                && proof.value.is_none_or(|value| g1 * value == proof.challenge_evaluation)
+        });
+        if !well_formed {
+            return false;
+        }
+
+        let rho = self.verifier_challenge.aggregation_challenge(&aggregate.proofs);
+        let randomness: Vec<Fr> = std::iter::successors(Some(Fr::one()), |power| Some(*power * rho))
+            .take(aggregate.proofs.len())
+            .collect();
+        verifier::verify_aggregate::<Bls12_381>(&aggregate.proofs, &randomness, self.g2, self.g2_s)
+    }
+
+    /// Verifies that the two committed polynomials of the proof take the same value at z,
+    /// rejecting points outside the prime-order subgroup as `verify` does.
+    pub fn verify_equal_opening(&self, proof: &EqualOpeningProof<Fr, G1>, z: Fr) -> Result<bool, KZGError> {
+        if ![proof.commitment_f, proof.commitment_g, proof.witness].iter().all(is_in_prime_order_subgroup) {
+            return Err(KZGError::MalformedPoint);
+        }
+
+        Ok(verifier::verify_equal_opening::<Bls12_381>(proof, z, self.g2, self.g2_s))
+    }
+
+    /// Commits to the polynomials and opens their combination at z with coefficients drawn from the
+    /// commitments and z, see `FoldedOpeningProof`. Returns the commitments with the proof.
+    pub fn prove_folded_opening(&self, polynomials: &[DensePolynomial<Fr>], z: Fr) -> Result<(Vec<G1>, FoldedOpeningProof<Fr, G1>), KZGError> {
+        let commitments: Vec<G1> = polynomials.iter().map(|polynomial| prover::prover_commit(&self.crs, polynomial)).collect();
+        let r_coeffs = self.verifier_challenge.folding_coefficients(&commitments, z);
+        let proof = prover::prove_folded_opening(&self.crs, polynomials, &r_coeffs, &z)?;
+        Ok((commitments, proof))
+    }
+
+    /// Verifies an opening of the combination of the committed polynomials, rederiving the
+    /// coefficients from the commitments and the point of the proof.
+    pub fn verify_folded_opening(&self, commitments: &[G1], proof: &FoldedOpeningProof<Fr, G1>) -> bool {
+        if !commitments.iter().chain([&proof.witness]).all(is_in_prime_order_subgroup) {
+            return false;
+        }
+        let r_coeffs = self.verifier_challenge.folding_coefficients(commitments, proof.challenge);
+        verifier::verify_folded_opening::<Bls12_381>(commitments, &r_coeffs, proof, self.crs.g1_powers[0], self.g2, self.g2_s)
+    }
+
+    /// Proves f = g · h by opening the three polynomials at a point derived from their commitments.
+    /// Fails if the CRS does not support the degree of one of them.
+    pub fn prove_product_relation(
+        &self,
+        f: &DensePolynomial<Fr>,
+        g: &DensePolynomial<Fr>,
+        h: &DensePolynomial<Fr>,
+    ) -> Result<ProductRelationProof<Fr, G1>, KZGError> {
+        let commitments = [f, g, h].map(|polynomial| prover::prover_commit(&self.crs, polynomial));
+        let z = self.verifier_challenge.hash_commitments(&commitments);
+
+        prover::prove_product_relation(&self.crs, f, g, h, &z)
+    }
+
+    /// Verifies a proof of f = g · h, rederiving the point from the three commitments.
+    pub fn verify_product_relation(&self, proof: ProductRelationProof<Fr, G1>) -> bool {
+        let z = self.verifier_challenge.hash_commitments(&[proof.f.commitment, proof.g.commitment, proof.h.commitment]);
+
+        verifier::verify_product_relation::<Bls12_381>(proof, z, self.crs.g1_powers[0], self.g2, self.g2_s)
+    }
+}
+
+/// A Poseidon sponge with `secure_config` of rate 2.
+impl Default for KZGVerifierChallenger {
+    fn default() -> Self {
+        Self::new(secure_config(2, 1))
     }
 }
 
@@ -58,11 +212,61 @@ impl KZGVerifierChallenger {
     }
 
     fn hash_commitment(&self, commitment: &G1) -> Fr {
-        let mut sponge = PoseidonSponge::new(&self.poseidon_config);
-        let affine = commitment.into_affine();
-        sponge.absorb(&affine.x());
-        sponge.absorb(&affine.y());
-        sponge.squeeze_field_elements(1)[0]
+        self.hash_commitments(std::slice::from_ref(commitment))
+    }
+
+    /// Absorbs the commitment into a transcript shared with the rest of a larger protocol, such as
+    /// PLONK, and draws the challenge from it, so that it also depends on the wire commitments and
+    /// everything else the transcript absorbed before. The challenge becomes the new state.
+    pub fn generate_challenge_in_transcript(&self, transcript: &mut Transcript<Fr>, commitment: &KZGCommitment<Fr, G1>) -> Fr {
+        transcript.absorb_with(|state| self.hash_commitments_after(std::slice::from_ref(state), std::slice::from_ref(&commitment.value)));
+        *transcript.state()
+    }
+
+    /// Absorbs every opening in order, its witness included, and squeezes the challenge whose
+    /// powers combine them in `verify_aggregate`.
+    fn aggregation_challenge(&self, proofs: &[KZGProof<Fr, G1>]) -> Fr {
+        let mut transcript = PoseidonTranscript::new(&self.poseidon_config);
+        for proof in proofs {
+            transcript.absorb_group(b"kzg/aggregate/commitment", &proof.commitment);
+            transcript.absorb_field(b"kzg/aggregate/point", &proof.challenge);
+            transcript.absorb_group(b"kzg/aggregate/evaluation", &proof.challenge_evaluation);
+            transcript.absorb_group(b"kzg/aggregate/witness", &proof.witness);
+        }
+        transcript.squeeze_nonzero_field(b"kzg/aggregate/challenge")
+    }
+
+    /// The coefficients `prove_folded_opening` combines the committed polynomials with: the powers
+    /// 1, ρ, ρ², ... of a challenge drawn from every commitment in order and the point.
+    pub fn folding_coefficients(&self, commitments: &[G1], z: Fr) -> Vec<Fr> {
+        let mut transcript = PoseidonTranscript::new(&self.poseidon_config);
+        for commitment in commitments {
+            transcript.absorb_group(b"kzg/fold/commitment", commitment);
+        }
+        transcript.absorb_field(b"kzg/fold/point", &z);
+        let rho = transcript.squeeze_nonzero_field(b"kzg/fold/challenge");
+        std::iter::successors(Some(Fr::one()), |power| Some(*power * rho))
+            .take(commitments.len())
+            .collect()
+    }
+
+    /// Absorbs the commitments in order and squeezes a single challenge.
+    fn hash_commitments(&self, commitments: &[G1]) -> Fr {
+        self.hash_commitments_after(&[], commitments)
+    }
+
+    /// Absorbs the prefix, then the commitments in order, into a fresh transcript and squeezes a
+    /// single challenge. The commitments are absorbed as their compressed encodings, as their
+    /// coordinates live in the base field rather than in the sponge's.
+    pub(super) fn hash_commitments_after(&self, prefix: &[Fr], commitments: &[G1]) -> Fr {
+        let mut transcript = PoseidonTranscript::new(&self.poseidon_config);
+        for element in prefix {
+            transcript.absorb_field(b"kzg/prefix", element);
+        }
+        for commitment in commitments {
+            transcript.absorb_group(b"kzg/commitment", commitment);
+        }
+        transcript.squeeze_field(b"kzg/challenge")
     }
 }
 
@@ -1,6 +1,7 @@
 #![allow(unused_imports)]
+#![allow(dead_code)]
 
-use ark_ec::{pairing::Pairing, Group};
+use ark_ec::{pairing::Pairing, Group, VariableBaseMSM};
 use ark_ff::Field;
 use ark_poly::Polynomial;
 
@@ -16,7 +17,7 @@ impl<F: Field, G: Group<ScalarField = F>> CRSGenerator<F, G> for TestCRSGenerato
     fn generate(&self, degree: usize) -> CRS<G> {
         let g1_powers: Vec<G> = (0..=degree)
             .map(|i| {
-                let exponent = self.point.pow(&[i as u64]);
+                let exponent = self.point.pow([i as u64]);
                 self.generator.mul(exponent)
             })
             .collect();
@@ -34,16 +35,45 @@ mod tests {
     use super::*;
     use ark_bls12_381::{Bls12_381, Fr as F, G1Projective as G, G1Projective as G1, G2Projective as G2};
     use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
-    use ark_ff::UniformRand;
-    use ark_poly::{polynomial::univariate::DensePolynomial, DenseUVPolynomial};
+    use crate::util::poseidon::secure_config;
+    use ark_ff::{UniformRand, Zero};
+    use ark_poly::{polynomial::univariate::DensePolynomial, DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain};
+    use ark_serialize::CanonicalDeserialize;
     use ark_std::rand::thread_rng;
+    use crate::fri::transcript::Transcript;
+    use crate::util::TranscriptDigest;
+    use crate::kzg::{BivariateCRS, BivariateCRSGenerator, CommitError, KZGCommitment, KZGError, ShiftError};
+
+    /// A struct for testing purposes that implements the BivariateCRSGenerator trait
+    pub struct TestBivariateCRSGenerator<F: Field, G: Group<ScalarField = F>> {
+        pub generator: G,
+        pub s: F,
+        pub t: F,
+    }
+
+    impl<F: Field, G: Group<ScalarField = F>> BivariateCRSGenerator<F, G> for TestBivariateCRSGenerator<F, G> {
+        fn generate(&self, degree_x: usize, degree_y: usize) -> BivariateCRS<G> {
+            let g1_powers: Vec<Vec<G>> = (0..=degree_x)
+                .map(|i| {
+                    (0..=degree_y)
+                        .map(|j| {
+                            let exponent = self.s.pow([i as u64]) * self.t.pow([j as u64]);
+                            self.generator.mul(exponent)
+                        })
+                        .collect()
+                })
+                .collect();
+
+            BivariateCRS { g1_powers }
+        }
+    }
 
     fn verify_prover_commit_equals_to_g_pow_valuation_point<F, G, P>(
         polynomial: &P,
         evaluation_point: F,
     ) where
         F: Field,
-        G: Group<ScalarField = F>,
+        G: Group<ScalarField = F> + VariableBaseMSM,
         P: DenseUVPolynomial<F>,
     {
         // Generate a random generator
@@ -93,14 +123,14 @@ mod tests {
         );
     }
 
-    fn verify_prover_open_equals_to_g_pow_quotient_polynomial<F, G: PartialEq>(
+    fn verify_prover_open_equals_to_g_pow_quotient_polynomial<F, G>(
         polynomial: &DensePolynomial<F>,
         quotient_polynomial: &DensePolynomial<F>,
         evaluation_point: F,
         challenge_point: F,
     ) where
         F: Field,
-        G: Group<ScalarField = F>,
+        G: Group<ScalarField = F> + VariableBaseMSM + PartialEq,
     {
         let mut rng = thread_rng();
         let generator = G::rand(&mut rng);
@@ -263,7 +293,7 @@ mod tests {
             let g2_s = g2 * s;
         
             // Create a PoseidonConfig for the verifier challenger
-            let poseidon_config = PoseidonConfig::<F>::new(8, 57, 5, vec![vec![F::from(1u64); 3]; 3], vec![vec![F::from(0u64); 3]; 65], 2, 1);
+            let poseidon_config = secure_config::<F>(2, 1);
             let verifier_challenger = KZGVerifierChallenger::new(poseidon_config);
         
             let system = KZGSystemImpl {
@@ -278,12 +308,596 @@ mod tests {
             let polynomial = DensePolynomial::<F>::rand(degree, rng);
         
             // Prove
-            let proof = system.prove(&polynomial);
+            let proof = system.prove(&polynomial).unwrap();
         
             // Verify
             let result = system.verify(proof);
         
-            assert!(result, "Verification should succeed for a valid proof");
+            assert_eq!(result, Ok(true), "Verification should succeed for a valid proof");
+        }
+
+
+    #[test]
+    fn test_openings_differ_across_challenges() {
+        let mut rng = thread_rng();
+        let crs = TestCRSGenerator { generator: G1::rand(&mut rng), point: F::rand(&mut rng) }.generate(4);
+        let polynomial = DensePolynomial::<F>::rand(4, &mut rng);
+        let commitment = prover::prover_commit::<F, G1, DensePolynomial<F>>(&crs, &polynomial);
+
+        let first = prover::prover_open(&crs, &polynomial, &F::from(2u64), &commitment);
+        let second = prover::prover_open(&crs, &polynomial, &F::from(3u64), &commitment);
+
+        assert_eq!(first.opening(), (F::from(2u64), Some(polynomial.evaluate(&F::from(2u64)))));
+        assert_eq!(second.opening(), (F::from(3u64), Some(polynomial.evaluate(&F::from(3u64)))));
+        assert_ne!(first.opening(), second.opening());
+    }
+
+    #[test]
+    fn test_streaming_commit_matches_batch_commit() {
+        let mut rng = thread_rng();
+        let crs = TestCRSGenerator { generator: G1::rand(&mut rng), point: F::rand(&mut rng) }.generate(1000);
+        let polynomial = DensePolynomial::<F>::rand(1000, &mut rng);
+
+        let streamed = prover::prover_commit_streaming(&crs, polynomial.coeffs().iter().copied());
+        assert_eq!(streamed, Ok(prover::prover_commit(&crs, &polynomial)));
+
+        // Coefficients generated lazily, one more than the CRS supports
+        let too_long = (0..1002u64).map(F::from);
+        assert_eq!(
+            prover::prover_commit_streaming::<F, G1>(&crs, too_long),
+            Err(CommitError::TooManyCoefficients { max_degree: 1000 })
+        );
+
+        // An empty CRS commits to no coefficient at all
+        let empty = CRS::<G1> { g1_powers: Vec::new() };
+        assert_eq!(prover::prover_commit_streaming::<F, G1>(&empty, std::iter::empty()), Ok(G1::zero()));
+        assert_eq!(
+            prover::prover_commit_streaming::<F, G1>(&empty, std::iter::once(F::from(1u64))),
+            Err(CommitError::TooManyCoefficients { max_degree: 0 })
+        );
+    }
+
+    #[test]
+    fn test_commit_with_bytes_round_trips() {
+        let mut rng = thread_rng();
+        let crs = TestCRSGenerator { generator: G1::rand(&mut rng), point: F::rand(&mut rng) }.generate(16);
+        let polynomial = DensePolynomial::<F>::rand(16, &mut rng);
+
+        let (commitment, bytes) = prover::prover_commit_with_bytes(&crs, &polynomial);
+        assert_eq!(commitment, prover::prover_commit(&crs, &polynomial));
+        assert_eq!(G1::deserialize_compressed(&bytes[..]).unwrap(), commitment);
+    }
+
+    #[test]
+    fn test_commit_shifted_matches_multiplying_by_x() {
+        let mut rng = thread_rng();
+        let crs = TestCRSGenerator { generator: G1::rand(&mut rng), point: F::rand(&mut rng) }.generate(6);
+        let polynomial = DensePolynomial::<F>::rand(5, &mut rng);
+        let x = DensePolynomial::from_coefficients_vec(vec![F::from(0u64), F::from(1u64)]);
+        let x_times_f = &polynomial * &x;
+
+        let expected = prover::prover_commit::<F, G1, DensePolynomial<F>>(&crs, &x_times_f);
+        assert_eq!(prover::commit_shifted(&crs, &polynomial, 1), Ok(expected));
+        assert_eq!(prover::commit_shifted(&crs, &x_times_f, -1), Ok(prover::prover_commit(&crs, &polynomial)));
+
+        // f has a non-zero constant term, so f(x) / x needs s^-1
+        assert_eq!(prover::commit_shifted(&crs, &polynomial, -1), Err(ShiftError::NegativePower { shift: -1 }));
+        assert_eq!(
+            prover::commit_shifted(&crs, &polynomial, 2),
+            Err(ShiftError::DegreeTooLarge { degree: 7, max_degree: 6 })
+        );
+    }
+
+    /// A system whose CRS and verification key share the same secret.
+    fn setup_system(degree: usize) -> KZGSystemImpl {
+        let mut rng = thread_rng();
+        let s = F::rand(&mut rng);
+        let g2 = G2::rand(&mut rng);
+        let poseidon_config = secure_config::<F>(2, 1);
+        KZGSystemImpl {
+            crs: TestCRSGenerator { generator: G1::rand(&mut rng), point: s }.generate(degree),
+            degree,
+            g2,
+            g2_s: g2 * s,
+            verifier_challenge: KZGVerifierChallenger::new(poseidon_config),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_proofs_at_distinct_points() {
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+        let proofs: Vec<_> = (0..10u64)
+            .map(|i| {
+                let polynomial = DensePolynomial::<F>::rand(8, &mut rng);
+                let commitment = prover::prover_commit(&system.crs, &polynomial);
+                prover::try_prover_open(&system.crs, &polynomial, &F::from(100 + i), &commitment).unwrap()
+            })
+            .collect();
+        let aggregate = system.aggregate_proofs(&proofs);
+        assert!(system.verify_aggregate(&aggregate));
+
+        let mut wrong_witness = aggregate.clone();
+        wrong_witness.proofs[4].witness += G1::generator();
+        assert!(!system.verify_aggregate(&wrong_witness));
+        let mut wrong_value = aggregate.clone();
+        wrong_value.proofs[7].challenge_evaluation += system.crs.g1_powers[0];
+        wrong_value.proofs[7].value = None;
+        assert!(!system.verify_aggregate(&wrong_value));
+        let mut swapped_points = aggregate.clone();
+        swapped_points.proofs.swap(0, 1);
+        swapped_points.proofs[0].challenge = aggregate.proofs[0].challenge;
+        assert!(!system.verify_aggregate(&swapped_points));
+        assert!(!system.verify_aggregate(&system.aggregate_proofs(&[])));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_commit_matches_serial() {
+        use ark_std::rand::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(1464);
+        let crs = TestCRSGenerator { generator: G1::rand(&mut rng), point: F::rand(&mut rng) }.generate(1023);
+        let polynomial = DensePolynomial::<F>::rand(1023, &mut rng);
+
+        let serial = prover::prover_commit_streaming(&crs, polynomial.coeffs.iter().copied()).unwrap();
+        assert_eq!(prover::prover_commit(&crs, &polynomial), serial);
+    }
+
+    #[test]
+    fn test_prove_verify_equal_opening() {
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+        let z = F::from(4u64);
+
+        // g = f + (x - 4) r(x) agrees with f at 4 only
+        let f = DensePolynomial::<F>::rand(8, &mut rng);
+        let x_minus_z = DensePolynomial::from_coefficients_vec(vec![-z, F::from(1u64)]);
+        let g = &f + &(&x_minus_z * &DensePolynomial::rand(6, &mut rng));
+        assert_ne!(f, g);
+
+        let proof = prover::prove_equal_opening(&system.crs, &f, &g, &z).unwrap();
+        assert!(verifier::verify_equal_opening::<Bls12_381>(&proof, z, system.g2, system.g2_s));
+        assert_eq!(system.verify_equal_opening(&proof, z), Ok(true));
+        assert_eq!(proof.commitment_f, prover::prover_commit(&system.crs, &f));
+
+        // They disagree elsewhere, so the proof cannot be made, nor moved to another point
+        let elsewhere = F::from(5u64);
+        assert_eq!(prover::prove_equal_opening(&system.crs, &f, &g, &elsewhere).unwrap_err(), KZGError::NonZeroRemainder);
+        assert!(!verifier::verify_equal_opening::<Bls12_381>(&proof, elsewhere, system.g2, system.g2_s));
+        let mut moved = proof.clone();
+        moved.challenge = elsewhere;
+        assert!(!verifier::verify_equal_opening::<Bls12_381>(&moved, z, system.g2, system.g2_s));
+        assert!(!verifier::verify_equal_opening::<Bls12_381>(&moved, elsewhere, system.g2, system.g2_s));
+        let mut other = proof.clone();
+        other.commitment_g = prover::prover_commit(&system.crs, &DensePolynomial::<F>::rand(8, &mut rng));
+        assert!(!verifier::verify_equal_opening::<Bls12_381>(&other, z, system.g2, system.g2_s));
+
+        // A witness outside the prime-order subgroup is rejected before any pairing
+        let off_subgroup = (0u64..)
+            .filter_map(|x| ark_bls12_381::G1Affine::get_point_from_x_unchecked(ark_bls12_381::Fq::from(x), false))
+            .find(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+            .unwrap();
+        let mut malformed = proof.clone();
+        malformed.witness = off_subgroup.into();
+        assert_eq!(system.verify_equal_opening(&malformed, z), Err(KZGError::MalformedPoint));
+    }
+
+    #[test]
+    fn test_prove_verify_folded_opening() {
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+        let z = F::from(2u64);
+        let polynomials: Vec<DensePolynomial<F>> = (0..4).map(|i| DensePolynomial::rand(5 + i, &mut rng)).collect();
+
+        let (commitments, proof) = system.prove_folded_opening(&polynomials, z).unwrap();
+        assert!(system.verify_folded_opening(&commitments, &proof));
+
+        // The value is the combination of the values with the coefficients from the transcript
+        let r_coeffs = system.verifier_challenge.folding_coefficients(&commitments, z);
+        let value: F = polynomials.iter().zip(&r_coeffs).map(|(polynomial, r)| *r * polynomial.evaluate(&z)).sum();
+        assert_eq!(proof.value, value);
+        let g1 = system.crs.g1_powers[0];
+        assert!(verifier::verify_folded_opening::<Bls12_381>(&commitments, &r_coeffs, &proof, g1, system.g2, system.g2_s));
+
+        // Other coefficients, a swapped commitment, another value or another point are rejected
+        let ones = vec![F::from(1u64); 4];
+        assert!(!verifier::verify_folded_opening::<Bls12_381>(&commitments, &ones, &proof, g1, system.g2, system.g2_s));
+        let mut swapped = commitments.clone();
+        swapped.swap(0, 1);
+        assert!(!system.verify_folded_opening(&swapped, &proof));
+        let mut wrong_value = proof.clone();
+        wrong_value.value += F::from(1u64);
+        assert!(!system.verify_folded_opening(&commitments, &wrong_value));
+        let mut moved = proof.clone();
+        moved.challenge = F::from(3u64);
+        assert!(!system.verify_folded_opening(&commitments, &moved));
+
+        assert_eq!(
+            prover::prove_folded_opening(&system.crs, &polynomials, &r_coeffs[..3], &z).unwrap_err(),
+            KZGError::CoefficientCountMismatch { polynomials: 4, coefficients: 3 }
+        );
+    }
+
+    #[test]
+    fn test_setup_proves_and_verifies() {
+        let mut rng = thread_rng();
+        let poseidon_config = secure_config::<F>(2, 1);
+        let system = KZGSystemImpl::setup(8, KZGVerifierChallenger::new(poseidon_config), &mut rng);
+        assert_eq!(system.crs.g1_powers.len(), 9);
+
+        let proof = system.prove(&DensePolynomial::<F>::rand(8, &mut rng)).unwrap();
+        assert_eq!(system.verify(proof), Ok(true));
+        assert!(system.prove(&DensePolynomial::<F>::rand(9, &mut rng)).is_err());
+    }
+
+    #[test]
+    fn test_verify_rejects_points_outside_subgroup() {
+        let mut rng = thread_rng();
+        let system = setup_system(4);
+        let proof = system.prove(&DensePolynomial::<F>::rand(4, &mut rng)).unwrap();
+        assert_eq!(system.verify(proof.clone()), Ok(true));
+
+        // A point of the curve whose order is not r, found by trying small x coordinates
+        let off_subgroup = (0u64..)
+            .filter_map(|x| ark_bls12_381::G1Affine::get_point_from_x_unchecked(ark_bls12_381::Fq::from(x), false))
+            .find(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+            .unwrap();
+        assert!(off_subgroup.is_on_curve());
+
+        let mut bad_witness = proof.clone();
+        bad_witness.witness = off_subgroup.into();
+        assert_eq!(system.verify(bad_witness), Err(KZGError::MalformedPoint));
+
+        let mut bad_evaluation = proof.clone();
+        bad_evaluation.challenge_evaluation += G1::from(off_subgroup);
+        bad_evaluation.value = None;
+        assert_eq!(system.verify(bad_evaluation), Err(KZGError::MalformedPoint));
+    }
+
+    #[test]
+    fn test_multi_pairing_agrees_with_two_pairings() {
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+        let polynomial = DensePolynomial::<F>::rand(8, &mut rng);
+        let proof = system.prove(&polynomial).unwrap();
+
+        let mut wrong_witness = proof.clone();
+        wrong_witness.witness += system.crs.g1_powers[0];
+        let mut wrong_evaluation = proof.clone();
+        wrong_evaluation.challenge_evaluation = system.crs.g1_powers[0] * F::rand(&mut rng);
+
+        for (proof, valid) in [(proof, true), (wrong_witness, false), (wrong_evaluation, false)] {
+            let challenge = proof.challenge;
+            let two_pairings = verifier::verify_with_two_pairings::<Bls12_381>(proof.clone(), challenge, system.g2, system.g2_s);
+            let multi_pairing = verifier::verify::<Bls12_381>(proof, challenge, system.g2, system.g2_s);
+            assert_eq!(two_pairings, valid);
+            assert_eq!(multi_pairing, two_pairings);
         }
+    }
+
+    #[test]
+    fn test_verify_parts_agrees_with_verify() {
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+        let g1 = system.crs.g1_powers[0];
+        let proof = system.prove(&DensePolynomial::<F>::rand(8, &mut rng)).unwrap();
+        let value = proof.value.unwrap();
+        let (commitment, challenge, witness) = (proof.commitment, proof.challenge, proof.witness);
+
+        assert!(verifier::verify::<Bls12_381>(proof, challenge, system.g2, system.g2_s));
+        assert!(verifier::verify_parts::<Bls12_381>(commitment, challenge, value, witness, g1, system.g2, system.g2_s));
+
+        // Any part out of place fails as the bundled proof would
+        let one = F::from(1u64);
+        assert!(!verifier::verify_parts::<Bls12_381>(commitment, challenge, value + one, witness, g1, system.g2, system.g2_s));
+        assert!(!verifier::verify_parts::<Bls12_381>(commitment, challenge + one, value, witness, g1, system.g2, system.g2_s));
+        assert!(!verifier::verify_parts::<Bls12_381>(commitment + g1, challenge, value, witness, g1, system.g2, system.g2_s));
+        assert!(!verifier::verify_parts::<Bls12_381>(commitment, challenge, value, witness + g1, g1, system.g2, system.g2_s));
+    }
+
+    #[test]
+    fn test_commit_and_prove() {
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+        let polynomial = DensePolynomial::<F>::rand(8, &mut rng);
+
+        let (commitment, proof) = system.commit_and_prove(&polynomial).unwrap();
+        assert_eq!(commitment.value, proof.commitment);
+        assert_eq!(commitment.value, prover::prover_commit(&system.crs, &polynomial));
+        assert_eq!(system.verify(proof), Ok(true));
+    }
+
+    #[test]
+    fn test_challenge_is_drawn_from_the_commitment_transcript() {
+        use crate::util::{PoseidonTranscript, Transcript};
+
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+        let proof = system.prove(&DensePolynomial::<F>::rand(8, &mut rng)).unwrap();
+
+        // The verifier rebuilds the prover's transcript from the commitment alone
+        let poseidon_config = secure_config::<F>(2, 1);
+        let mut transcript = PoseidonTranscript::new(&poseidon_config);
+        transcript.absorb_group(b"kzg/commitment", &proof.commitment);
+        assert_eq!(transcript.squeeze_field(b"kzg/challenge"), proof.challenge);
+        assert_eq!(system.verify(proof), Ok(true));
+    }
+
+    #[test]
+    fn test_prove_rejects_oversized_polynomials() {
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+
+        // The commitment would silently drop the coefficients past the CRS
+        let oversized = DensePolynomial::<F>::rand(9, &mut rng);
+        assert_eq!(system.prove(&oversized).unwrap_err(), KZGError::DegreeTooLarge { degree: 9, max_degree: 8 });
+        assert!(matches!(system.commit_and_prove(&oversized), Err(KZGError::DegreeTooLarge { degree: 9, max_degree: 8 })));
+
+        // A wrong opening is not an error, just invalid
+        let mut proof = system.prove(&DensePolynomial::<F>::rand(8, &mut rng)).unwrap();
+        proof.witness += system.crs.g1_powers[1];
+        assert_eq!(system.verify(proof), Ok(false));
+    }
+
+    #[test]
+    fn test_transcript_digest_is_stable() {
+        let crs = TestCRSGenerator { generator: G1::generator(), point: F::from(5u64) }.generate(4);
+        let polynomial = DensePolynomial::from_coefficients_vec((1..=5u64).map(F::from).collect());
+        let proof = || prover::prover_open(&crs, &polynomial, &F::from(2u64), &prover::prover_commit(&crs, &polynomial));
+
+        let digest = proof().transcript_digest();
+        assert_eq!(digest, proof().transcript_digest());
+        let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(hex, "3851854eba22193b0fd87836099f099f77cc1a6e33d8c095a15ee5d0b46ff1c5");
+
+        let mut other = proof();
+        other.challenge += F::from(1u64);
+        assert_ne!(other.transcript_digest(), digest);
+        let mut other = proof();
+        other.witness += G1::generator();
+        assert_ne!(other.transcript_digest(), digest);
+        let mut other = proof();
+        other.value = None;
+        assert_ne!(other.transcript_digest(), digest);
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_prove_verify_at_zero() {
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+        let polynomial = DensePolynomial::<F>::rand(8, &mut rng);
+
+        let proof = prover::prove_at_zero(&system.crs, &polynomial);
+        assert_eq!(proof.opening(), (F::zero(), Some(polynomial.coeffs[0])));
+        assert_eq!(proof.witness, prover::prover_open(&system.crs, &polynomial, &F::zero(), &proof.commitment).witness);
+        assert!(verifier::verify_at_zero::<Bls12_381>(proof.clone(), system.crs.g1_powers[0], system.g2, system.g2_s));
+        assert!(verifier::verify::<Bls12_381>(proof.clone(), F::zero(), system.g2, system.g2_s));
+
+        let mut wrong_value = proof.clone();
+        wrong_value.challenge_evaluation += system.crs.g1_powers[0];
+        assert!(!verifier::verify_at_zero::<Bls12_381>(wrong_value, system.crs.g1_powers[0], system.g2, system.g2_s));
+
+        // The revealed value is bound to the evaluation the pairing checks
+        let mut wrong_revealed = proof.clone();
+        wrong_revealed.value = Some(polynomial.coeffs[0] + F::from(1u64));
+        assert!(!verifier::verify_at_zero::<Bls12_381>(wrong_revealed, system.crs.g1_powers[0], system.g2, system.g2_s));
+        let mut hidden = proof.clone();
+        hidden.value = None;
+        assert!(verifier::verify_at_zero::<Bls12_381>(hidden, system.crs.g1_powers[0], system.g2, system.g2_s));
+
+        // An opening at another point is not one at zero
+        let elsewhere = prover::prover_open(&system.crs, &polynomial, &F::from(2u64), &proof.commitment);
+        assert!(!verifier::verify_at_zero::<Bls12_381>(elsewhere, system.crs.g1_powers[0], system.g2, system.g2_s));
+    }
+
+    #[test]
+    fn test_challenge_in_transcript_depends_on_prior_state() {
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+        let (commitment, _) = system.commit_and_prove(&DensePolynomial::<F>::rand(8, &mut rng)).unwrap();
+        let challenger = &system.verifier_challenge;
+
+        // The same commitment after different wire commitments gets different challenges
+        let wires = [G1::rand(&mut rng), G1::rand(&mut rng)].map(|value| KZGCommitment { value });
+        let mut transcript = Transcript::from_state(F::from(0u64));
+        let mut other_transcript = transcript.clone();
+        challenger.generate_challenge_in_transcript(&mut transcript, &wires[0]);
+        challenger.generate_challenge_in_transcript(&mut other_transcript, &wires[1]);
+        let challenge = challenger.generate_challenge_in_transcript(&mut transcript, &commitment);
+        assert_ne!(challenge, challenger.generate_challenge_in_transcript(&mut other_transcript, &commitment));
+        assert_ne!(challenge, challenger.generate_challenge(&commitment));
+        assert_eq!(*transcript.state(), challenge);
+
+        // Replaying the transcript gives the same challenge
+        let mut replay = Transcript::from_state(F::from(0u64));
+        challenger.generate_challenge_in_transcript(&mut replay, &wires[0]);
+        assert_eq!(challenger.generate_challenge_in_transcript(&mut replay, &commitment), challenge);
+
+        // and the standalone challenge is bound to the commitment too
+        assert_ne!(challenger.generate_challenge(&wires[0]), challenger.generate_challenge(&wires[1]));
+    }
+
+    #[test]
+    fn test_product_relation() {
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+
+        let g = DensePolynomial::<F>::rand(3, &mut rng);
+        let h = DensePolynomial::<F>::rand(4, &mut rng);
+        let f = &g * &h;
+        let proof = system.prove_product_relation(&f, &g, &h).unwrap();
+        assert_eq!(proof.f.challenge, proof.h.challenge);
+        assert!(system.verify_product_relation(proof.clone()));
+
+        let wrong_h = DensePolynomial::<F>::rand(4, &mut rng);
+        assert!(!system.verify_product_relation(system.prove_product_relation(&f, &g, &wrong_h).unwrap()));
+
+        // The point is bound to the commitments, so openings of the wrong h at the point of the
+        // honest proof do not verify either
+        let mut wrong_proof = proof;
+        let wrong_commitment = prover::prover_commit(&system.crs, &wrong_h);
+        wrong_proof.h = prover::prover_open(&system.crs, &wrong_h, &wrong_proof.h.challenge, &wrong_commitment);
+        assert!(!system.verify_product_relation(wrong_proof));
+
+        // A polynomial the CRS cannot commit to is refused rather than opened truncated
+        let max_degree = system.crs.g1_powers.len() - 1;
+        let too_large = DensePolynomial::<F>::rand(max_degree + 1, &mut rng);
+        assert_eq!(
+            system.prove_product_relation(&too_large, &g, &h).unwrap_err(),
+            KZGError::DegreeTooLarge { degree: max_degree + 1, max_degree }
+        );
+    }
+
+    #[test]
+    fn test_bivariate_prove_verify() {
+        let mut rng = thread_rng();
+
+        // f(x, y) = x * y + 1
+        let coeffs = vec![
+            vec![F::from(1u64), F::from(0u64)],
+            vec![F::from(0u64), F::from(1u64)],
+        ];
+
+        let s = F::rand(&mut rng);
+        let t = F::rand(&mut rng);
+        let g1 = G1::rand(&mut rng);
+        let g2 = G2::rand(&mut rng);
+        let crs = TestBivariateCRSGenerator { generator: g1, s, t }.generate(1, 1);
+
+        let commitment = prover::commit_bivariate(&crs, &coeffs).unwrap();
+        assert_eq!(commitment, g1 * (s * t + F::from(1u64)));
+
+        // f(2, 3) = 7
+        let proof = prover::open_bivariate(&crs, &coeffs, (F::from(2u64), F::from(3u64))).unwrap();
+        assert_eq!(proof.commitment, commitment);
+        assert_eq!(proof.challenge_evaluation, g1 * F::from(7u64));
+        assert!(verifier::verify_bivariate::<Bls12_381>(proof.clone(), g2, g2 * s, g2 * t));
+
+        // A wrong evaluation is rejected
+        let mut wrong_proof = proof;
+        wrong_proof.challenge_evaluation = g1 * F::from(8u64);
+        assert!(!verifier::verify_bivariate::<Bls12_381>(wrong_proof, g2, g2 * s, g2 * t));
+
+        // Degrees beyond the CRS in either variable are refused
+        let x_squared = vec![vec![F::from(0u64)], vec![F::from(0u64)], vec![F::from(1u64)]];
+        assert_eq!(prover::commit_bivariate(&crs, &x_squared), Err(KZGError::DegreeTooLarge { degree: 2, max_degree: 1 }));
+        let y_squared = vec![vec![F::from(0u64), F::from(0u64), F::from(1u64)]];
+        assert_eq!(
+            prover::open_bivariate(&crs, &y_squared, (F::from(2u64), F::from(3u64))).unwrap_err(),
+            KZGError::DegreeTooLarge { degree: 2, max_degree: 1 }
+        );
+    }
+
+    #[test]
+    fn test_prove_verify_permutation() {
+        use crate::error::Error;
+
+        let mut rng = thread_rng();
+        let system = setup_system(8);
+        let domain = GeneralEvaluationDomain::<F>::new(8).unwrap();
+        let sigma = [3, 0, 7, 5, 1, 6, 2, 4];
+
+        let f_evals: Vec<F> = (0..8).map(|_| F::rand(&mut rng)).collect();
+        let interpolate = |evals: &[F]| DensePolynomial::from_coefficients_vec(domain.ifft(evals));
+        let f = interpolate(&f_evals);
+        let mut g_evals: Vec<F> = sigma.iter().map(|&i| f_evals[i]).collect();
+
+        let proof = system.prove_permutation(&f, &interpolate(&g_evals), &sigma).unwrap();
+        assert!(system.verify_permutation(proof.clone(), &sigma));
+
+        // The proof is only for the permutation it was made with
+        assert!(!system.verify_permutation(proof, &[0, 3, 7, 5, 1, 6, 2, 4]));
+
+        // g repeats one value of f and misses another
+        g_evals[0] = g_evals[1];
+        let proof = system.prove_permutation(&f, &interpolate(&g_evals), &sigma).unwrap();
+        assert!(!system.verify_permutation(proof, &sigma));
+
+        // σ must permute a subgroup, whose size f and g and the CRS must fit
+        let g = interpolate(&g_evals);
+        let prove_err = |system: &KZGSystemImpl, f: &DensePolynomial<F>, sigma: &[usize]| system.prove_permutation(f, &g, sigma).unwrap_err();
+        assert_eq!(prove_err(&system, &f, &[0, 2, 1]), Error::KZG(KZGError::InvalidPermutation { size: 3 }));
+        assert_eq!(prove_err(&system, &f, &[0, 0, 7, 5, 1, 6, 2, 4]), Error::KZG(KZGError::InvalidPermutation { size: 8 }));
+        let too_large = DensePolynomial::<F>::rand(8, &mut rng);
+        assert_eq!(prove_err(&system, &too_large, &sigma), Error::KZG(KZGError::DegreeTooLarge { degree: 8, max_degree: 7 }));
+        assert_eq!(prove_err(&setup_system(6), &f, &sigma), Error::KZG(KZGError::DegreeTooLarge { degree: 7, max_degree: 6 }));
+    }
+
+    #[test]
+    fn test_kzg_and_pedersen_commitments_agree() {
+        use crate::bulletproofs::bls12_381::bls12_381_system;
+        use crate::bulletproofs::BulletproofGenerators;
+        use crate::kzg::pedersen::{pedersen_commitment, KZGPedersenProof};
+        use crate::BulletproofSystem;
+
+        let mut rng = thread_rng();
+        let system = setup_system(7);
+        let poseidon_config = secure_config::<F>(2, 1);
+        let bulletproofs = bls12_381_system(&poseidon_config);
+        let generators = BulletproofGenerators::<G1>::random(8, &mut rng);
+        let f = DensePolynomial::<F>::rand(7, &mut rng);
+
+        // A polynomial of lower degree is padded with zero coefficients
+        let proof = system.prove_kzg_pedersen_equality(&f, &generators, &bulletproofs).unwrap();
+        assert_eq!(proof.pedersen_commitment, pedersen_commitment(&f, &generators));
+        assert!(system.verify_kzg_pedersen_equality(proof, &generators, &bulletproofs));
+        let low = DensePolynomial::<F>::rand(2, &mut rng);
+        let proof = system.prove_kzg_pedersen_equality(&low, &generators, &bulletproofs).unwrap();
+        assert!(system.verify_kzg_pedersen_equality(proof, &generators, &bulletproofs));
+
+        // A Pedersen commitment to other coefficients, opened honestly at the challenge both
+        // commitments give, cannot open to the KZG value
+        let mut coeffs = f.coeffs.clone();
+        coeffs[3] += F::from(1u64);
+        let g = DensePolynomial::from_coefficients_vec(coeffs.clone());
+        let kzg_commitment = prover::prover_commit(&system.crs, &f);
+        let mismatched_commitment = pedersen_commitment(&g, &generators);
+        let zeta = system.verifier_challenge.hash_commitments_after(&[], &[kzg_commitment, mismatched_commitment]);
+        let powers: Vec<F> = (0..8).map(|i| zeta.pow([i as u64])).collect();
+        let mismatched = KZGPedersenProof {
+            kzg: prover::prover_open(&system.crs, &f, &zeta, &kzg_commitment),
+            pedersen_commitment: mismatched_commitment,
+            inner_product: bulletproofs.prove(generators.clone(), coeffs, powers).unwrap(),
+        };
+        assert!(!system.verify_kzg_pedersen_equality(mismatched, &generators, &bulletproofs));
+
+        // Nor can a proof be moved to another Pedersen commitment
+        let mut proof = system.prove_kzg_pedersen_equality(&f, &generators, &bulletproofs).unwrap();
+        proof.pedersen_commitment = mismatched_commitment;
+        assert!(!system.verify_kzg_pedersen_equality(proof, &generators, &bulletproofs));
+    }
+
+    #[test]
+    fn test_kzg_pedersen_equality_reports_which_system_failed() {
+        use crate::bulletproofs::bls12_381::bls12_381_system;
+        use crate::bulletproofs::{BulletproofError, BulletproofGenerators};
+        use crate::error::Error;
+
+        let mut rng = thread_rng();
+        let poseidon_config = secure_config::<F>(2, 1);
+        let bulletproofs = bls12_381_system(&poseidon_config);
+        let generators = BulletproofGenerators::<G1>::random(8, &mut rng);
+
+        // More coefficients than generators, or a degree the CRS does not support
+        let f = DensePolynomial::<F>::rand(8, &mut rng);
+        assert_eq!(
+            setup_system(8).prove_kzg_pedersen_equality(&f, &generators, &bulletproofs).unwrap_err(),
+            Error::KZG(KZGError::DegreeTooLarge { degree: 8, max_degree: 7 })
+        );
+        let f = DensePolynomial::<F>::rand(7, &mut rng);
+        assert_eq!(
+            setup_system(3).prove_kzg_pedersen_equality(&f, &generators, &bulletproofs).unwrap_err(),
+            Error::KZG(KZGError::DegreeTooLarge { degree: 7, max_degree: 3 })
+        );
+
+        // The inner product argument needs a power of two generators
+        let generators = BulletproofGenerators::<G1>::random(6, &mut rng);
+        let f = DensePolynomial::<F>::rand(3, &mut rng);
+        assert_eq!(
+            setup_system(7).prove_kzg_pedersen_equality(&f, &generators, &bulletproofs).unwrap_err(),
+            Error::Bulletproof(BulletproofError::NotPowerOfTwo { length: 6 })
+        );
+    }
+}
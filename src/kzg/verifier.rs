@@ -1,8 +1,13 @@
 use ark_ec::{pairing::Pairing, Group};
+use ark_ff::Zero;
 
+#[allow(clippy::module_inception)]
 pub mod verifier {
 
-    use crate::kzg::KZGProof;
+    use crate::kzg::{BivariateKZGProof, EqualOpeningProof, FoldedOpeningProof, KZGProof, ProductRelationProof};
+
+    use crate::util::ct::{ct_eq, ct_is_zero};
+    use crate::util::msm::msm_projective;
 
     use super::*;
 
@@ -22,12 +27,106 @@ pub mod verifier {
     /// - e(·,·) is the pairing operation
     ///
     /// This equation verifies that the polynomial committed to by C indeed evaluates to y at point α.
+    ///
+    /// It is checked as e(C - [y]G₁, G₂) · e(-π, [s]G₂ - [α]G₂) = 1 with a single multi-pairing, which
+    /// shares the final exponentiation between both pairings.
     pub fn verify<E>(
         proof: KZGProof<E::ScalarField, E::G1>,
         challenge: E::ScalarField,
         g2: E::G2,
         g2_s: E::G2,
     ) -> bool
+    where
+        E: Pairing,
+        E::G1: Group<ScalarField = E::ScalarField>,
+        E::G2: Group<ScalarField = E::ScalarField>,
+    {
+        let lhs_g1 = proof.commitment - proof.challenge_evaluation;
+        let g2_s_minus_alpha = g2_s - (g2 * challenge);
+
+        ct_is_zero(&E::multi_pairing([lhs_g1, -proof.witness], [g2, g2_s_minus_alpha]))
+    }
+
+    /// The check of `verify` from loose parts, for a commitment and claimed value received
+    /// separately from the witness, e.g. a commitment published earlier. g1 is the generator the
+    /// CRS is built on, with which [y]G₁ is computed from the value.
+    pub fn verify_parts<E>(
+        commitment: E::G1,
+        challenge: E::ScalarField,
+        value: E::ScalarField,
+        witness: E::G1,
+        g1: E::G1,
+        g2: E::G2,
+        g2_s: E::G2,
+    ) -> bool
+    where
+        E: Pairing,
+        E::G1: Group<ScalarField = E::ScalarField>,
+        E::G2: Group<ScalarField = E::ScalarField>,
+    {
+        let lhs_g1 = commitment - g1 * value;
+        let g2_s_minus_alpha = g2_s - (g2 * challenge);
+
+        ct_is_zero(&E::multi_pairing([lhs_g1, -witness], [g2, g2_s_minus_alpha]))
+    }
+
+    /// Verifies many openings, each at its own point, with two pairings.
+    ///
+    /// The check of `verify` for the i-th opening rearranges to
+    ///
+    /// e(C_i - [y_i]G₁ + [z_i]π_i, G₂) = e(π_i, [s]G₂)
+    ///
+    /// whose right-hand pairings share [s]G₂, so that a combination with the random r_i is
+    ///
+    /// e(Σ r_i (C_i - [y_i]G₁ + [z_i]π_i), G₂) = e(Σ r_i π_i, [s]G₂)
+    ///
+    /// with the witnesses shifted by their points on the left. It holds for a wrong opening with
+    /// probability about N / |F| when the r_i are powers of a challenge drawn after every opening,
+    /// witnesses included, is fixed.
+    pub fn verify_aggregate<E>(proofs: &[KZGProof<E::ScalarField, E::G1>], randomness: &[E::ScalarField], g2: E::G2, g2_s: E::G2) -> bool
+    where
+        E: Pairing,
+        E::G1: Group<ScalarField = E::ScalarField>,
+        E::G2: Group<ScalarField = E::ScalarField>,
+    {
+        if proofs.is_empty() || proofs.len() != randomness.len() {
+            return false;
+        }
+
+        let (shifted, witness) = proofs.iter().zip(randomness).fold(
+            (E::G1::zero(), E::G1::zero()),
+            |(shifted, witness), (proof, r)| {
+                let opening = proof.commitment - proof.challenge_evaluation + proof.witness * proof.challenge;
+                (shifted + opening * r, witness + proof.witness * r)
+            },
+        );
+
+        ct_is_zero(&E::multi_pairing([shifted, -witness], [g2, g2_s]))
+    }
+
+    /// Verifies an opening at zero, made with `prove_at_zero`, for which the check of `verify`
+    /// becomes e(C - [y]G₁, G₂) = e(π, [s]G₂). A revealed value must be the y of [y]G₁, computed
+    /// with g1, the generator the CRS is built on.
+    pub fn verify_at_zero<E>(proof: KZGProof<E::ScalarField, E::G1>, g1: E::G1, g2: E::G2, g2_s: E::G2) -> bool
+    where
+        E: Pairing,
+        E::G1: Group<ScalarField = E::ScalarField>,
+        E::G2: Group<ScalarField = E::ScalarField>,
+    {
+        if !proof.challenge.is_zero() {
+            return false;
+        }
+        let value_holds = proof.value.is_none_or(|value| ct_eq(&(g1 * value), &proof.challenge_evaluation));
+        value_holds & ct_is_zero(&E::multi_pairing([proof.commitment - proof.challenge_evaluation, -proof.witness], [g2, g2_s]))
+    }
+
+    /// The same check as `verify`, comparing two separate pairings.
+    pub fn verify_with_two_pairings<E>(
+        proof: KZGProof<E::ScalarField, E::G1>,
+        challenge: E::ScalarField,
+        g2: E::G2,
+        g2_s: E::G2,
+    ) -> bool
     where
         E: Pairing,
         E::G1: Group<ScalarField = E::ScalarField>,
@@ -49,6 +148,112 @@ pub mod verifier {
         let rhs = E::pairing(witness, g2_s_minus_alpha);
     
         // Check if the pairings are equal
-        lhs == rhs
+        ct_eq(&lhs, &rhs)
+    }
+
+    /// Verifies the three openings of a product relation proof at the point z, and that the revealed
+    /// values satisfy f(z) = g(z) · h(z).
+    pub fn verify_product_relation<E>(
+        proof: ProductRelationProof<E::ScalarField, E::G1>,
+        z: E::ScalarField,
+        g1: E::G1,
+        g2: E::G2,
+        g2_s: E::G2,
+    ) -> bool
+    where
+        E: Pairing,
+        E::G1: Group<ScalarField = E::ScalarField>,
+        E::G2: Group<ScalarField = E::ScalarField>,
+    {
+        let ProductRelationProof { f, g, h } = proof;
+        let (Some(f_z), Some(g_z), Some(h_z)) = (f.value, g.value, h.value) else {
+            return false;
+        };
+        if f_z != g_z * h_z {
+            return false;
+        }
+
+        [f, g, h].into_iter().all(|opening| {
+            opening.challenge == z
+                && opening.value.is_some_and(|value| g1 * value == opening.challenge_evaluation)
+                && verify::<E>(opening, z, g2, g2_s)
+        })
+    }
+
+    /// Verifies that the two committed polynomials take the same value at z, checking that the
+    /// proof is for z and that C_f - C_g opens to 0 there:
+    ///
+    /// e(C_f - C_g, G₂) = e(W, [s]G₂ - [z]G₂)
+    pub fn verify_equal_opening<E>(proof: &EqualOpeningProof<E::ScalarField, E::G1>, z: E::ScalarField, g2: E::G2, g2_s: E::G2) -> bool
+    where
+        E: Pairing,
+        E::G1: Group<ScalarField = E::ScalarField>,
+        E::G2: Group<ScalarField = E::ScalarField>,
+    {
+        if proof.challenge != z {
+            return false;
+        }
+
+        let difference = proof.commitment_f - proof.commitment_g;
+        let g2_s_minus_z = g2_s - (g2 * z);
+
+        ct_is_zero(&E::multi_pairing([difference, -proof.witness], [g2, g2_s_minus_z]))
+    }
+
+    /// Verifies an opening of Σ r_i f_i at the point, recombining the commitments of the f_i with
+    /// the coefficients into that of the combination, then checking as `verify` does that
+    ///
+    /// e(Σ r_i C_i - [y]G₁, G₂) = e(W, [s]G₂ - [z]G₂)
+    ///
+    /// with a single multi-pairing. g1 is the generator the CRS is built on.
+    pub fn verify_folded_opening<E>(
+        commitments: &[E::G1],
+        r_coeffs: &[E::ScalarField],
+        proof: &FoldedOpeningProof<E::ScalarField, E::G1>,
+        g1: E::G1,
+        g2: E::G2,
+        g2_s: E::G2,
+    ) -> bool
+    where
+        E: Pairing,
+        E::G1: Group<ScalarField = E::ScalarField>,
+        E::G2: Group<ScalarField = E::ScalarField>,
+    {
+        if commitments.is_empty() || commitments.len() != r_coeffs.len() {
+            return false;
+        }
+
+        let folded = msm_projective(commitments, r_coeffs);
+        let g2_s_minus_z = g2_s - (g2 * proof.challenge);
+        ct_is_zero(&E::multi_pairing([folded - g1 * proof.value, -proof.witness], [g2, g2_s_minus_z]))
+    }
+
+    /// Verifies a bivariate KZG proof.
+    ///
+    /// This function checks that
+    ///
+    /// e(C - [y]G₁, G₂) = e(W_x, [s]G₂ - [x0]G₂) · e(W_y, [t]G₂ - [y0]G₂)
+    ///
+    /// Where:
+    /// - C is the commitment and y the claimed evaluation f(x0, y0)
+    /// - W_x, W_y are the witnesses for the quotients by (x - x0) and (y - y0)
+    /// - s, t are the secrets used in the trusted setup
+    pub fn verify_bivariate<E>(
+        proof: BivariateKZGProof<E::ScalarField, E::G1>,
+        g2: E::G2,
+        g2_s: E::G2,
+        g2_t: E::G2,
+    ) -> bool
+    where
+        E: Pairing,
+        E::G1: Group<ScalarField = E::ScalarField>,
+        E::G2: Group<ScalarField = E::ScalarField>,
+    {
+        let (x0, y0) = proof.challenge;
+
+        let lhs = E::pairing(proof.commitment - proof.challenge_evaluation, g2);
+        let rhs = E::pairing(proof.witness_x, g2_s - (g2 * x0)) + E::pairing(proof.witness_y, g2_t - (g2 * y0));
+
+        ct_eq(&lhs, &rhs)
     }
-}
\ No newline at end of file
+}
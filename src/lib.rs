@@ -1,9 +1,15 @@
+#![allow(incomplete_features)]
 #![feature(generic_const_exprs)]
 
 
 pub mod bulletproofs;
+pub mod error;
 pub mod kzg;
 pub mod util;
 pub mod fri;
+pub mod pcs;
+pub mod report;
+#[cfg(test)]
+mod test;
 
 pub use bulletproofs::BulletproofSystem;
\ No newline at end of file
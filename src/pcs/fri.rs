@@ -0,0 +1,62 @@
+use std::marker::PhantomData;
+
+use ark_crypto_primitives::crh::{CRHScheme, TwoToOneCRHScheme};
+use ark_ff::FftField;
+use ark_poly::DenseUVPolynomial;
+
+use crate::fri::deep::OodEvalProof;
+use crate::fri::error::FriError;
+use crate::fri::merkle_tree::MerkleTreeOperator;
+use crate::fri::protocol::FRISystemImpl;
+use crate::util::VerifierChallenge;
+
+use super::PolynomialCommitmentScheme;
+
+/// DEEP-FRI as a polynomial commitment scheme: the commitment is the root of the polynomial's
+/// evaluations and an opening proves the quotient by x - z is of low degree, see `prove_ood_eval`.
+/// Points of the evaluation domain cannot be opened.
+pub struct FriScheme<F, P, MT, VC, INCH, LCH>(PhantomData<(F, P, MT, VC, INCH, LCH)>);
+
+/// The parameters of the FRI scheme: the system and the degree every committed polynomial is at
+/// most. They are public and there is no trusted setup, so the prover and verifier keys are the
+/// same.
+pub struct FriKey<F, P, MT, VC, INCH, LCH>
+where
+    F: FftField,
+    P: DenseUVPolynomial<F>,
+    MT: MerkleTreeOperator<F, INCH> + Clone,
+    VC: VerifierChallenge,
+    INCH: TwoToOneCRHScheme,
+{
+    pub system: FRISystemImpl<F, P, MT, VC, INCH, LCH>,
+    pub degree: usize,
+}
+
+impl<F, P, LCH, INCH, MT, VC> PolynomialCommitmentScheme<F> for FriScheme<F, P, MT, VC, INCH, LCH>
+where
+    F: FftField,
+    P: DenseUVPolynomial<F>,
+    LCH: CRHScheme<Input = [F], Output = INCH::Output>,
+    INCH: TwoToOneCRHScheme,
+    MT: MerkleTreeOperator<F, INCH> + Clone,
+    VC: VerifierChallenge<Commitment = INCH::Output, Challenge = F>,
+{
+    type Polynomial = P;
+    type Commitment = INCH::Output;
+    type Opening = OodEvalProof<F, INCH::Output>;
+    type ProverKey = FriKey<F, P, MT, VC, INCH, LCH>;
+    type VerifierKey = FriKey<F, P, MT, VC, INCH, LCH>;
+    type Error = FriError;
+
+    fn commit(key: &Self::ProverKey, polynomial: &P) -> Result<INCH::Output, FriError> {
+        key.system.ood_commitment(polynomial, key.degree)
+    }
+
+    fn open(key: &Self::ProverKey, polynomial: &P, point: F) -> Result<(F, Self::Opening), FriError> {
+        key.system.prove_ood_eval(polynomial, key.degree, point)
+    }
+
+    fn verify(key: &Self::VerifierKey, commitment: &INCH::Output, point: F, value: F, opening: &Self::Opening) -> bool {
+        opening.commitment == *commitment && key.system.verify_ood_eval(opening, point, value)
+    }
+}
@@ -0,0 +1,57 @@
+use ark_bls12_381::{Bls12_381, Fr, G1Projective as G1, G2Projective as G2};
+use ark_poly::{univariate::DensePolynomial, Polynomial};
+
+use crate::kzg::prover::prover;
+use crate::kzg::system::{is_in_prime_order_subgroup, KZGSystemImpl};
+use crate::kzg::verifier::verifier;
+use crate::kzg::{KZGCommitment, KZGError, CRS};
+
+use super::PolynomialCommitmentScheme;
+
+/// KZG over BLS12-381 as a polynomial commitment scheme. The prover key is the CRS of a trusted
+/// setup and the opening is the witness, a commitment to the quotient.
+pub struct KZGScheme;
+
+/// What a verifier keeps from the trusted setup: the generator of G1 the CRS is built on, that
+/// of G2 and [s]G₂.
+#[derive(Clone, Debug)]
+pub struct KZGVerifierKey {
+    pub g1: G1,
+    pub g2: G2,
+    pub g2_s: G2,
+}
+
+impl KZGVerifierKey {
+    pub fn from_system(system: &KZGSystemImpl) -> Self {
+        Self { g1: system.crs.g1_powers[0], g2: system.g2, g2_s: system.g2_s }
+    }
+}
+
+impl PolynomialCommitmentScheme<Fr> for KZGScheme {
+    type Polynomial = DensePolynomial<Fr>;
+    type Commitment = KZGCommitment<Fr, G1>;
+    type Opening = G1;
+    type ProverKey = CRS<G1>;
+    type VerifierKey = KZGVerifierKey;
+    type Error = KZGError;
+
+    fn commit(key: &CRS<G1>, polynomial: &DensePolynomial<Fr>) -> Result<KZGCommitment<Fr, G1>, KZGError> {
+        let max_degree = key.g1_powers.len().saturating_sub(1);
+        if polynomial.degree() > max_degree {
+            return Err(KZGError::DegreeTooLarge { degree: polynomial.degree(), max_degree });
+        }
+        Ok(KZGCommitment { value: prover::prover_commit(key, polynomial) })
+    }
+
+    fn open(key: &CRS<G1>, polynomial: &DensePolynomial<Fr>, point: Fr) -> Result<(Fr, G1), KZGError> {
+        let commitment = Self::commit(key, polynomial)?;
+        let proof = prover::try_prover_open(key, polynomial, &point, &commitment.value)?;
+        Ok((polynomial.evaluate(&point), proof.witness))
+    }
+
+    fn verify(key: &KZGVerifierKey, commitment: &KZGCommitment<Fr, G1>, point: Fr, value: Fr, opening: &G1) -> bool {
+        is_in_prime_order_subgroup(&commitment.value)
+            && is_in_prime_order_subgroup(opening)
+            && verifier::verify_parts::<Bls12_381>(commitment.value, point, value, *opening, key.g1, key.g2, key.g2_s)
+    }
+}
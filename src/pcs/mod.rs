@@ -0,0 +1,31 @@
+//! A common interface to the polynomial commitment schemes, so that a protocol can be written
+//! once and run over either KZG or FRI.
+
+pub mod kzg;
+pub mod fri;
+mod test;
+
+use ark_ff::Field;
+
+/// A scheme to commit to a polynomial and later prove its value at a point.
+///
+/// Whatever a scheme needs besides the polynomial is in its keys: for KZG the powers of the secret
+/// of a trusted setup, for FRI the public parameters of the low degree test, the same for the
+/// prover and the verifier. Nothing is assumed about adding commitments, which FRI cannot do.
+pub trait PolynomialCommitmentScheme<F: Field> {
+    type Polynomial;
+    type Commitment;
+    type Opening;
+    type ProverKey;
+    type VerifierKey;
+    type Error;
+
+    fn commit(key: &Self::ProverKey, polynomial: &Self::Polynomial) -> Result<Self::Commitment, Self::Error>;
+
+    /// The value of the polynomial at the point, with a proof of it for the commitment `commit`
+    /// makes.
+    fn open(key: &Self::ProverKey, polynomial: &Self::Polynomial, point: F) -> Result<(F, Self::Opening), Self::Error>;
+
+    /// Whether the opening proves that the committed polynomial evaluates to the value at the point.
+    fn verify(key: &Self::VerifierKey, commitment: &Self::Commitment, point: F, value: F, opening: &Self::Opening) -> bool;
+}
@@ -0,0 +1,73 @@
+#![allow(unused_imports)]
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+
+use ark_bls12_381::Fr;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use crate::util::poseidon::secure_config;
+use ark_ff::One;
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
+use ark_std::rand::thread_rng;
+
+use crate::fri::merkle_tree::MerkleTreeOperatorImpl;
+use crate::fri::protocol::FRISystemImpl;
+use crate::fri::sha256::{Sha256Challenger, Sha256LeafHash, Sha256TwoToOne};
+use crate::kzg::system::{KZGSystemImpl, KZGVerifierChallenger};
+
+use super::fri::{FriKey, FriScheme};
+use super::kzg::{KZGScheme, KZGVerifierKey};
+use super::PolynomialCommitmentScheme;
+
+type Sha256FriScheme = FriScheme<Fr, DensePolynomial<Fr>, MerkleTreeOperatorImpl<Sha256LeafHash<Fr>, Sha256TwoToOne>, Sha256Challenger<Fr>, Sha256TwoToOne, Sha256LeafHash<Fr>>;
+
+const DEGREE: usize = 31;
+
+/// The same polynomials and points for every scheme, the points outside the domains FRI uses
+fn openings() -> Vec<(DensePolynomial<Fr>, Fr)> {
+    vec![
+        (DensePolynomial::from_coefficients_vec((1..=DEGREE as u64 + 1).map(Fr::from).collect()), Fr::from(123456789u64)),
+        (DensePolynomial::from_coefficients_vec(vec![Fr::from(7u64); DEGREE + 1]), -Fr::from(987654321u64)),
+        (DensePolynomial::from_coefficients_vec(vec![Fr::from(3u64), Fr::from(5u64)]), Fr::from(42u64)),
+    ]
+}
+
+/// Commits to and opens every polynomial, checking the honest openings are accepted and a wrong
+/// value is not, whichever the scheme.
+fn check_scheme<S>(prover_key: &S::ProverKey, verifier_key: &S::VerifierKey)
+where
+    S: PolynomialCommitmentScheme<Fr, Polynomial = DensePolynomial<Fr>>,
+    S::Error: Debug,
+{
+    for (polynomial, point) in openings() {
+        let commitment = S::commit(prover_key, &polynomial).unwrap();
+        let (value, opening) = S::open(prover_key, &polynomial, point).unwrap();
+        assert_eq!(value, polynomial.evaluate(&point));
+
+        assert!(S::verify(verifier_key, &commitment, point, value, &opening));
+        assert!(!S::verify(verifier_key, &commitment, point, value + Fr::one(), &opening));
+    }
+}
+
+#[test]
+fn test_kzg_scheme() {
+    let poseidon_config = secure_config::<Fr>(2, 1);
+    let system = KZGSystemImpl::setup(DEGREE, KZGVerifierChallenger::new(poseidon_config), &mut thread_rng());
+    let verifier_key = KZGVerifierKey::from_system(&system);
+    check_scheme::<KZGScheme>(&system.crs, &verifier_key);
+}
+
+#[test]
+fn test_fri_scheme() {
+    let key = FriKey {
+        system: FRISystemImpl::new(3, 8, MerkleTreeOperatorImpl::new((), ()), Sha256Challenger::new()),
+        degree: DEGREE,
+    };
+    check_scheme::<Sha256FriScheme>(&key, &key);
+
+    // An opening made for another commitment is rejected
+    let (polynomial, point) = openings().remove(0);
+    let other = Sha256FriScheme::commit(&key, &DensePolynomial::from_coefficients_vec(vec![Fr::one(); DEGREE + 1])).unwrap();
+    let (value, opening) = Sha256FriScheme::open(&key, &polynomial, point).unwrap();
+    assert!(!Sha256FriScheme::verify(&key, &other, point, value, &opening));
+}
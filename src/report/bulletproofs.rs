@@ -0,0 +1,45 @@
+use std::fmt::Debug;
+
+use ark_ec::Group;
+use ark_ff::Field;
+use ark_serialize::CanonicalSerialize;
+
+use crate::bulletproofs::BulletproofProof;
+
+use super::{Metrics, ProofMetrics, LENGTH_BYTES};
+
+/// A bulletproof of vectors of the given length, a power of two.
+#[derive(Clone, Copy, Debug)]
+pub struct BulletproofParams {
+    pub length: usize,
+}
+
+impl<S: Field + Debug, G: Group<ScalarField = S> + Debug> Metrics for BulletproofProof<S, G> {
+    type Params = BulletproofParams;
+
+    fn metrics(&self) -> ProofMetrics {
+        ProofMetrics { proof_bytes: self.compressed_size(), ..verifier_cost(self.rec_proofs.len()) }
+    }
+
+    fn estimate_for(params: &BulletproofParams) -> ProofMetrics {
+        let rounds = params.length.trailing_zeros() as usize;
+        let (scalar, point) = (S::zero().compressed_size(), G::zero().compressed_size());
+
+        // Each round holds its dot product, P, L, R and challenge, the base case two values, their
+        // product and P
+        let proof_bytes = LENGTH_BYTES + rounds * (2 * scalar + 3 * point) + 3 * scalar + point;
+        ProofMetrics { proof_bytes, ..verifier_cost(rounds) }
+    }
+}
+
+/// The work of `BulletproofSystem::verify`: x²L and x⁻²R every round, one challenge drawn every
+/// round, both generator vectors folded with n terms each, and the commitment of the base case.
+fn verifier_cost(rounds: usize) -> ProofMetrics {
+    let folding = if rounds == 0 { 0 } else { 2 << rounds };
+    ProofMetrics {
+        proof_bytes: 0,
+        verifier_group_ops: 2 * rounds + folding + 3,
+        verifier_pairings: 0,
+        verifier_hashes: rounds,
+    }
+}
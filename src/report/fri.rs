@@ -0,0 +1,74 @@
+use ark_ff::Field;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::fri::config::FriConfig;
+use crate::fri::protocol::FRIProtocolProof;
+
+use super::{Metrics, ProofMetrics};
+
+/// A FRI proof of a polynomial of exactly the given degree under the configuration.
+#[derive(Clone, Copy, Debug)]
+pub struct FriParams {
+    pub degree: usize,
+    pub config: FriConfig,
+}
+
+impl<F: Field, H: CanonicalSerialize + CanonicalDeserialize + Default> Metrics for FRIProtocolProof<F, H> {
+    type Params = FriParams;
+
+    /// The hashes are those of every Merkle path opened and of every cap up to its root, the
+    /// folding and query challenges, and the tree the final polynomial is committed to again.
+    fn metrics(&self) -> ProofMetrics {
+        let queries = self.round_proofs.first().map_or(0, |round| round.openings.len());
+        let mut hashes = 0;
+        for round in &self.round_proofs {
+            hashes += (round.current_cap.len() - 1) + (round.next_cap.len() - 1);
+            for opening in &round.openings {
+                hashes += opening.current.iter().chain([&opening.next]).map(|proof| 1 + proof.path.len()).sum::<usize>();
+            }
+        }
+        if let Some(last) = self.round_proofs.last() {
+            // The final tree has a leaf under every path from its cap
+            let path = last.openings.first().map_or(0, |opening| opening.next.path.len());
+            let leaves = last.next_cap.len() << path;
+            hashes += self.round_proofs.len() + queries + 2 * leaves - 1;
+        }
+
+        ProofMetrics { proof_bytes: self.compressed_size(), verifier_group_ops: 0, verifier_pairings: 0, verifier_hashes: hashes }
+    }
+
+    /// Takes the size from `FriConfig::estimate`, and counts the hashes over the same trees: each
+    /// round opens `num_queries` queries in a tree and the one it folds into.
+    fn estimate_for(params: &FriParams) -> ProofMetrics {
+        let FriParams { mut degree, config } = *params;
+
+        // The leaf width, the length of the paths below the cap and the size of the cap of the
+        // tree for a degree
+        let layer = |degree: usize| {
+            let domain = (degree + 1).next_power_of_two() * config.blowup;
+            let leaf_width = config.leaf_width.min(domain);
+            let height = (domain / leaf_width).trailing_zeros() as usize;
+            let cap_height = config.cap_height.min(height);
+            (leaf_width, height - cap_height, 1 << cap_height)
+        };
+
+        let mut hashes = 0;
+        let mut rounds = 0;
+        while degree > config.max_degree {
+            let (width, path, cap) = layer(degree);
+            let (_, next_path, next_cap) = layer(degree / config.folding_factor);
+            let leaves = config.folding_factor.div_ceil(width);
+            hashes += (cap - 1) + (next_cap - 1) + config.num_queries * (leaves * (1 + path) + 1 + next_path);
+
+            degree /= config.folding_factor;
+            rounds += 1;
+        }
+        if rounds > 0 {
+            let (_, path, cap) = layer(degree);
+            hashes += rounds + config.num_queries + 2 * (cap << path) - 1;
+        }
+
+        let proof_bytes = config.estimate::<F, H>(params.degree).proof_bytes;
+        ProofMetrics { proof_bytes, verifier_group_ops: 0, verifier_pairings: 0, verifier_hashes: hashes }
+    }
+}
@@ -0,0 +1,44 @@
+use ark_ec::Group;
+use ark_ff::Field;
+use ark_serialize::CanonicalSerialize;
+
+use crate::kzg::KZGProof;
+
+use super::{Metrics, ProofMetrics};
+
+/// The byte flagging whether an `Option` holds a value.
+const OPTION_TAG_BYTES: usize = 1;
+
+/// A KZG opening, with or without the value of the polynomial at the point.
+#[derive(Clone, Copy, Debug)]
+pub struct KZGParams {
+    pub reveals_value: bool,
+}
+
+impl<F: Field, G: Group<ScalarField = F>> Metrics for KZGProof<F, G> {
+    type Params = KZGParams;
+
+    fn metrics(&self) -> ProofMetrics {
+        ProofMetrics { proof_bytes: self.compressed_size(), ..verifier_cost(self.value.is_some()) }
+    }
+
+    fn estimate_for(params: &KZGParams) -> ProofMetrics {
+        let (scalar, point) = (F::zero().compressed_size(), G::zero().compressed_size());
+
+        // The commitment, [y]G₁ and the witness, the point, and the value if revealed
+        let value_bytes = if params.reveals_value { scalar } else { 0 };
+        let proof_bytes = 3 * point + scalar + OPTION_TAG_BYTES + value_bytes;
+        ProofMetrics { proof_bytes, ..verifier_cost(params.reveals_value) }
+    }
+}
+
+/// The work of `KZGSystem::verify`: the challenge recomputed from the commitment, [z]G₂, [y]G₁
+/// to check a revealed value against, and a multi-pairing of two pairings.
+fn verifier_cost(reveals_value: bool) -> ProofMetrics {
+    ProofMetrics {
+        proof_bytes: 0,
+        verifier_group_ops: 1 + reveals_value as usize,
+        verifier_pairings: 2,
+        verifier_hashes: 1,
+    }
+}
@@ -0,0 +1,37 @@
+//! Proof sizes and verifier costs of the three systems in the same units, so that they can be
+//! compared for a statement before choosing one.
+//!
+//! Every count is worked out from the shape of a proof, or from the parameters that determine it
+//! with `estimate_for`, without running the verifier. Group operations are scalar
+//! multiplications, an MSM of n terms counting n; hashes are Merkle hashes and challenge
+//! derivations.
+
+pub mod bulletproofs;
+pub mod kzg;
+pub mod fri;
+mod test;
+
+/// The bytes of the length prefix of a serialized vector, a `u64`.
+const LENGTH_BYTES: usize = 8;
+
+/// The size of a proof and the work of verifying it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProofMetrics {
+    /// The length of the compressed canonical serialization
+    pub proof_bytes: usize,
+    pub verifier_group_ops: usize,
+    pub verifier_pairings: usize,
+    pub verifier_hashes: usize,
+}
+
+/// A proof whose size and verifier cost can be reported.
+pub trait Metrics {
+    /// What the shape of a proof depends on, known before proving.
+    type Params;
+
+    /// The metrics of this proof.
+    fn metrics(&self) -> ProofMetrics;
+
+    /// The metrics of a proof with the given parameters, the same as `metrics` gives once it is made.
+    fn estimate_for(params: &Self::Params) -> ProofMetrics;
+}
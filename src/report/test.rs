@@ -0,0 +1,84 @@
+#![allow(unused_imports)]
+#![allow(dead_code)]
+
+use ark_bls12_381::{Fr, G1Projective as G1};
+use ark_ff::UniformRand;
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::thread_rng;
+
+use crate::bulletproofs::bls12_381::bls12_381_system;
+use crate::bulletproofs::{BulletproofGenerators, BulletproofProof, BulletproofSystem};
+use crate::fri::config::FriConfig;
+use crate::fri::merkle_tree::MerkleTreeOperatorImpl;
+use crate::fri::protocol::{FRIProtocol, FRIProtocolProof, FRISystemImpl};
+use crate::fri::sha256::{Sha256Challenger, Sha256Digest, Sha256LeafHash, Sha256TwoToOne};
+use crate::kzg::system::{KZGSystemImpl, KZGVerifierChallenger};
+use crate::kzg::{KZGProof, KZGSystem};
+use crate::util::poseidon::secure_config;
+
+use super::bulletproofs::BulletproofParams;
+use super::fri::FriParams;
+use super::kzg::KZGParams;
+use super::{Metrics, ProofMetrics};
+
+type Sha256FRISystem = FRISystemImpl<Fr, DensePolynomial<Fr>, MerkleTreeOperatorImpl<Sha256LeafHash<Fr>, Sha256TwoToOne>, Sha256Challenger<Fr>, Sha256TwoToOne, Sha256LeafHash<Fr>>;
+
+/// Checks the metrics of the proof against its serialization and against the estimate for the
+/// parameters it was made with.
+fn check<T: Metrics + CanonicalSerialize>(proof: &T, params: &T::Params) -> ProofMetrics {
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+
+    let metrics = proof.metrics();
+    assert_eq!(metrics.proof_bytes, bytes.len());
+    assert_eq!(metrics, T::estimate_for(params));
+    metrics
+}
+
+#[test]
+fn test_bulletproof_metrics() {
+    let mut rng = thread_rng();
+    let system = bls12_381_system(&secure_config::<Fr>(2, 1));
+
+    for length in [1, 2, 64] {
+        let generators = BulletproofGenerators::<G1>::random(length, &mut rng);
+        let v1 = (0..length).map(|_| Fr::rand(&mut rng)).collect();
+        let v2 = (0..length).map(|_| Fr::rand(&mut rng)).collect();
+        let proof: BulletproofProof<Fr, G1> = system.prove(generators, v1, v2).unwrap();
+
+        let metrics = check(&proof, &BulletproofParams { length });
+        assert_eq!(metrics.verifier_pairings, 0);
+        assert_eq!(metrics.verifier_hashes, length.trailing_zeros() as usize);
+    }
+}
+
+#[test]
+fn test_kzg_metrics() {
+    let mut rng = thread_rng();
+    let system = KZGSystemImpl::setup(63, KZGVerifierChallenger::new(secure_config::<Fr>(2, 1)), &mut rng);
+    let mut proof: KZGProof<Fr, G1> = system.prove(&DensePolynomial::rand(63, &mut rng)).unwrap();
+
+    let metrics = check(&proof, &KZGParams { reveals_value: true });
+    assert_eq!(metrics.verifier_pairings, 2);
+
+    proof.value = None;
+    check(&proof, &KZGParams { reveals_value: false });
+}
+
+#[test]
+fn test_fri_metrics() {
+    let mut rng = thread_rng();
+    let polynomial = DensePolynomial::<Fr>::rand(63, &mut rng);
+
+    let system = Sha256FRISystem::new(3, 8, MerkleTreeOperatorImpl::new((), ()), Sha256Challenger::new());
+    let proof: FRIProtocolProof<Fr, Sha256Digest> = system.prove(&polynomial, 63).unwrap();
+    check(&proof, &FriParams { degree: 63, config: system.config });
+
+    // Packed leaves, a cap and a blowup change every part of the shape
+    let config = FriConfig { leaf_width: 4, cap_height: 1, blowup: 2, ..FriConfig::new(3, 8) };
+    let system = Sha256FRISystem::with_config(config, MerkleTreeOperatorImpl::with_leaf_width((), (), 4), Sha256Challenger::new()).unwrap();
+    let proof: FRIProtocolProof<Fr, Sha256Digest> = system.prove(&polynomial, 63).unwrap();
+    let metrics = check(&proof, &FriParams { degree: 63, config });
+    assert_eq!((metrics.verifier_group_ops, metrics.verifier_pairings), (0, 0));
+}
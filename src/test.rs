@@ -0,0 +1,325 @@
+#![allow(unused_imports)]
+#![allow(dead_code)]
+
+use ark_bls12_381::{Fr, G1Projective as G1, G2Projective as G2};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use crate::util::poseidon::secure_config;
+use ark_ec::Group;
+use ark_ff::{Field, UniformRand};
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::thread_rng;
+
+use crate::bulletproofs::bls12_381::bls12_381_system;
+use crate::bulletproofs::{BulletproofGenerators, BulletproofSystem};
+use crate::fri::config::{FriConfig, SecurityPreset};
+use crate::fri::deep::OodEvalProof;
+use crate::fri::merkle_tree::MerkleTreeOperatorImpl;
+use crate::fri::protocol::{FRIProtocol, FRIProtocolProof, FRISystemImpl};
+use crate::fri::sha256::{Sha256Challenger, Sha256Digest, Sha256LeafHash, Sha256TwoToOne};
+use crate::kzg::system::{KZGSystemImpl, KZGVerifierChallenger};
+use crate::kzg::{KZGCommitment, KZGProof, KZGSystem, CRS};
+
+type Sha256FRISystem = FRISystemImpl<Fr, DensePolynomial<Fr>, MerkleTreeOperatorImpl<Sha256LeafHash<Fr>, Sha256TwoToOne>, Sha256Challenger<Fr>, Sha256TwoToOne, Sha256LeafHash<Fr>>;
+
+fn poseidon_config() -> PoseidonConfig<Fr> {
+    secure_config::<Fr>(2, 1)
+}
+
+/// Serializes the value compressed, as it would be sent to another process, and reads it back
+/// with every point and field element checked.
+fn round_trip<T: CanonicalSerialize + CanonicalDeserialize>(value: &T) -> T {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), value.compressed_size());
+
+    let decoded = T::deserialize_compressed(&bytes[..]).unwrap();
+    let mut reencoded = Vec::new();
+    decoded.serialize_compressed(&mut reencoded).unwrap();
+    assert_eq!(reencoded, bytes);
+
+    // A truncated encoding is an error rather than a shorter proof
+    assert!(T::deserialize_compressed(&bytes[..bytes.len() - 1]).is_err());
+    decoded
+}
+
+#[test]
+fn test_bulletproof_survives_serialization() {
+    let mut rng = thread_rng();
+    let system = bls12_381_system(&poseidon_config());
+    let generators = BulletproofGenerators::<G1>::random(8, &mut rng);
+    let v1: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let v2: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+
+    let proof = system.prove(generators.clone(), v1, v2).unwrap();
+    assert!(system.verify(round_trip(&proof), round_trip(&generators)));
+}
+
+#[test]
+fn test_kzg_proof_survives_serialization() {
+    let mut rng = thread_rng();
+    let s = Fr::rand(&mut rng);
+    let (g1, g2) = (G1::rand(&mut rng), G2::rand(&mut rng));
+    let crs = CRS { g1_powers: (0..=8u64).map(|i| g1 * s.pow([i])).collect() };
+    let system = KZGSystemImpl { crs, degree: 8, g2, g2_s: g2 * s, verifier_challenge: KZGVerifierChallenger::new(poseidon_config()) };
+
+    let proof: KZGProof<Fr, G1> = system.prove(&DensePolynomial::rand(8, &mut rng)).unwrap();
+    let verifier = KZGSystemImpl { crs: round_trip(&system.crs), ..system };
+    assert_eq!(verifier.verify(round_trip(&proof)), Ok(true));
+}
+
+#[test]
+fn test_fri_proofs_survive_serialization() {
+    let mut rng = thread_rng();
+    let system = Sha256FRISystem::new(3, 8, MerkleTreeOperatorImpl::new((), ()), Sha256Challenger::new());
+    let polynomial = DensePolynomial::<Fr>::rand(31, &mut rng);
+
+    let proof: FRIProtocolProof<Fr, Sha256Digest> = system.prove(&polynomial, 31).unwrap();
+    assert!(system.verify(&round_trip(&proof)));
+
+    let z = Fr::rand(&mut rng);
+    let (claimed, proof): (Fr, OodEvalProof<Fr, Sha256Digest>) = system.prove_ood_eval(&polynomial, 31, z).unwrap();
+    assert!(system.verify_ood_eval(&round_trip(&proof), z, claimed));
+}
+
+#[cfg(all(test, feature = "serde"))]
+fn serde_round_trip<T: serde::Serialize + serde::de::DeserializeOwned>(value: &T) -> (T, T) {
+    let json = serde_json::to_string(value).unwrap();
+    let binary = bincode::serialize(value).unwrap();
+    (serde_json::from_str(&json).unwrap(), bincode::deserialize(&binary).unwrap())
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[test]
+fn test_bulletproof_survives_serde() {
+    let mut rng = thread_rng();
+    let system = bls12_381_system(&poseidon_config());
+    let generators = BulletproofGenerators::<G1>::random(8, &mut rng);
+    let v1: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let v2: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+
+    let proof = system.prove(generators.clone(), v1, v2).unwrap();
+    let (from_json, from_binary) = serde_round_trip(&proof);
+    assert!(system.verify(from_json, generators.clone()));
+    assert!(system.verify(from_binary, generators));
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[test]
+fn test_kzg_proof_survives_serde() {
+    let mut rng = thread_rng();
+    let s = Fr::rand(&mut rng);
+    let (g1, g2) = (G1::rand(&mut rng), G2::rand(&mut rng));
+    let crs = CRS { g1_powers: (0..=8u64).map(|i| g1 * s.pow([i])).collect() };
+    let system = KZGSystemImpl { crs, degree: 8, g2, g2_s: g2 * s, verifier_challenge: KZGVerifierChallenger::new(poseidon_config()) };
+
+    let proof: KZGProof<Fr, G1> = system.prove(&DensePolynomial::rand(8, &mut rng)).unwrap();
+    let (from_json, from_binary) = serde_round_trip(&proof);
+    assert_eq!(system.verify(from_json), Ok(true));
+    assert_eq!(system.verify(from_binary), Ok(true));
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[test]
+fn test_fri_proof_survives_serde() {
+    let mut rng = thread_rng();
+    let system = Sha256FRISystem::new(3, 8, MerkleTreeOperatorImpl::new((), ()), Sha256Challenger::new());
+    let polynomial = DensePolynomial::<Fr>::rand(31, &mut rng);
+
+    let proof: FRIProtocolProof<Fr, Sha256Digest> = system.prove(&polynomial, 31).unwrap();
+    let (from_json, from_binary) = serde_round_trip(&proof);
+    assert!(system.verify(&from_json));
+    assert!(system.verify(&from_binary));
+
+    let config = FriConfig::preset(SecurityPreset::Bits100);
+    assert_eq!(serde_round_trip(&config), (config, config));
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[test]
+fn test_commitment_json_is_stable_hex() {
+    let commitment = KZGCommitment { value: G1::generator() * Fr::from(7u64) };
+    let mut bytes = Vec::new();
+    commitment.serialize_compressed(&mut bytes).unwrap();
+
+    let json = serde_json::to_string(&commitment).unwrap();
+    assert_eq!(json, format!("\"{}\"", hex::encode(&bytes)));
+    assert_eq!(json, serde_json::to_string(&KZGCommitment { value: G1::generator() * Fr::from(7u64) }).unwrap());
+
+    // Anything but the hex of a valid point is rejected
+    assert!(serde_json::from_str::<KZGCommitment<Fr, G1>>("\"not hex\"").is_err());
+    assert!(serde_json::from_str::<KZGCommitment<Fr, G1>>(&format!("\"{}\"", hex::encode(&bytes[1..]))).is_err());
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn test_wipe_zeroes_secrets() {
+    use crate::util::secret::{wipe, Secret};
+
+    let mut rng = thread_rng();
+    let mut s = Fr::rand(&mut rng);
+    let mut witness: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    wipe(&mut s);
+    wipe(&mut witness);
+    assert_eq!(s, Fr::from(0u64));
+    assert!(witness.is_empty());
+
+    let mut blinder = Secret::new(Fr::from(5u64));
+    *blinder += Fr::from(2u64);
+    assert_eq!(*blinder, Fr::from(7u64));
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn test_proofs_with_zeroized_secrets() {
+    let mut rng = thread_rng();
+
+    let system = bls12_381_system(&poseidon_config());
+    let generators = BulletproofGenerators::<G1>::random(8, &mut rng);
+    let v1: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let v2: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let proof = system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
+    assert!(system.verify(round_trip(&proof), generators.clone()));
+    let proof = system.prove_zk(generators.clone(), v1, v2, &mut rng).unwrap();
+    assert!(system.verify_zk(&round_trip(&proof), &generators));
+
+    let system = KZGSystemImpl::setup(8, KZGVerifierChallenger::new(poseidon_config()), &mut rng);
+    let proof = system.prove(&DensePolynomial::rand(8, &mut rng)).unwrap();
+    assert_eq!(system.verify(round_trip(&proof)), Ok(true));
+
+    let system = Sha256FRISystem::new(3, 8, MerkleTreeOperatorImpl::new((), ()), Sha256Challenger::new());
+    let polynomial = DensePolynomial::<Fr>::rand(31, &mut rng);
+    assert!(system.verify(&round_trip(&system.prove_zk(&polynomial, &mut rng).unwrap())));
+    assert!(system.verify(&round_trip(&system.prove_with_blinded_final(&polynomial, &mut rng).unwrap())));
+}
+
+/// The name of a span and the names of the fields it was given.
+#[cfg(all(test, feature = "tracing"))]
+type RecordedSpan = (String, Vec<String>);
+
+/// Records the name of every span opened, with the fields it was given.
+#[cfg(all(test, feature = "tracing"))]
+#[derive(Clone, Default)]
+struct SpanRecorder(std::sync::Arc<std::sync::Mutex<Vec<RecordedSpan>>>);
+
+#[cfg(all(test, feature = "tracing"))]
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanRecorder {
+    fn on_new_span(&self, attrs: &tracing::span::Attributes<'_>, _: &tracing::span::Id, _: tracing_subscriber::layer::Context<'_, S>) {
+        let fields = attrs.metadata().fields().iter().map(|field| field.name().to_string()).collect();
+        self.0.lock().unwrap().push((attrs.metadata().name().to_string(), fields));
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+impl SpanRecorder {
+    /// Runs the closure with the recorder as the subscriber, returning the spans it opened.
+    fn record(f: impl FnOnce()) -> Vec<RecordedSpan> {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let recorder = SpanRecorder::default();
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(recorder.clone()), f);
+        let spans = recorder.0.lock().unwrap().clone();
+        spans
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+#[test]
+fn test_proofs_emit_spans() {
+    let mut rng = thread_rng();
+    let names = |spans: &[(String, Vec<String>)]| spans.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+
+    let system = bls12_381_system(&poseidon_config());
+    let generators = BulletproofGenerators::<G1>::random(8, &mut rng);
+    let v1: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let v2: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let spans = SpanRecorder::record(|| {
+        let proof = system.prove(generators.clone(), v1, v2).unwrap();
+        assert!(system.verify(proof, generators));
+    });
+    assert_eq!(names(&spans), ["bulletproofs::prove", "bulletproofs::round", "bulletproofs::round", "bulletproofs::round", "bulletproofs::verify"]);
+    assert!(spans.iter().filter(|(name, _)| name == "bulletproofs::round").all(|(_, fields)| fields == &["round", "size"]));
+
+    let system = KZGSystemImpl::setup(8, KZGVerifierChallenger::new(poseidon_config()), &mut rng);
+    let polynomial = DensePolynomial::rand(8, &mut rng);
+    let spans = SpanRecorder::record(|| {
+        let proof = system.prove(&polynomial).unwrap();
+        assert_eq!(system.verify(proof), Ok(true));
+    });
+    assert_eq!(names(&spans), ["kzg::prove", "kzg::commit", "kzg::open", "kzg::commit", "kzg::verify"]);
+    assert_eq!(spans[1].1, ["msm_size"]);
+
+    let system = Sha256FRISystem::new(3, 8, MerkleTreeOperatorImpl::new((), ()), Sha256Challenger::new());
+    let polynomial = DensePolynomial::<Fr>::rand(31, &mut rng);
+    let spans = SpanRecorder::record(|| {
+        let proof = system.prove(&polynomial, 31).unwrap();
+        assert!(system.verify(&proof));
+    });
+    let names = names(&spans);
+    assert_eq!(names.first().map(String::as_str), Some("fri::prove"));
+    assert!(names.iter().any(|name| name == "fri::round"));
+    assert!(names.iter().any(|name| name == "fri::build_tree"));
+    assert!(names.contains(&"fri::verify".to_string()));
+    assert!(spans.iter().filter(|(name, _)| name == "fri::round").all(|(_, fields)| fields == &["round", "domain_size"]));
+}
+
+/// Runs under both comparisons: with `ct-verify` every decision below goes through the
+/// constant-time one, and must be the same as with `==`.
+#[test]
+fn test_verification_battery() {
+    use crate::kzg::verifier::verifier;
+    use ark_bls12_381::Bls12_381;
+
+    let mut rng = thread_rng();
+    let system = bls12_381_system(&poseidon_config());
+    let generators = BulletproofGenerators::<G1>::random(8, &mut rng);
+    let v1: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let v2: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let prove = || system.prove(generators.clone(), v1.clone(), v2.clone()).unwrap();
+    let check = |proof: crate::bulletproofs::BulletproofProof<Fr, G1>| {
+        let with_msm = system.verify_msm(&proof, &generators);
+        assert_eq!(with_msm, system.verify(proof, generators.clone()));
+        with_msm
+    };
+    assert!(check(prove()));
+    for round in 0..3 {
+        let mut proof = prove();
+        proof.rec_proofs[round].0.l_value += G1::generator();
+        assert!(!check(proof));
+        let mut proof = prove();
+        proof.rec_proofs[round].0.r_value += G1::generator();
+        assert!(!check(proof));
+    }
+    let mut proof = prove();
+    proof.small_proof.value1 += Fr::from(1u64);
+    assert!(!check(proof));
+    let mut proof = prove();
+    proof.small_proof.dot_product += Fr::from(1u64);
+    assert!(!check(proof));
+
+    let system = KZGSystemImpl::setup(8, KZGVerifierChallenger::default(), &mut rng);
+    let proofs: Vec<KZGProof<Fr, G1>> = (0..4).map(|_| system.prove(&DensePolynomial::rand(8, &mut rng)).unwrap()).collect();
+    let g1 = system.crs.g1_powers[0];
+    let verify = |proof: &KZGProof<Fr, G1>| {
+        let parts = verifier::verify_parts::<Bls12_381>(proof.commitment, proof.challenge, proof.value.unwrap(), proof.witness, g1, system.g2, system.g2_s);
+        assert_eq!(parts, verifier::verify::<Bls12_381>(proof.clone(), proof.challenge, system.g2, system.g2_s));
+        parts
+    };
+    assert!(proofs.iter().all(verify));
+    let mut tampered = proofs[0].clone();
+    tampered.witness += G1::generator();
+    assert!(!verify(&tampered));
+    let mut tampered = proofs[0].clone();
+    tampered.value = Some(tampered.value.unwrap() + Fr::from(1u64));
+    tampered.challenge_evaluation = g1 * tampered.value.unwrap();
+    assert!(!verify(&tampered));
+    let mut tampered = proofs[0].clone();
+    tampered.commitment = proofs[1].commitment;
+    assert!(!verify(&tampered));
+
+    let aggregate = system.aggregate_proofs(&proofs);
+    assert!(system.verify_aggregate(&aggregate));
+    let mut tampered = aggregate.clone();
+    tampered.proofs[2].witness += G1::generator();
+    assert!(!system.verify_aggregate(&tampered));
+}
@@ -0,0 +1,36 @@
+//! Equality for the final accept or reject decisions of the verifiers. With the `ct-verify`
+//! feature, values are compared through their compressed canonical serializations in constant
+//! time, so that the time a rejection takes does not tell where a forged proof first differs from
+//! what the verifier expected; without it they are compared with `==`, which stops at the first
+//! difference.
+//!
+//! Only the comparison is constant time: the group operations and pairings before it are those
+//! of arkworks, and serializing a projective point normalizes it first.
+
+use ark_ff::Zero;
+use ark_serialize::CanonicalSerialize;
+
+/// Whether the two values are equal.
+#[cfg(feature = "ct-verify")]
+pub(crate) fn ct_eq<T: PartialEq + CanonicalSerialize>(a: &T, b: &T) -> bool {
+    use subtle::ConstantTimeEq;
+
+    let serialize = |value: &T| {
+        let mut bytes = Vec::with_capacity(value.compressed_size());
+        value.serialize_compressed(&mut bytes).expect("Serializing to a vector cannot fail");
+        bytes
+    };
+    // Values of the same type have encodings of the same length, which is all `ct_eq` leaks
+    serialize(a).ct_eq(&serialize(b)).into()
+}
+
+/// Whether the two values are equal.
+#[cfg(not(feature = "ct-verify"))]
+pub(crate) fn ct_eq<T: PartialEq + CanonicalSerialize>(a: &T, b: &T) -> bool {
+    a == b
+}
+
+/// Whether the value is zero, e.g. whether a product of pairings is the identity.
+pub(crate) fn ct_is_zero<T: Zero + PartialEq + CanonicalSerialize>(value: &T) -> bool {
+    ct_eq(value, &T::zero())
+}
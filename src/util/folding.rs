@@ -0,0 +1,72 @@
+use std::cell::OnceCell;
+
+use ark_ff::{batch_inversion, Field};
+
+/// The folding challenges of a recursive protocol, one per round, the first round first.
+///
+/// Bulletproofs and FRI both halve their instance with a challenge every round. The challenges
+/// are generated non-zero, and their inverses are computed together the first time they are needed.
+#[derive(Clone, Debug)]
+pub struct FoldingChallenges<S: Field> {
+    challenges: Vec<S>,
+    inverses: OnceCell<Vec<S>>,
+}
+
+impl<S: Field> FoldingChallenges<S> {
+    pub fn new(challenges: Vec<S>) -> Self {
+        assert!(challenges.iter().all(|challenge| !challenge.is_zero()), "Folding challenges must be non-zero");
+        Self { challenges, inverses: OnceCell::new() }
+    }
+
+    /// The challenges with their inverses already computed, which are not checked.
+    pub fn with_inverses(challenges: Vec<S>, inverses: Vec<S>) -> Self {
+        assert_eq!(challenges.len(), inverses.len(), "Expected an inverse for every challenge");
+        let folding_challenges = Self::new(challenges);
+        folding_challenges.inverses.set(inverses).expect("The inverses are not computed yet");
+        folding_challenges
+    }
+
+    pub fn challenges(&self) -> &[S] {
+        &self.challenges
+    }
+
+    pub fn len(&self) -> usize {
+        self.challenges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.challenges.is_empty()
+    }
+
+    /// The inverses of the challenges, in the same order.
+    pub fn inverses(&self) -> &[S] {
+        self.inverses.get_or_init(|| {
+            let mut inverses = self.challenges.clone();
+            batch_inversion(&mut inverses);
+            inverses
+        })
+    }
+
+    /// Every challenge raised to the given power, which may be negative.
+    pub fn powers(&self, exponent: i64) -> Vec<S> {
+        let bases = if exponent < 0 { self.inverses() } else { self.challenges() };
+        bases.iter().map(|base| base.pow([exponent.unsigned_abs()])).collect()
+    }
+
+    /// The coefficients of the original generators in the folded one.
+    ///
+    /// Folding `g` with x keeps `x^-1 g_L + x g_R`, so after k rounds the generator at index i is
+    /// weighted by the product over rounds j of `x_j` if bit `k - 1 - j` of i is set and `x_j^-1`
+    /// otherwise; the first round splits on the most significant bit.
+    pub fn s_vector(&self) -> Vec<S> {
+        let inverses = self.inverses();
+        let mut s = vec![S::one()];
+        for (challenge, inverse) in self.challenges.iter().zip(inverses) {
+            s = s
+                .iter()
+                .flat_map(|coefficient| [*coefficient * inverse, *coefficient * challenge])
+                .collect();
+        }
+        s
+    }
+}
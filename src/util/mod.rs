@@ -1,4 +1,20 @@
-use ark_ff::Field;
+pub mod folding;
+pub mod msm;
+pub(crate) mod ct;
+pub mod parallel;
+pub mod poseidon;
+pub mod transcript;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub(crate) mod secret;
+mod test;
+
+pub use transcript::{PoseidonTranscript, Sha256Transcript, Transcript};
+
+use ark_ff::{Field, PrimeField};
+use ark_serialize::CanonicalSerialize;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
 
 pub trait VerifierChallenge {
     type Commitment;
@@ -6,4 +22,52 @@ pub trait VerifierChallenge {
 
     fn generate_challenge(&self, commitment: &Self::Commitment) -> Self::Challenge;
     fn verify_challenge_generation(&self, commitment: &Self::Commitment, challenge: &Self::Challenge) -> bool;
-}
\ No newline at end of file
+
+    /// Derives the `counter`-th candidate challenge for a commitment, the first being the plain challenge.
+    /// Sponge-based challengers should override this to absorb the counter before squeezing.
+    fn generate_challenge_with_counter(&self, commitment: &Self::Commitment, counter: u64) -> Self::Challenge {
+        self.generate_challenge(commitment) + Self::Challenge::from(counter)
+    }
+
+    /// Generates a challenge that is guaranteed to be invertible.
+    fn generate_nonzero_challenge(&self, commitment: &Self::Commitment) -> Self::Challenge {
+        first_nonzero_challenge(|counter| self.generate_challenge_with_counter(commitment, counter))
+    }
+}
+
+/// Returns the first non-zero candidate challenge, trying counters 0, 1, 2, ...
+pub fn first_nonzero_challenge<F: Field>(candidate: impl FnMut(u64) -> F) -> F {
+    (0..)
+        .map(candidate)
+        .find(|challenge| !challenge.is_zero())
+        .expect("Ran out of counters looking for a non-zero challenge")
+}
+
+/// Why integers could not be converted to elements of a field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// The coefficient at the index is not below the modulus, so converting it would reduce it
+    ExceedsModulus { index: usize },
+}
+
+/// Converts coefficients to elements of F, failing rather than reducing any that is not below the
+/// modulus, e.g. when coefficients of a polynomial over one curve's field are committed to over another.
+pub fn check_coeffs_fit<F: PrimeField>(coeffs: &[BigUint]) -> Result<Vec<F>, ConversionError> {
+    let modulus: BigUint = F::MODULUS.into();
+    coeffs
+        .iter()
+        .enumerate()
+        .map(|(index, coeff)| if *coeff < modulus { Ok(F::from(coeff.clone())) } else { Err(ConversionError::ExceedsModulus { index }) })
+        .collect()
+}
+
+/// A stable identifier for a proof, e.g. for audit logs: SHA-256 of its compressed canonical
+/// serialization, which holds the public inputs and every message of the prover. Identical proofs
+/// share a digest and changing any field changes it.
+pub trait TranscriptDigest: CanonicalSerialize {
+    fn transcript_digest(&self) -> [u8; 32] {
+        let mut bytes = Vec::new();
+        self.serialize_compressed(&mut bytes).expect("Serializing to a vector cannot fail");
+        Sha256::digest(&bytes).into()
+    }
+}
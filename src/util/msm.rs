@@ -0,0 +1,30 @@
+//! Multi-scalar multiplications Σ sᵢ·Pᵢ, computed with Pippenger's algorithm through arkworks'
+//! `VariableBaseMSM`, on rayon's thread pool with the `parallel` feature.
+//!
+//! The MSM takes its points in the base form of the group, the affine form for a curve. Points in
+//! projective form are normalized with a single inversion by `batch_normalize`, so that callers
+//! reusing the same points, such as a CRS, can normalize them once.
+
+use ark_ec::VariableBaseMSM;
+
+/// Σ sᵢ·Pᵢ over points in the base form of the group. Panics if there are not as many scalars as
+/// points.
+pub fn msm<G: VariableBaseMSM>(points: &[G::MulBase], scalars: &[G::ScalarField]) -> G {
+    assert_eq!(points.len(), scalars.len(), "Scalars and points must have the same length");
+    if points.is_empty() {
+        return G::zero();
+    }
+    G::msm_unchecked(points, scalars)
+}
+
+/// Σ sᵢ·Pᵢ over points in projective form, normalized first. Panics if there are not as many
+/// scalars as points.
+pub fn msm_projective<G: VariableBaseMSM>(points: &[G], scalars: &[G::ScalarField]) -> G {
+    assert_eq!(points.len(), scalars.len(), "Scalars and points must have the same length");
+    msm(&batch_normalize(points), scalars)
+}
+
+/// The points in the base form `msm` takes, normalized together with a single inversion.
+pub fn batch_normalize<G: VariableBaseMSM>(points: &[G]) -> Vec<G::MulBase> {
+    G::batch_convert_to_mul_base(points)
+}
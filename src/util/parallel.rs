@@ -0,0 +1,16 @@
+//! The `parallel` feature runs the hot loops of the crate on rayon's thread pool through the
+//! `cfg_*` macros of ark-std, which fall back to the serial iterators without it.
+
+/// `Send + Sync` with the `parallel` feature, for the values the parallel loops share between
+/// threads, and nothing without it, so that the serial build asks no more of its type parameters.
+#[cfg(feature = "parallel")]
+pub trait ThreadSafe: Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> ThreadSafe for T {}
+
+#[cfg(not(feature = "parallel"))]
+pub trait ThreadSafe {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T> ThreadSafe for T {}
@@ -0,0 +1,63 @@
+//! Poseidon parameters for the sponges the crate's challengers and hashes are built on.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use ark_crypto_primitives::sponge::poseidon::{find_poseidon_ark_and_mds, PoseidonConfig};
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+
+/// The number of full rounds, 8 at every width in the Poseidon paper.
+const FULL_ROUNDS: u64 = 8;
+
+/// Configurations generated so far, by field, rate and capacity.
+type ConfigCache = HashMap<(TypeId, usize, usize), Box<dyn Any + Send + Sync>>;
+
+/// Poseidon over `F` with a state of `rate + capacity` elements and the round numbers of the
+/// Poseidon paper for 128-bit security over fields of about 255 bits: 8 full rounds, and 57, 60
+/// or 63 partial rounds for states of up to 3, 5 or 9 elements. The round constants and the MDS
+/// matrix are generated by the Grain LFSR of the reference implementation.
+///
+/// The S-box is `x^α` for the smallest α that is a permutation of F, i.e. coprime to `p - 1`: 5
+/// over BLS12-381, but 7 over Goldilocks, where 3 and 5 both divide `p - 1`.
+///
+/// Generating the constants runs the LFSR for every round, so each configuration is generated
+/// once and cloned out of a cache on later calls.
+///
+/// # Panics
+/// If the rate or the capacity is zero, or the state has more than 9 elements, for which there
+/// are no round numbers here.
+pub fn secure_config<F: PrimeField>(rate: usize, capacity: usize) -> PoseidonConfig<F> {
+    static CACHE: OnceLock<Mutex<ConfigCache>> = OnceLock::new();
+
+    // A panic while generating leaves no entry behind, so the cache is still sound
+    let mut cache = CACHE.get_or_init(Default::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache
+        .entry((TypeId::of::<F>(), rate, capacity))
+        .or_insert_with(|| Box::new(generate_config::<F>(rate, capacity)))
+        .downcast_ref::<PoseidonConfig<F>>()
+        .expect("Configurations are cached under the type of their field")
+        .clone()
+}
+
+fn generate_config<F: PrimeField>(rate: usize, capacity: usize) -> PoseidonConfig<F> {
+    assert!(rate > 0 && capacity > 0, "Poseidon needs a non-zero rate and capacity");
+    let partial_rounds = match rate + capacity {
+        ..=3 => 57,
+        4..=5 => 60,
+        6..=9 => 63,
+        width => panic!("No round numbers for a Poseidon state of {} elements", width),
+    };
+
+    let order: BigUint = F::MODULUS.into() - 1u64;
+    let alpha = [3u64, 5, 7, 11, 13]
+        .into_iter()
+        .find(|alpha| (&order % *alpha) != BigUint::from(0u64))
+        .expect("Some small prime is coprime to p - 1");
+
+    // The LFSR is seeded with the width of the state, which it takes as a rate for a single
+    // element of capacity
+    let (ark, mds) = find_poseidon_ark_and_mds::<F>(F::MODULUS_BIT_SIZE as u64, rate + capacity - 1, FULL_ROUNDS, partial_rounds, 0);
+    PoseidonConfig::new(FULL_ROUNDS as usize, partial_rounds as usize, alpha, mds, ark, rate, capacity)
+}
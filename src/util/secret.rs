@@ -0,0 +1,45 @@
+//! Secrets held only while a proof or a setup is computed: the trusted-setup s, blinding factors
+//! and the witness vectors of a bulletproof. With the `zeroize` feature they are overwritten with
+//! zeros once no longer needed; without it `Secret` and `wipe` do nothing, so callers read the same.
+
+#[cfg(feature = "zeroize")]
+pub(crate) use zeroize::Zeroizing as Secret;
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Overwrites the secret with zeros, when the `zeroize` feature is enabled.
+#[cfg(feature = "zeroize")]
+pub(crate) fn wipe<T: Zeroize>(secret: &mut T) {
+    secret.zeroize();
+}
+
+#[cfg(not(feature = "zeroize"))]
+pub(crate) fn wipe<T>(_secret: &mut T) {}
+
+/// A value wiped when dropped if the `zeroize` feature is enabled, read through `Deref`.
+#[cfg(not(feature = "zeroize"))]
+pub(crate) struct Secret<T>(T);
+
+#[cfg(not(feature = "zeroize"))]
+impl<T> Secret<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(not(feature = "zeroize"))]
+impl<T> std::ops::Deref for Secret<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[cfg(not(feature = "zeroize"))]
+impl<T> std::ops::DerefMut for Secret<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
@@ -0,0 +1,124 @@
+//! serde support for the proof, commitment and setup types, through their compressed canonical
+//! serialization: a lowercase hex string in human-readable formats such as JSON, the raw bytes in
+//! binary ones such as bincode. `serialize` and `deserialize` also work with `#[serde(with = ...)]`
+//! for any other type that has a canonical serialization.
+
+use std::fmt::{self, Debug};
+
+use ::serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use ::serde::ser::{self, Serializer};
+use ark_ec::Group;
+use ark_ff::{FftField, Field};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::bulletproofs::verifier_challenger::BulletproofVerifierChallenge;
+use crate::bulletproofs::{BulletproofGenerators, BulletproofProof, BulletproofProofSmall, BulletproofProofSmallZK, BulletproofProofZK, BulletproofRecProof};
+use crate::fri::deep::OodEvalProof;
+use crate::fri::extension::ExtensionFRIProof;
+use crate::fri::merkle_tree::{CompactMerkleProof, LeafIndex, MerkleProof, MerkleRangeProof};
+use crate::fri::oracle::{CommitmentOracle, OracleFRIProof};
+use crate::fri::protocol::{CompactFRIProof, FRIProtocolProof};
+use crate::fri::prover::{FRIRecCommitment, FRIRecProof, QueryOpening, RowOpening};
+use crate::kzg::pedersen::KZGPedersenProof;
+use crate::kzg::permutation::PermutationProof;
+use crate::kzg::{AggregateKZGProof, BivariateCRS, BivariateKZGProof, EqualOpeningProof, FoldedOpeningProof, KZGCommitment, KZGProof, ProductRelationProof, CRS};
+
+pub fn serialize<T: CanonicalSerialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut bytes = Vec::with_capacity(value.compressed_size());
+    value.serialize_compressed(&mut bytes).map_err(ser::Error::custom)?;
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&hex::encode(bytes))
+    } else {
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+/// Reads a value written by `serialize`, checking every point and field element.
+pub fn deserialize<'de, T: CanonicalDeserialize, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    let bytes = if deserializer.is_human_readable() {
+        let encoded = String::deserialize(deserializer)?;
+        hex::decode(encoded).map_err(de::Error::custom)?
+    } else {
+        deserializer.deserialize_byte_buf(BytesVisitor)?
+    };
+    T::deserialize_compressed(&bytes[..]).map_err(de::Error::custom)
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a compressed canonical serialization")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Ok(bytes.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(bytes)
+    }
+
+    // Formats without a byte type, such as CBOR in some configurations, send a sequence
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+macro_rules! impl_serde_via_canonical {
+    ($([$($generics:tt)*] $ty:ty),* $(,)?) => {
+        $(
+            impl<$($generics)*> ::serde::Serialize for $ty {
+                fn serialize<__Ser: Serializer>(&self, serializer: __Ser) -> Result<__Ser::Ok, __Ser::Error> {
+                    serialize(self, serializer)
+                }
+            }
+
+            impl<'de, $($generics)*> ::serde::Deserialize<'de> for $ty {
+                fn deserialize<__De: Deserializer<'de>>(deserializer: __De) -> Result<Self, __De::Error> {
+                    deserialize(deserializer)
+                }
+            }
+        )*
+    };
+}
+
+impl_serde_via_canonical!(
+    [G: Group + Clone + Debug] BulletproofGenerators<G>,
+    [S: Field + Clone] BulletproofVerifierChallenge<S>,
+    [S: Field + Clone + Debug, G: Group<ScalarField = S> + Clone + Debug] BulletproofRecProof<S, G>,
+    [S: Field + Debug, G: Group<ScalarField = S> + Debug] BulletproofProofSmall<S, G>,
+    [S: Field + Debug, G: Group<ScalarField = S> + Debug] BulletproofProof<S, G>,
+    [S: Field + Debug, G: Group<ScalarField = S> + Debug] BulletproofProofSmallZK<S, G>,
+    [S: Field + Debug, G: Group<ScalarField = S> + Debug] BulletproofProofZK<S, G>,
+    [F: Field, G: Group<ScalarField = F>] KZGCommitment<F, G>,
+    [F: Field, G: Group<ScalarField = F>] KZGProof<F, G>,
+    [F: Field, G: Group<ScalarField = F>] ProductRelationProof<F, G>,
+    [F: Field, G: Group<ScalarField = F>] AggregateKZGProof<F, G>,
+    [F: Field, G: Group<ScalarField = F>] EqualOpeningProof<F, G>,
+    [F: Field, G: Group<ScalarField = F>] FoldedOpeningProof<F, G>,
+    [F: Field, G: Group<ScalarField = F>] BivariateKZGProof<F, G>,
+    [F: Field, G: Group<ScalarField = F>] PermutationProof<F, G>,
+    [F: Field, G: Group<ScalarField = F>] KZGPedersenProof<F, G>,
+    [G: Group] CRS<G>,
+    [G: Group] BivariateCRS<G>,
+    [F: Field] LeafIndex<F>,
+    [F: Field, H: CanonicalSerialize + CanonicalDeserialize] MerkleProof<F, H>,
+    [F: Field, H: CanonicalSerialize + CanonicalDeserialize] CompactMerkleProof<F, H>,
+    [F: Field, H: CanonicalSerialize + CanonicalDeserialize] MerkleRangeProof<F, H>,
+    [H: CanonicalSerialize + CanonicalDeserialize] FRIRecCommitment<H>,
+    [F: Field, H: CanonicalSerialize + CanonicalDeserialize] QueryOpening<F, H>,
+    [F: Field, H: CanonicalSerialize + CanonicalDeserialize] RowOpening<F, H>,
+    [F: Field, H: CanonicalSerialize + CanonicalDeserialize] FRIRecProof<F, H>,
+    [F: Field, H: CanonicalSerialize + CanonicalDeserialize] FRIProtocolProof<F, H>,
+    [F: Field, H: CanonicalSerialize + CanonicalDeserialize] CompactFRIProof<F, H>,
+    [F: Field, H: CanonicalSerialize + CanonicalDeserialize] OodEvalProof<F, H>,
+    [E: Field, H: CanonicalSerialize + CanonicalDeserialize] ExtensionFRIProof<E, H>,
+    [F: FftField, O: CommitmentOracle<F>] OracleFRIProof<F, O>,
+);
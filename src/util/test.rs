@@ -0,0 +1,238 @@
+#![allow(unused_imports)]
+#![allow(dead_code)]
+
+use ark_bls12_381::Fr;
+use ark_ff::{Field, PrimeField, UniformRand, Zero};
+use num_bigint::BigUint;
+use ark_std::rand::thread_rng;
+
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use crate::util::poseidon::secure_config;
+use ark_bls12_381::G1Projective as G1;
+use ark_ec::Group;
+
+use crate::util::folding::FoldingChallenges;
+use crate::util::{check_coeffs_fit, ConversionError, PoseidonTranscript, Sha256Transcript, Transcript};
+
+#[test]
+fn test_s_vector_matches_product_of_challenge_powers() {
+    let mut rng = thread_rng();
+    let challenges: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+    let folding = FoldingChallenges::new(challenges.clone());
+
+    // Round j splits on bit 3 - j of the index
+    let naive: Vec<Fr> = (0..16)
+        .map(|i| {
+            challenges
+                .iter()
+                .enumerate()
+                .map(|(j, x)| if (i >> (3 - j)) & 1 == 1 { *x } else { x.inverse().unwrap() })
+                .product()
+        })
+        .collect();
+    assert_eq!(folding.s_vector(), naive);
+
+    let squares: Vec<Fr> = challenges.iter().map(|x| x.square()).collect();
+    let inverse_squares: Vec<Fr> = challenges.iter().map(|x| x.inverse().unwrap().square()).collect();
+    assert_eq!(folding.powers(2), squares);
+    assert_eq!(folding.powers(-2), inverse_squares);
+}
+
+#[test]
+fn test_check_coeffs_fit() {
+    let modulus: BigUint = Fr::MODULUS.into();
+    let in_range = vec![BigUint::from(7u64), &modulus - 1u64];
+    assert_eq!(check_coeffs_fit::<Fr>(&in_range), Ok(vec![Fr::from(7u64), -Fr::from(1u64)]));
+
+    // The modulus itself would silently become zero
+    let out_of_range = vec![BigUint::from(7u64), modulus.clone(), &modulus + 1u64];
+    assert_eq!(check_coeffs_fit::<Fr>(&out_of_range), Err(ConversionError::ExceedsModulus { index: 1 }));
+
+    // A coefficient of the larger base field does not fit the scalar field
+    let base_field_element: BigUint = ark_bls12_381::Fq::from(-1i64).into();
+    assert!(check_coeffs_fit::<Fr>(std::slice::from_ref(&base_field_element)).is_err());
+    assert!(check_coeffs_fit::<ark_bls12_381::Fq>(&[base_field_element]).is_ok());
+}
+
+/// Absorbs one message of each kind and squeezes a challenge and an index below 10.
+fn run_transcript<T: Transcript<Fr>>(transcript: &mut T, label: &[u8], element: Fr) -> (Fr, usize) {
+    transcript.absorb_bytes(b"bytes", b"message");
+    transcript.absorb_field(label, &element);
+    transcript.absorb_group(b"group", &G1::generator());
+    (transcript.squeeze_field(b"challenge"), transcript.squeeze_usize(b"index", 10))
+}
+
+fn check_transcript<T: Transcript<Fr> + Clone>(fresh: T) {
+    let squeezed = run_transcript(&mut fresh.clone(), b"field", Fr::from(7u64));
+    assert_eq!(run_transcript(&mut fresh.clone(), b"field", Fr::from(7u64)), squeezed);
+    assert!(squeezed.1 < 10);
+
+    // Another message, or the same one under another label, gives other challenges
+    assert_ne!(run_transcript(&mut fresh.clone(), b"field", Fr::from(8u64)).0, squeezed.0);
+    assert_ne!(run_transcript(&mut fresh.clone(), b"other", Fr::from(7u64)).0, squeezed.0);
+
+    // Squeezing moves the transcript on
+    let mut transcript = fresh.clone();
+    assert_ne!(transcript.squeeze_field(b"challenge"), transcript.squeeze_field(b"challenge"));
+    assert!(!fresh.clone().squeeze_nonzero_field(b"challenge").is_zero());
+}
+
+#[test]
+fn test_transcripts_are_deterministic_and_bind_their_messages() {
+    let poseidon_config = secure_config::<Fr>(2, 1);
+    check_transcript(PoseidonTranscript::new(&poseidon_config));
+    check_transcript(Sha256Transcript::new(b"test"));
+
+    // The domain separator of a SHA-256 transcript changes every challenge
+    let first = run_transcript(&mut Sha256Transcript::new(b"test"), b"field", Fr::from(7u64));
+    assert_ne!(run_transcript(&mut Sha256Transcript::new(b"other test"), b"field", Fr::from(7u64)).0, first.0);
+}
+
+/// Whether the square matrix has an inverse, by Gaussian elimination.
+fn is_invertible<F: Field>(matrix: &[Vec<F>]) -> bool {
+    let mut rows = matrix.to_vec();
+    for column in 0..rows.len() {
+        let Some(pivot) = (column..rows.len()).find(|&row| !rows[row][column].is_zero()) else {
+            return false;
+        };
+        rows.swap(column, pivot);
+        let inverse = rows[column][column].inverse().unwrap();
+        let pivot_row = rows[column].clone();
+        for row in rows.iter_mut().skip(column + 1) {
+            let factor = row[column] * inverse;
+            for (k, entry) in row.iter_mut().enumerate().skip(column) {
+                *entry -= factor * pivot_row[k];
+            }
+        }
+    }
+    true
+}
+
+#[test]
+fn test_secure_config_is_non_degenerate() {
+    for (rate, capacity, partial_rounds) in [(2, 1, 57), (4, 1, 60), (3, 2, 60), (8, 1, 63)] {
+        let config = secure_config::<Fr>(rate, capacity);
+        let width = rate + capacity;
+        assert_eq!((config.rate, config.capacity, config.alpha), (rate, capacity, 5));
+        assert_eq!((config.full_rounds, config.partial_rounds), (8, partial_rounds));
+        assert_eq!(config.ark.len(), 8 + partial_rounds);
+        assert!(config.ark.iter().all(|row| row.len() == width && row.iter().any(|c| !c.is_zero())));
+        assert!(config.mds.len() == width && config.mds.iter().all(|row| row.len() == width));
+        assert!(is_invertible(&config.mds));
+    }
+
+    // The S-box must be a permutation of the field
+    let jubjub = secure_config::<ark_ed_on_bls12_381::Fr>(2, 1);
+    assert_eq!(jubjub.alpha, 5);
+    assert!(is_invertible(&jubjub.mds));
+}
+
+#[test]
+fn test_secure_config_is_deterministic() {
+    use ark_crypto_primitives::sponge::poseidon::find_poseidon_ark_and_mds;
+
+    let (first, second) = (secure_config::<Fr>(2, 1), secure_config::<Fr>(2, 1));
+    assert_eq!((&first.ark, &first.mds), (&second.ark, &second.mds));
+
+    // The same constants as generating them directly, rather than ones left in the cache by
+    // another field or width
+    let (ark, mds) = find_poseidon_ark_and_mds::<Fr>(255, 2, 8, 57, 0);
+    assert_eq!((first.ark, first.mds), (ark, mds));
+
+    // The constants depend on the width alone, but the split of the state is kept
+    let (wide_rate, wide_capacity) = (secure_config::<Fr>(4, 1), secure_config::<Fr>(3, 2));
+    assert_eq!((&wide_rate.ark, &wide_rate.mds), (&wide_capacity.ark, &wide_capacity.mds));
+    assert_eq!((wide_rate.rate, wide_capacity.rate), (4, 3));
+}
+
+#[test]
+fn test_challengers_with_secure_config_are_stable() {
+    use crate::bulletproofs::verifier_challenger::{DefaultVerifierChallenger, VerifierChallenger};
+    use crate::bulletproofs::BulletproofRecProof;
+    use crate::kzg::system::KZGVerifierChallenger;
+    use crate::kzg::KZGCommitment;
+    use crate::util::VerifierChallenge;
+    use ark_crypto_primitives::sponge::poseidon::find_poseidon_ark_and_mds;
+
+    // The parameters fixed by hand, so the defaults cannot change unnoticed
+    let (ark, mds) = find_poseidon_ark_and_mds::<Fr>(255, 2, 8, 57, 0);
+    let pinned = PoseidonConfig::new(8, 57, 5, mds, ark, 2, 1);
+
+    let proof = BulletproofRecProof {
+        dot_product: Fr::from(3u64),
+        pedersen_commitment: G1::generator(),
+        l_value: G1::generator() * Fr::from(2u64),
+        r_value: G1::generator() * Fr::from(5u64),
+    };
+    let challenge = |mut challenger: DefaultVerifierChallenger<Fr>| -> Fr { challenger.generate_challenge(&proof) };
+    let expected = challenge(DefaultVerifierChallenger::new(&pinned));
+    assert_eq!(challenge(DefaultVerifierChallenger::default()), expected);
+    assert_eq!(challenge(DefaultVerifierChallenger::default()), expected);
+
+    let commitment = KZGCommitment { value: G1::generator() * Fr::from(7u64) };
+    let expected = KZGVerifierChallenger::new(pinned).generate_challenge(&commitment);
+    assert_eq!(KZGVerifierChallenger::default().generate_challenge(&commitment), expected);
+    assert_eq!(KZGVerifierChallenger::default().generate_challenge(&commitment), expected);
+    assert_ne!(KZGVerifierChallenger::default().generate_challenge(&KZGCommitment { value: G1::generator() }), expected);
+}
+
+#[test]
+fn test_ct_eq_matches_eq() {
+    use crate::util::ct::{ct_eq, ct_is_zero};
+    use ark_bls12_381::{Bls12_381, G2Projective as G2};
+    use ark_ec::pairing::Pairing;
+
+    let mut rng = thread_rng();
+    for _ in 0..16 {
+        let (a, b) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+        assert!(ct_eq(&a, &a) && !ct_eq(&a, &b));
+        assert!(ct_is_zero(&Fr::zero()) && !ct_is_zero(&a));
+
+        // Projective points with different coordinates for the same point are equal
+        let point = G1::generator() * a;
+        assert!(ct_eq(&(point + point), &point.double()));
+        assert!(!ct_eq(&point, &(point + G1::generator())));
+        assert!(ct_is_zero(&(point - point)) && !ct_is_zero(&point));
+
+        let pairing = Bls12_381::pairing(point, G2::generator());
+        assert!(ct_eq(&pairing, &Bls12_381::pairing(G1::generator(), G2::generator() * a)));
+        assert!(!ct_eq(&pairing, &Bls12_381::pairing(G1::generator(), G2::generator() * b)));
+        assert!(ct_is_zero(&Bls12_381::multi_pairing([point, -point], [G2::generator(), G2::generator()])));
+        assert!(!ct_is_zero(&pairing));
+    }
+}
+
+#[test]
+fn test_msm_matches_naive() {
+    use crate::util::msm::{batch_normalize, msm, msm_projective};
+    use ark_ec::CurveGroup;
+    use ark_ed_on_bls12_381::{EdwardsProjective, Fr as JubjubScalar};
+
+    let mut rng = thread_rng();
+    for size in [0, 1, 2, 3, 17, 64, 129] {
+        let points: Vec<G1> = (0..size).map(|_| G1::rand(&mut rng)).collect();
+        let scalars: Vec<Fr> = (0..size).map(|_| Fr::rand(&mut rng)).collect();
+        let naive: G1 = points.iter().zip(&scalars).map(|(point, scalar)| *point * scalar).sum();
+
+        assert_eq!(msm_projective(&points, &scalars), naive, "size {size}");
+        let affine = batch_normalize(&points);
+        assert_eq!(affine, G1::normalize_batch(&points));
+        assert_eq!(msm::<G1>(&affine, &scalars), naive, "size {size}");
+
+        // A second curve, with a different scalar field
+        let points: Vec<EdwardsProjective> = (0..size).map(|_| EdwardsProjective::rand(&mut rng)).collect();
+        let scalars: Vec<JubjubScalar> = (0..size).map(|_| JubjubScalar::rand(&mut rng)).collect();
+        let naive: EdwardsProjective = points.iter().zip(&scalars).map(|(point, scalar)| *point * scalar).sum();
+        assert_eq!(msm_projective(&points, &scalars), naive, "size {size}");
+    }
+}
+
+#[test]
+#[should_panic(expected = "Scalars and points must have the same length")]
+fn test_msm_rejects_mismatched_lengths() {
+    let mut rng = thread_rng();
+    let points: Vec<G1> = (0..4).map(|_| G1::rand(&mut rng)).collect();
+    let scalar = Fr::rand(&mut rng);
+    // A silently truncated MSM would return the first term alone
+    assert_eq!(crate::util::msm::msm_projective(&points, &[scalar]), points[0] * scalar);
+}
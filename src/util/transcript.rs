@@ -0,0 +1,131 @@
+use ark_crypto_primitives::sponge::poseidon::{PoseidonConfig, PoseidonSponge};
+use ark_crypto_primitives::sponge::{Absorb, CryptographicSponge};
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+/// A Fiat-Shamir transcript over F, absorbing the messages of a protocol in order and squeezing
+/// challenges that depend on all of them.
+///
+/// Every message and challenge carries a label naming its role, e.g. `b"bulletproof/L"`, so that
+/// two protocols, or two messages of one protocol, never absorb the same input for different
+/// reasons. Prover and verifier must make the same calls in the same order.
+pub trait Transcript<F: PrimeField> {
+    fn absorb_bytes(&mut self, label: &[u8], bytes: &[u8]);
+
+    fn absorb_field(&mut self, label: &[u8], element: &F);
+
+    /// Absorbs the compressed encoding of a group element, or of anything else with a canonical
+    /// serialization, such as a Merkle root.
+    fn absorb_group<G: CanonicalSerialize>(&mut self, label: &[u8], point: &G) {
+        let mut bytes = Vec::new();
+        point.serialize_compressed(&mut bytes).expect("Serializing to a vector cannot fail");
+        self.absorb_bytes(label, &bytes);
+    }
+
+    fn squeeze_field(&mut self, label: &[u8]) -> F;
+
+    /// Squeezes until the challenge is invertible.
+    fn squeeze_nonzero_field(&mut self, label: &[u8]) -> F {
+        loop {
+            let challenge = self.squeeze_field(label);
+            if !challenge.is_zero() {
+                return challenge;
+            }
+        }
+    }
+
+    /// Squeezes an index below the bound, e.g. a query into a domain of that size, by reducing a
+    /// squeezed element modulo it.
+    fn squeeze_usize(&mut self, label: &[u8], bound: usize) -> usize {
+        assert!(bound > 0, "Cannot squeeze an index below 0");
+        let value: BigUint = self.squeeze_field(label).into_bigint().into();
+        (value % bound).try_into().expect("The remainder is below a usize")
+    }
+}
+
+/// A transcript absorbing into a Poseidon sponge, for challenges that are cheap to recompute in
+/// a circuit over F.
+#[derive(Clone)]
+pub struct PoseidonTranscript<F: PrimeField> {
+    sponge: PoseidonSponge<F>,
+}
+
+impl<F: PrimeField + Absorb> PoseidonTranscript<F> {
+    pub fn new(config: &PoseidonConfig<F>) -> Self {
+        Self { sponge: PoseidonSponge::new(config) }
+    }
+}
+
+impl<F: PrimeField + Absorb> Transcript<F> for PoseidonTranscript<F> {
+    fn absorb_bytes(&mut self, label: &[u8], bytes: &[u8]) {
+        self.sponge.absorb(&label);
+        self.sponge.absorb(&(bytes.len() as u64));
+        self.sponge.absorb(&bytes);
+    }
+
+    fn absorb_field(&mut self, label: &[u8], element: &F) {
+        self.sponge.absorb(&label);
+        self.sponge.absorb(element);
+    }
+
+    fn squeeze_field(&mut self, label: &[u8]) -> F {
+        self.sponge.absorb(&label);
+        self.sponge.squeeze_field_elements(1)[0]
+    }
+}
+
+/// A transcript chaining SHA-256 over its state and every message, for fields without a Poseidon
+/// configuration, e.g. those of the small-field FRI instances.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sha256Transcript {
+    state: [u8; 32],
+}
+
+impl Sha256Transcript {
+    /// Starts from the hash of the domain separator, which names the protocol the transcript is for.
+    pub fn new(domain: &[u8]) -> Self {
+        Self { state: Sha256::new().chain_update(SHA256_TRANSCRIPT_DOMAIN).chain_update(domain).finalize().into() }
+    }
+
+    /// Replaces the state by the hash of it, the kind of the message, and its label and bytes,
+    /// both prefixed by their length.
+    fn update(&mut self, kind: u8, label: &[u8], bytes: &[u8]) {
+        self.state = Sha256::new()
+            .chain_update(self.state)
+            .chain_update([kind])
+            .chain_update((label.len() as u64).to_le_bytes())
+            .chain_update(label)
+            .chain_update((bytes.len() as u64).to_le_bytes())
+            .chain_update(bytes)
+            .finalize()
+            .into();
+    }
+}
+
+/// Separates the SHA-256 transcript from other uses of SHA-256 on the same domain separator.
+const SHA256_TRANSCRIPT_DOMAIN: &[u8] = b"nemesis/transcript/sha256";
+
+impl<F: PrimeField> Transcript<F> for Sha256Transcript {
+    fn absorb_bytes(&mut self, label: &[u8], bytes: &[u8]) {
+        self.update(0, label, bytes);
+    }
+
+    fn absorb_field(&mut self, label: &[u8], element: &F) {
+        let mut bytes = Vec::new();
+        element.serialize_compressed(&mut bytes).expect("Serializing to a vector cannot fail");
+        self.update(1, label, &bytes);
+    }
+
+    /// 512 bits hashed from the state after absorbing the label, reduced into F, so that the
+    /// challenge is all but uniform over fields of up to 256 bits.
+    fn squeeze_field(&mut self, label: &[u8]) -> F {
+        self.update(2, label, &[]);
+        let wide: Vec<u8> = [0u8, 1]
+            .iter()
+            .flat_map(|half| Sha256::new().chain_update(self.state).chain_update([*half]).finalize())
+            .collect();
+        F::from_le_bytes_mod_order(&wide)
+    }
+}
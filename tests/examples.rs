@@ -0,0 +1,34 @@
+//! Runs every example with small arguments, checking that it builds and exits successfully.
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::process::Command;
+
+fn run_example(name: &str, args: &[&str]) {
+    let output = Command::new(env!("CARGO"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["run", "--quiet", "--example", name, "--"])
+        .args(args)
+        .output()
+        .expect("cargo runs");
+    assert!(
+        output.status.success(),
+        "{name} exited with {}:\n{}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_bulletproof_dot_product_example() {
+    run_example("bulletproof_dot_product", &["16"]);
+}
+
+#[test]
+fn test_kzg_commit_open_example() {
+    run_example("kzg_commit_open", &["Cargo.toml", "7"]);
+}
+
+#[test]
+fn test_fri_low_degree_example() {
+    run_example("fri_low_degree", &["63"]);
+}
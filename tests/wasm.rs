@@ -0,0 +1,43 @@
+//! Verifies KZG and bulletproof proofs on wasm32-unknown-unknown, run with
+//! `wasm-pack test --node` or `cargo test --target wasm32-unknown-unknown` with the wasm-bindgen
+//! test runner. The randomness comes from a seeded ChaCha20 stream, so no entropy source is needed.
+#![cfg(target_arch = "wasm32")]
+
+use ark_bls12_381::{Fr, G1Projective as G1};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_ff::UniformRand;
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
+use ark_std::rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use nemesis::bulletproofs::bls12_381::bls12_381_system;
+use nemesis::bulletproofs::BulletproofGenerators;
+use nemesis::kzg::system::{KZGSystemImpl, KZGVerifierChallenger};
+use nemesis::kzg::KZGSystem;
+use nemesis::util::poseidon::secure_config;
+use nemesis::BulletproofSystem;
+
+fn poseidon_config() -> PoseidonConfig<Fr> {
+    secure_config::<Fr>(2, 1)
+}
+
+#[wasm_bindgen_test]
+fn kzg_verifies_on_wasm() {
+    let mut rng = ChaCha20Rng::seed_from_u64(1465);
+    let system = KZGSystemImpl::setup(8, KZGVerifierChallenger::new(poseidon_config()), &mut rng);
+    let proof = system.prove(&DensePolynomial::rand(8, &mut rng)).unwrap();
+    assert_eq!(system.verify(proof), Ok(true));
+}
+
+#[wasm_bindgen_test]
+fn bulletproof_verifies_on_wasm() {
+    let mut rng = ChaCha20Rng::seed_from_u64(1465);
+    let system = bls12_381_system(&poseidon_config());
+    let generators = BulletproofGenerators::<G1>::from_seed(b"wasm", 8);
+    let v1: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let v2: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+
+    let proof = system.prove(generators.clone(), v1, v2).unwrap();
+    assert!(system.verify(proof, generators));
+}